@@ -0,0 +1,54 @@
+use std::collections::hash_map::RandomState;
+
+use bench::{Mix, Skew, Workload};
+use criterion::measurement::WallTime;
+use criterion::{criterion_group, BenchmarkGroup, BenchmarkId, Criterion, Throughput};
+use rsds::map::CoarseMap;
+use rsds::sync::{RawLock, RawMcsLock, RawSpinLock, RawTicketLock};
+use rsds::workload;
+
+const KEYS: usize = 1 << 12;
+const SCRIPT_LEN: usize = 1 << 16;
+
+fn bench_lock<L: RawLock>(
+    group: &mut BenchmarkGroup<WallTime>,
+    name: &str,
+    workload: &Workload,
+    threads: usize,
+) where
+    CoarseMap<u64, u64, RandomState, L>: Sync,
+{
+    let scripts: Vec<_> = (0..threads)
+        .map(|t| workload.ops(t as u64, SCRIPT_LEN))
+        .collect();
+    let map = CoarseMap::<u64, u64, RandomState, L>::new();
+    workload.prefill(&map);
+    group.bench_with_input(BenchmarkId::new(name, threads), &scripts, |b, scripts| {
+        b.iter_custom(|iters| workload::run(&map, scripts, iters))
+    });
+}
+
+/// Compares the locks a [`CoarseMap`] can be guarded by, under contention.
+fn locks(c: &mut Criterion) {
+    let workload = Workload {
+        mix: Mix::BALANCED,
+        skew: Skew::Uniform,
+        keys: KEYS,
+    };
+    let mut group = c.benchmark_group(format!("locks/{}", workload));
+    group.throughput(Throughput::Elements(1));
+    for threads in bench::thread_counts() {
+        bench_lock::<RawSpinLock>(&mut group, "RawSpinLock", &workload, threads);
+        bench_lock::<RawTicketLock>(&mut group, "RawTicketLock", &workload, threads);
+        bench_lock::<RawMcsLock>(&mut group, "RawMcsLock", &workload, threads);
+    }
+    group.finish();
+}
+
+criterion_group!(benches, locks);
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+    bench::write_summary();
+}
@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use bench::{Mix, Skew, Workload};
+use criterion::measurement::WallTime;
+use criterion::{criterion_group, BenchmarkGroup, BenchmarkId, Criterion, Throughput};
+use dashmap::DashMap;
+use rsds::map::{CoarseMap, StripedHashMap};
+use rsds::workload::{self, MapLike};
+
+const KEYS: usize = 1 << 16;
+const SCRIPT_LEN: usize = 1 << 16;
+
+fn bench_map<M, Via>(
+    group: &mut BenchmarkGroup<WallTime>,
+    name: &str,
+    workload: &Workload,
+    threads: usize,
+    map: M,
+) where
+    M: MapLike<Via>,
+{
+    let scripts: Vec<_> = (0..threads)
+        .map(|t| workload.ops(t as u64, SCRIPT_LEN))
+        .collect();
+    workload.prefill(&map);
+    group.bench_with_input(BenchmarkId::new(name, threads), &scripts, |b, scripts| {
+        b.iter_custom(|iters| workload::run(&map, scripts, iters))
+    });
+}
+
+fn maps(c: &mut Criterion) {
+    for mix in [Mix::READ_HEAVY, Mix::BALANCED, Mix::WRITE_HEAVY] {
+        for skew in [Skew::Uniform, Skew::Zipf(0.99)] {
+            let workload = Workload {
+                mix,
+                skew,
+                keys: KEYS,
+            };
+            let mut group = c.benchmark_group(format!("maps/{}", workload));
+            // an iteration is a single operation, on whichever thread
+            group.throughput(Throughput::Elements(1));
+            for threads in bench::thread_counts() {
+                let keys = workload.keys;
+                bench_map(
+                    &mut group,
+                    "StripedHashMap",
+                    &workload,
+                    threads,
+                    StripedHashMap::<u64, u64>::with_capacity(keys),
+                );
+                bench_map(
+                    &mut group,
+                    "CoarseMap",
+                    &workload,
+                    threads,
+                    CoarseMap::<u64, u64>::new(),
+                );
+                bench_map(
+                    &mut group,
+                    "DashMap",
+                    &workload,
+                    threads,
+                    DashMap::<u64, u64>::with_capacity(keys),
+                );
+                bench_map(
+                    &mut group,
+                    "flurry",
+                    &workload,
+                    threads,
+                    flurry::HashMap::<u64, u64>::with_capacity(keys),
+                );
+                bench_map(
+                    &mut group,
+                    "Mutex<HashMap>",
+                    &workload,
+                    threads,
+                    Mutex::new(HashMap::<u64, u64>::with_capacity(keys)),
+                );
+            }
+            group.finish();
+        }
+    }
+}
+
+criterion_group!(benches, maps);
+
+fn main() {
+    benches();
+    Criterion::default().configure_from_args().final_summary();
+    bench::write_summary();
+}
@@ -0,0 +1,23 @@
+use rsds::workload::MapLike;
+
+/// Marks the [`MapLike`] impls of the maps `rsds` is compared against that
+/// it has no `Map` impl for.
+///
+/// `DashMap` and `Mutex<HashMap>` need none, as `rsds` implements `Map` for
+/// them with the `adapters` feature.
+#[derive(Debug)]
+pub enum Baseline {}
+
+impl MapLike<Baseline> for flurry::HashMap<u64, u64> {
+    fn get(&self, key: u64) -> bool {
+        self.pin().get(&key).is_some()
+    }
+
+    fn insert(&self, key: u64, value: u64) {
+        self.pin().insert(key, value);
+    }
+
+    fn remove(&self, key: u64) -> bool {
+        self.pin().remove(&key).is_some()
+    }
+}
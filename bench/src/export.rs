@@ -0,0 +1,87 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value};
+
+/// Returns the directory criterion writes its results to: `$CRITERION_HOME`
+/// if set, and otherwise `criterion/` in `$CARGO_TARGET_DIR` or the
+/// workspace's `target/` directory.
+pub fn criterion_dir() -> PathBuf {
+    if let Some(home) = std::env::var_os("CRITERION_HOME") {
+        return home.into();
+    }
+    let target = std::env::var_os("CARGO_TARGET_DIR").map_or_else(
+        || Path::new(env!("CARGO_MANIFEST_DIR")).join("../target"),
+        PathBuf::from,
+    );
+    target.join("criterion")
+}
+
+/// Writes the summary of the benchmarks' estimates to criterion's output
+/// directory, reporting where it went.
+pub fn write_summary() {
+    match export_summary(&criterion_dir()) {
+        Ok(path) => println!("Summary written to {}", path.display()),
+        Err(err) => eprintln!("Could not write summary: {}", err),
+    }
+}
+
+/// Collects the latest estimates of every benchmark under `dir` into
+/// `dir/summary.json`, and returns the summary's path.
+///
+/// Each entry has the benchmark's id, its mean and median time per iteration
+/// in nanoseconds, and, for benchmarks measuring a throughput in elements,
+/// the mean number of elements per second.
+pub fn export_summary(dir: &Path) -> io::Result<PathBuf> {
+    let mut benchmarks = Vec::new();
+    collect(dir, &mut benchmarks)?;
+    benchmarks.sort_by(|a, b| a["id"].as_str().cmp(&b["id"].as_str()));
+    let path = dir.join("summary.json");
+    fs::write(&path, serde_json::to_string_pretty(&benchmarks)?)?;
+    Ok(path)
+}
+
+/// Walks `dir` for the `new/` directories holding the latest run of each
+/// benchmark.
+fn collect(dir: &Path, benchmarks: &mut Vec<Value>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            continue;
+        }
+        if path.ends_with("new") {
+            if let Some(benchmark) = summarize(&path)? {
+                benchmarks.push(benchmark);
+            }
+        } else {
+            collect(&path, benchmarks)?;
+        }
+    }
+    Ok(())
+}
+
+fn summarize(dir: &Path) -> io::Result<Option<Value>> {
+    let read = |name| -> io::Result<Option<Value>> {
+        match fs::read_to_string(dir.join(name)) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    };
+    let (Some(benchmark), Some(estimates)) = (read("benchmark.json")?, read("estimates.json")?)
+    else {
+        return Ok(None);
+    };
+    let mean = estimates["mean"]["point_estimate"].as_f64();
+    let per_sec = match (benchmark["throughput"]["Elements"].as_f64(), mean) {
+        (Some(elements), Some(mean)) if mean > 0.0 => json!(elements * 1e9 / mean),
+        _ => Value::Null,
+    };
+    Ok(Some(json!({
+        "id": benchmark["full_id"],
+        "mean_ns": mean,
+        "median_ns": estimates["median"]["point_estimate"],
+        "elements_per_sec": per_sec,
+    })))
+}
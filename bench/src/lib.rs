@@ -0,0 +1,14 @@
+//! Workloads, baseline maps, and result export shared by the criterion
+//! benchmarks in `benches/`, which drive maps with `rsds::workload`.
+//!
+//! Run the benchmarks with `cargo bench -p bench`. Each run also writes a
+//! summary of every benchmark's estimates to `summary.json` in criterion's
+//! output directory.
+
+mod adapter;
+mod export;
+mod workload;
+
+pub use adapter::Baseline;
+pub use export::{criterion_dir, export_summary, write_summary};
+pub use workload::{thread_counts, Mix, Skew, Workload};
@@ -0,0 +1,148 @@
+use std::fmt;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::Zipf;
+use rsds::workload::{self, MapLike, Op};
+
+/// The percentages of reads, writes, and removes in a workload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Mix {
+    /// Percentage of lookups.
+    pub read: u32,
+    /// Percentage of insertions.
+    pub write: u32,
+    /// Percentage of removals.
+    pub remove: u32,
+}
+
+impl Mix {
+    /// Mostly lookups, as in a cache.
+    pub const READ_HEAVY: Mix = Mix {
+        read: 90,
+        write: 9,
+        remove: 1,
+    };
+
+    /// As many lookups as updates.
+    pub const BALANCED: Mix = Mix {
+        read: 50,
+        write: 40,
+        remove: 10,
+    };
+
+    /// Mostly insertions, as in an index being built.
+    pub const WRITE_HEAVY: Mix = Mix {
+        read: 10,
+        write: 80,
+        remove: 10,
+    };
+}
+
+impl fmt::Display for Mix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "r{}-w{}-d{}", self.read, self.write, self.remove)
+    }
+}
+
+/// How keys are drawn from the key space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Skew {
+    /// Every key is equally likely.
+    Uniform,
+    /// The `k`th most popular key is drawn with probability proportional to
+    /// `1 / k^s`, for the given exponent `s`.
+    Zipf(f64),
+}
+
+impl fmt::Display for Skew {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Skew::Uniform => write!(f, "uniform"),
+            Skew::Zipf(exponent) => write!(f, "zipf-{}", exponent),
+        }
+    }
+}
+
+/// A mix of operations on keys drawn from a key space.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Workload {
+    /// The operations' mix.
+    pub mix: Mix,
+    /// How the keys are drawn.
+    pub skew: Skew,
+    /// Size of the key space.
+    pub keys: usize,
+}
+
+impl Workload {
+    /// Generates `len` operations, seeded by `seed`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the mix's percentages do not add up to 100.
+    pub fn ops(&self, seed: u64, len: usize) -> Vec<Op> {
+        let Mix {
+            read,
+            write,
+            remove,
+        } = self.mix;
+        assert_eq!(read + write + remove, 100, "mix should add up to 100%");
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let zipf = match self.skew {
+            Skew::Uniform => None,
+            Skew::Zipf(exponent) => Some(Zipf::new(self.keys as u64, exponent).unwrap()),
+        };
+        (0..len)
+            .map(|_| {
+                let key = match &zipf {
+                    // ranks start at 1
+                    Some(zipf) => rng.sample::<f64, _>(zipf) as u64 - 1,
+                    None => rng.gen_range(0..self.keys as u64),
+                };
+                match rng.gen_range(0..100) {
+                    p if p < read => Op::Get(key),
+                    p if p < read + write => Op::Insert(key, rng.gen()),
+                    _ => Op::Remove(key),
+                }
+            })
+            .collect()
+    }
+
+    /// Inserts every other key, so that lookups start out hitting about half
+    /// the time.
+    pub fn prefill<M, Via>(&self, map: &M)
+    where
+        M: MapLike<Via>,
+    {
+        workload::prefill(map, self.keys as u64);
+    }
+}
+
+impl fmt::Display for Workload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.mix, self.skew)
+    }
+}
+
+/// Returns the thread counts to benchmark, from the comma-separated
+/// `BENCH_THREADS` environment variable if set, and 1, 2, 4, and 8 otherwise.
+///
+/// # Panics
+///
+/// Panics if `BENCH_THREADS` is not a list of positive integers.
+pub fn thread_counts() -> Vec<usize> {
+    match std::env::var("BENCH_THREADS") {
+        Ok(counts) => counts
+            .split(',')
+            .map(|count| match count.trim().parse() {
+                Ok(count) if count > 0 => count,
+                _ => panic!(
+                    "BENCH_THREADS (is {:?}) should list positive integers",
+                    counts
+                ),
+            })
+            .collect(),
+        Err(_) => vec![1, 2, 4, 8],
+    }
+}
@@ -0,0 +1,36 @@
+//! Detects what the target platform offers the structures that block or keep
+//! time, and sets the crate's cfgs accordingly:
+//!
+//! - `has_clock` when `std::time::Instant` works, which it does everywhere
+//!   but on `wasm32-unknown-unknown`.
+//! - `can_block` when threads can park and wait on each other, which on
+//!   `wasm32-unknown-unknown` takes the `wasm-threads` feature and a build
+//!   with shared memory.
+
+use std::env;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let wasm_unknown = env::var("CARGO_CFG_TARGET_ARCH").unwrap() == "wasm32"
+        && env::var("CARGO_CFG_TARGET_OS").unwrap() == "unknown";
+    let atomics = env::var("CARGO_CFG_TARGET_FEATURE")
+        .unwrap_or_default()
+        .split(',')
+        .any(|feature| feature == "atomics");
+    let wasm_threads = env::var_os("CARGO_FEATURE_WASM_THREADS").is_some();
+
+    if wasm_threads && wasm_unknown && !atomics {
+        panic!(
+            "the `wasm-threads` feature needs shared memory: build with \
+             `-C target-feature=+atomics,+bulk-memory` and a std built for it"
+        );
+    }
+
+    if !wasm_unknown {
+        println!("cargo:rustc-cfg=has_clock");
+    }
+    if !wasm_unknown || (wasm_threads && atomics) {
+        println!("cargo:rustc-cfg=can_block");
+    }
+}
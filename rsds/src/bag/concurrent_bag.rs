@@ -0,0 +1,204 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::queue::TwoLockDeque;
+use crate::sync::thread_index;
+
+/// An unordered concurrent collection, for handing out work when consumers
+/// do not care which item they get.
+///
+/// The bag keeps a block of items per thread, where each block is a
+/// [`TwoLockDeque`] picked by the calling thread's index. A thread inserts
+/// into its own block and removes from it, most recent first, so that it
+/// mostly works alone on items that are still in its cache. Once its own
+/// block is empty, it steals the oldest item of another block, trying the
+/// blocks after its own in turn, so that thieves spread over their victims
+/// and lock the opposite end of a block from its owner.
+///
+/// With no order to keep, the bag scales better than any FIFO queue for
+/// pure work distribution. A count of the items lets removals from an empty
+/// bag return without visiting every block.
+///
+/// ```
+/// use rsds::bag::Bag;
+///
+/// let bag = Bag::new();
+/// std::thread::scope(|s| {
+///     for i in 0..4 {
+///         let bag = &bag;
+///         s.spawn(move || bag.insert(i));
+///     }
+/// });
+/// let mut items: Vec<_> = std::iter::from_fn(|| bag.try_remove_any()).collect();
+/// items.sort_unstable();
+/// assert_eq!(items, [0, 1, 2, 3]);
+/// assert!(bag.is_empty());
+/// ```
+pub struct Bag<T> {
+    blocks: Box<[TwoLockDeque<T>]>,
+    /// Number of items, which may run ahead of the items in the blocks while
+    /// they are being inserted.
+    len: AtomicUsize,
+}
+
+impl<T> Default for Bag<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Bag<T> {
+    /// Creates a new, empty [`Bag`] with a block per available core.
+    pub fn new() -> Self {
+        let num_blocks = std::thread::available_parallelism().map_or(8, |n| n.get());
+        Self::with_blocks(num_blocks)
+    }
+
+    /// Creates a new, empty [`Bag`] with `num_blocks` blocks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_blocks` is zero.
+    pub fn with_blocks(num_blocks: usize) -> Self {
+        assert!(
+            num_blocks > 0,
+            "number of blocks (is {}) should be positive",
+            num_blocks
+        );
+        Self {
+            blocks: (0..num_blocks).map(|_| TwoLockDeque::new()).collect(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of blocks.
+    pub fn num_blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Inserts `item` into the calling thread's block.
+    pub fn insert(&self, item: T) {
+        // Counting the item first keeps the count from dropping below zero
+        // when a thief takes it right away.
+        self.len.fetch_add(1, Ordering::SeqCst);
+        self.blocks[thread_index() % self.blocks.len()].push_back(item);
+    }
+
+    /// Removes an item from the bag, preferring the calling thread's own
+    /// block, or returns `None` if every block appears empty.
+    pub fn try_remove_any(&self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let num_blocks = self.blocks.len();
+        let own = thread_index() % num_blocks;
+        let item = self.blocks[own].pop_back().or_else(|| {
+            (1..num_blocks)
+                .map(|i| &self.blocks[(own + i) % num_blocks])
+                .find_map(TwoLockDeque::pop_front)
+        })?;
+        self.len.fetch_sub(1, Ordering::SeqCst);
+        Some(item)
+    }
+
+    /// Returns the number of items in the bag at the time of the call.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::SeqCst)
+    }
+
+    /// Checks whether the bag is empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Extend<T> for Bag<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.insert(item);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for Bag<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut bag = Self::new();
+        bag.extend(iter);
+        bag
+    }
+}
+
+impl<T> fmt::Debug for Bag<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bag")
+            .field("len", &self.len())
+            .field("num_blocks", &self.num_blocks())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bag() {
+        let bag = Bag::with_blocks(4);
+        assert_eq!(bag.try_remove_any(), None);
+        (0..10).for_each(|i| bag.insert(i.to_string()));
+        assert_eq!(bag.len(), 10);
+        // a thread gets its own items back, most recent first
+        assert_eq!(bag.try_remove_any(), Some("9".to_string()));
+
+        // another thread steals the oldest item, unless it shares our block
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                bag.insert("10".to_string());
+                assert_eq!(bag.try_remove_any(), Some("10".to_string()));
+                let item = bag.try_remove_any().unwrap();
+                assert!(item == "0" || item == "8", "{}", item);
+            });
+        });
+        assert_eq!(format!("{:?}", bag), "Bag { len: 8, num_blocks: 4, .. }");
+
+        let bag: Bag<_> = (0..100).collect();
+        let mut items: Vec<_> = std::iter::from_fn(|| bag.try_remove_any()).collect();
+        items.sort_unstable();
+        assert!(items.into_iter().eq(0..100));
+    }
+
+    #[test]
+    fn bag_concurrent() {
+        let num_thrs = 8;
+        let num_items = 10_000;
+        let bag = Bag::with_blocks(4);
+
+        let mut removed: Vec<_> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..num_thrs)
+                .map(|t| {
+                    let bag = &bag;
+                    s.spawn(move || {
+                        let mut removed = Vec::new();
+                        for i in 0..num_items {
+                            bag.insert(t * num_items + i);
+                            // every other thread only inserts, leaving its
+                            // items to be stolen
+                            if t % 2 == 0 {
+                                removed.extend(bag.try_remove_any());
+                            }
+                        }
+                        removed
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        });
+        removed.extend(std::iter::from_fn(|| bag.try_remove_any()));
+        removed.sort_unstable();
+        assert!(removed.into_iter().eq(0..num_thrs * num_items));
+        assert!(bag.is_empty());
+    }
+}
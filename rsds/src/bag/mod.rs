@@ -0,0 +1,6 @@
+//! This module contains concurrent bags, which hold items in no particular
+//! order.
+
+mod concurrent_bag;
+
+pub use concurrent_bag::Bag;
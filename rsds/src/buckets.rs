@@ -0,0 +1,151 @@
+//! Storage for elements that are appended by index and never move, shared by
+//! the structures that hand out stable addresses.
+//!
+//! Elements live in buckets of doubling sizes, each allocated in full by
+//! whichever thread first needs it, and only freed along with the storage, so
+//! a reference to an element stays valid for as long as the storage does.
+
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// Number of buckets, enough for every index a `usize` can hold.
+const NUM_BUCKETS: usize = usize::BITS as usize;
+
+/// Returns the bucket holding `index`, and the index's offset in it. Bucket
+/// `b` holds `2^b` elements, so element addresses never move.
+fn locate(index: usize) -> (usize, usize) {
+    let pos = index + 1;
+    let bucket = (usize::BITS - 1 - pos.leading_zeros()) as usize;
+    (bucket, pos - (1 << bucket))
+}
+
+/// Buckets of doubling sizes holding elements of type `T`, addressed by
+/// index.
+pub(crate) struct Buckets<T> {
+    buckets: [AtomicPtr<T>; NUM_BUCKETS],
+}
+
+// SAFETY: elements are shared between threads through `get`, and created by
+// whichever thread allocates their bucket.
+unsafe impl<T: Send> Send for Buckets<T> {}
+unsafe impl<T: Send + Sync> Sync for Buckets<T> {}
+
+impl<T> Buckets<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            buckets: [(); NUM_BUCKETS].map(|_| AtomicPtr::new(ptr::null_mut())),
+        }
+    }
+
+    /// Returns the element at `index`, or `None` if its bucket has not been
+    /// allocated yet.
+    pub(crate) fn get(&self, index: usize) -> Option<&T> {
+        let (bucket, offset) = locate(index);
+        let elems = self.buckets[bucket].load(Ordering::Acquire);
+        if elems.is_null() {
+            return None;
+        }
+        // SAFETY: an installed bucket holds `2^bucket` initialized elements,
+        // which are only freed along with the storage.
+        Some(unsafe { &*elems.add(offset) })
+    }
+
+    /// Returns the element at `index`, allocating its bucket if needed with
+    /// each element created by `init` from its index.
+    pub(crate) fn get_or_alloc<F>(&self, index: usize, init: F) -> &T
+    where
+        F: FnMut(usize) -> T,
+    {
+        let (bucket, offset) = locate(index);
+        let elems = self.bucket(bucket, init);
+        // SAFETY: as in `get`.
+        unsafe { &*elems.add(offset) }
+    }
+
+    /// Iterates over the elements of every allocated bucket, along with
+    /// their indices.
+    // Only the structures whose elements need dropping iterate over them.
+    #[cfg_attr(not(any(feature = "slab", feature = "vec")), allow(dead_code))]
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        self.buckets
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(bucket, elems)| {
+                let elems = *elems.get_mut();
+                let elems: &mut [T] = if elems.is_null() {
+                    &mut []
+                } else {
+                    // SAFETY: we have exclusive access, and the bucket holds
+                    // `2^bucket` initialized elements.
+                    unsafe { &mut *ptr::slice_from_raw_parts_mut(elems, 1 << bucket) }
+                };
+                let first = (1 << bucket) - 1;
+                (first..).zip(elems)
+            })
+    }
+
+    /// Returns the bucket with the given number, allocating it if needed.
+    fn bucket<F>(&self, bucket: usize, mut init: F) -> *mut T
+    where
+        F: FnMut(usize) -> T,
+    {
+        let elems = self.buckets[bucket].load(Ordering::Acquire);
+        if !elems.is_null() {
+            return elems;
+        }
+        let first = (1 << bucket) - 1;
+        let new: Box<[T]> = (0..1 << bucket)
+            .map(|offset| init(first + offset))
+            .collect();
+        let new = Box::into_raw(new) as *mut T;
+        match self.buckets[bucket].compare_exchange(
+            ptr::null_mut(),
+            new,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => new,
+            Err(winner) => {
+                // SAFETY: our bucket was never published.
+                drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(new, 1 << bucket)) });
+                winner
+            }
+        }
+    }
+}
+
+impl<T> Drop for Buckets<T> {
+    fn drop(&mut self) {
+        for (bucket, elems) in self.buckets.iter_mut().enumerate() {
+            let elems = *elems.get_mut();
+            if !elems.is_null() {
+                // SAFETY: we have exclusive access, and the bucket was
+                // allocated with `2^bucket` elements.
+                drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(elems, 1 << bucket)) });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets() {
+        assert_eq!(locate(0), (0, 0));
+        assert_eq!(locate(6), (2, 3));
+        assert_eq!(locate(7), (3, 0));
+
+        let mut buckets = Buckets::new();
+        assert!(buckets.get(0).is_none());
+        assert_eq!(*buckets.get_or_alloc(5, |i| i * 10), 50);
+        // the whole bucket of 3 through 6 was allocated at once
+        assert_eq!(buckets.get(3), Some(&30));
+        assert!(buckets.get(2).is_none());
+        assert_eq!(*buckets.get_or_alloc(0, |_| 7), 7);
+        assert_eq!(*buckets.get_or_alloc(5, |_| unreachable!()), 50);
+        let elems: Vec<_> = buckets.iter_mut().map(|(i, e)| (i, *e)).collect();
+        assert_eq!(elems, [(0, 7), (3, 30), (4, 40), (5, 50), (6, 60)]);
+    }
+}
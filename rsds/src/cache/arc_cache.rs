@@ -0,0 +1,310 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use super::slab_list::{List, Slab};
+use super::{EvictionPolicy, PolicyCache};
+
+/// The list an entry is on, named T1, T2, B1 and B2 in the paper.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Tier {
+    /// Entries used once since they were last cached.
+    Recent,
+    /// Entries used at least twice since they were last cached.
+    Frequent,
+    /// Keys recently evicted from `Recent`.
+    RecentGhost,
+    /// Keys recently evicted from `Frequent`.
+    FrequentGhost,
+}
+
+struct Entry<K> {
+    key: K,
+    tier: Tier,
+}
+
+/// A policy that evicts by the adaptive replacement policy of Megiddo and
+/// Modha.
+///
+/// The policy splits its keys between a tier of keys used once since they
+/// were cached, and a tier of keys used more often, both kept in LRU order.
+/// Each tier also remembers the keys it evicted last, as ghosts. When a
+/// ghost key is inserted again, the policy grows the target size of the
+/// tier that evicted it, so it adapts between favouring recency and
+/// frequency as the workload shifts, and a burst of one-off keys cannot
+/// flush entries that are used repeatedly.
+pub struct ArcPolicy<K> {
+    index: HashMap<K, usize>,
+    entries: Slab<Entry<K>>,
+    /// The lists of each tier, from most to least recently used.
+    lists: [List; 4],
+    /// The target size of the `Recent` tier, which ghost hits adapt.
+    target: usize,
+    capacity: usize,
+    /// The tier the key being inserted was found in, a ghost tier or
+    /// `Recent` for a new key, from the first call to `evict_candidate` for
+    /// it until it is cached.
+    admitting: Option<Tier>,
+}
+
+impl<K> ArcPolicy<K>
+where
+    K: Hash + Eq + Clone,
+{
+    fn tier_len(&self, tier: Tier) -> usize {
+        self.lists[tier as usize].len()
+    }
+
+    /// Returns the number of cached keys, leaving out ghosts.
+    fn len(&self) -> usize {
+        self.tier_len(Tier::Recent) + self.tier_len(Tier::Frequent)
+    }
+
+    fn push(&mut self, i: usize, tier: Tier) {
+        self.entries.get_mut(i).tier = tier;
+        self.lists[tier as usize].push_front(&mut self.entries, i);
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let tier = self.entries.get(i).tier;
+        self.lists[tier as usize].unlink(&mut self.entries, i);
+    }
+
+    /// Drops the least recently used entry of a tier entirely.
+    fn pop(&mut self, tier: Tier) -> K {
+        let i = self.lists[tier as usize]
+            .pop_back(&mut self.entries)
+            .unwrap();
+        let entry = self.entries.remove(i);
+        self.index.remove(&entry.key);
+        entry.key
+    }
+
+    /// Moves the target size of the `Recent` tier towards the tier whose
+    /// ghosts were hit, by more the fewer ghosts that tier has.
+    fn adapt(&mut self, hit: Tier) {
+        let recent_ghosts = self.tier_len(Tier::RecentGhost);
+        let frequent_ghosts = self.tier_len(Tier::FrequentGhost);
+        if hit == Tier::RecentGhost {
+            let delta = (frequent_ghosts / recent_ghosts).max(1);
+            self.target = (self.target + delta).min(self.capacity);
+        } else {
+            let delta = (recent_ghosts / frequent_ghosts).max(1);
+            self.target = self.target.saturating_sub(delta);
+        }
+    }
+
+    /// Evicts a key if the shard is full, keeping it as a ghost.
+    ///
+    /// The key is evicted from the `Recent` tier if it is over its target
+    /// size, and from the `Frequent` tier otherwise.
+    fn make_room(&mut self, frequent_ghost_hit: bool) -> Option<K> {
+        if self.len() < self.capacity {
+            return None;
+        }
+        let recent = self.tier_len(Tier::Recent);
+        let from_recent = recent > 0
+            && (recent > self.target
+                || (frequent_ghost_hit && recent == self.target)
+                || self.tier_len(Tier::Frequent) == 0);
+        let (from, to) = if from_recent {
+            (Tier::Recent, Tier::RecentGhost)
+        } else {
+            (Tier::Frequent, Tier::FrequentGhost)
+        };
+        let i = self.lists[from as usize]
+            .pop_back(&mut self.entries)
+            .unwrap();
+        let key = self.entries.get(i).key.clone();
+        self.push(i, to);
+        Some(key)
+    }
+}
+
+impl<K, V> EvictionPolicy<K, V> for ArcPolicy<K>
+where
+    K: Hash + Eq + Clone,
+{
+    fn new(capacity: usize) -> Self {
+        Self {
+            index: HashMap::with_capacity(2 * capacity),
+            entries: Slab::new(),
+            lists: [List::default(); 4],
+            target: 0,
+            capacity,
+            admitting: None,
+        }
+    }
+
+    fn on_hit(&mut self, key: &K) {
+        let Some(&i) = self.index.get(key) else {
+            return;
+        };
+        if matches!(self.entries.get(i).tier, Tier::Recent | Tier::Frequent) {
+            self.unlink(i);
+            self.push(i, Tier::Frequent);
+        }
+    }
+
+    fn evict_candidate(&mut self, key: &K, _value: &V) -> Option<K> {
+        if let Some(tier) = self.admitting {
+            return self.make_room(tier == Tier::FrequentGhost);
+        }
+
+        let ghost = self
+            .index
+            .get(key)
+            .map(|&i| (i, self.entries.get(i).tier))
+            .filter(|&(_, tier)| matches!(tier, Tier::RecentGhost | Tier::FrequentGhost));
+        if let Some((i, tier)) = ghost {
+            // the key comes back into the `Frequent` tier once cached
+            self.adapt(tier);
+            self.unlink(i);
+            self.entries.remove(i);
+            self.index.remove(key);
+            self.admitting = Some(tier);
+            return self.make_room(tier == Tier::FrequentGhost);
+        }
+
+        self.admitting = Some(Tier::Recent);
+        let recent = self.tier_len(Tier::Recent);
+        if recent + self.tier_len(Tier::RecentGhost) >= self.capacity {
+            if recent < self.capacity {
+                self.pop(Tier::RecentGhost);
+            } else {
+                // `Recent` fills the shard by itself, so its LRU entry goes
+                // without leaving a ghost
+                return Some(self.pop(Tier::Recent));
+            }
+        } else if self.index.len() >= 2 * self.capacity {
+            self.pop(Tier::FrequentGhost);
+        }
+        self.make_room(false)
+    }
+
+    fn on_insert(&mut self, key: &K, _value: &V) {
+        let tier = match self.admitting.take() {
+            Some(Tier::RecentGhost | Tier::FrequentGhost) => Tier::Frequent,
+            _ => Tier::Recent,
+        };
+        let i = self.entries.insert(Entry {
+            key: key.clone(),
+            tier,
+        });
+        self.index.insert(key.clone(), i);
+        self.push(i, tier);
+    }
+
+    fn on_remove(&mut self, key: &K) {
+        if let Some(i) = self.index.remove(key) {
+            self.unlink(i);
+            self.entries.remove(i);
+        }
+    }
+}
+
+/// A concurrent cache that evicts by the adaptive replacement policy of
+/// Megiddo and Modha.
+///
+/// ```
+/// use rsds::cache::{ArcCache, Cache};
+///
+/// let cache = ArcCache::with_shards(2, 1);
+/// cache.insert("home", 1);
+/// cache.insert("about", 2);
+/// // "home" is used again, so it moves to the frequent tier
+/// assert_eq!(cache.get(&"home"), Some(1));
+/// assert_eq!(cache.insert("blog", 3), Some(("about", 2)));
+/// ```
+pub type ArcCache<K, V, S = RandomState> = PolicyCache<K, V, ArcPolicy<K>, S>;
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::cache::Cache;
+
+    #[test]
+    fn arc_cache() {
+        let cache = ArcCache::with_shards(2, 1);
+        assert_eq!(cache.insert(1, "a"), None);
+        assert_eq!(cache.insert(2, "b"), None);
+        assert_eq!(cache.get(&1), Some("a"));
+
+        // 2 has only been used once
+        assert_eq!(cache.insert(3, "c"), Some((2, "b")));
+        assert_eq!(cache.get(&2), None);
+        // 2 comes back as a ghost hit, which makes room for a recent entry
+        // at the expense of the frequent ones
+        assert_eq!(cache.insert(2, "b"), Some((1, "a")));
+        // and a ghost hit on 1 turns that around again
+        assert_eq!(cache.insert(1, "a"), Some((3, "c")));
+        assert_eq!(cache.len(), 2);
+
+        assert_eq!(cache.remove(&3), None);
+        assert_eq!(cache.remove(&2), Some("b"));
+        assert_eq!(cache.insert(4, "d"), None);
+        assert_eq!(cache.get(&1), Some("a"));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.capacity(), 2);
+    }
+
+    #[test]
+    fn arc_cache_bounds() {
+        let capacity = 8;
+        let mut policy: ArcPolicy<u64> = EvictionPolicy::<u64, u64>::new(capacity);
+        let mut cached = HashSet::new();
+        for i in 0..10_000u64 {
+            // a mix of scans and a few repeatedly used keys
+            let key = if i % 3 == 0 { i % 5 } else { i % 50 };
+            if i % 11 == 0 {
+                cached.remove(&key);
+                EvictionPolicy::<u64, u64>::on_remove(&mut policy, &key);
+            } else if cached.contains(&key) {
+                EvictionPolicy::<u64, u64>::on_hit(&mut policy, &key);
+            } else {
+                while let Some(evicted) = policy.evict_candidate(&key, &key) {
+                    assert!(cached.remove(&evicted));
+                }
+                policy.on_insert(&key, &key);
+                cached.insert(key);
+            }
+            let recent = policy.tier_len(Tier::Recent) + policy.tier_len(Tier::RecentGhost);
+            let ghosts = policy.tier_len(Tier::RecentGhost) + policy.tier_len(Tier::FrequentGhost);
+            assert_eq!(policy.len(), cached.len());
+            assert!(policy.len() <= capacity);
+            assert!(recent <= capacity);
+            assert!(policy.len() + ghosts <= 2 * capacity);
+            assert!(policy.target <= capacity);
+            assert_eq!(policy.index.len(), policy.len() + ghosts);
+        }
+    }
+
+    #[test]
+    fn arc_cache_concurrent() {
+        let num_thrs = 8;
+        let capacity = 100;
+        let cache = ArcCache::with_shards(capacity, 4);
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let cache = &cache;
+                s.spawn(move || {
+                    for i in 0..1_000 {
+                        let key = (t * 1_000 + i) % 300;
+                        match cache.get(&key) {
+                            Some(value) => assert_eq!(value, key),
+                            None => {
+                                if let Some((evicted, value)) = cache.insert(key, key) {
+                                    assert_eq!(evicted, value);
+                                }
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        assert!(cache.len() <= capacity);
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::{EvictionPolicy, PolicyCache};
+
+struct Slot<K> {
+    key: K,
+    /// Set by hits, and cleared by the clock hand as it passes.
+    referenced: AtomicBool,
+}
+
+/// A policy that evicts by the clock algorithm, also known as second chance.
+///
+/// Keys are kept in a ring of slots with a reference bit per key, and a hand
+/// pointing into the ring. Hits set the key's bit, and to make room the hand
+/// sweeps the ring, clearing set bits, until it reaches a key whose bit is
+/// clear, which is evicted. This approximates evicting the least recently
+/// used entry, but unlike LRU leaves nothing to reorder on a hit: the bits
+/// are atomics, so the policy records hits with
+/// [`on_shared_hit`](EvictionPolicy::on_shared_hit), and reads of a
+/// [`ClockCache`] only take their shard's lock for reading, and never wait on
+/// each other.
+pub struct ClockPolicy<K> {
+    index: HashMap<K, usize>,
+    /// The clock face, which is only ever full or growing towards full,
+    /// except for slots freed by removal.
+    slots: Vec<Option<Slot<K>>>,
+    free: Vec<usize>,
+    hand: usize,
+    capacity: usize,
+}
+
+impl<K> ClockPolicy<K> {
+    /// Sweeps the hand past referenced slots, clearing their bits, and
+    /// returns the first unreferenced slot, which is the one to evict.
+    ///
+    /// Only called when every slot is occupied.
+    fn advance(&mut self) -> usize {
+        loop {
+            let i = self.hand;
+            self.hand = (self.hand + 1) % self.slots.len();
+            let slot = self.slots[i].as_mut().unwrap();
+            if !std::mem::take(slot.referenced.get_mut()) {
+                return i;
+            }
+        }
+    }
+}
+
+impl<K, V> EvictionPolicy<K, V> for ClockPolicy<K>
+where
+    K: Hash + Eq + Clone,
+{
+    fn new(capacity: usize) -> Self {
+        Self {
+            index: HashMap::with_capacity(capacity),
+            slots: Vec::with_capacity(capacity),
+            free: Vec::new(),
+            hand: 0,
+            capacity,
+        }
+    }
+
+    const SHARED_HITS: bool = true;
+
+    fn on_hit(&mut self, key: &K) {
+        if let Some(&i) = self.index.get(key) {
+            *self.slots[i].as_mut().unwrap().referenced.get_mut() = true;
+        }
+    }
+
+    fn on_shared_hit(&self, key: &K) {
+        let Some(&i) = self.index.get(key) else {
+            return;
+        };
+        let slot = self.slots[i].as_ref().unwrap();
+        // skip the store if the bit is set already, to keep hot entries'
+        // cache lines shared between readers
+        if !slot.referenced.load(Ordering::Relaxed) {
+            slot.referenced.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn evict_candidate(&mut self, _key: &K, _value: &V) -> Option<K> {
+        if !self.free.is_empty() || self.slots.len() < self.capacity {
+            return None;
+        }
+        let i = self.advance();
+        let slot = self.slots[i].take().unwrap();
+        self.index.remove(&slot.key);
+        self.free.push(i);
+        Some(slot.key)
+    }
+
+    fn on_insert(&mut self, key: &K, _value: &V) {
+        let slot = Slot {
+            key: key.clone(),
+            referenced: AtomicBool::new(false),
+        };
+        let i = if let Some(i) = self.free.pop() {
+            self.slots[i] = Some(slot);
+            i
+        } else {
+            self.slots.push(Some(slot));
+            self.slots.len() - 1
+        };
+        self.index.insert(key.clone(), i);
+    }
+
+    fn on_remove(&mut self, key: &K) {
+        if let Some(i) = self.index.remove(key) {
+            self.slots[i] = None;
+            self.free.push(i);
+        }
+    }
+}
+
+/// A concurrent cache that evicts by the clock algorithm, also known as
+/// second chance.
+///
+/// ```
+/// use rsds::cache::{Cache, ClockCache};
+///
+/// let cache = ClockCache::with_shards(2, 1);
+/// cache.insert("home", 1);
+/// cache.insert("about", 2);
+/// // "home" is visited again, so it gets a second chance
+/// assert_eq!(cache.get(&"home"), Some(1));
+/// assert_eq!(cache.insert("blog", 3), Some(("about", 2)));
+/// ```
+pub type ClockCache<K, V, S = RandomState> = PolicyCache<K, V, ClockPolicy<K>, S>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Cache;
+
+    #[test]
+    fn clock_cache() {
+        let cache = ClockCache::with_shards(3, 1);
+        assert_eq!(cache.insert(1, "a"), None);
+        assert_eq!(cache.insert(2, "b"), None);
+        assert_eq!(cache.insert(3, "c"), None);
+        assert_eq!(cache.get(&1), Some("a"));
+
+        // the hand clears 1's bit and moves on to 2
+        assert_eq!(cache.insert(4, "d"), Some((2, "b")));
+        assert_eq!(cache.insert(5, "e"), Some((3, "c")));
+        // 1 has had its second chance
+        assert_eq!(cache.get(&4), Some("d"));
+        assert_eq!(cache.insert(6, "f"), Some((1, "a")));
+        assert_eq!(cache.len(), 3);
+
+        // removal frees a slot, so the next insertion evicts nothing
+        assert_eq!(cache.remove(&5), Some("e"));
+        assert_eq!(cache.remove(&5), None);
+        assert_eq!(cache.insert(7, "g"), None);
+        assert_eq!(cache.get(&7), Some("g"));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.capacity(), 3);
+    }
+
+    #[test]
+    fn clock_cache_concurrent() {
+        let num_thrs = 8;
+        let capacity = 100;
+        let cache = ClockCache::with_shards(capacity, 4);
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let cache = &cache;
+                s.spawn(move || {
+                    for i in 0..1_000 {
+                        let key = (t * 1_000 + i) % 300;
+                        if let Some(value) = cache.get(&key) {
+                            assert_eq!(value, key);
+                        } else if let Some((evicted, value)) = cache.insert(key, key) {
+                            assert_eq!(evicted, value);
+                        }
+                        if i % 7 == 0 {
+                            cache.remove(&key);
+                        }
+                    }
+                });
+            }
+        });
+        assert!(cache.len() <= capacity);
+    }
+}
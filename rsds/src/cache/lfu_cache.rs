@@ -0,0 +1,180 @@
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+use super::slab_list::{List, Slab};
+use super::{EvictionPolicy, PolicyCache};
+
+struct Entry<K> {
+    key: K,
+    freq: u64,
+}
+
+/// A policy that evicts the least frequently used entry, by the O(1) LFU
+/// algorithm of Shah, Mitra and Matani.
+///
+/// The policy counts the hits on each key and evicts the key with the fewest,
+/// breaking ties by evicting the least recently used. Keys are kept in a list
+/// per hit count, so every operation takes constant time apart from hashing.
+pub struct LfuPolicy<K> {
+    index: HashMap<K, usize>,
+    entries: Slab<Entry<K>>,
+    /// Entries by access count, each list from most to least recently used.
+    freqs: BTreeMap<u64, List>,
+    capacity: usize,
+}
+
+impl<K> LfuPolicy<K> {
+    /// Moves an entry to the list for one more access.
+    fn touch(&mut self, i: usize) {
+        let freq = self.entries.get(i).freq;
+        self.unlink(i);
+        self.entries.get_mut(i).freq = freq + 1;
+        self.link(i);
+    }
+
+    fn link(&mut self, i: usize) {
+        let freq = self.entries.get(i).freq;
+        self.freqs
+            .entry(freq)
+            .or_default()
+            .push_front(&mut self.entries, i);
+    }
+
+    fn unlink(&mut self, i: usize) {
+        let freq = self.entries.get(i).freq;
+        let list = self.freqs.get_mut(&freq).unwrap();
+        list.unlink(&mut self.entries, i);
+        if list.is_empty() {
+            self.freqs.remove(&freq);
+        }
+    }
+}
+
+impl<K, V> EvictionPolicy<K, V> for LfuPolicy<K>
+where
+    K: Hash + Eq + Clone,
+{
+    fn new(capacity: usize) -> Self {
+        Self {
+            index: HashMap::with_capacity(capacity),
+            entries: Slab::new(),
+            freqs: BTreeMap::new(),
+            capacity,
+        }
+    }
+
+    fn on_hit(&mut self, key: &K) {
+        if let Some(&i) = self.index.get(key) {
+            self.touch(i);
+        }
+    }
+
+    /// Evicts the least recently used of the least frequently used entries.
+    fn evict_candidate(&mut self, _key: &K, _value: &V) -> Option<K> {
+        if self.index.len() < self.capacity {
+            return None;
+        }
+        let mut least = self.freqs.first_entry()?;
+        let i = least.get_mut().pop_back(&mut self.entries).unwrap();
+        if least.get().is_empty() {
+            least.remove();
+        }
+        let entry = self.entries.remove(i);
+        self.index.remove(&entry.key);
+        Some(entry.key)
+    }
+
+    fn on_insert(&mut self, key: &K, _value: &V) {
+        let i = self.entries.insert(Entry {
+            key: key.clone(),
+            freq: 1,
+        });
+        self.index.insert(key.clone(), i);
+        self.link(i);
+    }
+
+    fn on_remove(&mut self, key: &K) {
+        if let Some(i) = self.index.remove(key) {
+            self.unlink(i);
+            self.entries.remove(i);
+        }
+    }
+}
+
+/// A concurrent cache that evicts the least frequently used entries.
+///
+/// Since shards evict on their own, an entry may be evicted while another
+/// shard holds entries with fewer accesses.
+///
+/// ```
+/// use rsds::cache::{Cache, LfuCache};
+///
+/// let cache = LfuCache::with_shards(2, 1);
+/// cache.insert("home", 1);
+/// cache.insert("about", 2);
+/// // "home" is visited again, so "about" is evicted to make room
+/// assert_eq!(cache.get(&"home"), Some(1));
+/// assert_eq!(cache.insert("blog", 3), Some(("about", 2)));
+/// ```
+pub type LfuCache<K, V, S = RandomState> = PolicyCache<K, V, LfuPolicy<K>, S>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Cache;
+
+    #[test]
+    fn lfu_cache() {
+        let cache = LfuCache::with_shards(3, 1);
+        assert_eq!(cache.insert(1, "a"), None);
+        assert_eq!(cache.insert(2, "b"), None);
+        assert_eq!(cache.insert(3, "c"), None);
+        assert_eq!(cache.get(&1), Some("a"));
+        assert_eq!(cache.get(&1), Some("a"));
+        assert_eq!(cache.get(&2), Some("b"));
+        assert_eq!(cache.len(), 3);
+
+        // 3 has the fewest accesses
+        assert_eq!(cache.insert(4, "d"), Some((3, "c")));
+        // 4 is new, so it goes next despite being the most recent
+        assert_eq!(cache.insert(5, "e"), Some((4, "d")));
+        // overwriting counts as an access, and 2 is used less recently than 5
+        assert_eq!(cache.insert(5, "f"), None);
+        assert_eq!(cache.insert(6, "g"), Some((2, "b")));
+
+        assert_eq!(cache.remove(&1), Some("a"));
+        assert_eq!(cache.remove(&1), None);
+        assert_eq!(cache.get(&5), Some("f"));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.capacity(), 3);
+    }
+
+    #[test]
+    fn lfu_cache_concurrent() {
+        let num_thrs = 8;
+        let capacity = 100;
+        let cache = LfuCache::with_shards(capacity, 4);
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let cache = &cache;
+                s.spawn(move || {
+                    for i in 0..1_000 {
+                        // a few hot keys, hit by every thread, and many cold ones
+                        let hot = i % 10;
+                        cache.insert(hot, hot);
+                        cache.get(&hot);
+                        let cold = 10 + t * 1_000 + i;
+                        cache.insert(cold, cold);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(cache.len(), capacity);
+        for hot in 0..10 {
+            assert_eq!(cache.get(&hot), Some(hot));
+        }
+    }
+}
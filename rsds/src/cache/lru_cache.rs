@@ -0,0 +1,103 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use super::slab_list::{List, Slab};
+use super::{EvictionPolicy, PolicyCache};
+
+/// A policy that evicts the least recently used entry.
+///
+/// Keys are kept in a list from most to least recently used, which every
+/// hit moves its key to the front of, so every operation takes constant time
+/// apart from hashing.
+pub struct LruPolicy<K> {
+    index: HashMap<K, usize>,
+    keys: Slab<K>,
+    list: List,
+    capacity: usize,
+}
+
+impl<K, V> EvictionPolicy<K, V> for LruPolicy<K>
+where
+    K: Hash + Eq + Clone,
+{
+    fn new(capacity: usize) -> Self {
+        Self {
+            index: HashMap::with_capacity(capacity),
+            keys: Slab::new(),
+            list: List::default(),
+            capacity,
+        }
+    }
+
+    fn on_hit(&mut self, key: &K) {
+        if let Some(&i) = self.index.get(key) {
+            self.list.unlink(&mut self.keys, i);
+            self.list.push_front(&mut self.keys, i);
+        }
+    }
+
+    fn evict_candidate(&mut self, _key: &K, _value: &V) -> Option<K> {
+        if self.index.len() < self.capacity {
+            return None;
+        }
+        let i = self.list.pop_back(&mut self.keys)?;
+        let key = self.keys.remove(i);
+        self.index.remove(&key);
+        Some(key)
+    }
+
+    fn on_insert(&mut self, key: &K, _value: &V) {
+        let i = self.keys.insert(key.clone());
+        self.index.insert(key.clone(), i);
+        self.list.push_front(&mut self.keys, i);
+    }
+
+    fn on_remove(&mut self, key: &K) {
+        if let Some(i) = self.index.remove(key) {
+            self.list.unlink(&mut self.keys, i);
+            self.keys.remove(i);
+        }
+    }
+}
+
+/// A concurrent cache that evicts the least recently used entries.
+///
+/// ```
+/// use rsds::cache::{Cache, LruCache};
+///
+/// let cache = LruCache::with_shards(2, 1);
+/// cache.insert("home", 1);
+/// cache.insert("about", 2);
+/// // "home" is visited again, so "about" is evicted to make room
+/// assert_eq!(cache.get(&"home"), Some(1));
+/// assert_eq!(cache.insert("blog", 3), Some(("about", 2)));
+/// ```
+pub type LruCache<K, V, S = RandomState> = PolicyCache<K, V, LruPolicy<K>, S>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::Cache;
+
+    #[test]
+    fn lru_cache() {
+        let cache = LruCache::with_shards(3, 1);
+        assert_eq!(cache.insert(1, "a"), None);
+        assert_eq!(cache.insert(2, "b"), None);
+        assert_eq!(cache.insert(3, "c"), None);
+        assert_eq!(cache.get(&1), Some("a"));
+
+        assert_eq!(cache.insert(4, "d"), Some((2, "b")));
+        // overwriting counts as a use
+        assert_eq!(cache.insert(3, "e"), None);
+        assert_eq!(cache.insert(5, "f"), Some((1, "a")));
+
+        assert_eq!(cache.remove(&4), Some("d"));
+        assert_eq!(cache.remove(&4), None);
+        assert_eq!(cache.insert(6, "g"), None);
+        assert_eq!(cache.get(&3), Some("e"));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.capacity(), 3);
+    }
+}
@@ -0,0 +1,174 @@
+//! This module contains concurrent bounded caches, which evict entries to
+//! stay within their capacity.
+//!
+//! Every cache is a [`PolicyCache`], which keeps its entries in a
+//! [`StripedHashMap`](crate::map::StripedHashMap) and leaves the choice of
+//! what to evict to an [`EvictionPolicy`]. The policies of LRU, LFU, clock
+//! and ARC come with aliases naming the caches they make, and other policies
+//! plug in the same way.
+
+mod arc_cache;
+mod clock_cache;
+mod lfu_cache;
+mod lru_cache;
+mod policy_cache;
+mod slab_list;
+
+pub use arc_cache::{ArcCache, ArcPolicy};
+pub use clock_cache::{ClockCache, ClockPolicy};
+pub use lfu_cache::{LfuCache, LfuPolicy};
+pub use lru_cache::{LruCache, LruPolicy};
+pub use policy_cache::PolicyCache;
+
+use std::hash::{BuildHasher, Hash};
+
+use crossbeam::utils::CachePadded;
+
+use crate::stats::Counters;
+
+/// Common functionalities for bounded caches.
+pub trait Cache {
+    /// Key type for a cache implementation.
+    type Key;
+    /// Value type for a cache implementation.
+    type Val;
+
+    /// Returns a copy of the value associated with a key, if it exists, and
+    /// records the access for the eviction policy.
+    fn get(&self, key: &Self::Key) -> Option<Self::Val>;
+
+    /// Inserts a key-value pair into the cache, overwriting any value already
+    /// associated with the key.
+    ///
+    /// If the cache had to make room for a new key, the evicted key-value pair
+    /// is returned.
+    fn insert(&self, key: Self::Key, value: Self::Val) -> Option<(Self::Key, Self::Val)>;
+
+    /// Removes the value associated with a key, returning it if it existed.
+    fn remove(&self, key: &Self::Key) -> Option<Self::Val>;
+
+    /// Returns the number of entries in the cache.
+    fn len(&self) -> usize;
+
+    /// Returns the maximum number of entries the cache holds.
+    fn capacity(&self) -> usize;
+
+    /// Checks whether the cache is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Decides which entries a [`PolicyCache`] evicts, by tracking the keys of a
+/// shard as they are cached, read and removed.
+///
+/// Each shard of the cache has a policy of its own, which it only calls under
+/// the shard's lock, and only with keys of that shard. The lock is held for
+/// writing, except around [`EvictionPolicy::on_shared_hit`]. Reads are looked up
+/// before the lock is taken, so a policy may be told of a hit on a key it has
+/// just had evicted, and should ignore it.
+///
+/// A policy may weigh entries by their values rather than count them, taking
+/// its capacity to be in whatever unit it weighs them in.
+pub trait EvictionPolicy<K, V> {
+    /// Creates a policy for a shard holding up to `capacity` entries.
+    fn new(capacity: usize) -> Self
+    where
+        Self: Sized;
+
+    /// Whether the cache records reads by [`EvictionPolicy::on_shared_hit`],
+    /// so that reads of a shard only take its lock for reading, and never
+    /// wait on each other.
+    const SHARED_HITS: bool = false;
+
+    /// Records a read of a cached key.
+    fn on_hit(&mut self, key: &K);
+
+    /// Records a read of a cached key with only shared access to the policy,
+    /// in place of [`EvictionPolicy::on_hit`] if
+    /// [`SHARED_HITS`](EvictionPolicy::SHARED_HITS) is set.
+    fn on_shared_hit(&self, _key: &K) {}
+
+    /// Records that a cached key's value was overwritten with `value`, which
+    /// counts as a read unless overridden.
+    fn on_update(&mut self, key: &K, _value: &V) {
+        self.on_hit(key);
+    }
+
+    /// Returns a cached key to evict to make room for `key`, which is not
+    /// cached, and its value, or `None` if there is room for them.
+    ///
+    /// The cache calls this before caching each new key, and again after
+    /// each eviction until it returns `None`. The key returned is evicted,
+    /// and the policy should stop tracking it as cached.
+    fn evict_candidate(&mut self, key: &K, value: &V) -> Option<K>;
+
+    /// Records that `key` was cached with `value`, once room was made for it.
+    fn on_insert(&mut self, key: &K, value: &V);
+
+    /// Records that `key` was removed, whether or not it was cached.
+    fn on_remove(&mut self, key: &K);
+}
+
+/// Independently locked shards of a cache, which keys are spread over by
+/// hash. Each shard is of type `L`, which wraps the shard's state in a lock.
+///
+/// The capacity is split as evenly as possible between the shards, and each
+/// shard evicts on its own, so the cache as a whole only approximates its
+/// eviction policy.
+struct Shards<L, S> {
+    shards: Box<[CachePadded<L>]>,
+    hasher: S,
+    capacity: usize,
+    counters: Counters,
+}
+
+impl<L, S: BuildHasher + Clone> Shards<L, S> {
+    /// Creates up to `num_shards` shards, using `new_shard` to create a shard
+    /// from its capacity and a copy of the hasher.
+    fn new<F>(capacity: usize, num_shards: usize, hasher: S, mut new_shard: F) -> Self
+    where
+        F: FnMut(usize, S) -> L,
+    {
+        assert!(
+            capacity > 0,
+            "capacity (is {}) should be positive",
+            capacity
+        );
+        assert!(
+            num_shards > 0,
+            "number of shards (is {}) should be positive",
+            num_shards
+        );
+        // every shard holds at least one entry
+        let num_shards = num_shards.min(capacity);
+        let shards = (0..num_shards)
+            .map(|i| {
+                let shard_capacity = capacity / num_shards + usize::from(i < capacity % num_shards);
+                CachePadded::new(new_shard(shard_capacity, hasher.clone()))
+            })
+            .collect();
+        Self {
+            shards,
+            hasher,
+            capacity,
+            counters: Counters::new(),
+        }
+    }
+
+    /// Returns the shard that `key` belongs to.
+    fn get<K: Hash>(&self, key: &K) -> &L {
+        // the cache's map hashes with the same hasher, so pick the shard by
+        // the high bits, leaving the low bits it indexes buckets by
+        let hash = self.hasher.hash_one(key) >> 32;
+        &self.shards[hash as usize % self.shards.len()]
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &L> {
+        self.shards.iter().map(|shard| &**shard)
+    }
+}
+
+fn default_num_shards() -> usize {
+    std::thread::available_parallelism().map_or(8, |n| n.get())
+}
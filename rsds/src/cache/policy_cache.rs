@@ -0,0 +1,319 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::sync::RwLock;
+
+use super::{default_num_shards, Cache, EvictionPolicy, Shards};
+use crate::map::{Map, MapMut, StripedHashMap};
+use crate::stats::{Statistics, Stats};
+use crate::trace::event;
+
+/// One shard's policy, and the number of entries it has cached.
+struct PolicyShard<P> {
+    policy: P,
+    len: usize,
+}
+
+/// A concurrent cache that evicts as set by an [`EvictionPolicy`] `P`.
+///
+/// Entries are kept in a [`StripedHashMap`], and their keys spread over
+/// shards, each with a policy of its own behind a lock. Reads look values up
+/// in the map and then take the key's shard lock to record the hit, for
+/// reading only if the policy sets [`EvictionPolicy::SHARED_HITS`], while
+/// writes hold it for writing throughout, so a shard's policy always agrees
+/// with the map about which of its keys are cached. Since shards evict on their own, the
+/// cache as a whole only approximates its policy.
+///
+/// [`LruCache`](super::LruCache), [`LfuCache`](super::LfuCache),
+/// [`ClockCache`](super::ClockCache) and [`ArcCache`](super::ArcCache) name
+/// the caches of the policies this module provides.
+///
+/// ```
+/// use rsds::cache::{Cache, LruPolicy, PolicyCache};
+///
+/// let cache = PolicyCache::<_, _, LruPolicy<_>>::with_shards(2, 1);
+/// cache.insert("home", 1);
+/// cache.insert("about", 2);
+/// assert_eq!(cache.get(&"home"), Some(1));
+/// assert_eq!(cache.insert("blog", 3), Some(("about", 2)));
+/// ```
+pub struct PolicyCache<K: Hash + PartialEq, V, P, S = RandomState> {
+    map: StripedHashMap<K, V, S>,
+    shards: Shards<RwLock<PolicyShard<P>>, S>,
+}
+
+impl<K, V, P> PolicyCache<K, V, P, RandomState>
+where
+    K: Hash + Eq + Clone,
+    P: EvictionPolicy<K, V>,
+{
+    /// Creates a new, empty [`PolicyCache`] holding up to `capacity` entries,
+    /// with a shard per available core.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        Self::with_shards(capacity, default_num_shards())
+    }
+
+    /// Creates a new, empty [`PolicyCache`] holding up to `capacity` entries
+    /// in up to `num_shards` shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` or `num_shards` is zero.
+    pub fn with_shards(capacity: usize, num_shards: usize) -> Self {
+        Self::with_shards_and_hasher(capacity, num_shards, RandomState::new())
+    }
+}
+
+impl<K, V, P, S> PolicyCache<K, V, P, S>
+where
+    K: Hash + Eq + Clone,
+    P: EvictionPolicy<K, V>,
+    S: BuildHasher + Clone,
+{
+    /// Creates a new, empty [`PolicyCache`] holding up to `capacity` entries
+    /// in up to `num_shards` shards, with a given hasher.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` or `num_shards` is zero.
+    pub fn with_shards_and_hasher(capacity: usize, num_shards: usize, hasher: S) -> Self {
+        Self {
+            map: StripedHashMap::with_hasher(hasher.clone()),
+            shards: Shards::new(capacity, num_shards, hasher, |capacity, _| {
+                RwLock::new(PolicyShard {
+                    policy: P::new(capacity),
+                    len: 0,
+                })
+            }),
+        }
+    }
+}
+
+impl<K, V, P, S> Cache for PolicyCache<K, V, P, S>
+where
+    K: Hash + Eq + Clone,
+    V: Clone,
+    P: EvictionPolicy<K, V>,
+    S: BuildHasher + Clone,
+{
+    type Key = K;
+    type Val = V;
+
+    fn get(&self, key: &K) -> Option<V> {
+        let value = self.map.get_with(key, V::clone);
+        if value.is_some() {
+            let shard = self.shards.get(key);
+            if P::SHARED_HITS {
+                shard.read().unwrap().policy.on_shared_hit(key);
+            } else {
+                shard.write().unwrap().policy.on_hit(key);
+            }
+        }
+        self.shards.counters.lookup(value.is_some());
+        value
+    }
+
+    /// Inserts a key-value pair into the cache, overwriting any value already
+    /// associated with the key.
+    ///
+    /// If the policy had more than one entry evicted to make room, only the
+    /// first is returned.
+    fn insert(&self, key: K, value: V) -> Option<(K, V)> {
+        self.shards.counters.op();
+        let mut shard = self.shards.get(&key).write().unwrap();
+        if self.map.contains(&key) {
+            shard.policy.on_update(&key, &value);
+            self.map.put(key, value);
+            return None;
+        }
+
+        let mut evicted = None;
+        while let Some(victim) = shard.policy.evict_candidate(&key, &value) {
+            let value = self
+                .map
+                .take(&victim)
+                .expect("eviction policy should only evict cached keys");
+            shard.len -= 1;
+            self.shards.counters.evict();
+            event!(debug, "evicted an entry");
+            evicted.get_or_insert((victim, value));
+        }
+        shard.policy.on_insert(&key, &value);
+        shard.len += 1;
+        self.map.put(key, value);
+        evicted
+    }
+
+    fn remove(&self, key: &K) -> Option<V> {
+        self.shards.counters.op();
+        let mut shard = self.shards.get(key).write().unwrap();
+        let value = self.map.take(key);
+        if value.is_some() {
+            shard.len -= 1;
+        }
+        shard.policy.on_remove(key);
+        value
+    }
+
+    fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap().len)
+            .sum()
+    }
+
+    fn capacity(&self) -> usize {
+        self.shards.capacity
+    }
+}
+
+impl<K: Hash + PartialEq, V, P, S> Statistics for PolicyCache<K, V, P, S> {
+    fn stats(&self) -> Stats {
+        self.shards.counters.snapshot()
+    }
+}
+
+impl<K: Hash + PartialEq, V, P, S> fmt::Debug for PolicyCache<K, V, P, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PolicyCache")
+            .field("policy", &std::any::type_name::<P>())
+            .field("capacity", &self.shards.capacity)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::cache::{ClockCache, LruPolicy};
+
+    /// Evicts the largest strings first, until the lengths of those cached
+    /// add up to no more than the capacity.
+    struct LargestFirst {
+        lens: HashMap<u32, usize>,
+        total: usize,
+        capacity: usize,
+    }
+
+    impl EvictionPolicy<u32, String> for LargestFirst {
+        fn new(capacity: usize) -> Self {
+            LargestFirst {
+                lens: HashMap::new(),
+                total: 0,
+                capacity,
+            }
+        }
+
+        fn on_hit(&mut self, _key: &u32) {}
+
+        fn on_update(&mut self, key: &u32, value: &String) {
+            let len = self.lens.insert(*key, value.len()).unwrap();
+            self.total = self.total - len + value.len();
+        }
+
+        fn evict_candidate(&mut self, _key: &u32, value: &String) -> Option<u32> {
+            if self.total + value.len() <= self.capacity {
+                return None;
+            }
+            let (&key, _) = self.lens.iter().max_by_key(|&(&key, &len)| (len, key))?;
+            self.on_remove(&key);
+            Some(key)
+        }
+
+        fn on_insert(&mut self, key: &u32, value: &String) {
+            self.lens.insert(*key, value.len());
+            self.total += value.len();
+        }
+
+        fn on_remove(&mut self, key: &u32) {
+            if let Some(len) = self.lens.remove(key) {
+                self.total -= len;
+            }
+        }
+    }
+
+    #[test]
+    fn policy_cache_custom_policy() {
+        let cache = PolicyCache::<_, _, LargestFirst>::with_shards(10, 1);
+        assert_eq!(cache.insert(1, "a".repeat(4)), None);
+        assert_eq!(cache.insert(2, "b".repeat(3)), None);
+        assert_eq!(cache.insert(3, "c".repeat(2)), None);
+        assert_eq!(cache.len(), 3);
+
+        // making room for 6 bytes takes evicting both 1 and 2
+        assert_eq!(cache.insert(4, "d".repeat(6)), Some((1, "a".repeat(4))));
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.remove(&3), Some("c".repeat(2)));
+        assert_eq!(cache.insert(5, "e".repeat(4)), None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.capacity(), 10);
+    }
+
+    #[test]
+    fn policy_cache_concurrent() {
+        let num_thrs = 8;
+        let capacity = 100;
+        let cache = PolicyCache::<_, _, LruPolicy<_>>::with_shards(capacity, 4);
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let cache = &cache;
+                s.spawn(move || {
+                    for i in 0..1_000 {
+                        let key = (t * 1_000 + i) % 300;
+                        if let Some(value) = cache.get(&key) {
+                            assert_eq!(value, key);
+                        } else if let Some((evicted, value)) = cache.insert(key, key) {
+                            assert_eq!(evicted, value);
+                        }
+                        if i % 5 == 0 {
+                            cache.remove(&key);
+                        }
+                    }
+                });
+            }
+        });
+        assert!(cache.len() <= capacity);
+        assert_eq!(cache.len(), cache.map.snapshot_iter().len());
+    }
+
+    #[test]
+    fn clock_cache_shared_reads() {
+        let cache = ClockCache::with_shards(2, 1);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+
+        // readers get through while the shard is held for reading, which a
+        // reader waiting on the write lock would not
+        let shard = cache.shards.get(&1).read().unwrap();
+        let (done, finished) = mpsc::channel();
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                let (cache, done) = (&cache, done.clone());
+                s.spawn(move || {
+                    for _ in 0..100 {
+                        assert_eq!(cache.get(&1), Some("a"));
+                    }
+                    done.send(()).unwrap();
+                });
+            }
+            for _ in 0..4 {
+                let received = finished.recv_timeout(Duration::from_secs(10));
+                assert!(received.is_ok(), "reader blocked on the shard lock");
+            }
+            drop(shard);
+        });
+
+        // the reads set 1's bit, so 2 goes first
+        assert_eq!(cache.insert(3, "c"), Some((2, "b")));
+    }
+}
@@ -0,0 +1,126 @@
+//! Doubly linked lists threaded through a slab, which the caches use to keep
+//! entries in recency order without allocating per entry. A slab can hold
+//! several lists, and an entry can move between them in constant time.
+
+/// Index standing for no entry.
+const NIL: usize = usize::MAX;
+
+struct Slot<T> {
+    value: Option<T>,
+    prev: usize,
+    next: usize,
+}
+
+/// Entries addressed by stable indices, each linked into at most one [`List`].
+pub(super) struct Slab<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<usize>,
+}
+
+impl<T> Slab<T> {
+    pub(super) fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Adds an unlinked entry, returning its index.
+    pub(super) fn insert(&mut self, value: T) -> usize {
+        let slot = Slot {
+            value: Some(value),
+            prev: NIL,
+            next: NIL,
+        };
+        match self.free.pop() {
+            Some(index) => {
+                self.slots[index] = slot;
+                index
+            }
+            None => {
+                self.slots.push(slot);
+                self.slots.len() - 1
+            }
+        }
+    }
+
+    /// Removes an entry, which must have been unlinked from its list.
+    pub(super) fn remove(&mut self, index: usize) -> T {
+        self.free.push(index);
+        self.slots[index].value.take().unwrap()
+    }
+
+    pub(super) fn get(&self, index: usize) -> &T {
+        self.slots[index].value.as_ref().unwrap()
+    }
+
+    pub(super) fn get_mut(&mut self, index: usize) -> &mut T {
+        self.slots[index].value.as_mut().unwrap()
+    }
+}
+
+/// A list of entries in a [`Slab`], from most to least recently pushed.
+#[derive(Clone, Copy)]
+pub(super) struct List {
+    head: usize,
+    tail: usize,
+    len: usize,
+}
+
+impl Default for List {
+    fn default() -> Self {
+        Self {
+            head: NIL,
+            tail: NIL,
+            len: 0,
+        }
+    }
+}
+
+impl List {
+    pub(super) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the least recently pushed entry.
+    pub(super) fn back(&self) -> Option<usize> {
+        (self.tail != NIL).then_some(self.tail)
+    }
+
+    /// Links an unlinked entry at the front of the list.
+    pub(super) fn push_front<T>(&mut self, slab: &mut Slab<T>, index: usize) {
+        slab.slots[index].prev = NIL;
+        slab.slots[index].next = self.head;
+        match self.head {
+            NIL => self.tail = index,
+            head => slab.slots[head].prev = index,
+        }
+        self.head = index;
+        self.len += 1;
+    }
+
+    /// Unlinks an entry of this list.
+    pub(super) fn unlink<T>(&mut self, slab: &mut Slab<T>, index: usize) {
+        let Slot { prev, next, .. } = slab.slots[index];
+        match prev {
+            NIL => self.head = next,
+            prev => slab.slots[prev].next = next,
+        }
+        match next {
+            NIL => self.tail = prev,
+            next => slab.slots[next].prev = prev,
+        }
+        self.len -= 1;
+    }
+
+    /// Unlinks and returns the least recently pushed entry.
+    pub(super) fn pop_back<T>(&mut self, slab: &mut Slab<T>) -> Option<usize> {
+        let index = self.back()?;
+        self.unlink(slab, index);
+        Some(index)
+    }
+}
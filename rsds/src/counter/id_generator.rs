@@ -0,0 +1,322 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crossbeam::utils::CachePadded;
+
+use crate::sync::thread_index;
+
+const DEFAULT_BLOCK_SIZE: u64 = 1024;
+
+/// A generator of unique 64-bit IDs that scales with the number of threads.
+///
+/// Handing out IDs from a single counter has every thread increment the same
+/// cache line. Instead, threads reserve blocks of IDs from a shared
+/// high-water mark, and hand IDs out of their block from a cache-padded cell
+/// of their own, so that the shared mark is only touched once per block.
+///
+/// IDs are unique, but are neither contiguous, as a block may be abandoned
+/// part way when threads share a cell, nor ordered across threads.
+///
+/// ```
+/// use rsds::counter::IdGenerator;
+///
+/// let ids = IdGenerator::new();
+/// let mut handed_out: Vec<_> = std::thread::scope(|s| {
+///     let ids = &ids;
+///     let handles: Vec<_> = (0..4).map(|_| s.spawn(move || ids.next_id())).collect();
+///     handles.into_iter().map(|h| h.join().unwrap()).collect()
+/// });
+/// handed_out.sort_unstable();
+/// handed_out.dedup();
+/// assert_eq!(handed_out.len(), 4);
+/// ```
+pub struct IdGenerator {
+    /// The start of the next block to reserve.
+    high_water_mark: CachePadded<AtomicU64>,
+    /// The next ID of each cell's block, which is a multiple of the block
+    /// size once the block is used up.
+    cells: Box<[CachePadded<AtomicU64>]>,
+    block_size: u64,
+}
+
+impl Default for IdGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdGenerator {
+    /// Creates a new [`IdGenerator`] with a cell per available core, which
+    /// reserves blocks of 1024 IDs.
+    pub fn new() -> Self {
+        let num_cells = std::thread::available_parallelism().map_or(8, |n| n.get());
+        Self::with_cells(num_cells, DEFAULT_BLOCK_SIZE)
+    }
+
+    /// Creates a new [`IdGenerator`] with `num_cells` cells, which reserves
+    /// blocks of `block_size` IDs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_cells` is zero, or `block_size` is not a power of two.
+    pub fn with_cells(num_cells: usize, block_size: u64) -> Self {
+        assert!(
+            num_cells > 0,
+            "number of cells (is {}) should be positive",
+            num_cells
+        );
+        assert!(
+            block_size.is_power_of_two(),
+            "block size (is {}) should be a power of two",
+            block_size
+        );
+        Self {
+            high_water_mark: CachePadded::new(AtomicU64::new(0)),
+            cells: (0..num_cells)
+                .map(|_| CachePadded::new(AtomicU64::new(0)))
+                .collect(),
+            block_size,
+        }
+    }
+
+    /// Returns a new ID, distinct from every other ID this generator hands
+    /// out.
+    pub fn next_id(&self) -> u64 {
+        let cell = &self.cells[thread_index() % self.cells.len()];
+        let mut next = cell.load(Ordering::Relaxed);
+        while next & (self.block_size - 1) != 0 {
+            match cell.compare_exchange_weak(next, next + 1, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => return next,
+                Err(current) => next = current,
+            }
+        }
+        // The cell's block is used up, so reserve a new one, and hand out its
+        // first ID. If another thread sharing the cell refilled it first, the
+        // rest of our block goes unused.
+        let start = self
+            .high_water_mark
+            .fetch_add(self.block_size, Ordering::Relaxed);
+        let _ = cell.compare_exchange(next, start + 1, Ordering::Relaxed, Ordering::Relaxed);
+        start
+    }
+
+    /// Returns the number of IDs reserved so far, which all IDs handed out
+    /// are below.
+    pub fn reserved(&self) -> u64 {
+        self.high_water_mark.load(Ordering::Relaxed)
+    }
+}
+
+impl fmt::Debug for IdGenerator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdGenerator")
+            .field("block_size", &self.block_size)
+            .field("reserved", &self.reserved())
+            .finish_non_exhaustive()
+    }
+}
+
+const MACHINE_BITS: u32 = 10;
+const SEQUENCE_BITS: u32 = 12;
+/// Milliseconds from the Unix epoch to 2020-01-01, the default epoch that
+/// snowflake timestamps count from.
+const DEFAULT_EPOCH_MILLIS: u64 = 1_577_836_800_000;
+/// The most cells a [`Snowflake`] splits the sequence numbers between, so
+/// that each cell can still hand out 16 IDs per millisecond.
+const MAX_SNOWFLAKE_CELLS: usize = 1 << 8;
+
+/// A generator of unique, roughly time-ordered 64-bit IDs in the style of
+/// Twitter's Snowflake.
+///
+/// From the most significant bit down, an ID holds a zero bit, 41 bits of
+/// milliseconds since the generator's epoch, a 10-bit machine ID, and a
+/// 12-bit sequence number, so that generators with distinct machine IDs
+/// never hand out the same ID.
+///
+/// As with [`IdGenerator`], threads hand out IDs from cache-padded cells of
+/// their own: the low bits of the sequence number are the cell's index, and
+/// each cell counts in the remaining bits. A cell that runs out of sequence
+/// numbers within a millisecond borrows the next one, so IDs from a cell
+/// always increase, even if the clock goes backwards, and may run ahead of
+/// the clock under bursts.
+///
+/// ```
+/// use rsds::counter::Snowflake;
+///
+/// let ids = Snowflake::new(7);
+/// let (a, b) = (ids.next_id(), ids.next_id());
+/// assert!(a < b);
+/// assert_eq!(Snowflake::machine_id_of(a), 7);
+/// ```
+pub struct Snowflake {
+    /// The timestamp and counter of the last ID each cell handed out, laid
+    /// out as in the IDs.
+    cells: Box<[CachePadded<AtomicU64>]>,
+    cell_bits: u32,
+    machine_id: u64,
+    epoch: SystemTime,
+}
+
+impl Snowflake {
+    /// Creates a new [`Snowflake`] with the given machine ID, whose
+    /// timestamps count from 2020-01-01.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `machine_id` does not fit in 10 bits.
+    pub fn new(machine_id: u16) -> Self {
+        Self::with_epoch(
+            machine_id,
+            UNIX_EPOCH + Duration::from_millis(DEFAULT_EPOCH_MILLIS),
+        )
+    }
+
+    /// Creates a new [`Snowflake`] with the given machine ID, whose
+    /// timestamps count from `epoch`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `machine_id` does not fit in 10 bits.
+    pub fn with_epoch(machine_id: u16, epoch: SystemTime) -> Self {
+        assert!(
+            machine_id < 1 << MACHINE_BITS,
+            "machine ID (is {}) should fit in {} bits",
+            machine_id,
+            MACHINE_BITS
+        );
+        let num_cells = std::thread::available_parallelism()
+            .map_or(8, |n| n.get())
+            .next_power_of_two()
+            .min(MAX_SNOWFLAKE_CELLS);
+        Self {
+            cells: (0..num_cells)
+                .map(|_| CachePadded::new(AtomicU64::new(0)))
+                .collect(),
+            cell_bits: num_cells.trailing_zeros(),
+            machine_id: machine_id as u64,
+            epoch,
+        }
+    }
+
+    /// Returns a new ID, distinct from every other ID this generator hands
+    /// out.
+    pub fn next_id(&self) -> u64 {
+        let index = thread_index() % self.cells.len();
+        let cell = &self.cells[index];
+        let count_bits = SEQUENCE_BITS - self.cell_bits;
+        let now = self.millis() << count_bits;
+        let mut last = cell.load(Ordering::Relaxed);
+        loop {
+            // The counter carries over into the timestamp when it runs out.
+            let next = now.max(last + 1);
+            match cell.compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => {
+                    let millis = next >> count_bits;
+                    let count = next & ((1 << count_bits) - 1);
+                    return millis << (MACHINE_BITS + SEQUENCE_BITS)
+                        | self.machine_id << SEQUENCE_BITS
+                        | count << self.cell_bits
+                        | index as u64;
+                }
+                Err(current) => last = current,
+            }
+        }
+    }
+
+    /// Returns the machine ID of the generator.
+    pub fn machine_id(&self) -> u16 {
+        self.machine_id as u16
+    }
+
+    /// Returns the machine ID of the generator that handed out `id`.
+    pub fn machine_id_of(id: u64) -> u16 {
+        (id >> SEQUENCE_BITS & ((1 << MACHINE_BITS) - 1)) as u16
+    }
+
+    /// Returns the time that `id`, handed out by this generator, was stamped
+    /// with.
+    pub fn timestamp_of(&self, id: u64) -> SystemTime {
+        self.epoch + Duration::from_millis(id >> (MACHINE_BITS + SEQUENCE_BITS))
+    }
+
+    /// Returns the milliseconds since the epoch, or zero before it.
+    fn millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(self.epoch)
+            .map_or(0, |elapsed| elapsed.as_millis() as u64)
+    }
+}
+
+impl fmt::Debug for Snowflake {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Snowflake")
+            .field("machine_id", &self.machine_id)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    /// Hands out IDs from many threads, checking that they are unique.
+    fn check_unique(next_id: impl Fn() -> u64 + Sync) -> HashSet<u64> {
+        let num_thrs = 8;
+        let num_ids = 10_000;
+        let ids: Vec<Vec<u64>> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..num_thrs)
+                .map(|_| s.spawn(|| (0..num_ids).map(|_| next_id()).collect()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let unique: HashSet<_> = ids.iter().flatten().copied().collect();
+        assert_eq!(unique.len(), num_thrs * num_ids);
+        unique
+    }
+
+    #[test]
+    fn id_generator() {
+        let ids = IdGenerator::with_cells(2, 4);
+        let first: Vec<_> = (0..5).map(|_| ids.next_id()).collect();
+        assert_eq!(first, [0, 1, 2, 3, 4]);
+        assert_eq!(ids.reserved(), 8);
+        assert_eq!(
+            format!("{:?}", ids),
+            "IdGenerator { block_size: 4, reserved: 8, .. }"
+        );
+
+        // a block per ID degenerates into a single counter
+        let ids = IdGenerator::with_cells(3, 1);
+        assert_eq!((ids.next_id(), ids.next_id()), (0, 1));
+    }
+
+    #[test]
+    fn id_generator_concurrent() {
+        // fewer cells than threads, so that some share
+        let ids = IdGenerator::with_cells(3, 16);
+        let unique = check_unique(|| ids.next_id());
+        assert!(unique.iter().all(|&id| id < ids.reserved()));
+    }
+
+    #[test]
+    fn snowflake() {
+        let epoch = SystemTime::now();
+        let ids = Snowflake::with_epoch(1023, epoch);
+        // IDs from a thread increase
+        let (a, b) = (ids.next_id(), ids.next_id());
+        assert!(a < b);
+        let unique = check_unique(|| ids.next_id());
+        assert!(unique
+            .iter()
+            .all(|&id| Snowflake::machine_id_of(id) == 1023));
+        // IDs are stamped with about the current time, unless bursts ran
+        // ahead of the clock
+        let stamped = ids.timestamp_of(ids.next_id());
+        assert!(stamped >= epoch);
+        assert!(stamped.duration_since(epoch).unwrap() < Duration::from_secs(60));
+        assert_eq!(ids.machine_id(), 1023);
+    }
+}
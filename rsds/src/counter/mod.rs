@@ -0,0 +1,10 @@
+//! This module contains scalable concurrent counters, accumulators, and ID
+//! generators.
+
+mod id_generator;
+mod striped_accumulator;
+mod striped_counter;
+
+pub use id_generator::{IdGenerator, Snowflake};
+pub use striped_accumulator::StripedAccumulator;
+pub use striped_counter::StripedCounter;
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+use crossbeam::utils::CachePadded;
+
+use crate::sync::thread_index;
+
+/// An accumulator striped over cache-padded cells, in the style of Java's
+/// `LongAccumulator`.
+///
+/// Values are combined with `f`, which must be associative and commutative,
+/// with `identity` as its identity element: e.g. `+` and `0.0` to sum floats,
+/// or `max` and `i64::MIN` to track a maximum. Each thread accumulates into a
+/// cell of its own, moving on to another cell if it finds its own busy, and
+/// [`StripedAccumulator::get`] folds the cells together.
+///
+/// For plain integer counting, [`StripedCounter`](super::StripedCounter)
+/// is cheaper, as its cells are atomics rather than locks.
+///
+/// ```
+/// use rsds::counter::StripedAccumulator;
+///
+/// let max = StripedAccumulator::new(i64::MIN, i64::max);
+/// std::thread::scope(|s| {
+///     for i in 0..4 {
+///         let max = &max;
+///         s.spawn(move || max.accumulate(i));
+///     }
+/// });
+/// assert_eq!(max.get(), 3);
+/// ```
+pub struct StripedAccumulator<T, F> {
+    cells: Box<[CachePadded<Mutex<T>>]>,
+    identity: T,
+    f: F,
+}
+
+impl<T, F> StripedAccumulator<T, F>
+where
+    T: Clone,
+    F: Fn(T, T) -> T,
+{
+    /// Creates a new [`StripedAccumulator`] with a cell per available core.
+    pub fn new(identity: T, f: F) -> Self {
+        let num_cells = std::thread::available_parallelism().map_or(8, |n| n.get());
+        Self::with_cells(num_cells, identity, f)
+    }
+
+    /// Creates a new [`StripedAccumulator`] with `num_cells` cells.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_cells` is zero.
+    pub fn with_cells(num_cells: usize, identity: T, f: F) -> Self {
+        assert!(
+            num_cells > 0,
+            "number of cells (is {}) should be positive",
+            num_cells
+        );
+        Self {
+            cells: (0..num_cells)
+                .map(|_| CachePadded::new(Mutex::new(identity.clone())))
+                .collect(),
+            identity,
+            f,
+        }
+    }
+
+    /// Combines `value` into the accumulator.
+    pub fn accumulate(&self, value: T) {
+        let mut cell = self.lock_cell();
+        let current = std::mem::replace(&mut *cell, self.identity.clone());
+        *cell = (self.f)(current, value);
+    }
+
+    /// Returns the combination of every value accumulated so far.
+    ///
+    /// Values accumulated concurrently with the call may or may not be
+    /// included.
+    pub fn get(&self) -> T {
+        self.cells.iter().fold(self.identity.clone(), |acc, cell| {
+            (self.f)(acc, cell.lock().unwrap().clone())
+        })
+    }
+
+    /// Resets the accumulator to its identity, returning the combination it
+    /// had.
+    ///
+    /// Values accumulated concurrently land either in the returned value or
+    /// in the reset accumulator, never in both or neither.
+    pub fn get_and_reset(&self) -> T {
+        self.cells.iter().fold(self.identity.clone(), |acc, cell| {
+            let value = std::mem::replace(&mut *cell.lock().unwrap(), self.identity.clone());
+            (self.f)(acc, value)
+        })
+    }
+
+    /// Locks the calling thread's cell, or the first free one after it if it
+    /// is busy, or else waits for the thread's own cell.
+    fn lock_cell(&self) -> MutexGuard<'_, T> {
+        let start = thread_index();
+        for i in 0..self.cells.len() {
+            match self.cells[(start + i) % self.cells.len()].try_lock() {
+                Ok(cell) => return cell,
+                Err(TryLockError::WouldBlock) => {}
+                Err(TryLockError::Poisoned(err)) => panic!("{}", err),
+            }
+        }
+        self.cells[start % self.cells.len()].lock().unwrap()
+    }
+}
+
+impl<T, F> fmt::Debug for StripedAccumulator<T, F>
+where
+    T: Clone + fmt::Debug,
+    F: Fn(T, T) -> T,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StripedAccumulator")
+            .field(&self.get())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn striped_accumulator() {
+        let num_thrs = 8;
+        let num_ops = 10_000;
+        let sum = StripedAccumulator::with_cells(4, 0.0, |a: f64, b| a + b);
+        let min = StripedAccumulator::new(usize::MAX, usize::min);
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let (sum, min) = (&sum, &min);
+                s.spawn(move || {
+                    for i in 0..num_ops {
+                        sum.accumulate(0.5);
+                        min.accumulate(t * num_ops + i + 1);
+                    }
+                });
+            }
+        });
+
+        assert_eq!(sum.get(), 0.5 * (num_thrs * num_ops) as f64);
+        assert_eq!(min.get(), 1);
+        assert_eq!(format!("{:?}", min), "StripedAccumulator(1)");
+        assert_eq!(min.get_and_reset(), 1);
+        assert_eq!(min.get(), usize::MAX);
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crossbeam::utils::CachePadded;
+
+use crate::sync::thread_index;
+
+/// A counter striped over cache-padded cells, in the style of Java's
+/// `LongAdder`.
+///
+/// Each thread updates a cell of its own, so concurrent updates rarely touch
+/// the same cache line, and [`StripedCounter::sum`] folds the cells together.
+/// This trades more expensive reads for updates that scale, which suits
+/// counters that are bumped constantly and read rarely, such as metrics.
+///
+/// Cells wrap around on overflow, so decrements may take a cell "below zero"
+/// while the sum stays exact.
+pub struct StripedCounter {
+    cells: Box<[CachePadded<AtomicUsize>]>,
+}
+
+impl Default for StripedCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StripedCounter {
+    /// Creates a new [`StripedCounter`] with a cell per available core.
+    pub fn new() -> Self {
+        Self::with_cells(std::thread::available_parallelism().map_or(8, |n| n.get()))
+    }
+
+    /// Creates a new [`StripedCounter`] with `num_cells` cells.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_cells` is zero.
+    pub fn with_cells(num_cells: usize) -> Self {
+        assert!(
+            num_cells > 0,
+            "number of cells (is {}) should be positive",
+            num_cells
+        );
+        Self {
+            cells: (0..num_cells)
+                .map(|_| CachePadded::new(AtomicUsize::new(0)))
+                .collect(),
+        }
+    }
+
+    /// Adds `delta` to the counter.
+    pub fn add(&self, delta: usize) {
+        self.cell().fetch_add(delta, Ordering::Relaxed);
+    }
+
+    /// Subtracts `delta` from the counter.
+    pub fn sub(&self, delta: usize) {
+        self.cell().fetch_sub(delta, Ordering::Relaxed);
+    }
+
+    /// Adds one to the counter.
+    pub fn increment(&self) {
+        self.add(1);
+    }
+
+    /// Returns the sum of the counter.
+    ///
+    /// Updates made concurrently with the call may or may not be counted, so
+    /// the sum is only exact when the counter is quiescent.
+    pub fn sum(&self) -> usize {
+        self.cells.iter().fold(0, |sum, cell| {
+            sum.wrapping_add(cell.load(Ordering::Relaxed))
+        })
+    }
+
+    /// Resets the counter to zero, returning the sum it had.
+    ///
+    /// As with [`StripedCounter::sum`], concurrent updates may or may not be
+    /// included, but none is lost: each lands either in the returned sum or in
+    /// the reset counter.
+    pub fn sum_and_reset(&self) -> usize {
+        self.cells.iter().fold(0, |sum, cell| {
+            sum.wrapping_add(cell.swap(0, Ordering::Relaxed))
+        })
+    }
+
+    fn cell(&self) -> &AtomicUsize {
+        &self.cells[thread_index() % self.cells.len()]
+    }
+}
+
+impl fmt::Debug for StripedCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("StripedCounter").field(&self.sum()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn striped_counter() {
+        let counter = StripedCounter::with_cells(4);
+        let num_thrs = 8;
+        let num_ops = 10_000;
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let counter = &counter;
+                s.spawn(move || {
+                    for _ in 0..num_ops {
+                        counter.add(3);
+                        // half of the threads take their own cell below zero
+                        if t % 2 == 0 {
+                            counter.sub(4);
+                        } else {
+                            counter.increment();
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(counter.sum(), num_thrs / 2 * num_ops * 3);
+        assert_eq!(format!("{:?}", counter), "StripedCounter(120000)");
+        assert_eq!(counter.sum_and_reset(), 120_000);
+        assert_eq!(counter.sum(), 0);
+    }
+}
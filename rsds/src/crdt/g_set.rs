@@ -0,0 +1,137 @@
+use std::fmt;
+
+use crate::list_set::{FineGrainedSet, Set};
+use crate::vec::ConcurrentVec;
+
+/// A grow-only set, whose replicas merge by union.
+///
+/// Membership is kept in a concurrent [`Set`] of type `S`, and elements are
+/// also logged, in the order they were first inserted, so that they can be
+/// listed for merging into other replicas.
+///
+/// ```
+/// use rsds::crdt::GSet;
+///
+/// let (a, b) = (GSet::new(), GSet::new());
+/// a.insert(1);
+/// b.insert(2);
+/// a.merge(&b);
+/// b.merge(&a);
+/// assert!(a.contains(&2) && b.contains(&1));
+/// ```
+pub struct GSet<T, S = FineGrainedSet<T>> {
+    members: S,
+    log: ConcurrentVec<T>,
+}
+
+impl<T, S> Default for GSet<T, S>
+where
+    S: Default,
+{
+    fn default() -> Self {
+        GSet {
+            members: S::default(),
+            log: ConcurrentVec::new(),
+        }
+    }
+}
+
+impl<T> GSet<T> {
+    /// Creates a new, empty [`GSet`] keeping its members in a
+    /// [`FineGrainedSet`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<T, S> GSet<T, S>
+where
+    T: Clone,
+    S: Set<Elem = T>,
+{
+    /// Inserts `elem`, returning whether it was new to the set.
+    pub fn insert(&self, elem: T) -> bool {
+        // Only the thread that adds an element logs it, so it is logged once.
+        let added = self.members.add(elem.clone());
+        if added {
+            self.log.push(elem);
+        }
+        added
+    }
+
+    /// Checks whether `elem` is in the set.
+    pub fn contains(&self, elem: &T) -> bool {
+        self.members.contains(elem)
+    }
+
+    /// Returns the number of elements in the set.
+    pub fn len(&self) -> usize {
+        self.log.len()
+    }
+
+    /// Checks whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.log.is_empty()
+    }
+
+    /// Iterates over the elements in the order they were inserted.
+    ///
+    /// Elements inserted concurrently may be missed.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.log.iter()
+    }
+
+    /// Merges `other` into this set, which then holds the union of both.
+    ///
+    /// Elements inserted into `other` during the merge may be missed, to be
+    /// picked up by the next.
+    pub fn merge<S2>(&self, other: &GSet<T, S2>)
+    where
+        S2: Set<Elem = T>,
+    {
+        for elem in other.iter() {
+            self.insert(elem.clone());
+        }
+    }
+}
+
+impl<T, S> fmt::Debug for GSet<T, S>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.log.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::list_set::CoarseSet;
+
+    #[test]
+    fn test_g_set_merge() {
+        let replicas: [GSet<i32>; 3] = Default::default();
+        std::thread::scope(|s| {
+            for (i, replica) in replicas.iter().enumerate() {
+                s.spawn(move || {
+                    for elem in 0..100 {
+                        replica.insert(elem * 3 + i as i32);
+                    }
+                });
+            }
+        });
+
+        // merging in any order, or twice, ends in the same set
+        let merged: GSet<i32, CoarseSet<i32>> = GSet::default();
+        for replica in replicas.iter().rev() {
+            merged.merge(replica);
+            merged.merge(replica);
+        }
+        replicas[0].merge(&replicas[1]);
+        replicas[0].merge(&merged);
+        assert_eq!(merged.len(), 300);
+        assert_eq!(replicas[0].len(), 300);
+        assert!((0..300).all(|elem| merged.contains(&elem) && replicas[0].contains(&elem)));
+    }
+}
@@ -0,0 +1,11 @@
+//! This module contains conflict-free replicated data types, whose replicas
+//! can be updated independently and merged back into agreement in any order.
+//!
+//! Each replica here is itself concurrent, so that threads can update it
+//! while it is merged with others.
+
+mod g_set;
+mod pn_counter;
+
+pub use g_set::GSet;
+pub use pn_counter::{PnCounter, ReplicaId};
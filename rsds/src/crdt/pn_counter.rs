@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ptr;
+use std::sync::Mutex;
+
+use crate::counter::StripedCounter;
+
+/// Identifies a replica of a [`PnCounter`], and must differ between replicas.
+/// A replica that loses its counts must come back under a new ID.
+pub type ReplicaId = u64;
+
+/// A counter that can be incremented and decremented, whose replicas merge by
+/// taking the greatest count each replica is known to have reached.
+///
+/// Each replica counts its own increments and decrements in a pair of
+/// [`StripedCounter`]s, so that threads update it without contending, and
+/// keeps the counts of the other replicas as of the last merge.
+///
+/// ```
+/// use rsds::crdt::PnCounter;
+///
+/// let (a, b) = (PnCounter::new(1), PnCounter::new(2));
+/// a.add(5);
+/// b.sub(2);
+/// a.merge(&b);
+/// b.merge(&a);
+/// assert_eq!(a.value(), 3);
+/// assert_eq!(b.value(), 3);
+/// ```
+pub struct PnCounter {
+    replica: ReplicaId,
+    increments: StripedCounter,
+    decrements: StripedCounter,
+    /// The increments and decrements of every other replica merged in, and
+    /// of this one as merged back from elsewhere.
+    merged: Mutex<HashMap<ReplicaId, (usize, usize)>>,
+}
+
+impl PnCounter {
+    /// Creates a new replica, counting from zero.
+    pub fn new(replica: ReplicaId) -> Self {
+        PnCounter {
+            replica,
+            increments: StripedCounter::new(),
+            decrements: StripedCounter::new(),
+            merged: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the replica's ID.
+    pub fn replica(&self) -> ReplicaId {
+        self.replica
+    }
+
+    /// Adds `delta` to the counter.
+    pub fn add(&self, delta: usize) {
+        self.increments.add(delta);
+    }
+
+    /// Subtracts `delta` from the counter.
+    pub fn sub(&self, delta: usize) {
+        self.decrements.add(delta);
+    }
+
+    /// Adds one to the counter.
+    pub fn increment(&self) {
+        self.add(1);
+    }
+
+    /// Subtracts one from the counter.
+    pub fn decrement(&self) {
+        self.sub(1);
+    }
+
+    /// Returns the counter's value, across every replica merged in.
+    ///
+    /// As with [`StripedCounter::sum`], updates made concurrently with the
+    /// call may or may not be counted.
+    pub fn value(&self) -> i64 {
+        self.counts()
+            .values()
+            .fold(0i64, |value, &(increments, decrements)| {
+                value
+                    .wrapping_add(increments as i64)
+                    .wrapping_sub(decrements as i64)
+            })
+    }
+
+    /// Merges `other` into this replica, which then counts every increment
+    /// and decrement either had seen.
+    pub fn merge(&self, other: &PnCounter) {
+        if ptr::eq(self, other) {
+            return;
+        }
+        // Copied out first, so that two replicas merging into each other do
+        // not hold each other's lock.
+        let theirs = other.counts();
+        let mut merged = self.merged.lock().unwrap();
+        for (replica, (increments, decrements)) in theirs {
+            let ours = merged.entry(replica).or_default();
+            ours.0 = ours.0.max(increments);
+            ours.1 = ours.1.max(decrements);
+        }
+    }
+
+    /// Returns the counts of every replica, this one's being the greater of
+    /// its own and those merged back.
+    fn counts(&self) -> HashMap<ReplicaId, (usize, usize)> {
+        let mut counts = self.merged.lock().unwrap().clone();
+        let own = counts.entry(self.replica).or_default();
+        own.0 = own.0.max(self.increments.sum());
+        own.1 = own.1.max(self.decrements.sum());
+        counts
+    }
+}
+
+impl fmt::Debug for PnCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PnCounter")
+            .field("replica", &self.replica)
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pn_counter_merge() {
+        let replicas = [PnCounter::new(0), PnCounter::new(1), PnCounter::new(2)];
+        std::thread::scope(|s| {
+            for replica in &replicas {
+                for _ in 0..2 {
+                    s.spawn(move || {
+                        for _ in 0..1_000 {
+                            replica.increment();
+                        }
+                        replica.sub(500);
+                    });
+                }
+            }
+            // merges racing with updates and with each other
+            s.spawn(|| replicas[0].merge(&replicas[1]));
+            s.spawn(|| replicas[1].merge(&replicas[0]));
+        });
+
+        for _ in 0..2 {
+            for (a, b) in [(0, 1), (1, 2), (2, 0)] {
+                replicas[a].merge(&replicas[b]);
+            }
+        }
+        for replica in &replicas {
+            replica.merge(replica);
+            assert_eq!(replica.value(), 3_000);
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Differential testing of two [`Map`] or two [`Set`] implementations
+//! against each other.
+//!
+//! [`check_maps`] and [`check_sets`] generate random [`Workload`]s with
+//! quickcheck, run each against a fresh instance of both implementations,
+//! and panic with the shrunk workload if the two ever return different
+//! results. A workload gives each thread its own script of operations on its
+//! own keys, so that the threads run concurrently, but every result is still
+//! determined by the script alone.
+//!
+//! The harness is behind the `stress` feature.
+//!
+//! ```
+//! use rsds::differential;
+//! use rsds::map::{CoarseMap, StripedHashMap};
+//!
+//! differential::check_maps::<StripedHashMap<u64, u64>, CoarseMap<u64, u64>>();
+//! ```
+
+use std::fmt;
+
+use quickcheck::{Arbitrary, Gen, QuickCheck, TestResult};
+
+use crate::linearizability::{MapOp, SetOp};
+use crate::list_set::Set;
+use crate::map::Map;
+
+/// Number of keys each thread's operations use.
+const KEYS_PER_THREAD: u64 = 8;
+
+/// Maximum number of threads in a workload.
+const MAX_THREADS: usize = 4;
+
+/// An operation in a workload.
+///
+/// For a [`Set`], `Get` and `Contains` both check whether the key is in the
+/// set, and `Put` adds it, ignoring the value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// Looks up a key.
+    Get(u64),
+    /// Checks whether a key is present.
+    Contains(u64),
+    /// Maps a key to a value.
+    Put(u64, u64),
+    /// Removes a key.
+    Remove(u64),
+}
+
+impl Arbitrary for Command {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let key = u64::arbitrary(g) % KEYS_PER_THREAD;
+        match u8::arbitrary(g) % 4 {
+            0 => Command::Get(key),
+            1 => Command::Contains(key),
+            2 => Command::Put(key, u64::arbitrary(g)),
+            _ => Command::Remove(key),
+        }
+    }
+}
+
+/// A script of operations for each of a number of threads.
+///
+/// Thread `t` runs its script on keys congruent to `t` modulo the number of
+/// threads, so no two threads share a key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Workload {
+    /// The threads' scripts, on keys below 8, before they are spread
+    /// across threads.
+    pub threads: Vec<Vec<Command>>,
+}
+
+impl Workload {
+    /// Runs the workload, returning each thread's operations along with what
+    /// they returned, and then what is left at each key the workload may have
+    /// touched.
+    fn run<O: Send>(&self, apply: impl Fn(Command) -> O + Sync) -> Vec<Vec<O>> {
+        let num_thrs = self.threads.len() as u64;
+        let spread = |key: u64, t: u64| key * num_thrs + t;
+        let mut results: Vec<Vec<O>> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..num_thrs)
+                .zip(&self.threads)
+                .map(|(t, script)| {
+                    let apply = &apply;
+                    s.spawn(move || {
+                        let script = script.iter().map(|&command| match command {
+                            Command::Get(key) => Command::Get(spread(key, t)),
+                            Command::Contains(key) => Command::Contains(spread(key, t)),
+                            Command::Put(key, value) => Command::Put(spread(key, t), value),
+                            Command::Remove(key) => Command::Remove(spread(key, t)),
+                        });
+                        script.map(apply).collect()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        let keys = 0..KEYS_PER_THREAD * num_thrs;
+        results.push(keys.map(|key| apply(Command::Get(key))).collect());
+        results
+    }
+}
+
+impl Arbitrary for Workload {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let num_thrs = usize::arbitrary(g) % MAX_THREADS + 1;
+        Self {
+            threads: (0..num_thrs).map(|_| Vec::arbitrary(g)).collect(),
+        }
+    }
+
+    fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+        let shrunk = self.threads.shrink().filter(|threads| !threads.is_empty());
+        Box::new(shrunk.map(|threads| Self { threads }))
+    }
+}
+
+fn apply_map<M>(map: &M, command: Command) -> MapOp<u64, u64>
+where
+    M: Map<Key = u64, Val = u64>,
+{
+    match command {
+        Command::Get(key) => MapOp::Get(key, map.get(&key).map(|value| *value)),
+        Command::Contains(key) => MapOp::Contains(key, map.contains(&key)),
+        Command::Put(key, value) => {
+            map.put(key, value);
+            MapOp::Put(key, value)
+        }
+        Command::Remove(key) => MapOp::Remove(key, map.remove(&key)),
+    }
+}
+
+fn apply_set<S>(set: &S, command: Command) -> SetOp<u64>
+where
+    S: Set<Elem = u64>,
+{
+    match command {
+        Command::Get(key) | Command::Contains(key) => SetOp::Contains(key, set.contains(&key)),
+        Command::Put(key, _) => SetOp::Add(key, set.add(key)),
+        Command::Remove(key) => SetOp::Remove(key, set.remove(&key)),
+    }
+}
+
+/// Compares the results of the same workload on two implementations.
+fn compare<O: PartialEq + fmt::Debug>(a: Vec<Vec<O>>, b: Vec<Vec<O>>) -> TestResult {
+    let num_thrs = a.len() - 1;
+    for (t, (a, b)) in a.iter().zip(&b).enumerate() {
+        if let Some((a, b)) = a.iter().zip(b).find(|(a, b)| a != b) {
+            let ran = if t < num_thrs {
+                format!("thread {}", t)
+            } else {
+                "the final reads".to_string()
+            };
+            return TestResult::error(format!("in {}: {:?} != {:?}", ran, a, b));
+        }
+    }
+    TestResult::passed()
+}
+
+fn maps_agree<A, B>(workload: Workload) -> TestResult
+where
+    A: Map<Key = u64, Val = u64> + Default + Sync,
+    B: Map<Key = u64, Val = u64> + Default + Sync,
+{
+    let (a, b) = (A::default(), B::default());
+    compare(
+        workload.run(|command| apply_map(&a, command)),
+        workload.run(|command| apply_map(&b, command)),
+    )
+}
+
+fn sets_agree<A, B>(workload: Workload) -> TestResult
+where
+    A: Set<Elem = u64> + Default + Sync,
+    B: Set<Elem = u64> + Default + Sync,
+{
+    let (a, b) = (A::default(), B::default());
+    compare(
+        workload.run(|command| apply_set(&a, command)),
+        workload.run(|command| apply_set(&b, command)),
+    )
+}
+
+/// Checks that maps of types `A` and `B` return the same results for random
+/// workloads.
+///
+/// The number of workloads is quickcheck's default, which the
+/// `QUICKCHECK_TESTS` environment variable overrides.
+///
+/// # Panics
+///
+/// Panics with the smallest diverging workload found, if any.
+pub fn check_maps<A, B>()
+where
+    A: Map<Key = u64, Val = u64> + Default + Sync,
+    B: Map<Key = u64, Val = u64> + Default + Sync,
+{
+    QuickCheck::new().quickcheck(maps_agree::<A, B> as fn(Workload) -> TestResult);
+}
+
+/// Checks that sets of types `A` and `B` return the same results for random
+/// workloads.
+///
+/// The number of workloads is quickcheck's default, which the
+/// `QUICKCHECK_TESTS` environment variable overrides.
+///
+/// # Panics
+///
+/// Panics with the smallest diverging workload found, if any.
+pub fn check_sets<A, B>()
+where
+    A: Set<Elem = u64> + Default + Sync,
+    B: Set<Elem = u64> + Default + Sync,
+{
+    QuickCheck::new().quickcheck(sets_agree::<A, B> as fn(Workload) -> TestResult);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::list_set::{CoarseSet, FineGrainedSet, FlatCombiningSet};
+    use crate::map::{CoarseMap, StripedHashMap};
+
+    /// A map that keeps a key's first value, as `StripedHashMap::put` once did.
+    #[derive(Default)]
+    struct FirstValueMap(Mutex<HashMap<u64, u64>>);
+
+    impl Map for FirstValueMap {
+        type Key = u64;
+        type Val = u64;
+        type ValueRef<'a> = Box<u64>;
+
+        fn get(&self, key: &u64) -> Option<Box<u64>> {
+            self.0.lock().unwrap().get(key).copied().map(Box::new)
+        }
+
+        fn contains(&self, key: &u64) -> bool {
+            self.0.lock().unwrap().contains_key(key)
+        }
+
+        fn put(&self, key: u64, value: u64) {
+            self.0.lock().unwrap().entry(key).or_insert(value);
+        }
+
+        fn remove(&self, key: &u64) -> bool {
+            self.0.lock().unwrap().remove(key).is_some()
+        }
+    }
+
+    #[test]
+    fn differential_maps() {
+        check_maps::<StripedHashMap<u64, u64>, CoarseMap<u64, u64>>();
+    }
+
+    #[test]
+    fn differential_sets() {
+        check_sets::<FineGrainedSet<u64>, CoarseSet<u64>>();
+        check_sets::<FlatCombiningSet<u64>, CoarseSet<u64>>();
+    }
+
+    #[test]
+    fn differential_divergence() {
+        let result = QuickCheck::new().quicktest(
+            maps_agree::<FirstValueMap, CoarseMap<u64, u64>> as fn(Workload) -> TestResult,
+        );
+        assert!(result.is_err());
+    }
+}
@@ -1,9 +1,44 @@
 //! This crate contains concurrent implementations for common data structures.
 
-#![feature(let_else)]
-#![feature(box_into_inner)]
-#![feature(generic_associated_types)]
 #![deny(warnings, missing_docs)]
 
+#[cfg(feature = "bag")]
+pub mod bag;
+mod buckets;
+#[cfg(feature = "cache")]
+pub mod cache;
+#[cfg(feature = "counter")]
+pub mod counter;
+#[cfg(feature = "crdt")]
+pub mod crdt;
+#[cfg(feature = "stress")]
+pub mod differential;
+#[cfg(feature = "stress")]
+pub mod linearizability;
+#[cfg(feature = "list-set")]
 pub mod list_set;
+#[cfg(feature = "map")]
 pub mod map;
+#[cfg(any(feature = "list-set", feature = "map"))]
+mod primitive;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "queue")]
+pub mod queue;
+pub mod reclaim;
+#[cfg(feature = "sketch")]
+pub mod sketch;
+#[cfg(feature = "slab")]
+pub mod slab;
+pub mod stats;
+#[cfg(feature = "stress")]
+pub mod stress;
+pub mod sync;
+#[cfg(any(feature = "cache", feature = "map"))]
+mod trace;
+#[cfg(feature = "tree")]
+pub mod tree;
+#[cfg(feature = "vec")]
+pub mod vec;
+#[cfg(feature = "workload")]
+pub mod workload;
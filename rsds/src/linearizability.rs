@@ -0,0 +1,520 @@
+//! A linearizability checker for recorded operation histories.
+//!
+//! A concurrent history is linearizable if each of its operations can be
+//! given a point between its call and its return at which it appears to take
+//! effect all at once, such that the operations, run one at a time in that
+//! order, return what they did concurrently.
+//!
+//! [`RecordedMap`] and [`RecordedSet`] wrap a [`Map`] or a [`Set`], and
+//! record a [`History`] of every operation run through them, timestamped
+//! with a logical clock at its call and its return. [`History::check`] then
+//! searches for a valid order, with the Wing–Gong algorithm memoized on the
+//! operations taken so far and the resulting state. Operations on different
+//! keys never interact, so the history is split by key first, and each key's
+//! sub-history is checked on its own.
+//!
+//! The checker is behind the `stress` feature.
+//!
+//! ```
+//! use rsds::linearizability::RecordedMap;
+//! use rsds::map::{CoarseMap, Map};
+//!
+//! let map = RecordedMap::new(CoarseMap::<u64, u64>::new());
+//! std::thread::scope(|s| {
+//!     for t in 0..4 {
+//!         let map = &map;
+//!         s.spawn(move || {
+//!             for i in 0..100 {
+//!                 map.put(i % 8, t);
+//!                 map.get(&(i % 8));
+//!                 map.remove(&(i % 8));
+//!             }
+//!         });
+//!     }
+//! });
+//! map.into_history().check().unwrap();
+//! ```
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::Hash;
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::list_set::Set;
+use crate::map::Map;
+
+/// An operation, along with what it returned, that can be replayed against a
+/// sequential specification.
+///
+/// Operations are partitioned by key, and operations on different keys must
+/// not affect each other.
+pub trait Spec {
+    /// The key the operation acts on.
+    type Key: Hash + Eq;
+    /// The state of a single key in the sequential specification.
+    type State: Default + Clone + Hash + Eq;
+
+    /// Returns the key the operation acts on.
+    fn key(&self) -> &Self::Key;
+
+    /// Applies the operation to `state`, returning whether the sequential
+    /// specification would have returned what the operation did.
+    fn apply(&self, state: &mut Self::State) -> bool;
+}
+
+/// A [`Map`] operation, along with what it returned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MapOp<K, V> {
+    /// A `get`, and the value it found.
+    Get(K, Option<V>),
+    /// A `contains`, and whether it found the key.
+    Contains(K, bool),
+    /// A `put` of a value.
+    Put(K, V),
+    /// A `remove`, and whether it found the key.
+    Remove(K, bool),
+}
+
+impl<K, V> Spec for MapOp<K, V>
+where
+    K: Hash + Eq,
+    V: Clone + Hash + Eq,
+{
+    type Key = K;
+    type State = Option<V>;
+
+    fn key(&self) -> &K {
+        match self {
+            MapOp::Get(key, _) | MapOp::Contains(key, _) => key,
+            MapOp::Put(key, _) | MapOp::Remove(key, _) => key,
+        }
+    }
+
+    fn apply(&self, state: &mut Option<V>) -> bool {
+        match self {
+            MapOp::Get(_, value) => state == value,
+            MapOp::Contains(_, found) => state.is_some() == *found,
+            MapOp::Put(_, value) => {
+                *state = Some(value.clone());
+                true
+            }
+            MapOp::Remove(_, found) => state.take().is_some() == *found,
+        }
+    }
+}
+
+/// A [`Set`] operation, along with what it returned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SetOp<T> {
+    /// An `add`, and whether it added the element.
+    Add(T, bool),
+    /// A `remove`, and whether it found the element.
+    Remove(T, bool),
+    /// A `contains`, and whether it found the element.
+    Contains(T, bool),
+}
+
+impl<T: Hash + Eq> Spec for SetOp<T> {
+    type Key = T;
+    type State = bool;
+
+    fn key(&self) -> &T {
+        match self {
+            SetOp::Add(elem, _) | SetOp::Remove(elem, _) | SetOp::Contains(elem, _) => elem,
+        }
+    }
+
+    fn apply(&self, present: &mut bool) -> bool {
+        match *self {
+            SetOp::Add(_, added) => mem::replace(present, true) != added,
+            SetOp::Remove(_, removed) => mem::replace(present, false) == removed,
+            SetOp::Contains(_, found) => *present == found,
+        }
+    }
+}
+
+/// An operation in a [`History`], with the logical times of its call and its
+/// return.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Operation<O> {
+    /// The operation, along with what it returned.
+    pub op: O,
+    /// When the operation was called.
+    pub call: u64,
+    /// When the operation returned.
+    pub ret: u64,
+}
+
+/// A history of completed operations.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct History<O> {
+    operations: Vec<Operation<O>>,
+}
+
+impl<O> Default for History<O> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<O> History<O> {
+    /// Creates a new, empty [`History`].
+    pub fn new() -> Self {
+        Self {
+            operations: Vec::new(),
+        }
+    }
+
+    /// Adds an operation that was called at `call` and returned at `ret`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ret` is not after `call`.
+    pub fn push(&mut self, op: O, call: u64, ret: u64) {
+        assert!(
+            call < ret,
+            "call (is {}) should be before ret (is {})",
+            call,
+            ret
+        );
+        self.operations.push(Operation { op, call, ret });
+    }
+
+    /// Returns the operations in the history, in the order they were added.
+    pub fn operations(&self) -> &[Operation<O>] {
+        &self.operations
+    }
+}
+
+impl<O: Spec + Clone> History<O> {
+    /// Checks whether the history is linearizable, returning the sub-history
+    /// of a key that is not otherwise.
+    pub fn check(&self) -> Result<(), Violation<O>> {
+        let mut keys: HashMap<&O::Key, Vec<&Operation<O>>> = HashMap::new();
+        for operation in &self.operations {
+            keys.entry(operation.op.key()).or_default().push(operation);
+        }
+        for mut operations in keys.into_values() {
+            operations.sort_by_key(|operation| operation.call);
+            if !linearizable(&operations) {
+                return Err(Violation {
+                    operations: operations.into_iter().cloned().collect(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Searches for a valid order of `operations`, which act on a single key and
+/// are sorted by call.
+fn linearizable<O: Spec>(operations: &[&Operation<O>]) -> bool {
+    let n = operations.len();
+    // the operations taken so far, as a bitset
+    let mut taken = vec![0u64; n.div_ceil(64)];
+    let is_taken = |taken: &[u64], i: usize| taken[i / 64] >> (i % 64) & 1 == 1;
+    // the configurations already found to be dead ends, or being explored
+    let mut seen = HashSet::new();
+    // the operations taken so far in order, with the state before each
+    let mut stack: Vec<(usize, O::State)> = Vec::with_capacity(n);
+    let mut state = O::State::default();
+    let mut next = 0;
+    loop {
+        if stack.len() == n {
+            return true;
+        }
+        // An operation can be taken next if it was called before every
+        // remaining operation returned.
+        let first_ret = (0..n)
+            .filter(|&i| !is_taken(&taken, i))
+            .map(|i| operations[i].ret)
+            .min()
+            .unwrap();
+        let mut step = None;
+        for i in next..n {
+            if operations[i].call > first_ret {
+                break;
+            }
+            if is_taken(&taken, i) {
+                continue;
+            }
+            let mut after = state.clone();
+            if operations[i].op.apply(&mut after) {
+                taken[i / 64] |= 1 << (i % 64);
+                if seen.insert((taken.clone(), after.clone())) {
+                    step = Some((i, after));
+                    break;
+                }
+                taken[i / 64] &= !(1 << (i % 64));
+            }
+        }
+        match step {
+            Some((i, after)) => {
+                stack.push((i, mem::replace(&mut state, after)));
+                next = 0;
+            }
+            None => {
+                // backtrack, and try the next operation in place of the last
+                let Some((i, before)) = stack.pop() else {
+                    return false;
+                };
+                taken[i / 64] &= !(1 << (i % 64));
+                state = before;
+                next = i + 1;
+            }
+        }
+    }
+}
+
+/// The sub-history of a key that is not linearizable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation<O> {
+    /// The operations on the key, sorted by call.
+    pub operations: Vec<Operation<O>>,
+}
+
+impl<O: fmt::Debug> fmt::Display for Violation<O> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "history is not linearizable:")?;
+        for operation in &self.operations {
+            write!(
+                f,
+                "\n  [{}, {}] {:?}",
+                operation.call, operation.ret, operation.op
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A log of operations, timestamped with a logical clock.
+struct Log<O> {
+    clock: AtomicU64,
+    history: Mutex<History<O>>,
+}
+
+impl<O> Log<O> {
+    fn new() -> Self {
+        Self {
+            clock: AtomicU64::new(0),
+            history: Mutex::new(History::new()),
+        }
+    }
+
+    /// Runs `f`, and records the operation `record` makes of its result.
+    fn record<R>(&self, f: impl FnOnce() -> R, record: impl FnOnce(&R) -> O) -> R {
+        // A single sequentially consistent clock orders the timestamps of
+        // every operation, so an operation that returned before another was
+        // called has the earlier timestamps.
+        let call = self.clock.fetch_add(1, Ordering::SeqCst);
+        let result = f();
+        let ret = self.clock.fetch_add(1, Ordering::SeqCst);
+        let op = record(&result);
+        self.history.lock().unwrap().push(op, call, ret);
+        result
+    }
+
+    fn into_history(self) -> History<O> {
+        self.history.into_inner().unwrap()
+    }
+}
+
+/// A [`Map`] that records a [`History`] of the operations run through it.
+///
+/// See the [module-level documentation](self) for more.
+pub struct RecordedMap<M: Map> {
+    map: M,
+    log: Log<MapOp<M::Key, M::Val>>,
+}
+
+impl<M: Map> RecordedMap<M> {
+    /// Wraps `map`, recording the operations run from now on.
+    pub fn new(map: M) -> Self {
+        Self {
+            map,
+            log: Log::new(),
+        }
+    }
+
+    /// Returns the map being recorded.
+    pub fn inner(&self) -> &M {
+        &self.map
+    }
+
+    /// Returns the recorded history.
+    pub fn into_history(self) -> History<MapOp<M::Key, M::Val>> {
+        self.log.into_history()
+    }
+}
+
+impl<M> Map for RecordedMap<M>
+where
+    M: Map,
+    M::Key: Clone,
+    M::Val: Clone,
+{
+    type Key = M::Key;
+    type Val = M::Val;
+    type ValueRef<'a> = M::ValueRef<'a> where M: 'a;
+
+    fn get(&self, key: &M::Key) -> Option<M::ValueRef<'_>> {
+        self.log.record(
+            || self.map.get(key),
+            |value| MapOp::Get(key.clone(), value.as_deref().cloned()),
+        )
+    }
+
+    fn contains(&self, key: &M::Key) -> bool {
+        self.log.record(
+            || self.map.contains(key),
+            |&found| MapOp::Contains(key.clone(), found),
+        )
+    }
+
+    fn put(&self, key: M::Key, value: M::Val) {
+        let op = MapOp::Put(key.clone(), value.clone());
+        self.log.record(|| self.map.put(key, value), |_| op);
+    }
+
+    fn remove(&self, key: &M::Key) -> bool {
+        self.log.record(
+            || self.map.remove(key),
+            |&found| MapOp::Remove(key.clone(), found),
+        )
+    }
+}
+
+impl<M: Map> fmt::Debug for RecordedMap<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordedMap").finish_non_exhaustive()
+    }
+}
+
+/// A [`Set`] that records a [`History`] of the operations run through it.
+///
+/// See the [module-level documentation](self) for more.
+pub struct RecordedSet<S: Set> {
+    set: S,
+    log: Log<SetOp<S::Elem>>,
+}
+
+impl<S: Set> RecordedSet<S> {
+    /// Wraps `set`, recording the operations run from now on.
+    pub fn new(set: S) -> Self {
+        Self {
+            set,
+            log: Log::new(),
+        }
+    }
+
+    /// Returns the set being recorded.
+    pub fn inner(&self) -> &S {
+        &self.set
+    }
+
+    /// Returns the recorded history.
+    pub fn into_history(self) -> History<SetOp<S::Elem>> {
+        self.log.into_history()
+    }
+}
+
+impl<S> Set for RecordedSet<S>
+where
+    S: Set,
+    S::Elem: Clone,
+{
+    type Elem = S::Elem;
+
+    fn add(&self, elem: S::Elem) -> bool {
+        let record = elem.clone();
+        self.log
+            .record(|| self.set.add(elem), |&added| SetOp::Add(record, added))
+    }
+
+    fn remove(&self, elem: &S::Elem) -> bool {
+        self.log.record(
+            || self.set.remove(elem),
+            |&removed| SetOp::Remove(elem.clone(), removed),
+        )
+    }
+
+    fn contains(&self, elem: &S::Elem) -> bool {
+        self.log.record(
+            || self.set.contains(elem),
+            |&found| SetOp::Contains(elem.clone(), found),
+        )
+    }
+}
+
+impl<S: Set> fmt::Debug for RecordedSet<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecordedSet").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::list_set::FineGrainedSet;
+    use crate::map::StripedHashMap;
+
+    #[test]
+    fn linearizability_check() {
+        // overlapping puts may take effect in either order
+        let mut history = History::new();
+        history.push(MapOp::Put(0, 1), 0, 3);
+        history.push(MapOp::Put(0, 2), 1, 4);
+        history.push(MapOp::Get(0, Some(1)), 5, 6);
+        history.push(MapOp::Get(1, None), 2, 7);
+        assert_eq!(history.check(), Ok(()));
+
+        // but not once one has returned before the other was called
+        let mut history = History::new();
+        history.push(MapOp::Put(0, 1), 0, 1);
+        history.push(MapOp::Put(0, 2), 2, 3);
+        history.push(MapOp::Get(0, Some(1)), 4, 5);
+        history.push(MapOp::Get(1, None), 6, 7);
+        let violation = history.check().unwrap_err();
+        assert_eq!(violation.operations.len(), 3);
+
+        // two concurrent adds cannot both succeed
+        let mut history = History::new();
+        history.push(SetOp::Add(0, true), 0, 2);
+        history.push(SetOp::Add(0, true), 1, 3);
+        assert!(history.check().is_err());
+        history = History::new();
+        history.push(SetOp::Add(0, true), 0, 2);
+        history.push(SetOp::Remove(0, true), 1, 4);
+        history.push(SetOp::Add(0, true), 3, 5);
+        assert_eq!(history.check(), Ok(()));
+    }
+
+    #[test]
+    fn linearizability_recorded() {
+        let num_thrs = 4;
+        let map = RecordedMap::new(StripedHashMap::new());
+        let set = RecordedSet::new(FineGrainedSet::default());
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let (map, set) = (&map, &set);
+                s.spawn(move || {
+                    for i in 0..500 {
+                        let key = i % 4;
+                        map.put(key, t * 1000 + i);
+                        map.get(&key);
+                        map.remove(&((key + 1) % 4));
+                        set.add(key);
+                        set.contains(&key);
+                        set.remove(&((key + 1) % 4));
+                    }
+                });
+            }
+        });
+        let history = map.into_history();
+        assert_eq!(history.operations().len(), num_thrs as usize * 1500);
+        history.check().unwrap();
+        set.into_history().check().unwrap();
+    }
+}
@@ -0,0 +1,255 @@
+//! A slab allocator for list nodes, enabled by the `arena` feature.
+//!
+//! Nodes are carved out of chunks holding many nodes at once, and freed nodes
+//! are kept on free lists for reuse instead of going back to the system
+//! allocator. Free lists are cached per thread, so allocating and freeing
+//! rarely synchronizes; a thread's cache spills over to a global pool when it
+//! grows too large or the thread exits.
+//!
+//! Blocks are pooled by layout rather than by type, so every structure using
+//! nodes of the same size and alignment shares them. Memory is never returned
+//! to the system, so the arena stays as large as the peak number of live
+//! nodes.
+
+use std::alloc::{self, Layout};
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+use std::sync::Mutex;
+
+/// Number of blocks allocated at once when a pool runs dry.
+const CHUNK_LEN: usize = 64;
+
+/// Free blocks of a single layout.
+struct Pool {
+    layout: Layout,
+    free: Vec<NonNull<u8>>,
+}
+
+/// A set of pools, one per layout in use.
+#[derive(Default)]
+struct Pools(Vec<Pool>);
+
+// SAFETY: free blocks are plain memory, owned by whoever holds the pool.
+unsafe impl Send for Pools {}
+
+impl Pools {
+    fn get(&mut self, layout: Layout) -> &mut Vec<NonNull<u8>> {
+        let index = match self.0.iter().position(|pool| pool.layout == layout) {
+            Some(index) => index,
+            None => {
+                self.0.push(Pool {
+                    layout,
+                    free: Vec::new(),
+                });
+                self.0.len() - 1
+            }
+        };
+        &mut self.0[index].free
+    }
+}
+
+/// A thread's cache of free blocks, spilled to the global pool on exit.
+struct LocalCache(Pools);
+
+impl Drop for LocalCache {
+    fn drop(&mut self) {
+        let mut global = GLOBAL.lock().unwrap();
+        for pool in self.0 .0.drain(..) {
+            global.get(pool.layout).extend(pool.free);
+        }
+    }
+}
+
+static GLOBAL: Mutex<Pools> = Mutex::new(Pools(Vec::new()));
+
+thread_local! {
+    static CACHE: RefCell<LocalCache> = const { RefCell::new(LocalCache(Pools(Vec::new()))) };
+}
+
+/// Allocates a block for `layout`, which must have a non-zero size.
+fn allocate(layout: Layout) -> NonNull<u8> {
+    let block = CACHE.try_with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let free = cache.0.get(layout);
+        if free.is_empty() {
+            refill(layout, free);
+        }
+        free.pop().unwrap()
+    });
+    // The thread is exiting and its cache is gone; allocate a fresh chunk.
+    block.unwrap_or_else(|_| {
+        let mut free = Vec::new();
+        refill(layout, &mut free);
+        let block = free.pop().unwrap();
+        GLOBAL.lock().unwrap().get(layout).extend(free);
+        block
+    })
+}
+
+/// Returns a block allocated for `layout` to the arena.
+fn deallocate(block: NonNull<u8>, layout: Layout) {
+    let cached = CACHE.try_with(|cache| {
+        let mut cache = cache.borrow_mut();
+        let free = cache.0.get(layout);
+        free.push(block);
+        if free.len() > 2 * CHUNK_LEN {
+            // Threads that free more than they allocate hand the surplus to
+            // those that allocate more than they free.
+            let surplus = free.split_off(CHUNK_LEN);
+            GLOBAL.lock().unwrap().get(layout).extend(surplus);
+        }
+    });
+    if cached.is_err() {
+        GLOBAL.lock().unwrap().get(layout).push(block);
+    }
+}
+
+/// Fills `free` with blocks from the global pool, or else from a new chunk.
+fn refill(layout: Layout, free: &mut Vec<NonNull<u8>>) {
+    {
+        let mut global = GLOBAL.lock().unwrap();
+        let global = global.get(layout);
+        let start = global.len().saturating_sub(CHUNK_LEN);
+        free.extend(global.drain(start..));
+    }
+    if !free.is_empty() {
+        return;
+    }
+
+    let size = layout.pad_to_align().size();
+    let chunk_layout = Layout::from_size_align(size * CHUNK_LEN, layout.align()).unwrap();
+    // SAFETY: the chunk layout has a non-zero size.
+    let chunk = unsafe { alloc::alloc(chunk_layout) };
+    let Some(chunk) = NonNull::new(chunk) else {
+        alloc::handle_alloc_error(chunk_layout);
+    };
+    // SAFETY: every block lies within the chunk.
+    free.extend(
+        (0..CHUNK_LEN).map(|i| unsafe { NonNull::new_unchecked(chunk.as_ptr().add(i * size)) }),
+    );
+}
+
+/// An owning pointer to a value allocated from the arena, used in place of
+/// [`Box`] for list nodes.
+pub(super) struct ArenaBox<T> {
+    ptr: NonNull<T>,
+    _marker: PhantomData<T>,
+}
+
+// SAFETY: `ArenaBox` owns its value just like `Box` does.
+unsafe impl<T: Send> Send for ArenaBox<T> {}
+unsafe impl<T: Sync> Sync for ArenaBox<T> {}
+
+impl<T> ArenaBox<T> {
+    /// Moves `value` into the arena.
+    pub(super) fn new(value: T) -> Self {
+        let layout = Layout::new::<T>();
+        let ptr: NonNull<T> = if layout.size() == 0 {
+            NonNull::dangling()
+        } else {
+            allocate(layout).cast()
+        };
+        // SAFETY: the block is valid for writes of a `T`.
+        unsafe { ptr.as_ptr().write(value) };
+        Self {
+            ptr,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Moves the value out of the arena.
+    pub(super) fn into_inner(this: Self) -> T {
+        let this = ManuallyDrop::new(this);
+        // SAFETY: the value is initialized, and is not used again.
+        let value = unsafe { this.ptr.as_ptr().read() };
+        this.free();
+        value
+    }
+
+    /// Returns a raw pointer to the value. Later borrows of the box do not
+    /// invalidate it, so lists can keep it to reach a node directly.
+    pub(super) fn as_ptr(this: &Self) -> *mut T {
+        this.ptr.as_ptr()
+    }
+
+    fn free(&self) {
+        let layout = Layout::new::<T>();
+        if layout.size() != 0 {
+            deallocate(self.ptr.cast(), layout);
+        }
+    }
+}
+
+impl<T> Drop for ArenaBox<T> {
+    fn drop(&mut self) {
+        // SAFETY: the value is initialized, and is not used again.
+        unsafe { self.ptr.as_ptr().drop_in_place() };
+        self.free();
+    }
+}
+
+impl<T> Deref for ArenaBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the value is initialized and owned by us.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for ArenaBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: as above.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> AsRef<T> for ArenaBox<T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T> AsMut<T> for ArenaBox<T> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::*;
+
+    #[test]
+    fn arena_reuses_blocks() {
+        let first = ArenaBox::new([1u64; 3]);
+        let addr = first.ptr;
+        drop(first);
+        let second = ArenaBox::new([2u64; 3]);
+        assert_eq!(second.ptr, addr);
+        assert_eq!(ArenaBox::into_inner(second), [2; 3]);
+
+        let counted = Rc::new(());
+        let boxes: Vec<_> = (0..3 * CHUNK_LEN)
+            .map(|_| ArenaBox::new(counted.clone()))
+            .collect();
+        assert_eq!(Rc::strong_count(&counted), 3 * CHUNK_LEN + 1);
+        drop(boxes);
+        assert_eq!(Rc::strong_count(&counted), 1);
+        assert_eq!(*ArenaBox::new(()), ());
+    }
+
+    #[test]
+    fn arena_across_threads() {
+        let boxes: Vec<_> = (0..1_000).map(ArenaBox::new).collect();
+        // blocks freed by another thread end up back in the global pool
+        std::thread::spawn(move || drop(boxes)).join().unwrap();
+        let boxes: Vec<_> = (0..1_000).map(ArenaBox::new).collect();
+        assert!(boxes.iter().map(|b| **b).eq(0..1_000));
+    }
+}
@@ -0,0 +1,138 @@
+use super::{ListInner, ListIter};
+
+/// Determines what a bounded list does when an element is added while it is
+/// full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Keep the existing elements and hand the new element back to the caller.
+    Reject,
+    /// Evict the oldest (front) element to make room for the new one.
+    DropOldest,
+}
+
+/// A linked list holding at most a fixed number of elements.
+///
+/// What happens when an element is added to a full list is decided by the
+/// list's [`EvictionPolicy`].
+pub struct BoundedList<T> {
+    inner: ListInner<T>,
+    capacity: usize,
+    policy: EvictionPolicy,
+}
+
+impl<T> BoundedList<T> {
+    /// Creates a new [`BoundedList`] holding at most `capacity` elements.
+    pub fn new(capacity: usize, policy: EvictionPolicy) -> Self {
+        Self {
+            inner: ListInner::default(),
+            capacity,
+            policy,
+        }
+    }
+
+    /// Appends an element to the end of the linked list.
+    ///
+    /// If the list is full, the outcome depends on the eviction policy:
+    ///
+    /// - [`EvictionPolicy::DropOldest`] inserts the element and returns
+    ///   `Ok(Some(evicted))`, where `evicted` was the first element.
+    /// - [`EvictionPolicy::Reject`] leaves the list untouched and returns
+    ///   `Err(elem)`.
+    ///
+    /// Otherwise the element is inserted and `Ok(None)` is returned.
+    pub fn add(&mut self, elem: T) -> Result<Option<T>, T> {
+        self.inner.add_bounded(elem, self.capacity, self.policy)
+    }
+
+    /// Removes the first element and returns it, or `None` if the list is
+    /// empty.
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.inner.pop_front()
+    }
+
+    /// Returns a reference to the first element, or `None` if the list is
+    /// empty.
+    pub fn front(&self) -> Option<&T> {
+        self.inner.front()
+    }
+
+    /// Returns a reference to the last element, or `None` if the list is
+    /// empty.
+    pub fn back(&self) -> Option<&T> {
+        self.inner.back()
+    }
+
+    /// Checks whether the given element is part of the linked list.
+    pub fn find(&self, target: &T) -> bool
+    where
+        T: PartialEq + Eq,
+    {
+        self.inner.find(target)
+    }
+
+    /// Returns the linked list's iterator.
+    pub fn iter(&self) -> ListIter<'_, T> {
+        self.inner.iter()
+    }
+
+    /// Returns the maximum number of elements the linked list can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the policy applied when adding to a full list.
+    pub fn policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    /// Returns the number of elements contained in this linked list.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Checks whether the linked list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Checks whether the linked list has reached its capacity.
+    pub fn is_full(&self) -> bool {
+        self.len() >= self.capacity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_list_drop_oldest() {
+        let mut list = BoundedList::new(3, EvictionPolicy::DropOldest);
+        for i in 0..3 {
+            assert_eq!(list.add(i), Ok(None));
+        }
+        assert!(list.is_full());
+        assert_eq!(list.add(3), Ok(Some(0)));
+        assert_eq!(list.add(4), Ok(Some(1)));
+        assert_eq!(list.len(), 3);
+        assert!(list.iter().copied().eq(2..5));
+        assert_eq!(list.back(), Some(&4));
+
+        let mut list = BoundedList::new(0, EvictionPolicy::DropOldest);
+        assert_eq!(list.add(0), Ok(Some(0)));
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    fn bounded_list_reject() {
+        let mut list = BoundedList::new(2, EvictionPolicy::Reject);
+        assert_eq!(list.add(0), Ok(None));
+        assert_eq!(list.add(1), Ok(None));
+        assert_eq!(list.add(2), Err(2));
+        assert!(list.iter().copied().eq(0..2));
+
+        assert_eq!(list.pop_front(), Some(0));
+        assert_eq!(list.add(2), Ok(None));
+        assert!(list.iter().copied().eq(1..3));
+    }
+}
@@ -1,11 +1,13 @@
 use std::sync::RwLock;
 
-use super::{Node, Set};
+use super::{unbox, Node, Set};
+use crate::stats::{Counters, Statistics, Stats};
 
 /// A linked list-based set implemented with coarse-grained locking.
 #[derive(Default)]
 pub struct CoarseSet<T> {
     head: RwLock<Option<Node<T>>>,
+    counters: Counters,
 }
 
 impl<T> Set for CoarseSet<T>
@@ -15,6 +17,7 @@ where
     type Elem = T;
 
     fn add(&self, elem: Self::Elem) -> bool {
+        self.counters.op();
         let mut head_guard = self.head.write().unwrap();
 
         if (*head_guard).is_none() {
@@ -58,6 +61,7 @@ where
     }
 
     fn remove(&self, elem: &Self::Elem) -> bool {
+        self.counters.op();
         let mut head_guard = self.head.write().unwrap();
 
         if (*head_guard).is_none() {
@@ -68,7 +72,7 @@ where
         if head_val == elem {
             let (_, maybe_rest) = (*head_guard).take().unwrap().into_parts();
             if let Some(rest) = maybe_rest {
-                *head_guard = Some(Box::into_inner(rest));
+                *head_guard = Some(unbox(rest));
             }
             true
         } else if head_val > elem {
@@ -100,6 +104,15 @@ where
     }
 
     fn contains(&self, elem: &Self::Elem) -> bool {
+        self.counters.lookup(self.find(elem))
+    }
+}
+
+impl<T> CoarseSet<T>
+where
+    T: PartialOrd + PartialEq + Eq,
+{
+    fn find(&self, elem: &T) -> bool {
         let head_guard = self.head.read().unwrap();
         match &*head_guard {
             None => false,
@@ -131,3 +144,9 @@ where
         }
     }
 }
+
+impl<T> Statistics for CoarseSet<T> {
+    fn stats(&self) -> Stats {
+        self.counters.snapshot()
+    }
+}
@@ -0,0 +1,249 @@
+use std::marker::PhantomData;
+use std::ptr::NonNull;
+
+struct Node<T> {
+    elem: T,
+    prev: Option<NonNull<Node<T>>>,
+    next: Option<NonNull<Node<T>>>,
+}
+
+impl<T> Node<T> {
+    fn new_boxed(elem: T) -> NonNull<Node<T>> {
+        let node = Box::new(Node {
+            elem,
+            prev: None,
+            next: None,
+        });
+        // SAFETY: `Box::into_raw` never returns a null pointer.
+        unsafe { NonNull::new_unchecked(Box::into_raw(node)) }
+    }
+}
+
+/// A doubly-linked list.
+///
+/// Unlike [`List`](super::List), every node keeps a pointer to its
+/// predecessor, which allows constant-time insertion and removal at both ends
+/// and iteration in either direction.
+pub struct DoublyLinkedList<T> {
+    head: Option<NonNull<Node<T>>>,
+    tail: Option<NonNull<Node<T>>>,
+    len: usize,
+    _marker: PhantomData<Box<Node<T>>>,
+}
+
+// SAFETY: the list owns all of its nodes, so it is safe to send or share
+// whenever `T` is.
+unsafe impl<T: Send> Send for DoublyLinkedList<T> {}
+unsafe impl<T: Sync> Sync for DoublyLinkedList<T> {}
+
+impl<T> Default for DoublyLinkedList<T> {
+    fn default() -> Self {
+        Self {
+            head: None,
+            tail: None,
+            len: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> DoublyLinkedList<T> {
+    /// Appends an element to the end of the linked list.
+    pub fn add(&mut self, elem: T) {
+        let mut node = Node::new_boxed(elem);
+        match self.tail {
+            Some(mut old_tail) => {
+                // SAFETY: `node` is freshly allocated and `old_tail` is owned
+                // by this list; we hold `&mut self`, so neither is aliased.
+                unsafe {
+                    node.as_mut().prev = Some(old_tail);
+                    old_tail.as_mut().next = Some(node);
+                }
+            }
+            None => self.head = Some(node),
+        }
+        self.tail = Some(node);
+        self.len += 1;
+    }
+
+    /// Prepends an element to the front of the linked list.
+    pub fn push_front(&mut self, elem: T) {
+        let mut node = Node::new_boxed(elem);
+        match self.head {
+            Some(mut old_head) => {
+                // SAFETY: see `add`.
+                unsafe {
+                    node.as_mut().next = Some(old_head);
+                    old_head.as_mut().prev = Some(node);
+                }
+            }
+            None => self.tail = Some(node),
+        }
+        self.head = Some(node);
+        self.len += 1;
+    }
+
+    /// Removes the first element and returns it, or `None` if the list is
+    /// empty.
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.head.map(|head| {
+            // SAFETY: `head` was allocated by `Node::new_boxed` and is owned
+            // by this list. It is unlinked below, so ownership is transferred
+            // back to the box.
+            let head = unsafe { Box::from_raw(head.as_ptr()) };
+            self.head = head.next;
+            match self.head {
+                // SAFETY: the new head is owned by this list.
+                Some(mut new_head) => unsafe { new_head.as_mut().prev = None },
+                None => self.tail = None,
+            }
+            self.len -= 1;
+            head.elem
+        })
+    }
+
+    /// Removes the last element and returns it, or `None` if the list is
+    /// empty.
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.tail.map(|tail| {
+            // SAFETY: see `pop_front`.
+            let tail = unsafe { Box::from_raw(tail.as_ptr()) };
+            self.tail = tail.prev;
+            match self.tail {
+                // SAFETY: the new tail is owned by this list.
+                Some(mut new_tail) => unsafe { new_tail.as_mut().next = None },
+                None => self.head = None,
+            }
+            self.len -= 1;
+            tail.elem
+        })
+    }
+
+    /// Checks whether the given element is part of the linked list.
+    pub fn find(&self, target: &T) -> bool
+    where
+        T: PartialEq + Eq,
+    {
+        self.iter().any(|elem| elem == target)
+    }
+
+    /// Returns the linked list's iterator, which can be traversed from either
+    /// end.
+    pub fn iter(&self) -> DoublyLinkedListIter<'_, T> {
+        DoublyLinkedListIter {
+            head: self.head,
+            tail: self.tail,
+            len: self.len,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of elements contained in this linked list.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether the linked list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Drop for DoublyLinkedList<T> {
+    fn drop(&mut self) {
+        while self.pop_front().is_some() {}
+    }
+}
+
+/// Doubly-linked list iterator.
+pub struct DoublyLinkedListIter<'a, T> {
+    head: Option<NonNull<Node<T>>>,
+    tail: Option<NonNull<Node<T>>>,
+    // Number of elements not yet yielded from either end. The two cursors
+    // cross over once this reaches zero.
+    len: usize,
+    _marker: PhantomData<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for DoublyLinkedListIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.head.map(|node| {
+            // SAFETY: the list outlives `'a` and cannot be mutated while the
+            // iterator borrows it.
+            let node = unsafe { &*node.as_ptr() };
+            self.len -= 1;
+            self.head = node.next;
+            &node.elem
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for DoublyLinkedListIter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.tail.map(|node| {
+            // SAFETY: see `next`.
+            let node = unsafe { &*node.as_ptr() };
+            self.len -= 1;
+            self.tail = node.prev;
+            &node.elem
+        })
+    }
+}
+
+impl<'a, T> ExactSizeIterator for DoublyLinkedListIter<'a, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doubly_linked_list() {
+        let len = 10_000;
+        let mut list = DoublyLinkedList::default();
+        assert!(list.is_empty());
+        for i in 0..len {
+            list.add(i);
+        }
+        assert_eq!(list.len(), len);
+        assert!(list.iter().copied().eq(0..len));
+        assert!(list.iter().rev().copied().eq((0..len).rev()));
+        assert!(list.find(&(len / 2)));
+        assert!(!list.find(&len));
+    }
+
+    #[test]
+    fn doubly_linked_list_both_ends() {
+        let mut list = DoublyLinkedList::default();
+        list.add(1);
+        list.push_front(0);
+        list.add(2);
+
+        let mut itr = list.iter();
+        assert_eq!(itr.next(), Some(&0));
+        assert_eq!(itr.next_back(), Some(&2));
+        assert_eq!(itr.next(), Some(&1));
+        assert_eq!(itr.next_back(), None);
+
+        assert_eq!(list.pop_back(), Some(2));
+        assert_eq!(list.pop_front(), Some(0));
+        assert_eq!(list.pop_back(), Some(1));
+        assert_eq!(list.pop_back(), None);
+        assert_eq!(list.pop_front(), None);
+        assert!(list.is_empty());
+
+        list.push_front(3);
+        assert!(list.iter().rev().copied().eq([3]));
+    }
+}
@@ -1,16 +1,18 @@
-use std::sync::{Mutex, MutexGuard};
-
-use super::{NodeRepr, Set};
+use super::{NodeBox, NodeRepr, Set};
+use crate::primitive::{Mutex, MutexGuard};
+use crate::stats::{Counters, Statistics, Stats};
 
 /// A linked list-based set implemented with fine-grained (hand-over-hand) locking.
 pub struct FineGrainedSet<T> {
     head: Node<T>,
+    counters: Counters,
 }
 
 impl<T> Default for FineGrainedSet<T> {
     fn default() -> Self {
         Self {
             head: Node::new_head(),
+            counters: Counters::new(),
         }
     }
 }
@@ -22,6 +24,7 @@ where
     type Elem = T;
 
     fn add(&self, elem: Self::Elem) -> bool {
+        self.counters.op();
         let mut head_ref = self.head.locked();
         if head_ref.is_empty() {
             head_ref.set_value_on_empty_head(elem);
@@ -52,6 +55,7 @@ where
     }
 
     fn remove(&self, elem: &Self::Elem) -> bool {
+        self.counters.op();
         let mut head_ref = self.head.locked();
         if head_ref.is_empty() {
             return false;
@@ -107,11 +111,19 @@ where
                 if next_elem == elem {
                     let next_of_next = next.into_next();
                     match next_of_next {
-                        Some(rest) => curr.replace_existing(|n| {
-                            let elem = n.into_elem();
+                        Some(rest) => {
+                            // `rest` locks a node owned by the node being
+                            // removed, so take its content and release the
+                            // lock before the removed node is dropped. Other
+                            // threads cannot reach it while we hold `curr`.
                             let parts = rest.into_parts().unwrap();
-                            LockedNode::new_intermediate(elem, LockedNode::from_parts(parts))
-                        }),
+                            curr.replace_existing(|n| {
+                                LockedNode::new_intermediate(
+                                    n.into_elem(),
+                                    LockedNode::from_parts(parts),
+                                )
+                            })
+                        }
                         None => curr.replace_existing(|n| LockedNode::new_tail(n.into_elem())),
                     }
                     return true;
@@ -123,6 +135,15 @@ where
     }
 
     fn contains(&self, elem: &Self::Elem) -> bool {
+        self.counters.lookup(self.find(elem))
+    }
+}
+
+impl<T> FineGrainedSet<T>
+where
+    T: PartialOrd + PartialEq + Eq,
+{
+    fn find(&self, elem: &T) -> bool {
         let head_ref = self.head.locked();
         if head_ref.is_empty() {
             return false;
@@ -144,6 +165,12 @@ where
     }
 }
 
+impl<T> Statistics for FineGrainedSet<T> {
+    fn stats(&self) -> Stats {
+        self.counters.snapshot()
+    }
+}
+
 struct LockedNodeRef<'a, T>(MutexGuard<'a, Option<LockedNode<T>>>);
 
 impl<'a, T> LockedNodeRef<'a, T> {
@@ -152,7 +179,7 @@ impl<'a, T> LockedNodeRef<'a, T> {
     }
 
     fn elem(&self) -> Option<&T> {
-        (&*self.0).as_ref().map(|node| node.elem())
+        (*self.0).as_ref().map(|node| node.elem())
     }
 
     fn replace_existing<F>(&mut self, replace_fn: F)
@@ -175,7 +202,7 @@ impl<'a, T> LockedNodeRef<'a, T> {
         *self.0 = None;
     }
 
-    fn into_parts(mut self) -> Option<(T, Option<Box<Node<T>>>)> {
+    fn into_parts(mut self) -> Option<(T, Option<NodeBox<Node<T>>>)> {
         self.0.take().map(|n| n.into_parts())
     }
 
@@ -197,20 +224,22 @@ impl<'a, T> LockedNodeRef<'a, T> {
         // guard). Below we acquire the lock guard of the next node, if it exists.
         // When this function returns, the destructor of this struct will be called,
         // at which point this node's lock is released.
-        let next = self.next();
+        let next: *const Node<T> = match &(*self.0).as_ref()?.inner {
+            NodeRepr::Elem((_, rest)) => rest.as_ref(),
+            NodeRepr::Tail(_) => return None,
+        };
 
-        // Extend the lifetime of the returned value to the caller's requirements.
+        // Lock the next node through a reference that is not tied to `self`,
+        // as the caller keeps the next node locked after releasing this one.
         //
         // SAFETY:
-        // `next` currently has exclusive access to the next node, which means the
-        // caller is the only party that can delete it.
+        // The next node is boxed, so it stays put when this node's content is
+        // moved. Once we hold its lock, the caller is the only party that can
+        // delete it.
         //
         // Care should be taken to ensure whoever holds `curr`'s lock in the future
         // doesn't delete its successor without first obtaining the next node's lock.
-        //
-        // `next`'s original lifetime was tied to `self` because we could only
-        // obtain the next node from the current node's reference to its successor.
-        unsafe { std::mem::transmute(next) }
+        Some(unsafe { &*next }.locked())
     }
 }
 
@@ -233,14 +262,14 @@ impl<T> LockedNode<T> {
 
     fn new_intermediate<R>(elem: T, rest: R) -> Self
     where
-        R: Into<Box<Node<T>>>,
+        R: Into<NodeBox<Node<T>>>,
     {
         Self {
             inner: NodeRepr::Elem((elem, rest.into())),
         }
     }
 
-    fn from_parts(parts: (T, Option<Box<Node<T>>>)) -> Self {
+    fn from_parts(parts: (T, Option<NodeBox<Node<T>>>)) -> Self {
         let (elem, maybe_rest) = parts;
         let inner = match maybe_rest {
             Some(rest) => NodeRepr::Elem((elem, rest)),
@@ -264,7 +293,7 @@ impl<T> LockedNode<T> {
         }
     }
 
-    fn into_parts(self) -> (T, Option<Box<Node<T>>>) {
+    fn into_parts(self) -> (T, Option<NodeBox<Node<T>>>) {
         self.inner.into_parts()
     }
 
@@ -274,9 +303,9 @@ impl<T> LockedNode<T> {
 }
 
 #[allow(clippy::from_over_into)]
-impl<T> Into<Box<Node<T>>> for LockedNode<T> {
-    fn into(self) -> Box<Node<T>> {
-        Box::new(self.into())
+impl<T> Into<NodeBox<Node<T>>> for LockedNode<T> {
+    fn into(self) -> NodeBox<Node<T>> {
+        NodeBox::new(self.into())
     }
 }
 
@@ -291,7 +320,7 @@ impl<T> Node<T> {
         }
     }
 
-    fn locked(&self) -> LockedNodeRef<T> {
+    fn locked(&self) -> LockedNodeRef<'_, T> {
         self.node.lock().unwrap().into()
     }
 }
@@ -0,0 +1,79 @@
+use super::{ListInner, Node};
+
+/// Number of fingers kept into a list once it is long enough to use them.
+const NUM_FINGERS: usize = 32;
+
+/// Lists shorter than this are searched from the head.
+const MIN_LEN: usize = 2 * NUM_FINGERS;
+
+/// Evenly-spaced pointers into a sorted list, used as starting points for
+/// searches.
+///
+/// The fingers are sorted by element, since they point into a sorted list.
+/// Inserting nodes keeps every finger valid but makes the spacing uneven, so
+/// the fingers are rebuilt whenever the list has doubled in length since they
+/// were last built. Removing nodes may leave a finger dangling, so any removal
+/// must [`clear`](Fingers::clear) them.
+pub(super) struct Fingers<T> {
+    nodes: Vec<*mut Node<T>>,
+    built_len: usize,
+}
+
+// SAFETY: fingers only point into nodes owned by the list they are kept
+// alongside of, and are only dereferenced through that list.
+unsafe impl<T: Send> Send for Fingers<T> {}
+unsafe impl<T: Sync> Sync for Fingers<T> {}
+
+impl<T> Default for Fingers<T> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            built_len: 0,
+        }
+    }
+}
+
+impl<T> Fingers<T> {
+    /// Drops all fingers. Must be called whenever a node is removed from the
+    /// list.
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.built_len = 0;
+    }
+
+    /// Rebuilds the fingers if the list has grown enough for their spacing to
+    /// have degraded.
+    pub fn maybe_refresh(&mut self, list: &mut ListInner<T>) {
+        let len = list.len();
+        if len < MIN_LEN || len < self.built_len * 2 {
+            return;
+        }
+
+        self.nodes.clear();
+        let spacing = len / NUM_FINGERS;
+        let mut curr = list.head_ptr();
+        let mut i = 0;
+        while let Some(node) = curr {
+            if i % spacing == 0 {
+                self.nodes.push(node);
+            }
+            // SAFETY: `node` was just obtained from a live node of the list.
+            curr = unsafe { (*node).next_ptr() };
+            i += 1;
+        }
+        self.built_len = len;
+    }
+
+    /// Returns the last finger whose element is not greater than `elem`, which
+    /// is the closest node a search for `elem` can safely start from.
+    pub fn start_for(&self, elem: &T) -> Option<*mut Node<T>>
+    where
+        T: PartialOrd,
+    {
+        // SAFETY: every finger points to a live node of the list.
+        let idx = self
+            .nodes
+            .partition_point(|&node| unsafe { (*node).get() } <= elem);
+        idx.checked_sub(1).map(|idx| self.nodes[idx])
+    }
+}
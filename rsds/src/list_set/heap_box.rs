@@ -0,0 +1,85 @@
+//! An owning pointer to a heap-allocated list node, for builds without the
+//! `arena` feature.
+
+use std::marker::PhantomData;
+use std::ops::{Deref, DerefMut};
+use std::ptr::NonNull;
+
+/// An owning pointer to a value on the heap, used in place of [`Box`] for
+/// list nodes.
+///
+/// Unlike a [`Box`], moving a `HeapBox` does not assert unique access to
+/// its value. This matters for [`FineGrainedSet`](super::FineGrainedSet),
+/// where a thread holding a node's lock moves the pointer to the next node
+/// while another thread may still be unlocking that node.
+pub(super) struct HeapBox<T> {
+    ptr: NonNull<T>,
+    _marker: PhantomData<T>,
+}
+
+// SAFETY: `HeapBox` owns its value just like `Box` does.
+unsafe impl<T: Send> Send for HeapBox<T> {}
+unsafe impl<T: Sync> Sync for HeapBox<T> {}
+
+impl<T> HeapBox<T> {
+    /// Moves `value` onto the heap.
+    pub(super) fn new(value: T) -> Self {
+        Self {
+            ptr: NonNull::from(Box::leak(Box::new(value))),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Moves the value off the heap.
+    pub(super) fn into_inner(this: Self) -> T {
+        *this.into_box()
+    }
+
+    /// Returns a raw pointer to the value. Later borrows of the box do not
+    /// invalidate it, so lists can keep it to reach a node directly.
+    pub(super) fn as_ptr(this: &Self) -> *mut T {
+        this.ptr.as_ptr()
+    }
+
+    fn into_box(self) -> Box<T> {
+        let ptr = self.ptr;
+        std::mem::forget(self);
+        // SAFETY: the pointer came from `Box::leak`, and is not used again.
+        unsafe { Box::from_raw(ptr.as_ptr()) }
+    }
+}
+
+impl<T> Drop for HeapBox<T> {
+    fn drop(&mut self) {
+        // SAFETY: the pointer came from `Box::leak`, and is not used again.
+        drop(unsafe { Box::from_raw(self.ptr.as_ptr()) });
+    }
+}
+
+impl<T> Deref for HeapBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the value is initialized and owned by us.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for HeapBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: as above.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> AsRef<T> for HeapBox<T> {
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T> AsMut<T> for HeapBox<T> {
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
@@ -1,12 +1,30 @@
 //! A module implementing set as linked lists.
 
+use std::fmt;
 use std::mem::MaybeUninit;
 
+use fingers::Fingers;
+
+#[cfg(feature = "arena")]
+mod arena;
+mod bounded_list;
 mod coarse_set;
+mod doubly_linked_list;
 mod fine_grained_set;
-
+mod fingers;
+mod flat_combining_set;
+#[cfg(not(feature = "arena"))]
+mod heap_box;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod sync_list;
+
+pub use bounded_list::{BoundedList, EvictionPolicy};
 pub use coarse_set::CoarseSet;
+pub use doubly_linked_list::{DoublyLinkedList, DoublyLinkedListIter};
 pub use fine_grained_set::FineGrainedSet;
+pub use flat_combining_set::FlatCombiningSet;
+pub use sync_list::SyncList;
 
 /// Defines common behavior for a set.
 pub trait Set {
@@ -29,8 +47,26 @@ pub trait Set {
     fn contains(&self, elem: &Self::Elem) -> bool;
 }
 
+/// Owning pointer to a list node: a heap allocation, or a block from the
+/// shared node arena with the `arena` feature.
+#[cfg(not(feature = "arena"))]
+type NodeBox<T> = heap_box::HeapBox<T>;
+#[cfg(feature = "arena")]
+type NodeBox<T> = arena::ArenaBox<T>;
+
+/// Moves a node out of its [`NodeBox`].
+#[cfg(not(feature = "arena"))]
+fn unbox<T>(node: NodeBox<T>) -> T {
+    heap_box::HeapBox::into_inner(node)
+}
+
+#[cfg(feature = "arena")]
+fn unbox<T>(node: NodeBox<T>) -> T {
+    arena::ArenaBox::into_inner(node)
+}
+
 enum NodeRepr<T, N> {
-    Elem((T, Box<N>)),
+    Elem((T, NodeBox<N>)),
     Tail(T),
 }
 
@@ -42,7 +78,7 @@ impl<T, N> NodeRepr<T, N> {
         }
     }
 
-    fn into_parts(self) -> (T, Option<Box<N>>) {
+    fn into_parts(self) -> (T, Option<NodeBox<N>>) {
         match self {
             NodeRepr::Elem((elem, rest)) => (elem, Some(rest)),
             NodeRepr::Tail(elem) => (elem, None),
@@ -65,6 +101,13 @@ impl<T, N> NodeRepr<T, N> {
             NodeRepr::Tail(e) => e,
         }
     }
+
+    fn elem_mut(&mut self) -> &mut T {
+        match self {
+            NodeRepr::Elem((e, _)) => e,
+            NodeRepr::Tail(e) => e,
+        }
+    }
 }
 
 struct Node<T> {
@@ -77,13 +120,24 @@ impl<T> Node<T> {
     }
 
     pub fn new_intermediate(elem: T, rest: Node<T>) -> Self {
-        NodeRepr::Elem((elem, Box::new(rest))).into()
+        NodeRepr::Elem((elem, NodeBox::new(rest))).into()
+    }
+
+    fn from_parts(elem: T, rest: Option<NodeBox<Node<T>>>) -> Self {
+        match rest {
+            Some(rest) => NodeRepr::Elem((elem, rest)).into(),
+            None => NodeRepr::Tail(elem).into(),
+        }
     }
 
     fn get(&self) -> &T {
         self.get_node_ref().elem()
     }
 
+    fn get_mut(&mut self) -> &mut T {
+        self.get_node_mut().elem_mut()
+    }
+
     fn next(&self) -> Option<&Self> {
         let node = self.get_node_ref();
         match node {
@@ -92,6 +146,15 @@ impl<T> Node<T> {
         }
     }
 
+    /// Returns a pointer to the next node, which stays usable while the
+    /// list is borrowed again.
+    fn next_ptr(&self) -> Option<*mut Self> {
+        match self.get_node_ref() {
+            NodeRepr::Tail(_) => None,
+            NodeRepr::Elem((_, rest)) => Some(NodeBox::as_ptr(rest)),
+        }
+    }
+
     fn next_mut(&mut self) -> Option<&mut Self> {
         let node = self.get_node_mut();
         match node {
@@ -100,8 +163,16 @@ impl<T> Node<T> {
         }
     }
 
+    fn split_mut(&mut self) -> (&mut T, Option<&mut Self>) {
+        let node = self.get_node_mut();
+        match node {
+            NodeRepr::Tail(elem) => (elem, None),
+            NodeRepr::Elem((elem, rest)) => (elem, Some(rest.as_mut())),
+        }
+    }
+
     /// Transforms a Node into a Tail, returning the rest of the list if exists.
-    fn take_next(&mut self) -> Option<Box<Node<T>>> {
+    fn take_next(&mut self) -> Option<NodeBox<Node<T>>> {
         let node = self.get_node_mut();
         match node {
             NodeRepr::Tail(_) => None,
@@ -120,7 +191,7 @@ impl<T> Node<T> {
         }
     }
 
-    fn set_next(&mut self, new_next: Option<Box<Node<T>>>) {
+    fn set_next(&mut self, new_next: Option<NodeBox<Node<T>>>) {
         self.replace_node_with(move |node| match new_next {
             Some(rest) => NodeRepr::Elem((node.into_elem(), rest)),
             None => NodeRepr::Tail(node.into_elem()),
@@ -129,15 +200,17 @@ impl<T> Node<T> {
 
     fn add(&mut self, elem: T) {
         self.replace_node_with(|node| match node {
-            NodeRepr::Tail(curr) => NodeRepr::Elem((curr, Box::new(NodeRepr::Tail(elem).into()))),
+            NodeRepr::Tail(curr) => {
+                NodeRepr::Elem((curr, NodeBox::new(NodeRepr::Tail(elem).into())))
+            }
             NodeRepr::Elem((curr, rest)) => {
                 let next = NodeRepr::Elem((elem, rest));
-                NodeRepr::Elem((curr, Box::new(next.into())))
+                NodeRepr::Elem((curr, NodeBox::new(next.into())))
             }
         })
     }
 
-    fn into_parts(self) -> (T, Option<Box<Node<T>>>) {
+    fn into_parts(self) -> (T, Option<NodeBox<Node<T>>>) {
         // SAFETY: we guarantee node to be initialized between method invocations.
         let node = unsafe { self.node.assume_init() };
         node.into_parts()
@@ -195,12 +268,52 @@ impl<'a, T> Iterator for ListIter<'a, T> {
     }
 }
 
+/// Linked list iterator yielding mutable references.
+pub struct ListIterMut<'a, T> {
+    curr: Option<&'a mut Node<T>>,
+}
+
+impl<'a, T> Iterator for ListIterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let curr = self.curr.take()?;
+        let (elem, next) = curr.split_mut();
+        self.curr = next;
+        Some(elem)
+    }
+}
+
+/// Linked list iterator that moves elements out of the list.
+pub struct ListIntoIter<T> {
+    inner: ListInner<T>,
+}
+
+impl<T> Iterator for ListIntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.pop_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.inner.len(), Some(self.inner.len()))
+    }
+}
+
+impl<T> ExactSizeIterator for ListIntoIter<T> {}
+
 struct ListInner<T> {
-    head: Option<Node<T>>,
+    head: Option<NodeBox<Node<T>>>,
     tail: Option<*mut Node<T>>,
     len: usize,
 }
 
+// SAFETY: `tail` only ever points into nodes owned by the list itself, so
+// `ListInner` is as thread-safe as the elements it owns.
+unsafe impl<T: Send> Send for ListInner<T> {}
+unsafe impl<T: Sync> Sync for ListInner<T> {}
+
 impl<T> Default for ListInner<T> {
     fn default() -> Self {
         Self {
@@ -211,63 +324,201 @@ impl<T> Default for ListInner<T> {
     }
 }
 
+impl<T> Drop for ListInner<T> {
+    fn drop(&mut self) {
+        // Nodes hold their contents in `MaybeUninit`, so dropping the head
+        // would not release anything behind it. Unlinking one node at a time
+        // frees every node and element, and does so without recursing once
+        // per node.
+        while self.pop_front().is_some() {}
+    }
+}
+
+impl<T: Clone> Clone for ListInner<T> {
+    fn clone(&self) -> Self {
+        // `add` links each copy after the cached tail, so this is a single pass.
+        let mut cloned = ListInner::default();
+        for elem in self.iter() {
+            cloned.add(elem.clone());
+        }
+        cloned
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for ListInner<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
 impl<T> ListInner<T> {
     pub fn add(&mut self, elem: T) {
         if self.head.is_none() {
-            self.head = Some(Node::new_tail(elem));
-            self.tail = Some(self.head.as_mut().unwrap());
+            self.head = Some(NodeBox::new(Node::new_tail(elem)));
+            self.tail = self.head_ptr();
         } else {
             // SAFETY: `tail` is guaranteed to be pointing to the list tail
             // and is guaranteed to be alive.
             let old_tail = unsafe { &mut *self.tail.unwrap() };
             old_tail.add(elem);
-            let new_tail: *mut Node<T> = old_tail.next_mut().unwrap();
-            self.tail = Some(new_tail);
+            self.tail = old_tail.next_ptr();
         }
         self.len += 1;
     }
 
-    pub fn add_ordered(&mut self, elem: T)
+    /// Inserts an element into a sorted list.
+    ///
+    /// If given, the search for the insertion point starts at `hint`, which
+    /// must be a node of this list whose element is not greater than `elem`.
+    pub fn add_ordered(&mut self, elem: T, hint: Option<*mut Node<T>>)
     where
         T: PartialOrd + PartialEq + Eq,
     {
+        match self.last_not_greater(&elem, hint) {
+            Some(prev) => self.insert_after(prev, elem),
+            None => self.push_front(elem),
+        }
+    }
+
+    /// Inserts an element into a sorted list unless an equal element is
+    /// already present, returning whether the element was inserted.
+    ///
+    /// `hint` has the same meaning as in [`ListInner::add_ordered`].
+    pub fn add_ordered_unique(&mut self, elem: T, hint: Option<*mut Node<T>>) -> bool
+    where
+        T: PartialOrd + PartialEq + Eq,
+    {
+        match self.last_not_greater(&elem, hint) {
+            // SAFETY: `prev` was just obtained from a live node of this list.
+            Some(prev) if unsafe { (*prev).get() } == &elem => false,
+            Some(prev) => {
+                self.insert_after(prev, elem);
+                true
+            }
+            None => {
+                self.push_front(elem);
+                true
+            }
+        }
+    }
+
+    /// Finds the last node of a sorted list whose element is not greater than
+    /// `elem`, which is where `elem` should be inserted after. Returns `None`
+    /// if `elem` belongs in front of the head.
+    ///
+    /// The search starts at `hint` if given, or at the head otherwise.
+    fn last_not_greater(&mut self, elem: &T, hint: Option<*mut Node<T>>) -> Option<*mut Node<T>>
+    where
+        T: PartialOrd,
+    {
+        // Appending to the end is the common case, and is answered by the
+        // tail without any traversal.
+        if let Some(tail) = self.tail {
+            // SAFETY: `tail` is guaranteed to be pointing to the list tail and
+            // is guaranteed to be alive.
+            if unsafe { (*tail).get() } <= elem {
+                return Some(tail);
+            }
+        }
+
+        let mut curr = match hint {
+            Some(hint) => hint,
+            None => self.head_ptr()?,
+        };
+        // SAFETY: `curr` always points to a live node of this list.
+        if unsafe { (*curr).get() } > elem {
+            return None;
+        }
+
+        loop {
+            // SAFETY: see above.
+            match unsafe { (*curr).next_ptr() } {
+                Some(next) if unsafe { (*next).get() } <= elem => curr = next,
+                _ => return Some(curr),
+            }
+        }
+    }
+
+    fn push_front(&mut self, elem: T) {
+        let rest = self.head.take();
+        self.head = Some(NodeBox::new(Node::from_parts(elem, rest)));
+        if self.tail.is_none() {
+            self.tail = self.head_ptr();
+        }
+        self.len += 1;
+    }
+
+    /// Links `elem` directly after `prev`, which must be a node of this list.
+    fn insert_after(&mut self, prev: *mut Node<T>, elem: T) {
+        // SAFETY: the caller guarantees `prev` is a live node of this list.
+        let prev = unsafe { &mut *prev };
+        let is_tail = prev.next().is_none();
+        prev.add(elem);
+        if is_tail {
+            self.tail = prev.next_ptr();
+        }
+        self.len += 1;
+    }
+
+    /// Links an already sorted sequence of elements into a sorted list in a
+    /// single pass over both.
+    pub fn extend_sorted<I>(&mut self, sorted: I)
+    where
+        I: IntoIterator<Item = T>,
+        T: PartialOrd,
+    {
+        let mut elems = sorted.into_iter().peekable();
+
+        // Elements that sort before the current head are linked into a
+        // separate prefix, which is then put in front of the existing nodes.
+        if let Some(head) = self.head.as_deref() {
+            let mut prefix = ListInner::default();
+            while let Some(elem) = elems.next_if(|elem| elem < head.get()) {
+                prefix.add(elem);
+            }
+            if !prefix.is_empty() {
+                prefix.append(std::mem::take(self));
+                *self = prefix;
+            }
+        }
+
         if self.head.is_none() {
-            self.head = Some(Node::new_tail(elem));
-            self.tail = Some(self.head.as_mut().unwrap());
-        } else {
-            let mut curr = self.head.as_mut().unwrap();
+            for elem in elems {
+                self.add(elem);
+            }
+            return;
+        }
+
+        let mut curr = self.head_ptr().unwrap();
+        for elem in elems {
+            // Since `elems` is sorted, the insertion point of the next element
+            // is never before the current one.
             loop {
-                // SAFTETY: allow forming two mutable borrows (one in
-                // `c.next_mut()`, and another in `curr.add(...)`).
-                //
-                // This is Ok because `curr.add(...)` would not invalidate the
-                // reference returned by `c.next_mut()`. Plus, if `curr.add(..)`
-                // were invoked, the return value of `c.next_mut()` isn't used.
-                let c = unsafe { &mut *(curr as *mut Node<T>) };
-                match c.next_mut() {
-                    Some(next) => {
-                        if *next.get() > elem {
-                            curr.add(elem);
-                            break;
-                        } else {
-                            curr = next;
-                        }
-                    }
-                    None => {
-                        curr.add(elem);
-                        break;
-                    }
+                // SAFETY: `curr` always points to a node of this list. Nodes
+                // are boxed, so linking in new nodes never moves it.
+                match unsafe { (*curr).next_ptr() } {
+                    Some(next) if unsafe { (*next).get() } <= &elem => curr = next,
+                    _ => break,
                 }
             }
+
+            // SAFETY: see above.
+            let node = unsafe { &mut *curr };
+            let is_tail = node.next().is_none();
+            node.add(elem);
+            curr = node.next_ptr().unwrap();
+            if is_tail {
+                self.tail = Some(curr);
+            }
+            self.len += 1;
         }
-        self.len += 1;
     }
 
     pub fn find(&self, target: &T) -> bool
     where
         T: PartialEq + Eq,
     {
-        let Some(mut curr) = self.head.as_ref() else {
+        let Some(mut curr) = self.head.as_deref() else {
             return false;
         };
 
@@ -284,11 +535,15 @@ impl<T> ListInner<T> {
         }
     }
 
-    pub fn find_ordered(&self, target: &T) -> bool
+    /// Searches a sorted list, starting at `hint` if given. `hint` must be a
+    /// node of this list whose element is not greater than `target`.
+    pub fn find_ordered(&self, target: &T, hint: Option<*mut Node<T>>) -> bool
     where
         T: PartialOrd + PartialEq + Eq,
     {
-        let Some(mut curr) = self.head.as_ref() else {
+        // SAFETY: the caller guarantees `hint` is a live node of this list.
+        let start = hint.map(|hint| unsafe { &*hint }).or(self.head.as_deref());
+        let Some(mut curr) = start else {
             return false;
         };
 
@@ -307,11 +562,355 @@ impl<T> ListInner<T> {
         }
     }
 
+    pub fn append(&mut self, mut other: ListInner<T>) {
+        let Some(other_head) = other.head.take() else {
+            return;
+        };
+
+        match self.tail {
+            Some(tail) => {
+                // SAFETY: `tail` is guaranteed to be pointing to the list tail
+                // and is guaranteed to be alive.
+                let tail = unsafe { &mut *tail };
+                tail.set_next(Some(other_head));
+            }
+            None => self.head = Some(other_head),
+        }
+
+        // `other`'s nodes are boxed, so its tail pointer remains valid after
+        // the chain is moved over.
+        self.tail = other.tail.take();
+        self.len += other.len;
+        other.len = 0;
+    }
+
+    /// Merges another sorted list into this one in a single pass, relinking
+    /// the existing nodes instead of allocating new ones.
+    ///
+    /// On ties, elements of `self` come before elements of `other`.
+    pub fn merge_sorted(&mut self, mut other: ListInner<T>)
+    where
+        T: PartialOrd,
+    {
+        let len = self.len + other.len;
+        let mut left = (self.head.take(), self.tail.take());
+        let mut right = (other.head.take(), other.tail.take());
+        self.len = 0;
+        other.len = 0;
+
+        while let (Some(l), Some(r)) = (&left.0, &right.0) {
+            let src = if l.get() <= r.get() {
+                &mut left
+            } else {
+                &mut right
+            };
+            let mut node = src.0.take().unwrap();
+            src.0 = node.take_next();
+            self.push_node(node);
+        }
+
+        // At most one side has nodes left. They are already sorted and end at
+        // that side's original tail, so the whole remainder is spliced at once.
+        let (rest, rest_tail) = if left.0.is_some() { left } else { right };
+        self.append(ListInner {
+            head: rest,
+            tail: rest_tail,
+            len: len - self.len,
+        });
+    }
+
+    pub fn take_first<F>(&mut self, mut pred: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        if pred(self.head.as_deref()?.get()) {
+            return self.pop_front();
+        }
+
+        let mut prev = self.head_ptr().unwrap();
+        loop {
+            // SAFETY: `prev` always points to a live node of this list.
+            let p = unsafe { &mut *prev };
+            let next = p.next_ptr()?;
+            // SAFETY: as above.
+            if pred(unsafe { (*next).get() }) {
+                let mut removed = p.take_next().unwrap();
+                let rest = removed.take_next();
+                if rest.is_none() {
+                    self.tail = Some(prev);
+                }
+                p.set_next(rest);
+                self.len -= 1;
+                return Some(unbox(removed).into_parts().0);
+            }
+            prev = next;
+        }
+    }
+
+    pub fn retain<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        // Kept nodes are relinked one by one behind a fresh tail, so both the
+        // tail pointer and the length come out right in a single pass.
+        let mut rest = self.head.take();
+        self.tail = None;
+        self.len = 0;
+
+        while let Some(mut node) = rest {
+            rest = node.take_next();
+            if keep(node.get()) {
+                self.push_node(node);
+            } else {
+                drop(unbox(node).into_parts());
+            }
+        }
+    }
+
+    /// Links a detached node after the tail.
+    fn push_node(&mut self, node: NodeBox<Node<T>>) {
+        match self.tail {
+            Some(tail) => {
+                // SAFETY: `tail` is guaranteed to be pointing to the list tail
+                // and is guaranteed to be alive.
+                let tail = unsafe { &mut *tail };
+                tail.set_next(Some(node));
+                self.tail = tail.next_ptr();
+            }
+            None => {
+                self.head = Some(node);
+                self.tail = self.head_ptr();
+            }
+        }
+        self.len += 1;
+    }
+
+    pub fn split_off(&mut self, at: usize) -> ListInner<T> {
+        assert!(
+            at <= self.len,
+            "split index (is {}) should be <= len (is {})",
+            at,
+            self.len
+        );
+
+        if at == 0 {
+            return std::mem::take(self);
+        }
+
+        let new_len = self.len - at;
+        let new_tail = self.node_ptr_at(at - 1).unwrap();
+        // SAFETY: `new_tail` was just obtained from a live node of this list.
+        let rest = unsafe { &mut *new_tail }.take_next();
+
+        let suffix = ListInner {
+            head: rest,
+            tail: if new_len == 0 { None } else { self.tail },
+            len: new_len,
+        };
+        self.tail = Some(new_tail);
+        self.len = at;
+        suffix
+    }
+
+    /// Appends an element, enforcing a maximum length of `capacity`.
+    ///
+    /// Returns the evicted element if one had to make room, or hands `elem`
+    /// back if it was rejected.
+    pub fn add_bounded(
+        &mut self,
+        elem: T,
+        capacity: usize,
+        policy: EvictionPolicy,
+    ) -> Result<Option<T>, T> {
+        if self.len() < capacity {
+            self.add(elem);
+            return Ok(None);
+        }
+
+        match policy {
+            EvictionPolicy::Reject => Err(elem),
+            // A zero-capacity list can never hold the new element.
+            EvictionPolicy::DropOldest if capacity == 0 => Ok(Some(elem)),
+            EvictionPolicy::DropOldest => {
+                let evicted = self.pop_front();
+                self.add(elem);
+                Ok(evicted)
+            }
+        }
+    }
+
+    pub fn insert_at(&mut self, idx: usize, elem: T) {
+        assert!(
+            idx <= self.len,
+            "insertion index (is {}) should be <= len (is {})",
+            idx,
+            self.len
+        );
+
+        if idx == self.len {
+            // Appending uses the cached tail pointer rather than a traversal.
+            self.add(elem);
+        } else if idx == 0 {
+            self.push_front(elem);
+        } else {
+            let prev = self.node_ptr_at(idx - 1).unwrap();
+            self.insert_after(prev, elem);
+        }
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        self.node_at(idx).map(|node| node.get())
+    }
+
+    pub fn front(&self) -> Option<&T> {
+        self.head.as_deref().map(|node| node.get())
+    }
+
+    pub fn back(&self) -> Option<&T> {
+        // SAFETY: `tail` is guaranteed to be pointing to the list tail and is
+        // guaranteed to be alive.
+        self.tail.map(|tail| unsafe { (*tail).get() })
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
+        self.node_at_mut(idx).map(|node| node.get_mut())
+    }
+
+    fn node_at(&self, idx: usize) -> Option<&Node<T>> {
+        if idx >= self.len {
+            return None;
+        }
+
+        let mut curr = self.head.as_deref()?;
+        for _ in 0..idx {
+            curr = curr.next()?;
+        }
+        Some(curr)
+    }
+
+    /// Returns a pointer to the node at `idx`, to be kept while the list is
+    /// borrowed again, as the tail pointer is.
+    fn node_ptr_at(&self, idx: usize) -> Option<*mut Node<T>> {
+        match idx {
+            0 => self.head_ptr(),
+            _ => self.node_at(idx - 1)?.next_ptr(),
+        }
+    }
+
+    fn head_ptr(&self) -> Option<*mut Node<T>> {
+        self.head.as_ref().map(NodeBox::as_ptr)
+    }
+
+    fn node_at_mut(&mut self, idx: usize) -> Option<&mut Node<T>> {
+        if idx >= self.len {
+            return None;
+        }
+
+        let mut curr = self.head.as_deref_mut()?;
+        for _ in 0..idx {
+            curr = curr.next_mut()?;
+        }
+        Some(curr)
+    }
+
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        let mut elems = Vec::with_capacity(self.len);
+        elems.extend(self.iter().cloned());
+        elems
+    }
+
+    pub fn for_each_chunk<F>(&self, chunk_size: usize, mut f: F)
+    where
+        T: Clone,
+        F: FnMut(&[T]),
+    {
+        assert!(chunk_size != 0, "chunk size must be non-zero");
+
+        // A single buffer is reused for every chunk, so at most `chunk_size`
+        // elements are copied out at any point in time.
+        let mut buf = Vec::with_capacity(chunk_size.min(self.len));
+        for elem in self.iter() {
+            buf.push(elem.clone());
+            if buf.len() == chunk_size {
+                f(&buf);
+                buf.clear();
+            }
+        }
+        if !buf.is_empty() {
+            f(&buf);
+        }
+    }
+
+    pub fn find_by<F>(&self, mut pred: F) -> Option<&T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.iter().find(|elem| pred(elem))
+    }
+
+    pub fn position<F>(&self, pred: F) -> Option<usize>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.iter().position(pred)
+    }
+
+    pub fn find_ordered_by<K, F>(&self, key: &K, mut key_fn: F) -> Option<&T>
+    where
+        K: PartialOrd,
+        F: FnMut(&T) -> K,
+    {
+        for elem in self.iter() {
+            let elem_key = key_fn(elem);
+            if elem_key == *key {
+                return Some(elem);
+            } else if elem_key > *key {
+                // keys past this point are all greater than `key`
+                return None;
+            }
+        }
+        None
+    }
+
     pub fn iter(&self) -> ListIter<'_, T> {
-        match self.head {
-            Some(ref h) => ListIter { curr: Some(h) },
-            None => ListIter { curr: None },
+        ListIter {
+            curr: self.head.as_deref(),
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> ListIterMut<'_, T> {
+        ListIterMut {
+            curr: self.head.as_deref_mut(),
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<T> {
+        let head = self.head.take()?;
+        let (elem, rest) = unbox(head).into_parts();
+        self.head = rest;
+        if self.head.is_none() {
+            self.tail = None;
+        }
+        self.len -= 1;
+        Some(elem)
+    }
+
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.len <= 1 {
+            return self.pop_front();
         }
+
+        // The list is singly-linked, so the new tail has to be found by walking
+        // from the front.
+        let new_tail = self.node_ptr_at(self.len - 2).unwrap();
+        // SAFETY: `new_tail` was just obtained from a live node of this list.
+        let old_tail = unsafe { &mut *new_tail }.take_next().unwrap();
+        self.tail = Some(new_tail);
+        self.len -= 1;
+        Some(unbox(old_tail).into_parts().0)
     }
 
     pub fn len(&self) -> usize {
@@ -324,11 +923,19 @@ impl<T> ListInner<T> {
 }
 
 /// A linked list.
-#[derive(Default)]
+#[derive(Clone)]
 pub struct List<T> {
     inner: ListInner<T>,
 }
 
+impl<T> Default for List<T> {
+    fn default() -> Self {
+        Self {
+            inner: ListInner::default(),
+        }
+    }
+}
+
 impl<T> List<T>
 where
     T: PartialEq + Eq,
@@ -338,16 +945,150 @@ where
         self.inner.add(elem)
     }
 
+    /// Moves all elements of `other` to the end of this linked list in
+    /// constant time.
+    pub fn append(&mut self, other: List<T>) {
+        self.inner.append(other.inner)
+    }
+
+    /// Splits the linked list into two at the given index, returning
+    /// everything from `at` onwards. This list keeps elements `[0, at)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&mut self, at: usize) -> List<T> {
+        List {
+            inner: self.inner.split_off(at),
+        }
+    }
+
+    /// Inserts an element at position `idx`, shifting all elements after it
+    /// towards the back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx > len`.
+    pub fn insert_at(&mut self, idx: usize, elem: T) {
+        self.inner.insert_at(idx, elem)
+    }
+
+    /// Returns a reference to the element at position `idx`, or `None` if out
+    /// of bounds.
+    pub fn get(&self, idx: usize) -> Option<&T> {
+        self.inner.get(idx)
+    }
+
+    /// Returns a mutable reference to the element at position `idx`, or `None`
+    /// if out of bounds.
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut T> {
+        self.inner.get_mut(idx)
+    }
+
+    /// Removes the first element and returns it, or `None` if the list is
+    /// empty.
+    pub fn pop_front(&mut self) -> Option<T> {
+        self.inner.pop_front()
+    }
+
+    /// Removes the last element and returns it, or `None` if the list is
+    /// empty.
+    ///
+    /// This operation takes linear time, since the list has to be traversed to
+    /// find the new tail. Use [`DoublyLinkedList`] for a constant-time
+    /// alternative.
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.inner.pop_back()
+    }
+
+    /// Retains only the elements for which `keep` returns `true`, unlinking
+    /// the rest in a single pass.
+    pub fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.inner.retain(keep)
+    }
+
+    /// Unlinks and returns the first element satisfying `pred`, or `None` if
+    /// no element matches.
+    pub fn take_first<F>(&mut self, pred: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.inner.take_first(pred)
+    }
+
     /// Checks whether the given element is part of the linked list.
     pub fn find(&self, target: &T) -> bool {
         self.inner.find(target)
     }
 
+    /// Returns the first element satisfying `pred`, if any.
+    pub fn find_by<F>(&self, pred: F) -> Option<&T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.inner.find_by(pred)
+    }
+
+    /// Returns the index of the first element satisfying `pred`, if any.
+    pub fn position<F>(&self, pred: F) -> Option<usize>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.inner.position(pred)
+    }
+
+    /// Returns a reference to the first element, or `None` if the list is
+    /// empty.
+    pub fn front(&self) -> Option<&T> {
+        self.inner.front()
+    }
+
+    /// Returns a reference to the last element, or `None` if the list is
+    /// empty.
+    ///
+    /// The list keeps track of its tail, so this takes constant time.
+    pub fn back(&self) -> Option<&T> {
+        self.inner.back()
+    }
+
     /// Returns the linked list's iterator.
     pub fn iter(&self) -> ListIter<'_, T> {
         self.inner.iter()
     }
 
+    /// Copies the elements of the linked list into a `Vec`, in order.
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.inner.to_vec()
+    }
+
+    /// Visits the linked list in order as contiguous chunks of at most
+    /// `chunk_size` elements each, for use with APIs that expect slices.
+    ///
+    /// Elements are copied into a reusable buffer, so only one chunk's worth
+    /// of elements is held at a time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn for_each_chunk<F>(&self, chunk_size: usize, f: F)
+    where
+        T: Clone,
+        F: FnMut(&[T]),
+    {
+        self.inner.for_each_chunk(chunk_size, f)
+    }
+
+    /// Returns an iterator that allows modifying each element in place.
+    pub fn iter_mut(&mut self) -> ListIterMut<'_, T> {
+        self.inner.iter_mut()
+    }
+
     /// Returns the number of elements contained in this linked list.
     pub fn len(&self) -> usize {
         self.inner.len()
@@ -359,24 +1100,167 @@ where
     }
 }
 
+impl<T> IntoIterator for List<T> {
+    type Item = T;
+    type IntoIter = ListIntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ListIntoIter { inner: self.inner }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<T> FromIterator<T> for List<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = List::default();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for List<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for elem in iter {
+            self.inner.add(elem);
+        }
+    }
+}
+
 /// A sorted linked list.
-#[derive(Default)]
+///
+/// Once the list grows long enough, it keeps a small set of evenly-spaced
+/// "fingers" into itself, so that searches and insertions can skip most of
+/// the list instead of always starting from the head.
 pub struct OrderedList<T> {
     inner: ListInner<T>,
+    fingers: Fingers<T>,
+}
+
+impl<T> Default for OrderedList<T> {
+    fn default() -> Self {
+        Self {
+            inner: ListInner::default(),
+            fingers: Fingers::default(),
+        }
+    }
+}
+
+impl<T: Clone> Clone for OrderedList<T> {
+    fn clone(&self) -> Self {
+        // Fingers point into the original's nodes, so the clone starts out
+        // without any.
+        Self {
+            inner: self.inner.clone(),
+            fingers: Fingers::default(),
+        }
+    }
 }
 
 impl<T> OrderedList<T>
 where
     T: PartialOrd + PartialEq + Eq,
 {
-    /// Appends an element to the end of the linked list.
+    /// Inserts an element into the linked list, keeping it sorted.
+    ///
+    /// Equal elements are kept in insertion order.
     pub fn add(&mut self, elem: T) {
-        self.inner.add_ordered(elem)
+        let hint = self.fingers.start_for(&elem);
+        self.inner.add_ordered(elem, hint);
+        self.fingers.maybe_refresh(&mut self.inner);
+    }
+
+    /// Inserts an element into the linked list unless an equal element is
+    /// already present.
+    ///
+    /// Returns `true` if the element was inserted, or `false` if it already
+    /// exists. This lets the list be used as a sorted set without a separate
+    /// call to [`OrderedList::find`].
+    pub fn add_unique(&mut self, elem: T) -> bool {
+        let hint = self.fingers.start_for(&elem);
+        let added = self.inner.add_ordered_unique(elem, hint);
+        self.fingers.maybe_refresh(&mut self.inner);
+        added
+    }
+
+    /// Merges all elements of `other` into this linked list in linear time,
+    /// keeping it sorted.
+    ///
+    /// No nodes are allocated; the nodes of both lists are relinked in place.
+    pub fn merge(&mut self, other: OrderedList<T>) {
+        // Relinking keeps every node alive and in sorted order, so existing
+        // fingers remain valid.
+        self.inner.merge_sorted(other.inner);
+        self.fingers.maybe_refresh(&mut self.inner);
+    }
+
+    /// Retains only the elements for which `keep` returns `true`, unlinking
+    /// the rest in a single pass.
+    pub fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&T) -> bool,
+    {
+        // Removed nodes may be pointed to by fingers.
+        self.fingers.clear();
+        self.inner.retain(keep);
+        self.fingers.maybe_refresh(&mut self.inner);
     }
 
     /// Checks whether the given element is part of the linked list.
     pub fn find(&self, target: &T) -> bool {
-        self.inner.find_ordered(target)
+        self.inner
+            .find_ordered(target, self.fingers.start_for(target))
+    }
+
+    /// Returns the first element satisfying `pred`, if any.
+    ///
+    /// This scans the whole list in the worst case. If `pred` compares a key
+    /// that the list is sorted by, prefer [`OrderedList::find_ordered_by`].
+    pub fn find_by<F>(&self, pred: F) -> Option<&T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.inner.find_by(pred)
+    }
+
+    /// Returns the index of the first element satisfying `pred`, if any.
+    pub fn position<F>(&self, pred: F) -> Option<usize>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.inner.position(pred)
+    }
+
+    /// Searches for an element whose key, as extracted by `key_fn`, equals
+    /// `key`.
+    ///
+    /// The list must be sorted by the extracted key, which is the case when
+    /// `T`'s ordering is defined by that key. The search stops as soon as it
+    /// passes the position where `key` would be.
+    pub fn find_ordered_by<K, F>(&self, key: &K, key_fn: F) -> Option<&T>
+    where
+        K: PartialOrd,
+        F: FnMut(&T) -> K,
+    {
+        self.inner.find_ordered_by(key, key_fn)
+    }
+
+    /// Returns a reference to the first element, or `None` if the list is
+    /// empty.
+    pub fn front(&self) -> Option<&T> {
+        self.inner.front()
+    }
+
+    /// Returns a reference to the last element, or `None` if the list is
+    /// empty.
+    ///
+    /// The list keeps track of its tail, so this takes constant time.
+    pub fn back(&self) -> Option<&T> {
+        self.inner.back()
     }
 
     /// Returns the linked list's iterator.
@@ -384,6 +1268,31 @@ where
         self.inner.iter()
     }
 
+    /// Copies the elements of the linked list into a `Vec`, in order.
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.inner.to_vec()
+    }
+
+    /// Visits the linked list in order as contiguous chunks of at most
+    /// `chunk_size` elements each, for use with APIs that expect slices.
+    ///
+    /// Elements are copied into a reusable buffer, so only one chunk's worth
+    /// of elements is held at a time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn for_each_chunk<F>(&self, chunk_size: usize, f: F)
+    where
+        T: Clone,
+        F: FnMut(&[T]),
+    {
+        self.inner.for_each_chunk(chunk_size, f)
+    }
+
     /// Returns the number of elements contained in this linked list.
     pub fn len(&self) -> usize {
         self.inner.len()
@@ -395,8 +1304,49 @@ where
     }
 }
 
+impl<T> IntoIterator for OrderedList<T> {
+    type Item = T;
+    type IntoIter = ListIntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        ListIntoIter { inner: self.inner }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for OrderedList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<T> FromIterator<T> for OrderedList<T>
+where
+    T: Ord,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut list = OrderedList::default();
+        list.extend(iter);
+        list
+    }
+}
+
+impl<T> Extend<T> for OrderedList<T>
+where
+    T: Ord,
+{
+    /// Sorts the incoming elements and links them into the list in a single
+    /// pass, rather than searching for an insertion point for each one.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let mut elems: Vec<T> = iter.into_iter().collect();
+        elems.sort();
+        self.inner.extend_sorted(elems);
+        self.fingers.maybe_refresh(&mut self.inner);
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
 
     use super::*;
@@ -414,6 +1364,29 @@ mod tests {
         assert!(list.iter().copied().eq(0..len));
     }
 
+    #[test]
+    fn linked_list_drop() {
+        struct DropCounter(Arc<AtomicUsize>);
+
+        impl Drop for DropCounter {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let len = 1_000_000;
+        let drops = Arc::new(AtomicUsize::new(0));
+        let list: List<_> = (0..len).map(|_| DropCounter(drops.clone())).collect();
+        drop(list);
+        assert_eq!(drops.load(Ordering::Relaxed), len);
+
+        let list: List<_> = (0..10).map(|_| DropCounter(drops.clone())).collect();
+        let mut itr = list.into_iter();
+        itr.next();
+        drop(itr);
+        assert_eq!(drops.load(Ordering::Relaxed), len + 10);
+    }
+
     #[quickcheck]
     fn linked_list_search_existing(elem: usize) -> bool {
         let mut list = List::default();
@@ -427,6 +1400,172 @@ mod tests {
         !list.find(&elem)
     }
 
+    #[test]
+    fn linked_list_indexed_access() {
+        let mut list = List::default();
+        assert_eq!(list.get(0), None);
+
+        list.insert_at(0, 1);
+        list.insert_at(0, 0);
+        list.insert_at(2, 3);
+        list.insert_at(2, 2);
+        assert_eq!(list.len(), 4);
+        assert!(list.iter().copied().eq(0..4));
+        assert_eq!(list.get(2), Some(&2));
+        assert_eq!(list.get(4), None);
+
+        *list.get_mut(3).unwrap() = 30;
+        assert_eq!(list.get(3), Some(&30));
+
+        // the tail pointer should still point at the last element
+        list.add(4);
+        assert!(list.iter().copied().eq([0, 1, 2, 30, 4]));
+    }
+
+    #[test]
+    fn linked_list_append() {
+        let mut list = List::default();
+        list.append(List::default());
+        assert!(list.is_empty());
+
+        let mut other = List::default();
+        other.add(0);
+        list.append(other);
+        assert!(list.iter().copied().eq([0]));
+
+        let mut other = List::default();
+        for i in 1..10 {
+            other.add(i);
+        }
+        list.append(other);
+        list.append(List::default());
+        assert_eq!(list.len(), 10);
+
+        // appending should carry over the tail pointer of the other list
+        list.add(10);
+        assert!(list.iter().copied().eq(0..11));
+    }
+
+    #[test]
+    fn linked_list_split_off() {
+        let mut list = List::default();
+        for i in 0..10 {
+            list.add(i);
+        }
+
+        let mut suffix = list.split_off(6);
+        assert_eq!(list.len(), 6);
+        assert_eq!(suffix.len(), 4);
+        assert!(list.iter().copied().eq(0..6));
+        assert!(suffix.iter().copied().eq(6..10));
+
+        // both halves should have a valid tail
+        list.add(100);
+        suffix.add(200);
+        assert!(list.iter().copied().eq((0..6).chain([100])));
+        assert!(suffix.iter().copied().eq((6..10).chain([200])));
+
+        let end = list.split_off(list.len());
+        assert!(end.is_empty());
+        let all = list.split_off(0);
+        assert!(list.is_empty());
+        assert_eq!(all.len(), 7);
+    }
+
+    #[test]
+    fn linked_list_pop() {
+        let mut list: List<_> = (0..5).collect();
+        assert_eq!(list.pop_front(), Some(0));
+        assert_eq!(list.pop_back(), Some(4));
+        assert_eq!(list.len(), 3);
+
+        // popping from the back should move the tail pointer
+        list.add(5);
+        assert!(list.iter().copied().eq([1, 2, 3, 5]));
+
+        assert_eq!(list.pop_back(), Some(5));
+        assert_eq!(list.pop_back(), Some(3));
+        assert_eq!(list.pop_front(), Some(1));
+        assert_eq!(list.pop_back(), Some(2));
+        assert_eq!(list.pop_back(), None);
+        assert_eq!(list.pop_front(), None);
+        assert!(list.is_empty());
+
+        list.add(6);
+        assert!(list.iter().copied().eq([6]));
+    }
+
+    #[test]
+    fn linked_list_retain() {
+        let mut list: List<_> = (0..10).collect();
+        list.retain(|i| i % 3 != 0);
+        assert_eq!(list.len(), 6);
+        assert!(list.iter().copied().eq([1, 2, 4, 5, 7, 8]));
+
+        // the last element was removed, so the tail must have moved back
+        list.add(10);
+        assert!(list.iter().copied().eq([1, 2, 4, 5, 7, 8, 10]));
+
+        list.retain(|_| false);
+        assert!(list.is_empty());
+        list.add(0);
+        assert!(list.iter().copied().eq([0]));
+
+        let mut list: OrderedList<_> = (0..10).collect();
+        list.retain(|i| i % 2 == 0);
+        assert!(list.iter().copied().eq((0..10).step_by(2)));
+    }
+
+    #[test]
+    fn list_front_back() {
+        let mut list = List::default();
+        assert_eq!(list.front(), None);
+        assert_eq!(list.back(), None);
+        list.add(1);
+        assert_eq!(list.front(), Some(&1));
+        assert_eq!(list.back(), Some(&1));
+        list.add(2);
+        list.insert_at(0, 0);
+        assert_eq!(list.front(), Some(&0));
+        assert_eq!(list.back(), Some(&2));
+
+        let mut list: OrderedList<_> = [5, 1, 3].into_iter().collect();
+        assert_eq!(list.front(), Some(&1));
+        assert_eq!(list.back(), Some(&5));
+        list.add(0);
+        list.add(6);
+        assert_eq!(list.front(), Some(&0));
+        assert_eq!(list.back(), Some(&6));
+    }
+
+    #[test]
+    fn linked_list_take_first() {
+        let mut list: List<_> = (0..10).collect();
+        assert_eq!(list.take_first(|i| i % 4 == 3), Some(3));
+        assert_eq!(list.take_first(|i| i % 4 == 3), Some(7));
+        assert_eq!(list.take_first(|&i| i == 0), Some(0));
+        assert_eq!(list.take_first(|&i| i == 9), Some(9));
+        assert_eq!(list.take_first(|&i| i > 100), None);
+        assert_eq!(list.len(), 6);
+
+        // taking the last element should move the tail back
+        list.add(10);
+        assert!(list.iter().copied().eq([1, 2, 4, 5, 6, 8, 10]));
+
+        let mut list = List::default();
+        assert_eq!(list.take_first(|_: &usize| true), None);
+        list.add(1);
+        assert_eq!(list.take_first(|_| true), Some(1));
+        assert!(list.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn linked_list_insert_out_of_bounds() {
+        let mut list = List::default();
+        list.insert_at(1, 0);
+    }
+
     #[test]
     fn ordered_list() {
         let min = 0;
@@ -443,8 +1582,225 @@ mod tests {
         for i in (min..max).rev() {
             rev_list.add(i);
         }
-        assert_eq!(list.len(), max - min);
-        assert!(list.iter().copied().eq(min..max));
+        assert_eq!(rev_list.len(), max - min);
+        assert!(rev_list.iter().copied().eq(min..max));
+    }
+
+    #[test]
+    fn list_to_vec() {
+        let list: List<_> = (0..10).collect();
+        assert_eq!(list.to_vec(), (0..10).collect::<Vec<_>>());
+
+        let mut chunks = Vec::new();
+        list.for_each_chunk(4, |chunk| chunks.push(chunk.to_vec()));
+        assert_eq!(chunks, vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]]);
+
+        let list: OrderedList<_> = (0..10).rev().collect();
+        assert_eq!(list.to_vec(), (0..10).collect::<Vec<_>>());
+
+        let mut visited = 0;
+        OrderedList::<usize>::default().for_each_chunk(4, |_| visited += 1);
+        assert_eq!(visited, 0);
+    }
+
+    #[test]
+    fn list_find_by() {
+        let list: List<_> = (0..10).map(|i| (i, i.to_string())).collect();
+        assert_eq!(list.find_by(|(_, s)| s == "4"), Some(&(4, "4".to_string())));
+        assert_eq!(list.find_by(|(i, _)| *i > 10), None);
+        assert_eq!(list.position(|(i, _)| *i == 7), Some(7));
+        assert_eq!(list.position(|(i, _)| *i == 10), None);
+
+        let list: OrderedList<_> = (0..10).map(|i| (i * 2, i)).collect();
+        assert_eq!(list.find_ordered_by(&6, |(k, _)| *k), Some(&(6, 3)));
+        assert_eq!(list.find_ordered_by(&7, |(k, _)| *k), None);
+        assert_eq!(list.find_ordered_by(&100, |(k, _)| *k), None);
+        assert_eq!(list.find_by(|(_, v)| *v == 9), Some(&(18, 9)));
+        assert_eq!(list.position(|(_, v)| *v == 9), Some(9));
+    }
+
+    #[test]
+    fn ordered_list_merge() {
+        let mut evens: OrderedList<_> = (0..20).step_by(2).collect();
+        let odds: OrderedList<_> = (1..30).step_by(2).collect();
+        evens.merge(odds);
+        assert_eq!(evens.len(), 25);
+        assert!(evens.iter().copied().eq((0..20).chain((21..30).step_by(2))));
+
+        // the merged list should end at a valid tail
+        evens.add(100);
+        assert_eq!(evens.iter().last(), Some(&100));
+
+        let mut list = OrderedList::default();
+        list.merge(evens);
+        assert_eq!(list.len(), 26);
+        list.merge(OrderedList::default());
+        assert_eq!(list.len(), 26);
+        list.add(1000);
+        assert_eq!(list.iter().last(), Some(&1000));
+    }
+
+    #[quickcheck]
+    fn ordered_list_with_fingers(elems: Vec<u8>, removed: u8) -> bool {
+        let mut list = OrderedList::default();
+        let mut unique = OrderedList::default();
+        for &elem in &elems {
+            list.add(elem);
+            unique.add_unique(elem);
+        }
+        let mut sorted = elems.clone();
+        sorted.sort_unstable();
+        let mut dedup = sorted.clone();
+        dedup.dedup();
+
+        let mut ok = list.iter().eq(sorted.iter())
+            && unique.iter().eq(dedup.iter())
+            && elems.iter().all(|e| list.find(e) && unique.find(e));
+
+        // removing nodes must not leave dangling fingers behind
+        list.retain(|&e| e != removed);
+        sorted.retain(|&e| e != removed);
+        for &elem in &elems {
+            list.add(elem);
+            sorted.push(elem);
+        }
+        sorted.sort_unstable();
+        ok &= list.iter().eq(sorted.iter());
+        ok
+    }
+
+    #[test]
+    fn ordered_list_large_appends() {
+        let len = 20_000;
+        let mut list = OrderedList::default();
+        for i in 0..len {
+            list.add(i * 2);
+        }
+        for i in 0..len {
+            assert!(list.add_unique(i * 2 + 1));
+        }
+        assert!(list.iter().copied().eq(0..len * 2));
+        assert!(list.find(&(len - 1)));
+        assert!(!list.find(&(len * 2)));
+    }
+
+    #[test]
+    fn ordered_list_add_unique() {
+        let mut list = OrderedList::default();
+        assert!(list.add_unique(5));
+        assert!(list.add_unique(1));
+        assert!(list.add_unique(9));
+        assert!(!list.add_unique(5));
+        assert!(!list.add_unique(1));
+        assert!(!list.add_unique(9));
+        assert!(list.add_unique(3));
+        assert_eq!(list.len(), 4);
+        assert!(list.iter().copied().eq([1, 3, 5, 9]));
+
+        // the tail should be tracked for unique and non-unique insertions alike
+        list.add(10);
+        assert!(list.add_unique(11));
+        list.extend([12]);
+        assert!(list.iter().copied().eq([1, 3, 5, 9, 10, 11, 12]));
+    }
+
+    #[test]
+    fn linked_list_iter_mut() {
+        let mut list = List::default();
+        assert!(list.iter_mut().next().is_none());
+        for i in 0..100 {
+            list.add(i);
+        }
+        for elem in list.iter_mut() {
+            *elem *= 2;
+        }
+        assert!(list.iter().copied().eq((0..100).map(|i| i * 2)));
+    }
+
+    #[test]
+    fn list_into_iter() {
+        let mut list = List::default();
+        for i in 0..100 {
+            list.add(i.to_string());
+        }
+        let mut itr = list.into_iter();
+        assert_eq!(itr.len(), 100);
+        assert_eq!(itr.next(), Some("0".to_string()));
+        assert!(itr.eq((1..100).map(|i| i.to_string())));
+
+        let mut list = OrderedList::default();
+        for i in 0..100 {
+            list.add(i);
+        }
+        assert!(list.into_iter().eq(0..100));
+    }
+
+    #[test]
+    fn list_from_iter() {
+        let mut list: List<_> = (0..10).collect();
+        list.extend(10..20);
+        assert_eq!(list.len(), 20);
+        list.add(20);
+        assert!(list.iter().copied().eq(0..21));
+    }
+
+    #[test]
+    fn ordered_list_from_iter() {
+        let mut list: OrderedList<_> = [5, 3, 9, 1, 7].into_iter().collect();
+        assert!(list.iter().copied().eq([1, 3, 5, 7, 9]));
+
+        list.extend([10, 0, 4, 4, 8, 2, 6, 11]);
+        assert_eq!(list.len(), 13);
+        assert!(list
+            .iter()
+            .copied()
+            .eq([0, 1, 2, 3, 4, 4, 5, 6, 7, 8, 9, 10, 11]));
+        assert!(list.find(&11));
+
+        let mut list = OrderedList::default();
+        list.extend(Vec::<usize>::new());
+        assert!(list.is_empty());
+        list.extend((0..1000).rev());
+        assert!(list.iter().copied().eq(0..1000));
+    }
+
+    #[test]
+    fn miri_linked_list() {
+        // few enough operations to run under Miri, moving the tail pointer
+        // every way the list does
+        let mut list: List<_> = (0..6).collect();
+        let mut suffix = list.split_off(4);
+        suffix.add(6);
+        assert_eq!(list.pop_back(), Some(3));
+        list.retain(|i| i % 2 == 0);
+        list.add(7);
+        list.append(suffix);
+        assert_eq!(list.take_first(|&i| i == 6), Some(6));
+        list.add(8);
+        assert!(list.iter().copied().eq([0, 2, 7, 4, 5, 8]));
+
+        // long enough for the ordered list to keep fingers into it
+        let mut ordered: OrderedList<_> = (0..150).step_by(2).collect();
+        ordered.add(149);
+        ordered.add(51);
+        ordered.retain(|&i| i != 148);
+        assert!(ordered.find(&51) && ordered.find(&149));
+        assert_eq!(ordered.back(), Some(&149));
+    }
+
+    #[test]
+    fn list_debug_clone() {
+        let list: List<_> = (0..3).collect();
+        assert_eq!(format!("{:?}", list), "[0, 1, 2]");
+
+        let mut cloned = list.clone();
+        cloned.add(3);
+        assert!(cloned.iter().copied().eq(0..4));
+        assert_eq!(list.len(), 3);
+
+        let ordered: OrderedList<_> = [2, 0, 1].into_iter().collect();
+        assert_eq!(format!("{:?}", ordered.clone()), "[0, 1, 2]");
+        assert_eq!(format!("{:?}", List::<usize>::default()), "[]");
     }
 
     #[test]
@@ -517,6 +1873,16 @@ mod tests {
         }
     }
 
+    #[cfg(test)]
+    mod flat_combining_set {
+        use crate::list_set::FlatCombiningSet;
+
+        #[test]
+        fn flat_combining_set() {
+            super::test_set::<FlatCombiningSet<usize>>((0..10_000).collect(), 8);
+        }
+    }
+
     #[cfg(test)]
     mod fine_grained_set {
         use crate::list_set::fine_grained_set::FineGrainedSet;
@@ -525,5 +1891,61 @@ mod tests {
         fn fine_grained_set() {
             super::test_set::<FineGrainedSet<usize>>((0..10_000).collect(), 8);
         }
+
+        #[cfg(loom)]
+        #[test]
+        fn loom_fine_grained_set() {
+            use loom::sync::Arc;
+
+            use crate::list_set::Set;
+
+            loom::model(|| {
+                let set = Arc::new(FineGrainedSet::default());
+                for elem in 1..=3 {
+                    set.add(elem);
+                }
+
+                // removals at the head and in the middle, racing with
+                // insertions around them
+                let thread = {
+                    let set = set.clone();
+                    loom::thread::spawn(move || {
+                        assert!(set.remove(&2));
+                        assert!(set.add(4));
+                    })
+                };
+                assert!(set.remove(&1));
+                assert!(set.add(0));
+                thread.join().unwrap();
+
+                for elem in [0, 3, 4] {
+                    assert!(set.contains(&elem));
+                }
+                assert!(!set.contains(&1));
+                assert!(!set.contains(&2));
+            });
+        }
+
+        #[test]
+        fn miri_fine_grained_set() {
+            use crate::list_set::Set;
+
+            // few enough operations to run under Miri
+            let set = FineGrainedSet::default();
+            (0..8).for_each(|elem| assert!(set.add(elem)));
+            std::thread::scope(|s| {
+                for t in 0..2 {
+                    let set = &set;
+                    s.spawn(move || {
+                        // hand-over-hand traversals racing with removals
+                        for elem in (t..8).step_by(2) {
+                            assert!(set.remove(&elem));
+                            assert!(set.add(elem + 8));
+                        }
+                    });
+                }
+            });
+            assert!((8..16).all(|elem| set.contains(&elem)));
+        }
     }
 }
@@ -0,0 +1,130 @@
+//! Serde support for the sequential linked lists.
+//!
+//! Lists are serialized as plain sequences. Deserialization collects the
+//! sequence first and then builds the list in bulk, which lets
+//! [`OrderedList`] sort once instead of searching for every insertion point.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeSeq, Serializer};
+
+use super::{List, OrderedList};
+
+fn serialize_seq<'a, S, T, I>(serializer: S, len: usize, elems: I) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize + 'a,
+    I: Iterator<Item = &'a T>,
+{
+    let mut seq = serializer.serialize_seq(Some(len))?;
+    for elem in elems {
+        seq.serialize_element(elem)?;
+    }
+    seq.end()
+}
+
+struct SeqVisitor<C, T> {
+    _marker: PhantomData<(C, T)>,
+}
+
+impl<'de, C, T> Visitor<'de> for SeqVisitor<C, T>
+where
+    C: FromIterator<T>,
+    T: Deserialize<'de>,
+{
+    type Value = C;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut elems = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(elem) = seq.next_element()? {
+            elems.push(elem);
+        }
+        Ok(elems.into_iter().collect())
+    }
+}
+
+impl<T> Serialize for List<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_seq(serializer, self.inner.len(), self.inner.iter())
+    }
+}
+
+impl<'de, T> Deserialize<'de> for List<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(SeqVisitor {
+            _marker: PhantomData,
+        })
+    }
+}
+
+impl<T> Serialize for OrderedList<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serialize_seq(serializer, self.inner.len(), self.inner.iter())
+    }
+}
+
+impl<'de, T> Deserialize<'de> for OrderedList<T>
+where
+    T: Deserialize<'de> + Ord,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(SeqVisitor {
+            _marker: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_round_trip() {
+        let list: List<usize> = (0..100).collect();
+        let json = serde_json::to_string(&list).unwrap();
+        let de: List<usize> = serde_json::from_str(&json).unwrap();
+        assert!(de.iter().eq(list.iter()));
+    }
+
+    #[test]
+    fn ordered_list_round_trip() {
+        let list: OrderedList<usize> = (0..100).rev().collect();
+        let json = serde_json::to_string(&list).unwrap();
+        let de: OrderedList<usize> = serde_json::from_str(&json).unwrap();
+        assert!(de.iter().copied().eq(0..100));
+
+        // unsorted input is sorted on the way in
+        let de: OrderedList<usize> = serde_json::from_str("[3, 1, 2]").unwrap();
+        assert!(de.iter().copied().eq([1, 2, 3]));
+    }
+}
@@ -0,0 +1,327 @@
+use std::ops::Deref;
+use std::sync::{RwLock, RwLockReadGuard};
+
+use super::{EvictionPolicy, List, ListInner};
+
+/// A linked list that can be shared across threads, implemented with
+/// coarse-grained locking.
+///
+/// All operations take `&self`; readers share a read lock over the whole
+/// list, while any mutation takes the write lock.
+///
+/// A list created with [`SyncList::bounded`] holds at most a fixed number of
+/// elements, and applies its [`EvictionPolicy`] whenever it would grow past
+/// that.
+pub struct SyncList<T> {
+    inner: RwLock<ListInner<T>>,
+    bound: Option<(usize, EvictionPolicy)>,
+}
+
+/// A reference to an element of a [`SyncList`].
+///
+/// The list stays read-locked for as long as the reference is alive.
+pub struct ElemRef<'a, T> {
+    vref: &'a T,
+    _guard: RwLockReadGuard<'a, ListInner<T>>,
+}
+
+impl<'a, T> Deref for ElemRef<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.vref
+    }
+}
+
+impl<T> Default for SyncList<T> {
+    fn default() -> Self {
+        Self {
+            inner: RwLock::new(ListInner::default()),
+            bound: None,
+        }
+    }
+}
+
+impl<T> From<List<T>> for SyncList<T> {
+    fn from(list: List<T>) -> Self {
+        Self {
+            inner: RwLock::new(list.inner),
+            bound: None,
+        }
+    }
+}
+
+impl<T> SyncList<T> {
+    /// Creates a new [`SyncList`] holding at most `capacity` elements.
+    pub fn bounded(capacity: usize, policy: EvictionPolicy) -> Self {
+        Self {
+            inner: RwLock::new(ListInner::default()),
+            bound: Some((capacity, policy)),
+        }
+    }
+
+    /// Returns the maximum number of elements the linked list can hold, or
+    /// `None` if it is unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.bound.map(|(capacity, _)| capacity)
+    }
+}
+
+impl<T> SyncList<T>
+where
+    T: PartialEq + Eq,
+{
+    /// Appends an element to the end of the linked list.
+    ///
+    /// On a bounded list this applies the eviction policy and discards the
+    /// outcome; use [`try_add`](SyncList::try_add) to get back the evicted or
+    /// rejected element.
+    pub fn add(&self, elem: T) {
+        let _ = self.try_add(elem);
+    }
+
+    /// Appends an element to the end of the linked list, reporting what the
+    /// list's bound did to make room for it.
+    ///
+    /// Returns `Ok(Some(evicted))` if the oldest element was evicted, or
+    /// `Err(elem)` if the element was rejected. Unbounded lists always return
+    /// `Ok(None)`.
+    pub fn try_add(&self, elem: T) -> Result<Option<T>, T> {
+        let mut inner = self.inner.write().unwrap();
+        match self.bound {
+            Some((capacity, policy)) => inner.add_bounded(elem, capacity, policy),
+            None => {
+                inner.add(elem);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Moves all elements of `other` to the end of this linked list in
+    /// constant time.
+    ///
+    /// On a bounded list, elements that do not fit are either dropped from the
+    /// front of this list or from the back of `other`, depending on the
+    /// eviction policy.
+    pub fn append(&self, mut other: List<T>) {
+        let mut inner = self.inner.write().unwrap();
+        match self.bound {
+            Some((capacity, EvictionPolicy::Reject)) => {
+                let room = capacity.saturating_sub(inner.len());
+                if other.len() > room {
+                    drop(other.inner.split_off(room));
+                }
+                inner.append(other.inner);
+            }
+            Some((capacity, EvictionPolicy::DropOldest)) => {
+                inner.append(other.inner);
+                while inner.len() > capacity {
+                    inner.pop_front();
+                }
+            }
+            None => inner.append(other.inner),
+        }
+    }
+
+    /// Splits the linked list into two at the given index, returning
+    /// everything from `at` onwards. This list keeps elements `[0, at)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `at > len`.
+    pub fn split_off(&self, at: usize) -> List<T> {
+        List {
+            inner: self.inner.write().unwrap().split_off(at),
+        }
+    }
+
+    /// Inserts an element at position `idx`, shifting all elements after it
+    /// towards the back.
+    ///
+    /// If a bounded list is full, the element is dropped under
+    /// [`EvictionPolicy::Reject`], and the first element is evicted after the
+    /// insertion under [`EvictionPolicy::DropOldest`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `idx > len`.
+    pub fn insert_at(&self, idx: usize, elem: T) {
+        let mut inner = self.inner.write().unwrap();
+        match self.bound {
+            Some((capacity, EvictionPolicy::Reject)) if inner.len() >= capacity => {
+                // Still validate the index, as an unbounded list would.
+                assert!(
+                    idx <= inner.len(),
+                    "insertion index (is {}) should be <= len (is {})",
+                    idx,
+                    inner.len()
+                );
+            }
+            Some((capacity, EvictionPolicy::DropOldest)) => {
+                inner.insert_at(idx, elem);
+                if inner.len() > capacity {
+                    inner.pop_front();
+                }
+            }
+            _ => inner.insert_at(idx, elem),
+        }
+    }
+
+    /// Returns a reference to the element at position `idx`, or `None` if out
+    /// of bounds.
+    pub fn get(&self, idx: usize) -> Option<ElemRef<'_, T>> {
+        let guard = self.inner.read().unwrap();
+        let vref = guard.get(idx)?;
+        // SAFETY: extending the lifetime of vref is safe here because vref will
+        // not be invalidated while the read guard is alive. ElemRef ensures the
+        // guard and vref will have the same lifetime.
+        let vref = unsafe { &*(vref as *const T) };
+        Some(ElemRef {
+            vref,
+            _guard: guard,
+        })
+    }
+
+    /// Removes the first element and returns it, or `None` if the list is
+    /// empty.
+    pub fn pop_front(&self) -> Option<T> {
+        self.inner.write().unwrap().pop_front()
+    }
+
+    /// Removes the last element and returns it, or `None` if the list is
+    /// empty.
+    ///
+    /// This operation takes linear time, since the list has to be traversed to
+    /// find the new tail.
+    pub fn pop_back(&self) -> Option<T> {
+        self.inner.write().unwrap().pop_back()
+    }
+
+    /// Unlinks and returns the first element satisfying `pred`, or `None` if
+    /// no element matches.
+    ///
+    /// The search and the removal happen under the same write lock, so two
+    /// threads can never claim the same element.
+    pub fn take_first<F>(&self, pred: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.inner.write().unwrap().take_first(pred)
+    }
+
+    /// Checks whether the given element is part of the linked list.
+    pub fn find(&self, target: &T) -> bool {
+        self.inner.read().unwrap().find(target)
+    }
+
+    /// Copies the elements of the linked list into a `Vec`, in order.
+    ///
+    /// The copy is taken under a single read lock, so it is a consistent
+    /// snapshot of the list.
+    pub fn to_vec(&self) -> Vec<T>
+    where
+        T: Clone,
+    {
+        self.inner.read().unwrap().to_vec()
+    }
+
+    /// Returns the number of elements contained in this linked list.
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap().len()
+    }
+
+    /// Checks whether the linked list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.inner.read().unwrap().is_empty()
+    }
+
+    /// Consumes the shared list, returning the underlying [`List`].
+    pub fn into_inner(self) -> List<T> {
+        List {
+            inner: self.inner.into_inner().unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn sync_list() {
+        let num_thrs = 8;
+        let num_elems = 1_000;
+        let list = Arc::new(SyncList::default());
+
+        let handles: Vec<_> = (0..num_thrs)
+            .map(|t| {
+                let list = list.clone();
+                std::thread::spawn(move || {
+                    let local: List<_> = (0..num_elems).map(|i| t * num_elems + i).collect();
+                    list.append(local);
+                    assert!(list.find(&(t * num_elems)));
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        assert_eq!(list.len(), num_thrs * num_elems);
+        assert!(list.get(num_thrs * num_elems).is_none());
+        let first = *list.get(0).unwrap();
+        assert_eq!(first % num_elems, 0);
+        assert_eq!(list.to_vec().len(), num_thrs * num_elems);
+
+        let claimed = list.take_first(|&i| i % num_elems == 1).unwrap();
+        assert!(!list.find(&claimed));
+        list.add(claimed);
+
+        let list = Arc::try_unwrap(list).ok().unwrap().into_inner();
+        let mut elems: Vec<_> = list.into_iter().collect();
+        elems.sort_unstable();
+        assert!(elems.into_iter().eq(0..num_thrs * num_elems));
+    }
+
+    #[test]
+    fn sync_list_bounded() {
+        let list = SyncList::bounded(4, EvictionPolicy::DropOldest);
+        assert_eq!(list.capacity(), Some(4));
+        for i in 0..4 {
+            assert_eq!(list.try_add(i), Ok(None));
+        }
+        assert_eq!(list.try_add(4), Ok(Some(0)));
+        list.append((5..8).collect());
+        assert_eq!(list.to_vec(), [4, 5, 6, 7]);
+        list.insert_at(2, 10);
+        assert_eq!(list.to_vec(), [5, 10, 6, 7]);
+
+        let list = SyncList::bounded(4, EvictionPolicy::Reject);
+        list.append((0..3).collect());
+        list.append((3..6).collect());
+        assert_eq!(list.try_add(6), Err(6));
+        list.insert_at(0, 6);
+        list.add(6);
+        assert_eq!(list.to_vec(), [0, 1, 2, 3]);
+
+        let num_thrs = 8;
+        let list = Arc::new(SyncList::bounded(100, EvictionPolicy::DropOldest));
+        let handles: Vec<_> = (0..num_thrs)
+            .map(|t| {
+                let list = list.clone();
+                std::thread::spawn(move || {
+                    for i in 0..1_000 {
+                        list.add(t * 1_000 + i);
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert_eq!(list.len(), 100);
+        assert_eq!(SyncList::<usize>::default().capacity(), None);
+    }
+}
@@ -0,0 +1,263 @@
+//! [`Map`] impls for other crates' concurrent maps, so that they can be
+//! used wherever the crate's maps are, such as in differential tests and
+//! benchmarks. Code written against [`Map`] can also start out on one of
+//! these maps and move to the crate's own later.
+//!
+//! The impls are behind the `adapters` feature.
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use std::sync::Mutex;
+//!
+//! use dashmap::DashMap;
+//! use rsds::map::{Map, StripedHashMap};
+//!
+//! fn count<M: Map<Key = &'static str, Val = u32>>(map: &M, words: &[&'static str]) {
+//!     for &word in words {
+//!         let count = map.get(&word).map_or(0, |count| *count);
+//!         map.put(word, count + 1);
+//!     }
+//! }
+//!
+//! let words = ["a", "b", "a"];
+//! let dash = DashMap::new();
+//! let locked = Mutex::new(HashMap::new());
+//! let striped = StripedHashMap::new();
+//! count(&dash, &words);
+//! count(&locked, &words);
+//! count(&striped, &words);
+//! assert_eq!(*dash.get("a").unwrap(), 2);
+//! assert_eq!(*Map::get(&locked, &"a").unwrap(), 2);
+//! assert_eq!(*striped.get(&"a").unwrap(), 2);
+//! ```
+
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard};
+
+use dashmap::mapref::one::{Ref, RefMut};
+use dashmap::DashMap;
+
+use super::{Map, MapMut};
+
+impl<K, V, S> Map for DashMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    type Key = K;
+    type Val = V;
+    type ValueRef<'a>
+        = Ref<'a, K, V, S>
+    where
+        K: 'a,
+        V: 'a,
+        S: 'a;
+
+    fn get(&self, key: &K) -> Option<Ref<'_, K, V, S>> {
+        DashMap::get(self, key)
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        self.contains_key(key)
+    }
+
+    fn put(&self, key: K, value: V) {
+        self.insert(key, value);
+    }
+
+    fn remove(&self, key: &K) -> bool {
+        DashMap::remove(self, key).is_some()
+    }
+}
+
+impl<K, V, S> MapMut for DashMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    type ValueRefMut<'a>
+        = RefMut<'a, K, V, S>
+    where
+        K: 'a,
+        V: 'a,
+        S: 'a;
+
+    fn get_mut(&self, key: &K) -> Option<RefMut<'_, K, V, S>> {
+        DashMap::get_mut(self, key)
+    }
+
+    fn take(&self, key: &K) -> Option<V> {
+        DashMap::remove(self, key).map(|(_, value)| value)
+    }
+}
+
+/// A reference to a value in a [`Mutex<HashMap>`], which holds the lock
+/// until it is dropped.
+pub struct MutexElemRef<'a, K, V, S> {
+    vref: &'a V,
+    _guard: MutexGuard<'a, HashMap<K, V, S>>,
+}
+
+impl<'a, K, V, S> Deref for MutexElemRef<'a, K, V, S> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.vref
+    }
+}
+
+/// Like using the map directly, every operation panics if the mutex is
+/// poisoned.
+impl<K, V, S> Map for Mutex<HashMap<K, V, S>>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    type Key = K;
+    type Val = V;
+    type ValueRef<'a>
+        = MutexElemRef<'a, K, V, S>
+    where
+        K: 'a,
+        V: 'a,
+        S: 'a;
+
+    fn get(&self, key: &K) -> Option<MutexElemRef<'_, K, V, S>> {
+        let guard = self.lock().unwrap();
+        let vref: *const V = guard.get(key)?;
+        // SAFETY: the value stays in place while the lock guard is alive, and
+        // MutexElemRef keeps the guard alive as long as the reference.
+        Some(MutexElemRef {
+            vref: unsafe { &*vref },
+            _guard: guard,
+        })
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        self.lock().unwrap().contains_key(key)
+    }
+
+    fn put(&self, key: K, value: V) {
+        self.lock().unwrap().insert(key, value);
+    }
+
+    fn remove(&self, key: &K) -> bool {
+        self.lock().unwrap().remove(key).is_some()
+    }
+}
+
+/// A mutable reference to a value in a [`Mutex<HashMap>`], which holds the
+/// lock until it is dropped.
+pub struct MutexElemRefMut<'a, K, V, S> {
+    vref: &'a mut V,
+    _guard: MutexGuard<'a, HashMap<K, V, S>>,
+}
+
+impl<'a, K, V, S> Deref for MutexElemRefMut<'a, K, V, S> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.vref
+    }
+}
+
+impl<'a, K, V, S> DerefMut for MutexElemRefMut<'a, K, V, S> {
+    fn deref_mut(&mut self) -> &mut V {
+        self.vref
+    }
+}
+
+impl<K, V, S> MapMut for Mutex<HashMap<K, V, S>>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    type ValueRefMut<'a>
+        = MutexElemRefMut<'a, K, V, S>
+    where
+        K: 'a,
+        V: 'a,
+        S: 'a;
+
+    fn get_mut(&self, key: &K) -> Option<MutexElemRefMut<'_, K, V, S>> {
+        let mut guard = self.lock().unwrap();
+        let vref: *mut V = guard.get_mut(key)?;
+        // SAFETY: as in `get`, and the guard is not used to reach the map
+        // again while the reference is alive.
+        Some(MutexElemRefMut {
+            vref: unsafe { &mut *vref },
+            _guard: guard,
+        })
+    }
+
+    fn take(&self, key: &K) -> Option<V> {
+        self.lock().unwrap().remove(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_map<M: MapMut<Key = String, Val = usize>>(map: &M) {
+        assert!(map.get(&"a".to_string()).is_none());
+        assert!(map.get_mut(&"a".to_string()).is_none());
+        map.put("a".to_string(), 1);
+        map.put("a".to_string(), 2);
+        assert_eq!(*map.get(&"a".to_string()).unwrap(), 2);
+        *map.get_mut(&"a".to_string()).unwrap() += 1;
+        assert_eq!(*map.get(&"a".to_string()).unwrap(), 3);
+        assert!(map.contains(&"a".to_string()));
+        assert!(map.remove(&"a".to_string()));
+        assert!(!map.remove(&"a".to_string()));
+        map.put("b".to_string(), 4);
+        assert_eq!(map.take(&"b".to_string()), Some(4));
+        assert_eq!(map.take(&"b".to_string()), None);
+        assert!(!map.contains(&"a".to_string()));
+    }
+
+    fn check_map_concurrent<M: Map<Key = usize, Val = usize> + Sync>(map: &M) {
+        let num_thrs = 8;
+        let num_keys = 1000;
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                s.spawn(move || {
+                    for key in (t..num_keys).step_by(num_thrs) {
+                        map.put(key, key * 2);
+                        assert_eq!(*map.get(&key).unwrap(), key * 2);
+                        if key % 2 == 0 {
+                            assert!(map.remove(&key));
+                        }
+                    }
+                });
+            }
+        });
+        for key in 0..num_keys {
+            assert_eq!(map.contains(&key), key % 2 == 1);
+        }
+    }
+
+    #[test]
+    fn adapters() {
+        check_map(&DashMap::new());
+        check_map(&Mutex::new(HashMap::new()));
+    }
+
+    #[test]
+    fn adapters_concurrent() {
+        check_map_concurrent(&DashMap::new());
+        check_map_concurrent(&Mutex::new(HashMap::new()));
+    }
+
+    #[cfg(feature = "stress")]
+    #[test]
+    fn adapters_differential() {
+        use crate::differential::check_maps;
+        use crate::map::StripedHashMap;
+
+        check_maps::<DashMap<u64, u64>, StripedHashMap<u64, u64>>();
+        check_maps::<Mutex<HashMap<u64, u64>>, StripedHashMap<u64, u64>>();
+    }
+}
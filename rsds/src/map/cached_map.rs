@@ -0,0 +1,290 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hash};
+use std::slice;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, TryLockError};
+
+use crossbeam::utils::CachePadded;
+
+use super::{Map, StripedHashMap};
+use crate::sync::thread_index;
+
+const CACHE_CAPACITY: usize = 16;
+
+/// A value in the underlying map, with the version counter its cached copies
+/// are checked against.
+struct Versioned<V> {
+    value: Arc<V>,
+    /// Bumped each time the value is overwritten or removed.
+    version: Arc<AtomicU64>,
+}
+
+impl<V> Versioned<V> {
+    fn new(value: V) -> Self {
+        Versioned {
+            value: Arc::new(value),
+            version: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Called under the bucket's write lock.
+    fn set(&mut self, value: V) {
+        self.value = Arc::new(value);
+        self.version.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+struct Cached<K, V> {
+    key: K,
+    value: Arc<V>,
+    version: Arc<AtomicU64>,
+    seen: u64,
+}
+
+/// A thread's cache, most recently used entry first.
+type ThreadCache<K, V> = Mutex<Vec<Cached<K, V>>>;
+
+/// A [`StripedHashMap`] with a small cache of recently read entries per
+/// thread, so that reads of the hottest keys skip the map and its locks.
+///
+/// Values are handed out as [`Arc`]s. Each entry in the map carries a version
+/// counter, which writers bump under the bucket lock, and a cached entry is
+/// only used while the counter still reads what it did when the entry was
+/// cached. Reads through the cache are therefore never stale, and each costs
+/// an uncontended lock of the thread's cache and a scan of its entries, most
+/// recently used first.
+///
+/// Threads are spread over as many caches as there are cores. A thread
+/// whose cache is in use by another thread reads the map directly.
+///
+/// ```
+/// use rsds::map::{CachedMap, Map};
+///
+/// let map = CachedMap::new();
+/// map.put("hot", 1);
+/// assert_eq!(*map.get(&"hot").unwrap(), 1);
+/// map.put("hot", 2);
+/// assert_eq!(*map.get(&"hot").unwrap(), 2);
+/// ```
+pub struct CachedMap<K: Hash + PartialEq, V, S = RandomState> {
+    map: StripedHashMap<K, Versioned<V>, S>,
+    caches: Box<[CachePadded<ThreadCache<K, V>>]>,
+    cache_capacity: usize,
+}
+
+impl<K, V> Default for CachedMap<K, V, RandomState>
+where
+    K: Hash + PartialEq,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> CachedMap<K, V, RandomState>
+where
+    K: Hash + PartialEq,
+{
+    /// Creates a new, empty [`CachedMap`], caching up to 16 entries per
+    /// thread.
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+
+    /// Creates a new, empty [`CachedMap`], caching up to `cache_capacity`
+    /// entries per thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cache_capacity` is zero.
+    pub fn with_cache_capacity(cache_capacity: usize) -> Self {
+        Self::build(StripedHashMap::new(), cache_capacity)
+    }
+}
+
+impl<K, V, S> CachedMap<K, V, S>
+where
+    K: Hash + PartialEq,
+    S: BuildHasher,
+{
+    /// Creates a new, empty [`CachedMap`] with a given hasher.
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::build(StripedHashMap::with_hasher(hasher), CACHE_CAPACITY)
+    }
+
+    fn build(map: StripedHashMap<K, Versioned<V>, S>, cache_capacity: usize) -> Self {
+        assert!(
+            cache_capacity > 0,
+            "cache capacity (is {}) should be positive",
+            cache_capacity
+        );
+        let num_caches = std::thread::available_parallelism().map_or(8, |n| n.get());
+        CachedMap {
+            map,
+            caches: (0..num_caches)
+                .map(|_| CachePadded::new(Mutex::new(Vec::with_capacity(cache_capacity))))
+                .collect(),
+            cache_capacity,
+        }
+    }
+}
+
+impl<K, V, S> CachedMap<K, V, S>
+where
+    K: Hash + PartialEq + Clone,
+    S: BuildHasher,
+{
+    /// Returns the value associated with `key`, if there is one, from the
+    /// calling thread's cache if it holds the current value.
+    pub fn get(&self, key: &K) -> Option<Arc<V>> {
+        let slot = &self.caches[thread_index() % self.caches.len()];
+        let mut cache = match slot.try_lock() {
+            Ok(cache) => cache,
+            Err(TryLockError::Poisoned(err)) => err.into_inner(),
+            Err(TryLockError::WouldBlock) => return self._read(key).map(|cached| cached.value),
+        };
+
+        if let Some(i) = cache.iter().position(|cached| cached.key == *key) {
+            let cached = &cache[i];
+            if cached.version.load(Ordering::SeqCst) == cached.seen {
+                let value = cached.value.clone();
+                cache[..=i].rotate_right(1);
+                return Some(value);
+            }
+            cache.remove(i);
+        }
+
+        let cached = self._read(key)?;
+        let value = cached.value.clone();
+        if cache.len() == self.cache_capacity {
+            cache.pop();
+        }
+        cache.insert(0, cached);
+        Some(value)
+    }
+
+    /// Reads the entry of `key` from the map.
+    fn _read(&self, key: &K) -> Option<Cached<K, V>> {
+        self.map.get_with(key, |entry| Cached {
+            key: key.clone(),
+            value: entry.value.clone(),
+            version: entry.version.clone(),
+            // The version only changes under the bucket's write lock.
+            seen: entry.version.load(Ordering::SeqCst),
+        })
+    }
+
+    /// Associates `value` with `key`, invalidating cached copies of the value
+    /// it replaces.
+    pub fn put(&self, key: K, value: V) {
+        let mut value = Some(value);
+        let updated = self
+            .map
+            .modify_with(&key, |entry| entry.set(value.take().unwrap()));
+        if updated.is_none() {
+            // The key was absent, but may have been inserted since.
+            let value = value.unwrap();
+            self.map.transact(slice::from_ref(&key), |entries| {
+                match entries[0].get_mut() {
+                    Some(entry) => entry.set(value),
+                    None => drop(entries[0].insert(Versioned::new(value))),
+                }
+            });
+        }
+    }
+
+    /// Removes the value associated with `key`, invalidating cached copies
+    /// of it, and returns whether there was one.
+    pub fn remove(&self, key: &K) -> bool {
+        self.map.transact(slice::from_ref(key), |entries| {
+            let removed = entries[0].remove();
+            if let Some(entry) = &removed {
+                entry.version.fetch_add(1, Ordering::SeqCst);
+            }
+            removed.is_some()
+        })
+    }
+
+    /// Returns whether `key` is associated with a value.
+    pub fn contains(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+}
+
+impl<K, V, S> Map for CachedMap<K, V, S>
+where
+    K: Hash + PartialEq + Clone,
+    S: BuildHasher,
+{
+    type Key = K;
+    type Val = V;
+    type ValueRef<'a> = Arc<V> where K: 'a, V: 'a, S: 'a;
+
+    fn get(&self, key: &K) -> Option<Arc<V>> {
+        CachedMap::get(self, key)
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        CachedMap::contains(self, key)
+    }
+
+    fn put(&self, key: K, value: V) {
+        CachedMap::put(self, key, value)
+    }
+
+    fn remove(&self, key: &K) -> bool {
+        CachedMap::remove(self, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cached_map() {
+        let map = CachedMap::with_cache_capacity(2);
+        for key in 0..4 {
+            map.put(key, key);
+        }
+        for _ in 0..3 {
+            for key in 0..4 {
+                assert_eq!(map.get(&key).as_deref(), Some(&key));
+            }
+        }
+
+        // cached copies are dropped once the value changes
+        let old = map.get(&0).unwrap();
+        map.put(0, 10);
+        assert_eq!(*map.get(&0).unwrap(), 10);
+        assert_eq!(*old, 0);
+        assert!(map.remove(&0));
+        assert!(map.get(&0).is_none());
+        assert!(!map.remove(&0));
+        map.put(0, 20);
+        assert_eq!(*map.get(&0).unwrap(), 20);
+    }
+
+    #[test]
+    fn test_cached_map_concurrent() {
+        let map = CachedMap::new();
+        map.put(0, 0);
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    // values only grow, which a stale cache would break
+                    let mut last = 0;
+                    for _ in 0..10_000 {
+                        let value = *map.get(&0).unwrap();
+                        assert!(value >= last);
+                        last = value;
+                    }
+                });
+            }
+            for value in 1..=1_000 {
+                map.put(0, value);
+            }
+        });
+        assert_eq!(*map.get(&0).unwrap(), 1_000);
+    }
+}
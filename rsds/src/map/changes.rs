@@ -0,0 +1,277 @@
+//! Change notifications for [`StripedHashMap`](super::StripedHashMap)
+//! subscribers.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+#[cfg(can_block)]
+use std::sync::Condvar;
+use std::sync::{Arc, Mutex, OnceLock, RwLock, Weak};
+
+/// What a change did to its key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The key was inserted.
+    Insert,
+    /// The key's value was overwritten or modified in place.
+    Update,
+    /// The key was removed.
+    Remove,
+}
+
+/// A change to a map, as received by a [`ChangeReceiver`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChangeEvent<K, V> {
+    /// The change's place among all of the map's changes. Changes to a key
+    /// are numbered in the order they were made, but threads racing to
+    /// update it may deliver them out of order.
+    pub seq: u64,
+    /// What the change did.
+    pub kind: ChangeKind,
+    /// The changed key.
+    pub key: K,
+    /// The key's new value, for inserts and updates seen by a receiver from
+    /// [`StripedHashMap::subscribe`](super::StripedHashMap::subscribe).
+    pub value: Option<V>,
+}
+
+/// The error a [`ChangeReceiver`] returns after falling so far behind that
+/// its buffer overflowed, with the number of events it missed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lagged(pub u64);
+
+impl fmt::Display for Lagged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "receiver lagged behind by {} events", self.0)
+    }
+}
+
+impl std::error::Error for Lagged {}
+
+struct Buffer<K, V> {
+    events: VecDeque<ChangeEvent<K, V>>,
+    /// Number of events dropped since the receiver last heard of it.
+    lagged: u64,
+}
+
+/// A receiver's buffer, shared with the map.
+struct Subscription<K, V> {
+    buffer: Mutex<Buffer<K, V>>,
+    capacity: usize,
+    values: bool,
+    closed: AtomicBool,
+    #[cfg(can_block)]
+    ready: Condvar,
+}
+
+impl<K, V> Subscription<K, V> {
+    fn push(&self, event: ChangeEvent<K, V>) {
+        let mut buffer = self.buffer.lock().unwrap();
+        if buffer.events.len() == self.capacity {
+            buffer.events.pop_front();
+            buffer.lagged += 1;
+        }
+        buffer.events.push_back(event);
+        drop(buffer);
+        #[cfg(can_block)]
+        self.ready.notify_one();
+    }
+
+    fn close(&self) {
+        // Taking the lock orders the store before a receiver's check and wait.
+        let _buffer = self.buffer.lock().unwrap();
+        self.closed.store(true, Ordering::Release);
+        #[cfg(can_block)]
+        self.ready.notify_one();
+    }
+}
+
+/// The subscribers to a map's changes.
+pub(super) struct Changes<K, V> {
+    subscribers: RwLock<Vec<Weak<Subscription<K, V>>>>,
+    /// Whether there may be subscribers, so that changes are only captured
+    /// while there are.
+    active: AtomicBool,
+    seq: AtomicU64,
+    /// How to copy keys into events, which only subscribing requires of them.
+    clone_key: OnceLock<fn(&K) -> K>,
+    /// How to copy values into events, set once a subscriber wants them.
+    clone_value: OnceLock<fn(&V) -> V>,
+}
+
+impl<K, V> Changes<K, V> {
+    pub(super) fn new() -> Self {
+        Changes {
+            subscribers: RwLock::new(Vec::new()),
+            active: AtomicBool::new(false),
+            seq: AtomicU64::new(0),
+            clone_key: OnceLock::new(),
+            clone_value: OnceLock::new(),
+        }
+    }
+
+    /// Adds a subscriber, whose events carry values if given `clone_value`.
+    pub(super) fn subscribe(
+        &self,
+        capacity: usize,
+        clone_value: Option<fn(&V) -> V>,
+    ) -> ChangeReceiver<K, V>
+    where
+        K: Clone,
+    {
+        assert!(
+            capacity > 0,
+            "capacity (is {}) should be positive",
+            capacity
+        );
+        self.clone_key.get_or_init(|| K::clone);
+        if let Some(clone_value) = clone_value {
+            self.clone_value.get_or_init(|| clone_value);
+        }
+        let subscription = Arc::new(Subscription {
+            buffer: Mutex::new(Buffer {
+                events: VecDeque::new(),
+                lagged: 0,
+            }),
+            capacity,
+            values: clone_value.is_some(),
+            closed: AtomicBool::new(false),
+            #[cfg(can_block)]
+            ready: Condvar::new(),
+        });
+        let mut subscribers = self.subscribers.write().unwrap();
+        subscribers.push(Arc::downgrade(&subscription));
+        self.active.store(true, Ordering::Release);
+        ChangeReceiver { subscription }
+    }
+
+    /// Captures a change, if anyone is subscribed, to be sent with
+    /// [`Changes::send`] once the bucket lock is released.
+    ///
+    /// This is called under the lock of the bucket holding `key`, so that
+    /// changes to a key are numbered in order.
+    pub(super) fn capture(
+        &self,
+        kind: ChangeKind,
+        key: &K,
+        value: Option<&V>,
+    ) -> Option<ChangeEvent<K, V>> {
+        if !self.active.load(Ordering::Acquire) {
+            return None;
+        }
+        let clone_key = self.clone_key.get()?;
+        let clone_value = self.clone_value.get();
+        Some(ChangeEvent {
+            seq: self.seq.fetch_add(1, Ordering::Relaxed),
+            kind,
+            key: clone_key(key),
+            value: value
+                .zip(clone_value)
+                .map(|(value, clone_value)| clone_value(value)),
+        })
+    }
+
+    /// Sends a captured change to every subscriber, dropping its oldest
+    /// event for those whose buffer is full.
+    pub(super) fn send(&self, event: Option<ChangeEvent<K, V>>) {
+        let Some(event) = event else {
+            return;
+        };
+        let (Some(clone_key), clone_value) = (self.clone_key.get(), self.clone_value.get()) else {
+            return;
+        };
+        let mut dropped = false;
+        for subscriber in self.subscribers.read().unwrap().iter() {
+            let Some(subscription) = subscriber.upgrade() else {
+                dropped = true;
+                continue;
+            };
+            let value = match (&event.value, clone_value) {
+                (Some(value), Some(clone_value)) if subscription.values => Some(clone_value(value)),
+                _ => None,
+            };
+            subscription.push(ChangeEvent {
+                seq: event.seq,
+                kind: event.kind,
+                key: clone_key(&event.key),
+                value,
+            });
+        }
+        if dropped {
+            let mut subscribers = self.subscribers.write().unwrap();
+            subscribers.retain(|subscriber| subscriber.strong_count() > 0);
+            if subscribers.is_empty() {
+                self.active.store(false, Ordering::Release);
+            }
+        }
+    }
+}
+
+impl<K, V> Drop for Changes<K, V> {
+    fn drop(&mut self) {
+        let subscribers = self
+            .subscribers
+            .get_mut()
+            .unwrap_or_else(|err| err.into_inner());
+        for subscription in subscribers.iter().filter_map(Weak::upgrade) {
+            subscription.close();
+        }
+    }
+}
+
+/// A receiver of a [`StripedHashMap`](super::StripedHashMap)'s changes,
+/// created by its `subscribe` methods.
+///
+/// Each receiver buffers up to a set number of events. A receiver that falls
+/// further behind loses the oldest of them, and is told how many it lost
+/// before it receives the rest.
+pub struct ChangeReceiver<K, V> {
+    subscription: Arc<Subscription<K, V>>,
+}
+
+impl<K, V> ChangeReceiver<K, V> {
+    /// Receives the oldest buffered event without blocking, returning `None`
+    /// if there is none.
+    ///
+    /// Returns [`Lagged`] instead, once, if events were lost since the last
+    /// call.
+    pub fn try_recv(&mut self) -> Option<Result<ChangeEvent<K, V>, Lagged>> {
+        let mut buffer = self.subscription.buffer.lock().unwrap();
+        Self::pop(&mut buffer)
+    }
+
+    /// Receives the oldest buffered event, blocking until there is one.
+    ///
+    /// Returns `None` once the buffer is empty and the map has been dropped,
+    /// and [`Lagged`] as with [`ChangeReceiver::try_recv`].
+    #[cfg(can_block)]
+    pub fn recv(&mut self) -> Option<Result<ChangeEvent<K, V>, Lagged>> {
+        let subscription = &*self.subscription;
+        let mut buffer = subscription.buffer.lock().unwrap();
+        loop {
+            if let Some(event) = Self::pop(&mut buffer) {
+                return Some(event);
+            }
+            if subscription.closed.load(Ordering::Acquire) {
+                return None;
+            }
+            buffer = subscription.ready.wait(buffer).unwrap();
+        }
+    }
+
+    fn pop(buffer: &mut Buffer<K, V>) -> Option<Result<ChangeEvent<K, V>, Lagged>> {
+        if buffer.lagged > 0 {
+            return Some(Err(Lagged(std::mem::take(&mut buffer.lagged))));
+        }
+        buffer.events.pop_front().map(Ok)
+    }
+}
+
+impl<K, V> fmt::Debug for ChangeReceiver<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangeReceiver")
+            .field("capacity", &self.subscription.capacity)
+            .field("values", &self.subscription.values)
+            .finish_non_exhaustive()
+    }
+}
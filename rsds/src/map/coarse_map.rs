@@ -1,19 +1,29 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
 use std::hash::{BuildHasher, Hash};
-use std::ops::Deref;
-use std::sync::MutexGuard;
-use std::{collections::HashMap, sync::Mutex};
+use std::ops::{Deref, DerefMut};
 
-use super::Map;
+use super::{Map, MapMut};
+use crate::stats::{Counters, Statistics, Stats};
+use crate::sync::{Lock, LockGuard, RawLock, RawSpinLock};
+use crate::trace::event;
 
 /// A concurrent hashmap implemented with coarse-grained locking.
-pub struct CoarseMap<K, V, S>(Mutex<HashMap<K, V, S>>);
+///
+/// The whole map is guarded by a single lock of type `L`, which can be any
+/// [`RawLock`], such as [`RawTicketLock`] or [`RawMcsLock`], to compare how
+/// locking algorithms behave under contention.
+///
+/// [`RawTicketLock`]: crate::sync::RawTicketLock
+/// [`RawMcsLock`]: crate::sync::RawMcsLock
+pub struct CoarseMap<K, V, S = RandomState, L = RawSpinLock>(Lock<L, HashMap<K, V, S>>, Counters);
 
-pub struct ElemRef<'a, K, V, S> {
+pub struct ElemRef<'a, K, V, S, L: RawLock> {
     vref: &'a V,
-    _guard: MutexGuard<'a, HashMap<K, V, S>>,
+    _guard: LockGuard<'a, L, HashMap<K, V, S>>,
 }
 
-impl<'a, K, V, S> Deref for ElemRef<'a, K, V, S> {
+impl<'a, K, V, S, L: RawLock> Deref for ElemRef<'a, K, V, S, L> {
     type Target = V;
 
     fn deref(&self) -> &Self::Target {
@@ -21,27 +31,83 @@ impl<'a, K, V, S> Deref for ElemRef<'a, K, V, S> {
     }
 }
 
-impl<K, V, S> Map for CoarseMap<K, V, S>
+pub struct ElemRefMut<'a, K, V, S, L: RawLock> {
+    vref: &'a mut V,
+    _guard: LockGuard<'a, L, HashMap<K, V, S>>,
+}
+
+impl<'a, K, V, S, L: RawLock> Deref for ElemRefMut<'a, K, V, S, L> {
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        self.vref
+    }
+}
+
+impl<'a, K, V, S, L: RawLock> DerefMut for ElemRefMut<'a, K, V, S, L> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.vref
+    }
+}
+
+impl<K, V, L> Default for CoarseMap<K, V, RandomState, L>
+where
+    L: RawLock,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, L> CoarseMap<K, V, RandomState, L>
+where
+    L: RawLock,
+{
+    /// Creates a new, empty [`CoarseMap`].
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K, V, S, L> CoarseMap<K, V, S, L>
+where
+    L: RawLock,
+{
+    /// Creates a new, empty [`CoarseMap`] with a given hasher.
+    pub fn with_hasher(hasher: S) -> Self {
+        Self(Lock::new(HashMap::with_hasher(hasher)), Counters::new())
+    }
+}
+
+impl<K, V, S, L> Map for CoarseMap<K, V, S, L>
 where
     K: PartialEq + Eq + Hash + PartialEq,
     S: BuildHasher,
+    L: RawLock,
 {
     type Key = K;
     type Val = V;
-    type ValueRef<'a> = ElemRef<'a, K, V, S> where K: 'a, V: 'a, S: 'a;
+    type ValueRef<'a>
+        = ElemRef<'a, K, V, S, L>
+    where
+        K: 'a,
+        V: 'a,
+        S: 'a,
+        L: 'a;
 
-    fn get(&self, key: &K) -> Option<ElemRef<'_, K, V, S>> {
-        let guard = self.0.lock().unwrap();
+    fn get(&self, key: &K) -> Option<ElemRef<'_, K, V, S, L>> {
+        let guard = self.0.lock();
         let val = guard.get(key);
+        self.1.lookup(val.is_some());
         match val {
             Some(vref) => {
+                let vref: *const V = vref;
                 // SAFETY: extending the lifetime of vref is safe here because
-                // vref will not be invalidated while the mutex guard is alive.
-                // ElemRef ensures the mutex guard and vref will have the same
+                // vref will not be invalidated while the lock guard is alive.
+                // ElemRef ensures the lock guard and vref will have the same
                 // lifetime.
-                let vref = unsafe { std::mem::transmute(vref) };
                 Some(ElemRef {
-                    vref,
+                    vref: unsafe { &*vref },
                     _guard: guard,
                 })
             }
@@ -50,14 +116,120 @@ where
     }
 
     fn contains(&self, key: &K) -> bool {
-        self.0.lock().unwrap().contains_key(key)
+        self.1.lookup(self.0.lock().contains_key(key))
     }
 
     fn put(&self, key: K, value: V) {
-        self.0.lock().unwrap().insert(key, value);
+        self.1.op();
+        let mut guard = self.0.lock();
+        let capacity = guard.capacity();
+        guard.insert(key, value);
+        if guard.capacity() != capacity {
+            self.1.resize();
+            event!(debug, capacity = guard.capacity(), "coarse map grew");
+        }
     }
 
     fn remove(&self, key: &K) -> bool {
-        self.0.lock().unwrap().remove(key).is_some()
+        self.take(key).is_some()
+    }
+}
+
+impl<K, V, S, L> MapMut for CoarseMap<K, V, S, L>
+where
+    K: PartialEq + Eq + Hash + PartialEq,
+    S: BuildHasher,
+    L: RawLock,
+{
+    type ValueRefMut<'a>
+        = ElemRefMut<'a, K, V, S, L>
+    where
+        K: 'a,
+        V: 'a,
+        S: 'a,
+        L: 'a;
+
+    fn get_mut(&self, key: &K) -> Option<ElemRefMut<'_, K, V, S, L>> {
+        self.1.op();
+        let mut guard = self.0.lock();
+        let vref: *mut V = guard.get_mut(key)?;
+        // SAFETY: as in `get`, the value stays in place while the lock guard
+        // is alive, and the guard only ever hands it to this reference.
+        Some(ElemRefMut {
+            vref: unsafe { &mut *vref },
+            _guard: guard,
+        })
+    }
+
+    fn take(&self, key: &K) -> Option<V> {
+        self.1.op();
+        self.0.lock().remove(key)
+    }
+}
+
+impl<K, V, S, L: RawLock> Statistics for CoarseMap<K, V, S, L> {
+    fn stats(&self) -> Stats {
+        self.1.snapshot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{RawMcsLock, RawTicketLock};
+
+    fn test_coarse_map<L: RawLock>() {
+        let num_thrs = 4;
+        let num_elems = 1_000;
+        let map = CoarseMap::<_, _, _, L>::new();
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let map = &map;
+                s.spawn(move || {
+                    for i in 0..num_elems {
+                        let key = t * num_elems + i;
+                        map.put(key, key.to_string());
+                        assert_eq!(*map.get(&key).unwrap(), key.to_string());
+                    }
+                });
+            }
+        });
+
+        *map.get_mut(&1).unwrap() += "!";
+        assert_eq!(*map.get(&1).unwrap(), "1!");
+        assert!(map.get_mut(&(num_thrs * num_elems)).is_none());
+        assert!(map.remove(&0));
+        assert!(!map.contains(&0));
+        assert!((1..num_thrs * num_elems).all(|key| map.contains(&key)));
+        assert_eq!(map.take(&1).as_deref(), Some("1!"));
+        assert_eq!(map.take(&1), None);
+    }
+
+    #[test]
+    fn coarse_map() {
+        test_coarse_map::<RawSpinLock>();
+        test_coarse_map::<RawTicketLock>();
+        test_coarse_map::<RawMcsLock>();
+    }
+
+    #[test]
+    fn miri_coarse_map() {
+        // few enough operations to run under Miri
+        let map = CoarseMap::<_, _, _, RawSpinLock>::new();
+        std::thread::scope(|s| {
+            for t in 0..2 {
+                let map = &map;
+                s.spawn(move || {
+                    for key in t * 4..t * 4 + 4 {
+                        map.put(key, key.to_string());
+                        // the reference outlives the lookup, holding the lock
+                        let value = map.get(&key).unwrap();
+                        assert_eq!(*value, key.to_string());
+                    }
+                });
+            }
+        });
+        assert!((0..8).all(|key| map.remove(&key)));
     }
 }
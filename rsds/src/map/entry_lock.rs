@@ -0,0 +1,158 @@
+//! Per-value locks for [`StripedHashMap`] values that are slow to update.
+
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::sync::{Arc, LockResult, Mutex, MutexGuard, PoisonError};
+
+use crate::map::{BucketLocking, PoisonPolicy, Poisoned, StripedHashMap};
+
+/// A value with a lock of its own, for a [`StripedHashMap`] whose updates to
+/// a value take long enough to hold up other keys sharing its bucket.
+///
+/// In a `StripedHashMap<K, EntryLock<V>>`, [`StripedHashMap::with_entry`]
+/// only holds the bucket lock to find the value, and updates it under the
+/// value's own lock. A slow update to one key then only holds up other
+/// updates to that key.
+///
+/// Clones of an [`EntryLock`] share the value. Taken out of the map, it
+/// stays usable after the key is removed, but updates to it then no longer
+/// show in the map.
+///
+/// ```
+/// use rsds::map::{EntryLock, Map, StripedHashMap};
+///
+/// let map = StripedHashMap::new();
+/// map.put("log", EntryLock::new(Vec::new()));
+/// map.with_entry(&"log", |log| log.push("started"));
+/// assert_eq!(*map.entry_lock(&"log").unwrap().lock().unwrap(), ["started"]);
+/// ```
+pub struct EntryLock<V>(Arc<Mutex<V>>);
+
+impl<V> EntryLock<V> {
+    /// Wraps `value` in a lock of its own.
+    pub fn new(value: V) -> Self {
+        EntryLock(Arc::new(Mutex::new(value)))
+    }
+
+    /// Locks the value, blocking until it is free.
+    ///
+    /// If a thread panicked while holding the lock, the guard is returned in
+    /// the error.
+    pub fn lock(&self) -> LockResult<MutexGuard<'_, V>> {
+        self.0.lock()
+    }
+}
+
+impl<V> Clone for EntryLock<V> {
+    fn clone(&self) -> Self {
+        EntryLock(self.0.clone())
+    }
+}
+
+impl<V> From<V> for EntryLock<V> {
+    fn from(value: V) -> Self {
+        EntryLock::new(value)
+    }
+}
+
+impl<V: fmt::Debug> fmt::Debug for EntryLock<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("EntryLock").field(&self.0).finish()
+    }
+}
+
+impl<K, V, S, L> StripedHashMap<K, EntryLock<V>, S, L>
+where
+    K: Hash + PartialEq,
+    S: BuildHasher,
+    L: BucketLocking,
+{
+    /// Returns the lock of the value associated with `key`, if there is one,
+    /// holding the bucket lock only to find it.
+    pub fn entry_lock(&self, key: &K) -> Option<EntryLock<V>> {
+        self.get_with(key, EntryLock::clone)
+    }
+
+    /// Calls `f` on the value associated with `key`, if there is one, under
+    /// the value's own lock, and returns its result.
+    ///
+    /// Unlike with [`StripedHashMap::modify_with`], other keys in the same
+    /// bucket stay free while `f` runs, and `f` may use the map, though not
+    /// to lock this value again.
+    pub fn with_entry<F, R>(&self, key: &K, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        self.try_with_entry(key, f).unwrap()
+    }
+
+    /// Like [`StripedHashMap::with_entry`], but returns [`Poisoned`] instead
+    /// of panicking.
+    ///
+    /// A value whose lock was poisoned is handled as set by the map's
+    /// [`PoisonPolicy`], as buckets are.
+    pub fn try_with_entry<F, R>(&self, key: &K, f: F) -> Result<Option<R>, Poisoned>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        let Some(entry) = self.try_get_with(key, EntryLock::clone)? else {
+            return Ok(None);
+        };
+        let mut value = match (entry.lock(), self.poison_policy) {
+            (Ok(value), _) => value,
+            (Err(_), PoisonPolicy::Propagate) => return Err(Poisoned),
+            (Err(err), PoisonPolicy::Recover) => {
+                entry.0.clear_poison();
+                PoisonError::into_inner(err)
+            }
+        };
+        Ok(Some(f(&mut value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::map::Map;
+
+    use super::*;
+
+    #[test]
+    fn test_entry_lock() {
+        // two buckets, so that the keys share them
+        let map = StripedHashMap::with_capacity(10);
+        for key in 0..8 {
+            map.put(key, EntryLock::new(key));
+        }
+
+        // while one value is locked, the rest of the map carries on
+        let entry = map.entry_lock(&0).unwrap();
+        let value = entry.lock().unwrap();
+        for key in 1..8 {
+            assert_eq!(
+                map.with_entry(&key, |value| std::mem::replace(value, 0)),
+                Some(key)
+            );
+        }
+        map.put(8, EntryLock::new(8));
+        assert!(map.remove(&0));
+        assert_eq!(*value, 0);
+        drop(value);
+
+        assert_eq!(map.with_entry(&0, |_| ()), None);
+        assert_eq!(map.with_entry(&8, |value| *value), Some(8));
+    }
+
+    #[test]
+    fn test_entry_lock_poisoned() {
+        let map = StripedHashMap::new();
+        map.put(1, EntryLock::new(1));
+        std::thread::scope(|s| {
+            let panicked = s.spawn(|| map.with_entry(&1, |_| panic!("poisoning entry")));
+            assert!(panicked.join().is_err());
+        });
+        assert_eq!(map.try_with_entry(&1, |value| *value), Err(Poisoned));
+
+        let map = map.poison_policy(PoisonPolicy::Recover);
+        assert_eq!(map.try_with_entry(&1, |value| *value), Ok(Some(1)));
+    }
+}
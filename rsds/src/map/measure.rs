@@ -0,0 +1,105 @@
+//! Accounting for the heap memory a map takes.
+
+use std::mem::size_of;
+
+/// Heap memory a [`StripedHashMap`](super::StripedHashMap) takes, in bytes,
+/// as reported by its `memory_usage` methods.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryUsage {
+    /// The bucket array: each bucket's lock and the header of its `Vec`.
+    pub buckets: usize,
+    /// The entries the buckets' `Vec`s have room for, including spare
+    /// capacity.
+    pub entries: usize,
+    /// What the values own on the heap beyond their inline size, as
+    /// measured by [`MeasureSize`]. Zero unless measured.
+    pub values: usize,
+}
+
+impl MemoryUsage {
+    /// Returns the bytes taken overall.
+    pub fn total(&self) -> usize {
+        self.buckets + self.entries + self.values
+    }
+}
+
+/// Types that can report what they own on the heap, for a map to account
+/// for values whose sizes vary.
+pub trait MeasureSize {
+    /// Returns the bytes owned on the heap, not counting `size_of::<Self>()`.
+    fn heap_size(&self) -> usize;
+}
+
+macro_rules! impl_measure_size_inline {
+    ($($ty:ty),*) => {
+        $(
+            impl MeasureSize for $ty {
+                fn heap_size(&self) -> usize {
+                    0
+                }
+            }
+        )*
+    };
+}
+
+impl_measure_size_inline!(
+    (),
+    bool,
+    char,
+    f32,
+    f64,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    isize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    usize
+);
+
+impl MeasureSize for String {
+    fn heap_size(&self) -> usize {
+        self.capacity()
+    }
+}
+
+impl<T: MeasureSize> MeasureSize for Vec<T> {
+    fn heap_size(&self) -> usize {
+        self.capacity() * size_of::<T>() + self.iter().map(T::heap_size).sum::<usize>()
+    }
+}
+
+impl<T: MeasureSize> MeasureSize for Box<T> {
+    fn heap_size(&self) -> usize {
+        size_of::<T>() + (**self).heap_size()
+    }
+}
+
+impl<T: MeasureSize> MeasureSize for Option<T> {
+    fn heap_size(&self) -> usize {
+        self.as_ref().map_or(0, T::heap_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heap_size() {
+        assert_eq!(7u64.heap_size(), 0);
+        assert_eq!(String::with_capacity(10).heap_size(), 10);
+        let strings = vec![String::with_capacity(3), String::with_capacity(5)];
+        assert_eq!(
+            strings.heap_size(),
+            strings.capacity() * size_of::<String>() + 8
+        );
+        assert_eq!(Some(Box::new(1u32)).heap_size(), 4);
+        assert_eq!(None::<String>.heap_size(), 0);
+    }
+}
@@ -1,13 +1,34 @@
 //! This module contains concurrent hashmap implementations.
 
+#[cfg(feature = "adapters")]
+mod adapters;
+mod cached_map;
+mod changes;
 mod coarse_map;
+mod entry_lock;
+mod measure;
+mod seeded;
+mod snapshot;
+mod string_interner;
 mod striped_map;
 
+pub use cached_map::CachedMap;
+pub use changes::{ChangeEvent, ChangeKind, ChangeReceiver, Lagged};
 pub use coarse_map::CoarseMap;
-pub use striped_map::StripedHashMap;
+pub use entry_lock::EntryLock;
+pub use measure::{MeasureSize, MemoryUsage};
+pub use seeded::SeededState;
+pub use snapshot::Codec;
+pub use string_interner::{StringInterner, Symbol};
+#[cfg(feature = "parking_lot")]
+pub use striped_map::ParkingLotLocking;
+pub use striped_map::{
+    BiasedLocking, BucketLocking, EntryView, PoisonPolicy, Poisoned, RwLocking, SnapshotIter,
+    StripedHashMap,
+};
 
 use std::hash::Hash;
-use std::ops::Deref;
+use std::ops::{Deref, DerefMut};
 
 /// Common functionalities for hash maps.
 pub trait Map {
@@ -36,3 +57,23 @@ pub trait Map {
     /// whether a key-value pair was found and removed.
     fn remove(&self, key: &Self::Key) -> bool;
 }
+
+/// Maps that hand out exclusive access to their values, to update them in
+/// place or take them out of the map.
+///
+/// Lock-free maps, which cannot hand out exclusive access to a value, only
+/// implement [`Map`].
+pub trait MapMut: Map {
+    /// HashMap mutable entry reference type.
+    type ValueRefMut<'a>: DerefMut<Target = Self::Val>
+    where
+        Self: 'a;
+
+    /// Get a mutable reference to a value associated with a key, if it
+    /// exists. Other operations on the entry wait until it is dropped.
+    fn get_mut(&self, key: &Self::Key) -> Option<Self::ValueRefMut<'_>>;
+
+    /// Removes a key-value pair based on the provided key, returning the
+    /// value if it existed.
+    fn take(&self, key: &Self::Key) -> Option<Self::Val>;
+}
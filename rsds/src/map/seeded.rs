@@ -0,0 +1,37 @@
+//! A hasher for maps that must hash alike from run to run.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{BuildHasher, Hasher};
+
+/// Builds hashers that hash as set by a seed, rather than randomly as
+/// [`RandomState`](std::collections::hash_map::RandomState) does, for
+/// [`StripedHashMap::with_seed`](super::StripedHashMap::with_seed).
+///
+/// Hashes are the standard library's SipHash of the seed and then the key.
+/// They are the same from run to run with one build of a program, but may
+/// change with the Rust release, as the algorithm behind
+/// [`DefaultHasher`] may.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SeededState(u64);
+
+impl SeededState {
+    /// Creates a [`SeededState`] hashing as set by `seed`.
+    pub const fn new(seed: u64) -> Self {
+        SeededState(seed)
+    }
+
+    /// Returns the seed.
+    pub const fn seed(&self) -> u64 {
+        self.0
+    }
+}
+
+impl BuildHasher for SeededState {
+    type Hasher = DefaultHasher;
+
+    fn build_hasher(&self) -> DefaultHasher {
+        let mut hasher = DefaultHasher::new();
+        hasher.write_u64(self.0);
+        hasher
+    }
+}
@@ -0,0 +1,180 @@
+//! The binary format [`StripedHashMap`](super::StripedHashMap) snapshots are
+//! saved in.
+//!
+//! A snapshot is a header followed by one frame per bucket, with integers in
+//! little-endian order:
+//!
+//! ```text
+//! header: b"RSDS" | version: u8 | buckets: u64 | hasher fingerprint: u64
+//! bucket: entries: u32 | (key len: u32 | key | value len: u32 | value)*
+//! ```
+//!
+//! The hasher fingerprint is the hash of a fixed probe key. A map loading the
+//! snapshot with a hasher of the same fingerprint, and so the same seed,
+//! files each bucket's entries straight into the same bucket, without
+//! hashing them.
+
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"RSDS";
+const VERSION: u8 = 1;
+
+/// The key hashed to fingerprint a map's hasher.
+pub(super) const FINGERPRINT_PROBE: &[u8] = b"rsds snapshot";
+
+/// Types that can be saved in a map snapshot, as bytes.
+pub trait Codec: Sized {
+    /// Appends the encoding of `self` to `buf`.
+    fn encode(&self, buf: &mut Vec<u8>);
+
+    /// Decodes a value from the bytes [`Codec::encode`] wrote.
+    fn decode(bytes: &[u8]) -> io::Result<Self>;
+}
+
+macro_rules! impl_codec_int {
+    ($($ty:ty),*) => {
+        $(
+            impl Codec for $ty {
+                fn encode(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(bytes: &[u8]) -> io::Result<Self> {
+                    let bytes = bytes.try_into().map_err(|_| invalid("bad integer length"))?;
+                    Ok(<$ty>::from_le_bytes(bytes))
+                }
+            }
+        )*
+    };
+}
+
+impl_codec_int!(i8, i16, i32, i64, i128, u8, u16, u32, u64, u128);
+
+impl Codec for String {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<Self> {
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid("string is not UTF-8"))
+    }
+}
+
+impl Codec for Vec<u8> {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+
+    fn decode(bytes: &[u8]) -> io::Result<Self> {
+        Ok(bytes.to_vec())
+    }
+}
+
+/// A snapshot's header.
+pub(super) struct Header {
+    pub(super) num_buckets: u64,
+    pub(super) fingerprint: u64,
+}
+
+impl Header {
+    pub(super) fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        writer.write_all(&self.num_buckets.to_le_bytes())?;
+        writer.write_all(&self.fingerprint.to_le_bytes())
+    }
+
+    pub(super) fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a map snapshot"));
+        }
+        let mut version = [0; 1];
+        reader.read_exact(&mut version)?;
+        if version[0] != VERSION {
+            return Err(invalid("unsupported snapshot version"));
+        }
+        Ok(Header {
+            num_buckets: read_u64(reader)?,
+            fingerprint: read_u64(reader)?,
+        })
+    }
+}
+
+/// Encodes the frame of a bucket, whose entries are added with
+/// [`BucketFrame::push`].
+pub(super) struct BucketFrame<'a> {
+    buf: &'a mut Vec<u8>,
+    len: u32,
+}
+
+impl<'a> BucketFrame<'a> {
+    /// Starts a frame in `buf`, clearing it.
+    pub(super) fn new(buf: &'a mut Vec<u8>) -> Self {
+        buf.clear();
+        buf.extend_from_slice(&0u32.to_le_bytes());
+        BucketFrame { buf, len: 0 }
+    }
+
+    pub(super) fn push<K: Codec, V: Codec>(&mut self, key: &K, value: &V) {
+        push_prefixed(self.buf, key);
+        push_prefixed(self.buf, value);
+        self.len += 1;
+    }
+
+    /// Fills in the frame's entry count.
+    pub(super) fn finish(self) {
+        self.buf[..4].copy_from_slice(&self.len.to_le_bytes());
+    }
+}
+
+/// Appends the encoding of `item` to `buf`, prefixed with its length.
+fn push_prefixed<T: Codec>(buf: &mut Vec<u8>, item: &T) {
+    let start = buf.len();
+    buf.extend_from_slice(&0u32.to_le_bytes());
+    item.encode(buf);
+    let len = (buf.len() - start - 4) as u32;
+    buf[start..start + 4].copy_from_slice(&len.to_le_bytes());
+}
+
+/// Reads a bucket frame, calling `f` on each of its entries.
+pub(super) fn read_bucket<R, K, V, F>(reader: &mut R, buf: &mut Vec<u8>, mut f: F) -> io::Result<()>
+where
+    R: Read,
+    K: Codec,
+    V: Codec,
+    F: FnMut(K, V),
+{
+    let len = read_u32(reader)?;
+    for _ in 0..len {
+        let key = K::decode(read_prefixed(reader, buf)?)?;
+        let value = V::decode(read_prefixed(reader, buf)?)?;
+        f(key, value);
+    }
+    Ok(())
+}
+
+/// Reads a length-prefixed item into `buf`, returning its bytes.
+fn read_prefixed<'a, R: Read>(reader: &mut R, buf: &'a mut Vec<u8>) -> io::Result<&'a [u8]> {
+    let len = read_u32(reader)? as usize;
+    buf.resize(len, 0);
+    reader.read_exact(buf)?;
+    Ok(buf)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+pub(super) fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::map::{Map, StripedHashMap};
+use crate::vec::ConcurrentVec;
+
+/// An interned string, which a [`StringInterner`] resolves back to it.
+///
+/// Symbols are handed out in order from zero, so they can index side tables.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Returns the symbol's index, which is the number of strings interned
+    /// before it.
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A table of strings that many threads can intern at once, mapping each
+/// distinct string to a stable [`Symbol`] and back.
+///
+/// Strings are looked up in a [`StripedHashMap`], and a string seen for the
+/// first time is appended to a [`ConcurrentVec`] under its bucket's write
+/// lock, so that racing threads agree on a single symbol for it. Resolving a
+/// symbol is a lock-free read from the vector, and the resolved string lives
+/// as long as the interner. Each string is stored once, shared between the
+/// map and the vector.
+///
+/// ```
+/// use rsds::map::StringInterner;
+///
+/// let interner = StringInterner::new();
+/// let (a, b) = std::thread::scope(|s| {
+///     let a = s.spawn(|| interner.intern("hello"));
+///     let b = s.spawn(|| interner.intern("hello"));
+///     (a.join().unwrap(), b.join().unwrap())
+/// });
+/// assert_eq!(a, b);
+/// assert_eq!(interner.resolve(a), Some("hello"));
+/// assert_eq!(interner.len(), 1);
+/// ```
+pub struct StringInterner {
+    symbols: StripedHashMap<Arc<str>, Symbol>,
+    strings: ConcurrentVec<Arc<str>>,
+}
+
+impl Default for StringInterner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StringInterner {
+    /// Creates a new, empty [`StringInterner`].
+    pub fn new() -> Self {
+        Self {
+            symbols: StripedHashMap::new(),
+            strings: ConcurrentVec::new(),
+        }
+    }
+
+    /// Returns the symbol for `string`, interning it if it is new.
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than `u32::MAX` strings would be interned.
+    pub fn intern(&self, string: &str) -> Symbol {
+        let key: Arc<str> = Arc::from(string);
+        if let Some(symbol) = self.symbols.get(&key) {
+            return *symbol;
+        }
+        self.symbols.get_or_insert_with(key.clone(), || {
+            let index = self.strings.push(key);
+            Symbol(u32::try_from(index).expect("too many interned strings"))
+        })
+    }
+
+    /// Returns the symbol for `string`, or `None` if it was never interned.
+    pub fn get(&self, string: &str) -> Option<Symbol> {
+        self.symbols.get(&Arc::from(string)).map(|symbol| *symbol)
+    }
+
+    /// Returns the string interned as `symbol`, or `None` if `symbol` was
+    /// handed out by another interner.
+    pub fn resolve(&self, symbol: Symbol) -> Option<&str> {
+        self.strings.get(symbol.index()).map(|string| &**string)
+    }
+
+    /// Returns the number of strings interned.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Checks whether no string has been interned.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+impl fmt::Debug for StringInterner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StringInterner")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_interner() {
+        let interner = StringInterner::new();
+        assert_eq!(interner.get("a"), None);
+        let a = interner.intern("a");
+        let b = interner.intern("b");
+        assert_eq!((a.index(), b.index()), (0, 1));
+        assert_eq!(interner.intern("a"), a);
+        assert_eq!(interner.get("b"), Some(b));
+        assert_eq!(interner.resolve(b), Some("b"));
+        assert_eq!(interner.resolve(Symbol(2)), None);
+        assert_eq!(format!("{:?}", interner), "StringInterner { len: 2, .. }");
+    }
+
+    #[test]
+    fn string_interner_concurrent() {
+        let num_thrs = 8;
+        let num_strings = 2_000;
+        let interner = StringInterner::new();
+
+        let symbols: Vec<Vec<Symbol>> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..num_thrs)
+                .map(|t| {
+                    let interner = &interner;
+                    s.spawn(move || {
+                        // every thread interns the same strings, in its own
+                        // order
+                        (0..num_strings)
+                            .map(|i| (i * 7 + t * 131) % num_strings)
+                            .map(|i| {
+                                let symbol = interner.intern(&i.to_string());
+                                assert_eq!(interner.resolve(symbol), Some(&*i.to_string()));
+                                symbol
+                            })
+                            .collect()
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert_eq!(interner.len(), num_strings);
+        // threads agree on every string's symbol
+        for (t, symbols) in symbols.iter().enumerate() {
+            for (j, &symbol) in symbols.iter().enumerate() {
+                let i = (j * 7 + t * 131) % num_strings;
+                assert_eq!(interner.get(&i.to_string()), Some(symbol));
+            }
+        }
+    }
+}
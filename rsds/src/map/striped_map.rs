@@ -1,32 +1,214 @@
-use crate::map::Map;
+use crate::map::changes::{ChangeEvent, ChangeKind, ChangeReceiver, Changes};
+use crate::map::snapshot::{self, BucketFrame, Codec, Header, FINGERPRINT_PROBE};
+use crate::map::{Map, MapMut, MeasureSize, MemoryUsage, SeededState};
+use crate::primitive::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
+use crate::primitive::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::reclaim::epoch::{self, Guard};
+use crate::stats::{Counters, Statistics, Stats};
+use crate::sync::{Backoff, BiasedReadGuard, BiasedRwLock, BiasedWriteGuard};
+use crate::trace::{enter_span, event};
 use crossbeam::utils::CachePadded;
+#[cfg(feature = "rayon")]
+use rayon::iter::{FromParallelIterator, IntoParallelIterator, ParallelExtend, ParallelIterator};
 use std::collections::hash_map::RandomState;
+use std::error::Error;
+use std::fmt;
 use std::hash::{BuildHasher, Hash, Hasher};
-use std::ops::Deref;
-use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
-use std::sync::RwLock;
-use std::sync::RwLockReadGuard;
-use std::sync::RwLockWriteGuard;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::ops::{Deref, DerefMut};
+use std::sync::{LockResult, PoisonError};
 
 const DEFAULT_NUM_BUCKETS: usize = 1 << 12;
 const DEFAULT_MAX_BUCKET_SIZE: usize = 10;
+/// How many times longer than the average bucket an overfull bucket must be
+/// for the map to take it for hash flooding, and reseed rather than grow.
+const FLOOD_FACTOR: usize = 4;
 
 type Bucket<K, V> = Vec<(K, V)>;
 
-type ProtectedBucket<K, V> = RwLock<Bucket<K, V>>;
+type ProtectedBucket<K, V, L> = <L as BucketLocking>::Lock<Bucket<K, V>>;
 
-struct MaybeElemRef<'a, K: PartialEq, V> {
-    guard: RwLockReadGuard<'a, Bucket<K, V>>,
+type BucketWriteGuard<'a, K, V, L> = <L as BucketLocking>::WriteGuard<'a, Bucket<K, V>>;
+
+/// Write-locked buckets, each with its index.
+type LockedBuckets<'a, K, V, L> = Vec<(usize, BucketWriteGuard<'a, K, V, L>)>;
+
+/// How each of a [`StripedHashMap`]'s buckets is locked.
+///
+/// [`RwLocking`], the default, suits most workloads. [`BiasedLocking`] makes
+/// lookups cheaper at the expense of updates, for read-mostly maps.
+pub trait BucketLocking {
+    /// A lock guarding a `T`.
+    type Lock<T>;
+    /// A read lock on a [`Self::Lock`].
+    type ReadGuard<'a, T: 'a>: Deref<Target = T>;
+    /// A write lock on a [`Self::Lock`].
+    type WriteGuard<'a, T: 'a>: DerefMut<Target = T>;
+
+    /// Creates a new, unlocked lock guarding `data`.
+    fn new<T>(data: T) -> Self::Lock<T>;
+
+    /// Acquires a read lock, blocking while a writer holds the lock.
+    ///
+    /// If a thread panicked while holding the lock, the guard is returned in
+    /// the error.
+    fn read<T>(lock: &Self::Lock<T>) -> LockResult<Self::ReadGuard<'_, T>>;
+
+    /// Acquires the write lock, blocking until it is free.
+    ///
+    /// If a thread panicked while holding the lock, the guard is returned in
+    /// the error.
+    fn write<T>(lock: &Self::Lock<T>) -> LockResult<Self::WriteGuard<'_, T>>;
+
+    /// Marks the lock as no longer poisoned by a thread that panicked while
+    /// holding it.
+    fn clear_poison<T>(lock: &Self::Lock<T>);
+
+    /// Returns the bytes the lock takes on the heap, not counting the data it
+    /// guards.
+    fn heap_size<T>(_lock: &Self::Lock<T>) -> usize {
+        0
+    }
+}
+
+/// What a [`StripedHashMap`] does on finding that a thread panicked while
+/// holding the lock of a bucket it needs, leaving the bucket poisoned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PoisonPolicy {
+    /// Fail every later operation on the bucket: the `try_` methods return
+    /// [`Poisoned`], and the others panic, as with the standard library's
+    /// locks.
+    #[default]
+    Propagate,
+    /// Clear the poison and carry on with the bucket as the panicking thread
+    /// left it. A bucket holds no invariants beyond its entries, so this only
+    /// risks seeing an update that was left half done.
+    Recover,
+}
+
+/// The error returned by a [`StripedHashMap`]'s `try_` methods when a bucket
+/// they need is poisoned under [`PoisonPolicy::Propagate`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Poisoned;
+
+impl fmt::Display for Poisoned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a thread panicked while holding a bucket lock")
+    }
+}
+
+impl Error for Poisoned {}
+
+/// Buckets guarded by the standard library's [`RwLock`], under which every
+/// lookup updates its bucket's reader count.
+///
+/// [`RwLock`]: std::sync::RwLock
+#[derive(Debug)]
+pub struct RwLocking;
+
+impl BucketLocking for RwLocking {
+    type Lock<T> = RwLock<T>;
+    type ReadGuard<'a, T: 'a> = RwLockReadGuard<'a, T>;
+    type WriteGuard<'a, T: 'a> = RwLockWriteGuard<'a, T>;
+
+    fn new<T>(data: T) -> RwLock<T> {
+        RwLock::new(data)
+    }
+
+    fn read<T>(lock: &RwLock<T>) -> LockResult<RwLockReadGuard<'_, T>> {
+        lock.read()
+    }
+
+    fn write<T>(lock: &RwLock<T>) -> LockResult<RwLockWriteGuard<'_, T>> {
+        lock.write()
+    }
+
+    #[cfg(not(loom))]
+    fn clear_poison<T>(lock: &RwLock<T>) {
+        lock.clear_poison();
+    }
+
+    // loom's locks have no way to clear their poison.
+    #[cfg(loom)]
+    fn clear_poison<T>(_lock: &RwLock<T>) {}
 }
 
-impl<'a, K: PartialEq, V> MaybeElemRef<'a, K, V> {
-    fn find(self, key: &K) -> Option<ElemRef<'a, K, V>> {
+/// Buckets guarded by a [`BiasedRwLock`], under which lookups on different
+/// threads write to no shared cache line, but updates scan a reader slot per
+/// core. Each bucket then also takes a cache line per core.
+///
+/// [`BiasedRwLock`]s are never poisoned.
+#[derive(Debug)]
+pub struct BiasedLocking;
+
+impl BucketLocking for BiasedLocking {
+    type Lock<T> = BiasedRwLock<T>;
+    type ReadGuard<'a, T: 'a> = BiasedReadGuard<'a, T>;
+    type WriteGuard<'a, T: 'a> = BiasedWriteGuard<'a, T>;
+
+    fn new<T>(data: T) -> BiasedRwLock<T> {
+        BiasedRwLock::new(data)
+    }
+
+    fn read<T>(lock: &BiasedRwLock<T>) -> LockResult<BiasedReadGuard<'_, T>> {
+        Ok(lock.read())
+    }
+
+    fn write<T>(lock: &BiasedRwLock<T>) -> LockResult<BiasedWriteGuard<'_, T>> {
+        Ok(lock.write())
+    }
+
+    fn clear_poison<T>(_lock: &BiasedRwLock<T>) {}
+
+    fn heap_size<T>(lock: &BiasedRwLock<T>) -> usize {
+        lock.heap_size()
+    }
+}
+
+/// Buckets guarded by `parking_lot`'s `RwLock`, which is never poisoned, so
+/// that a thread panicking while holding a bucket lock goes unnoticed by the
+/// others.
+#[cfg(feature = "parking_lot")]
+#[derive(Debug)]
+pub struct ParkingLotLocking;
+
+#[cfg(feature = "parking_lot")]
+impl BucketLocking for ParkingLotLocking {
+    type Lock<T> = parking_lot::RwLock<T>;
+    type ReadGuard<'a, T: 'a> = parking_lot::RwLockReadGuard<'a, T>;
+    type WriteGuard<'a, T: 'a> = parking_lot::RwLockWriteGuard<'a, T>;
+
+    fn new<T>(data: T) -> parking_lot::RwLock<T> {
+        parking_lot::RwLock::new(data)
+    }
+
+    fn read<T>(lock: &parking_lot::RwLock<T>) -> LockResult<parking_lot::RwLockReadGuard<'_, T>> {
+        Ok(lock.read())
+    }
+
+    fn write<T>(lock: &parking_lot::RwLock<T>) -> LockResult<parking_lot::RwLockWriteGuard<'_, T>> {
+        Ok(lock.write())
+    }
+
+    fn clear_poison<T>(_lock: &parking_lot::RwLock<T>) {}
+}
+
+struct MaybeElemRef<'a, K: PartialEq + 'a, V: 'a, L: BucketLocking> {
+    guard: L::ReadGuard<'a, Bucket<K, V>>,
+    epoch: Guard,
+}
+
+impl<'a, K: PartialEq + 'a, V: 'a, L: BucketLocking> MaybeElemRef<'a, K, V, L> {
+    fn find(self, key: &K) -> Option<ElemRef<'a, K, V, L>> {
         let itr = self.guard.iter();
         for (i, entry) in itr.enumerate() {
             if entry.0 == *key {
                 return Some(ElemRef {
                     idx: i,
                     guard: self.guard,
+                    _epoch: self.epoch,
                 });
             }
         }
@@ -34,12 +216,14 @@ impl<'a, K: PartialEq, V> MaybeElemRef<'a, K, V> {
     }
 }
 
-pub struct ElemRef<'a, K: PartialEq, V> {
+pub struct ElemRef<'a, K: PartialEq + 'a, V: 'a, L: BucketLocking = RwLocking> {
     idx: usize,
-    guard: RwLockReadGuard<'a, Bucket<K, V>>,
+    guard: L::ReadGuard<'a, Bucket<K, V>>,
+    // Keeps the bucket array alive until the lock guard above is dropped.
+    _epoch: Guard,
 }
 
-impl<'a, K: PartialEq, V> Deref for ElemRef<'a, K, V> {
+impl<'a, K: PartialEq + 'a, V: 'a, L: BucketLocking> Deref for ElemRef<'a, K, V, L> {
     type Target = V;
 
     fn deref(&self) -> &Self::Target {
@@ -47,6 +231,148 @@ impl<'a, K: PartialEq, V> Deref for ElemRef<'a, K, V> {
     }
 }
 
+pub struct ElemRefMut<'a, K: PartialEq + 'a, V: 'a, L: BucketLocking = RwLocking> {
+    idx: usize,
+    /// Taken on drop, to release the lock before notifying subscribers.
+    guard: Option<BucketWriteGuard<'a, K, V, L>>,
+    changes: &'a Changes<K, V>,
+    // Keeps the bucket array alive until the lock guard above is dropped.
+    _epoch: Guard,
+}
+
+impl<'a, K: PartialEq + 'a, V: 'a, L: BucketLocking> Deref for ElemRefMut<'a, K, V, L> {
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard.as_ref().unwrap()[self.idx].1
+    }
+}
+
+impl<'a, K: PartialEq + 'a, V: 'a, L: BucketLocking> DerefMut for ElemRefMut<'a, K, V, L> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard.as_mut().unwrap()[self.idx].1
+    }
+}
+
+impl<'a, K: PartialEq + 'a, V: 'a, L: BucketLocking> Drop for ElemRefMut<'a, K, V, L> {
+    fn drop(&mut self) {
+        let bucket = self.guard.take().unwrap();
+        let (key, value) = &bucket[self.idx];
+        let change = self.changes.capture(ChangeKind::Update, key, Some(value));
+        drop(bucket);
+        self.changes.send(change);
+    }
+}
+
+/// An iterator over the entries of a [`StripedHashMap`] as of a single
+/// point in time, returned by [`StripedHashMap::snapshot_iter`].
+#[derive(Debug)]
+pub struct SnapshotIter<K, V>(std::vec::IntoIter<(K, V)>);
+
+impl<K, V> Iterator for SnapshotIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for SnapshotIter<K, V> {}
+
+/// An entry of a [`StripedHashMap`] taking part in a
+/// [`StripedHashMap::transact`], whose key may or may not be in the map.
+#[derive(Debug)]
+pub struct EntryView<K, V> {
+    key: K,
+    value: Option<V>,
+    present: bool,
+    /// Index of the entry's bucket among those the transaction locked.
+    slot: usize,
+}
+
+impl<K, V> EntryView<K, V> {
+    fn new(key: K, value: Option<V>, slot: usize) -> Self {
+        let present = value.is_some();
+        EntryView {
+            key,
+            value,
+            present,
+            slot,
+        }
+    }
+
+    /// Returns the entry's key.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns the entry's value, if the key is in the map.
+    pub fn get(&self) -> Option<&V> {
+        self.value.as_ref()
+    }
+
+    /// Returns the entry's value for updating in place, if the key is in the
+    /// map.
+    pub fn get_mut(&mut self) -> Option<&mut V> {
+        self.value.as_mut()
+    }
+
+    /// Sets the entry's value, returning the old one, if any.
+    pub fn insert(&mut self, value: V) -> Option<V> {
+        self.value.replace(value)
+    }
+
+    /// Removes the key from the map, returning its value, if any.
+    pub fn remove(&mut self) -> Option<V> {
+        self.value.take()
+    }
+}
+
+/// The buckets locked by a transaction, and the entries taken out of them,
+/// which are put back when it is dropped, even if the transaction panics.
+struct Transaction<'a, K: 'a, V: 'a, L: BucketLocking> {
+    buckets: LockedBuckets<'a, K, V, L>,
+    views: Vec<EntryView<K, V>>,
+}
+
+impl<'a, K: 'a, V: 'a, L: BucketLocking> Transaction<'a, K, V, L> {
+    /// Puts the entries back, returning the changes they went through.
+    fn commit(&mut self, changes: &Changes<K, V>) -> Vec<ChangeEvent<K, V>> {
+        let mut events = Vec::new();
+        for view in self.views.drain(..) {
+            let bucket = &mut self.buckets[view.slot].1;
+            match (view.present, view.value) {
+                (present, Some(value)) => {
+                    let kind = if present {
+                        ChangeKind::Update
+                    } else {
+                        ChangeKind::Insert
+                    };
+                    events.extend(changes.capture(kind, &view.key, Some(&value)));
+                    bucket.push((view.key, value));
+                }
+                (true, None) => events.extend(changes.capture(ChangeKind::Remove, &view.key, None)),
+                (false, None) => {}
+            }
+        }
+        events
+    }
+}
+
+impl<'a, K: 'a, V: 'a, L: BucketLocking> Drop for Transaction<'a, K, V, L> {
+    fn drop(&mut self) {
+        for view in self.views.drain(..) {
+            if let Some(value) = view.value {
+                self.buckets[view.slot].1.push((view.key, value));
+            }
+        }
+    }
+}
+
 /// A concurrent hashmap that implements striped locking.
 ///
 /// Note:
@@ -54,11 +380,44 @@ impl<'a, K: PartialEq, V> Deref for ElemRef<'a, K, V> {
 /// The current implementation uses one lock per bucket; a lock never multiplexes
 /// over multiple buckets. This may change in the future to better reflect the
 /// requirements of stripe locking.
-pub struct StripedHashMap<K: Hash + PartialEq, V, S = RandomState> {
-    buckets: CachePadded<AtomicPtr<Vec<ProtectedBucket<K, V>>>>,
+///
+/// Buckets are locked as set by `L`, a [`BucketLocking`]. Build a map with
+/// [`StripedHashMap::read_mostly`] to have them guarded by
+/// [`BiasedRwLock`]s, for maps that are rarely updated.
+///
+/// A thread panicking while holding a bucket lock poisons the bucket, which
+/// later operations on it handle as set by the map's [`PoisonPolicy`]. Each
+/// panicking operation has a `try_` variant that returns [`Poisoned`]
+/// instead. A resize moves every entry to fresh locks, which clears any
+/// poison.
+///
+/// A bucket that overflows while far longer than the average, as under hash
+/// flooding, gets the map rehashed with a freshly seeded hash of its keys
+/// rather than grown, which spreads them again if `S` is a keyed hasher such
+/// as the default [`RandomState`]. Other hashers that collide no matter the
+/// seed leave the map growing as usual. [`StripedHashMap::max_chain_len`]
+/// probes for long buckets.
+///
+/// A map built by [`StripedHashMap::with_seed`], or made
+/// [`deterministic`](StripedHashMap::deterministic), behaves the same from
+/// run to run given the same operations in the same order.
+pub struct StripedHashMap<K: Hash + PartialEq, V, S = RandomState, L = RwLocking>
+where
+    L: BucketLocking,
+{
+    buckets: CachePadded<AtomicPtr<Vec<ProtectedBucket<K, V, L>>>>,
     max_bucket_size: usize,
+    pub(super) poison_policy: PoisonPolicy,
+    /// Whether reseeds and the order of entries follow from hashes alone.
+    deterministic: bool,
     resize_in_progress: CachePadded<AtomicBool>,
     state: S,
+    /// Mixed into every key's hash once the map has reseeded, and only
+    /// changed by a resize, along with the bucket array.
+    seed: AtomicU64,
+    counters: Counters,
+    changes: Changes<K, V>,
+    _locking: PhantomData<L>,
 }
 
 impl<K, V> Default for StripedHashMap<K, V, RandomState>
@@ -85,6 +444,65 @@ where
         let num_buckets = (capacity / DEFAULT_MAX_BUCKET_SIZE) * 2;
         StripedHashMap::build(num_buckets, RandomState::default())
     }
+
+    /// Loads a map saved with [`StripedHashMap::save_to`] from `reader`.
+    ///
+    /// The map has as many buckets as the saved one, but hashes with a new
+    /// seed, so every entry is rehashed. Reads are unbuffered, so pass a
+    /// buffered reader.
+    pub fn load_from<R: Read>(reader: R) -> io::Result<Self>
+    where
+        K: Codec,
+        V: Codec,
+    {
+        StripedHashMap::load_from_with_hasher(reader, RandomState::default())
+    }
+}
+
+impl<K, V> StripedHashMap<K, V, SeededState>
+where
+    K: Hash + PartialEq,
+{
+    /// Creates a new, [`deterministic`](StripedHashMap::deterministic)
+    /// [`StripedHashMap`] whose keys hash as set by `seed`, for tests and
+    /// simulations that must be reproducible.
+    ///
+    /// ```
+    /// use rsds::map::{Map, StripedHashMap};
+    ///
+    /// let (a, b) = (StripedHashMap::with_seed(7), StripedHashMap::with_seed(7));
+    /// for key in 0..100 {
+    ///     a.put(key, ());
+    ///     b.put(99 - key, ());
+    /// }
+    /// assert!(a.snapshot_iter().eq(b.snapshot_iter()));
+    /// ```
+    pub fn with_seed(seed: u64) -> Self {
+        StripedHashMap::build(DEFAULT_NUM_BUCKETS, SeededState::new(seed)).deterministic()
+    }
+}
+
+impl<K, V> StripedHashMap<K, V, RandomState, BiasedLocking>
+where
+    K: Hash + PartialEq,
+{
+    /// Creates a new [`StripedHashMap`] for read-mostly use, whose buckets
+    /// are guarded by [`BiasedRwLock`]s.
+    pub fn read_mostly() -> Self {
+        StripedHashMap::build(DEFAULT_NUM_BUCKETS, RandomState::default())
+    }
+}
+
+#[cfg(feature = "parking_lot")]
+impl<K, V> StripedHashMap<K, V, RandomState, ParkingLotLocking>
+where
+    K: Hash + PartialEq,
+{
+    /// Creates a new [`StripedHashMap`] whose buckets are guarded by
+    /// `parking_lot`'s locks, which a panicking thread never poisons.
+    pub fn with_parking_lot() -> Self {
+        StripedHashMap::build(DEFAULT_NUM_BUCKETS, RandomState::default())
+    }
 }
 
 impl<K, V, S> StripedHashMap<K, V, S>
@@ -97,9 +515,567 @@ where
         StripedHashMap::build(DEFAULT_NUM_BUCKETS, hasher)
     }
 
+    /// Like [`StripedHashMap::load_from`], but with a given hasher.
+    ///
+    /// If `hasher` hashes as the saved map's did, such as a
+    /// [`BuildHasherDefault`](std::hash::BuildHasherDefault) of the same
+    /// hasher, each bucket is loaded whole into the same bucket, without
+    /// hashing its keys.
+    pub fn load_from_with_hasher<R: Read>(mut reader: R, hasher: S) -> io::Result<Self>
+    where
+        K: Codec,
+        V: Codec,
+    {
+        let header = Header::read_from(&mut reader)?;
+        let num_buckets = usize::try_from(header.num_buckets)
+            .ok()
+            .filter(|&num_buckets| num_buckets > 0)
+            .ok_or_else(|| snapshot::invalid("bad bucket count"))?;
+        let map: Self = StripedHashMap::build(num_buckets, hasher);
+        let same_layout = map._fingerprint() == header.fingerprint;
+
+        // SAFETY: the map is not shared yet, so its bucket array is never
+        // replaced.
+        let buckets = unsafe { &*map.buckets.load(Ordering::Acquire) };
+        let mut buf = Vec::new();
+        for index in 0..num_buckets {
+            if same_layout {
+                let mut bucket = RwLocking::write(&buckets[index]).unwrap();
+                snapshot::read_bucket(&mut reader, &mut buf, |key, value| {
+                    bucket.push((key, value))
+                })?;
+            } else {
+                snapshot::read_bucket(&mut reader, &mut buf, |key: K, value| {
+                    let index = map.hash(&key) % num_buckets;
+                    RwLocking::write(&buckets[index])
+                        .unwrap()
+                        .push((key, value));
+                })?;
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl<K, V, S, L> StripedHashMap<K, V, S, L>
+where
+    K: Hash + PartialEq,
+    S: BuildHasher,
+    L: BucketLocking,
+{
+    /// Sets what the map does on finding a bucket poisoned by a thread that
+    /// panicked while holding its lock. The default is
+    /// [`PoisonPolicy::Propagate`].
+    pub fn poison_policy(mut self, policy: PoisonPolicy) -> Self {
+        self.poison_policy = policy;
+        self
+    }
+
+    /// Takes randomness out of the map beyond what its hasher brings:
+    /// flooded buckets are reseeded as set by the hasher rather than at
+    /// random, and [`StripedHashMap::snapshot_iter`] and
+    /// [`StripedHashMap::save_to`] list each bucket's entries in order of
+    /// hash rather than as they were put.
+    ///
+    /// With a hasher that is itself deterministic, the map's layout then
+    /// follows from its operations alone. Concurrent operations still
+    /// interleave as they may.
+    pub fn deterministic(mut self) -> Self {
+        self.deterministic = true;
+        self
+    }
+
+    /// Like [`Map::get`], but returns [`Poisoned`] instead of panicking.
+    pub fn try_get(&self, key: &K) -> Result<Option<ElemRef<'_, K, V, L>>, Poisoned> {
+        let epoch = epoch::pin();
+        let searcher = MaybeElemRef {
+            guard: self._get_read_bucket_by_key(key)?,
+            epoch,
+        };
+        let found = searcher.find(key);
+        self.counters.lookup(found.is_some());
+        Ok(found)
+    }
+
+    /// Like [`MapMut::get_mut`], but returns [`Poisoned`] instead of
+    /// panicking.
+    pub fn try_get_mut(&self, key: &K) -> Result<Option<ElemRefMut<'_, K, V, L>>, Poisoned> {
+        self.counters.op();
+        let epoch = epoch::pin();
+        let (_, bucket) = self._get_write_bucket_by_key(key)?;
+        let Some(idx) = bucket.iter().position(|entry| entry.0 == *key) else {
+            return Ok(None);
+        };
+        Ok(Some(ElemRefMut {
+            idx,
+            guard: Some(bucket),
+            changes: &self.changes,
+            _epoch: epoch,
+        }))
+    }
+
+    /// Like [`Map::contains`], but returns [`Poisoned`] instead of panicking.
+    pub fn try_contains(&self, key: &K) -> Result<bool, Poisoned> {
+        Ok(self.try_get(key)?.is_some())
+    }
+
+    /// Like [`Map::put`], but returns [`Poisoned`] instead of panicking.
+    pub fn try_put(&self, key: K, value: V) -> Result<(), Poisoned> {
+        self.counters.op();
+        let guard = epoch::pin();
+        let (_, mut bucket) = self._get_write_bucket_by_key(&key)?;
+        if let Some(entry) = bucket.iter_mut().find(|entry| entry.0 == key) {
+            entry.1 = value;
+            let change = self
+                .changes
+                .capture(ChangeKind::Update, &key, Some(&entry.1));
+            drop(bucket);
+            self.changes.send(change);
+            return Ok(());
+        }
+        let change = self.changes.capture(ChangeKind::Insert, &key, Some(&value));
+        bucket.push((key, value));
+        self._resize_if_overfull(bucket, &guard);
+        self.changes.send(change);
+        Ok(())
+    }
+
+    /// Like [`Map::remove`], but returns [`Poisoned`] instead of panicking.
+    pub fn try_remove(&self, key: &K) -> Result<bool, Poisoned> {
+        Ok(self.try_take(key)?.is_some())
+    }
+
+    /// Like [`MapMut::take`], but returns [`Poisoned`] instead of panicking.
+    pub fn try_take(&self, key: &K) -> Result<Option<V>, Poisoned> {
+        self.counters.op();
+        let _guard = epoch::pin();
+        let (_, mut bucket) = self._get_write_bucket_by_key(key)?;
+        let Some(i) = bucket.iter().position(|entry| entry.0 == *key) else {
+            return Ok(None);
+        };
+        let (key, value) = bucket.remove(i);
+        let change = self.changes.capture(ChangeKind::Remove, &key, None);
+        drop(bucket);
+        self.changes.send(change);
+        Ok(Some(value))
+    }
+
+    /// Returns a copy of the value associated with `key`, first inserting
+    /// the value returned by `make` if there is none.
+    ///
+    /// The lookup and the insertion happen under the same bucket lock, so
+    /// `make` is called at most once per key however many threads race to
+    /// insert it.
+    pub fn get_or_insert_with<F>(&self, key: K, make: F) -> V
+    where
+        V: Clone,
+        F: FnOnce() -> V,
+    {
+        self.try_get_or_insert_with(key, make).unwrap()
+    }
+
+    /// Like [`StripedHashMap::get_or_insert_with`], but returns [`Poisoned`]
+    /// instead of panicking.
+    pub fn try_get_or_insert_with<F>(&self, key: K, make: F) -> Result<V, Poisoned>
+    where
+        V: Clone,
+        F: FnOnce() -> V,
+    {
+        self.counters.op();
+        let guard = epoch::pin();
+        let (_, mut bucket) = self._get_write_bucket_by_key(&key)?;
+        if let Some(entry) = bucket.iter().find(|entry| entry.0 == key) {
+            return Ok(entry.1.clone());
+        }
+        let value = make();
+        let change = self.changes.capture(ChangeKind::Insert, &key, Some(&value));
+        bucket.push((key, value.clone()));
+        self._resize_if_overfull(bucket, &guard);
+        self.changes.send(change);
+        Ok(value)
+    }
+
+    /// Calls `f` on the value associated with `key`, if there is one,
+    /// returning its result.
+    ///
+    /// Unlike with [`Map::get`], no guard escapes to the caller: `f` runs
+    /// under the bucket's read lock, which is released before this returns.
+    pub fn get_with<F, R>(&self, key: &K, f: F) -> Option<R>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        self.try_get_with(key, f).unwrap()
+    }
+
+    /// Like [`StripedHashMap::get_with`], but returns [`Poisoned`] instead of
+    /// panicking.
+    pub fn try_get_with<F, R>(&self, key: &K, f: F) -> Result<Option<R>, Poisoned>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        let _guard = epoch::pin();
+        let bucket = self._get_read_bucket_by_key(key)?;
+        let result = bucket
+            .iter()
+            .find(|entry| entry.0 == *key)
+            .map(|entry| f(&entry.1));
+        self.counters.lookup(result.is_some());
+        Ok(result)
+    }
+
+    /// Calls `f` on the value associated with `key`, if there is one, letting
+    /// it update the value in place, and returns its result.
+    ///
+    /// `f` runs under the bucket's write lock, so it must not use the map. If
+    /// `f` panics, the bucket is poisoned.
+    pub fn modify_with<F, R>(&self, key: &K, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        self.try_modify_with(key, f).unwrap()
+    }
+
+    /// Like [`StripedHashMap::modify_with`], but returns [`Poisoned`] instead
+    /// of panicking.
+    pub fn try_modify_with<F, R>(&self, key: &K, f: F) -> Result<Option<R>, Poisoned>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        self.counters.op();
+        let _guard = epoch::pin();
+        let (_, mut bucket) = self._get_write_bucket_by_key(key)?;
+        let Some(entry) = bucket.iter_mut().find(|entry| entry.0 == *key) else {
+            return Ok(None);
+        };
+        let result = f(&mut entry.1);
+        let change = self
+            .changes
+            .capture(ChangeKind::Update, key, Some(&entry.1));
+        drop(bucket);
+        self.changes.send(change);
+        Ok(Some(result))
+    }
+
+    /// Runs `f` atomically over the entries of `keys`, one [`EntryView`] per
+    /// key in the same order, through which it can read, update, insert or
+    /// remove each of them.
+    ///
+    /// Every bucket holding one of the keys is write-locked for the duration,
+    /// in order of bucket index, so transactions never deadlock with one
+    /// another, and no other thread sees the entries halfway through `f`.
+    /// `f` must not use the map itself.
+    ///
+    /// ```
+    /// use rsds::map::{Map, StripedHashMap};
+    ///
+    /// let accounts = StripedHashMap::new();
+    /// accounts.put("alice", 100);
+    /// accounts.transact(&["alice", "bob"], |entries| {
+    ///     let amount = 30;
+    ///     *entries[0].get_mut().unwrap() -= amount;
+    ///     let bob = entries[1].get().copied().unwrap_or(0);
+    ///     entries[1].insert(bob + amount);
+    /// });
+    /// assert_eq!(*accounts.get(&"alice").unwrap(), 70);
+    /// assert_eq!(*accounts.get(&"bob").unwrap(), 30);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if a key appears twice in `keys`.
+    pub fn transact<F, R>(&self, keys: &[K], f: F) -> R
+    where
+        K: Clone,
+        F: FnOnce(&mut [EntryView<K, V>]) -> R,
+    {
+        self.try_transact(keys, f).unwrap()
+    }
+
+    /// Like [`StripedHashMap::transact`], but returns [`Poisoned`] instead
+    /// of panicking if a bucket is poisoned.
+    pub fn try_transact<F, R>(&self, keys: &[K], f: F) -> Result<R, Poisoned>
+    where
+        K: Clone,
+        F: FnOnce(&mut [EntryView<K, V>]) -> R,
+    {
+        self.counters.op();
+        let guard = epoch::pin();
+        let hashes = keys.iter().map(|key| self.hash(key)).collect::<Vec<_>>();
+        for (i, key) in keys.iter().enumerate() {
+            let duplicate = (0..i).any(|j| hashes[j] == hashes[i] && keys[j] == *key);
+            assert!(!duplicate, "key at index {} appears twice", i);
+        }
+
+        let buckets = self._get_write_buckets_by_keys(keys)?;
+        // The array cannot be replaced, nor the map reseeded, while we hold
+        // its buckets.
+        let num_buckets = self.num_buckets();
+        let mut tx: Transaction<'_, K, V, L> = Transaction {
+            buckets,
+            views: Vec::with_capacity(keys.len()),
+        };
+        for key in keys {
+            let slot = tx
+                .buckets
+                .binary_search_by_key(&(self.hash(key) % num_buckets), |(index, _)| *index)
+                .unwrap();
+            let bucket = &mut tx.buckets[slot].1;
+            let view = match bucket.iter().position(|entry| entry.0 == *key) {
+                Some(pos) => {
+                    let (key, value) = bucket.swap_remove(pos);
+                    EntryView::new(key, Some(value), slot)
+                }
+                None => EntryView::new(key.clone(), None, slot),
+            };
+            tx.views.push(view);
+        }
+
+        let result = f(&mut tx.views);
+        let changes = tx.commit(&self.changes);
+        let overfull = tx
+            .buckets
+            .iter()
+            .any(|(_, bucket)| bucket.len() > self.max_bucket_size);
+        drop(tx);
+        for change in changes {
+            self.changes.send(Some(change));
+        }
+        if overfull {
+            self._try_resize(&guard);
+        }
+        Ok(result)
+    }
+
+    /// Subscribes to the map's changes, returning a receiver of an event for
+    /// each insert, update and removal from now on, with the key's new value.
+    ///
+    /// Events are captured under the changed bucket's lock, but sent to the
+    /// receivers once it is released. Each receiver buffers up to `capacity`
+    /// events, losing the oldest ones if it falls further behind. While
+    /// nobody is subscribed, changes cost a single atomic load.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn subscribe(&self, capacity: usize) -> ChangeReceiver<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.changes.subscribe(capacity, Some(V::clone))
+    }
+
+    /// Like [`StripedHashMap::subscribe`], but the events carry no values,
+    /// which then need not be copied.
+    pub fn subscribe_keys(&self, capacity: usize) -> ChangeReceiver<K, V>
+    where
+        K: Clone,
+    {
+        self.changes.subscribe(capacity, None)
+    }
+
+    /// Returns the heap memory the map takes, counting values by their
+    /// inline size only.
+    ///
+    /// Buckets are read-locked one at a time, so while the map is updated
+    /// the result is no snapshot.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self._memory_usage(|_| 0)
+    }
+
+    /// Like [`StripedHashMap::memory_usage`], but also measures what the
+    /// values own on the heap.
+    pub fn deep_memory_usage(&self) -> MemoryUsage
+    where
+        V: MeasureSize,
+    {
+        self._memory_usage(V::heap_size)
+    }
+
+    fn _memory_usage(&self, value_size: impl Fn(&V) -> usize) -> MemoryUsage {
+        let _guard = epoch::pin();
+        // SAFETY: bucket arrays are only freed through the epoch collector,
+        // and we are pinned.
+        let buckets = unsafe { &*self.buckets.load(Ordering::Acquire) };
+        let mut usage = MemoryUsage {
+            buckets: size_of::<Vec<ProtectedBucket<K, V, L>>>()
+                + buckets.capacity() * size_of::<ProtectedBucket<K, V, L>>(),
+            ..MemoryUsage::default()
+        };
+        for bucket in buckets.iter() {
+            usage.buckets += L::heap_size(bucket);
+            // sizes are still worth reporting from a poisoned bucket
+            let entries = L::read(bucket).unwrap_or_else(PoisonError::into_inner);
+            usage.entries += entries.capacity() * size_of::<(K, V)>();
+            usage.values += entries
+                .iter()
+                .map(|entry| value_size(&entry.1))
+                .sum::<usize>();
+        }
+        usage
+    }
+
+    /// Returns the number of entries in the map's longest bucket, which a
+    /// hasher that spreads keys well keeps close to the average.
+    ///
+    /// As with [`StripedHashMap::memory_usage`], buckets are read-locked one
+    /// at a time.
+    pub fn max_chain_len(&self) -> usize {
+        let _guard = epoch::pin();
+        // SAFETY: bucket arrays are only freed through the epoch collector,
+        // and we are pinned.
+        let buckets = unsafe { &*self.buckets.load(Ordering::Acquire) };
+        buckets
+            .iter()
+            .map(|bucket| {
+                L::read(bucket)
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .len()
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns an iterator over a copy of the map's entries as of a single
+    /// point in time.
+    ///
+    /// Every bucket is read-locked at once while its entries are copied, so
+    /// writers wait out the copy, but not the iteration over it, and the copy
+    /// never mixes entries from before and after an update. The copy takes
+    /// as much memory as the entries themselves.
+    pub fn snapshot_iter(&self) -> SnapshotIter<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        self.try_snapshot_iter().unwrap()
+    }
+
+    /// Like [`StripedHashMap::snapshot_iter`], but returns [`Poisoned`]
+    /// instead of panicking.
+    pub fn try_snapshot_iter(&self) -> Result<SnapshotIter<K, V>, Poisoned>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let _guard = epoch::pin();
+        loop {
+            self._guard_resize();
+            let buckets_ptr = self.buckets.load(Ordering::Acquire);
+            // SAFETY: bucket arrays are only freed through the epoch collector,
+            // and we are pinned.
+            let buckets = unsafe { &*buckets_ptr };
+            let locked = buckets
+                .iter()
+                .map(|bucket| (bucket, L::read(bucket)))
+                .collect::<Vec<_>>();
+            // A resize waits for our locks, so it has either moved every entry
+            // out of this array already or not started on it.
+            if !self._is_current(buckets_ptr) {
+                drop(locked);
+                event!(trace, "bucket array replaced while locking, retrying");
+                continue;
+            }
+
+            let mut entries = Vec::new();
+            for (bucket, r) in locked {
+                let start = entries.len();
+                entries.extend(self._check_poison(bucket, r)?.iter().cloned());
+                if self.deterministic {
+                    entries[start..].sort_by_cached_key(|(key, _)| self.hash(key));
+                }
+            }
+            return Ok(SnapshotIter(entries.into_iter()));
+        }
+    }
+
+    /// Saves the map to `writer` in a compact binary format, which
+    /// [`StripedHashMap::load_from`] reads back.
+    ///
+    /// The map is streamed a bucket at a time, each encoded under its read
+    /// lock and written out once the lock is released, so a map updated
+    /// meanwhile is saved with each bucket as it was when visited. Writes
+    /// are unbuffered, so pass a buffered writer.
+    pub fn save_to<W: Write>(&self, mut writer: W) -> io::Result<()>
+    where
+        K: Codec,
+        V: Codec,
+    {
+        let (num_buckets, seed) = {
+            let _guard = epoch::pin();
+            (self.num_buckets(), self.seed.load(Ordering::Acquire))
+        };
+        let header = Header {
+            num_buckets: num_buckets as u64,
+            fingerprint: self._hash_seeded(seed, FINGERPRINT_PROBE),
+        };
+        header.write_to(&mut writer)?;
+        let mut buf = Vec::new();
+        for index in 0..num_buckets {
+            self._encode_bucket(index, num_buckets, seed, &mut buf)?;
+            writer.write_all(&buf)?;
+        }
+        writer.flush()
+    }
+
+    /// Encodes into `buf` the frame of bucket `index` of an array of
+    /// `num_buckets` buckets, whose keys were hashed under `seed`.
+    ///
+    /// If the array has since been resized, the bucket's entries have been
+    /// split between the buckets of the same index modulo `num_buckets`,
+    /// which are encoded together instead. If the map has since been
+    /// reseeded, its entries have been scattered, and this fails.
+    fn _encode_bucket(
+        &self,
+        index: usize,
+        num_buckets: usize,
+        seed: u64,
+        buf: &mut Vec<u8>,
+    ) -> io::Result<()>
+    where
+        K: Codec,
+        V: Codec,
+    {
+        let _guard = epoch::pin();
+        'retry: loop {
+            let mut frame = BucketFrame::new(buf);
+            self._guard_resize();
+            let buckets_ptr = self.buckets.load(Ordering::Acquire);
+            // SAFETY: bucket arrays are only freed through the epoch collector,
+            // and we are pinned.
+            let buckets = unsafe { &*buckets_ptr };
+            for bucket in buckets.iter().skip(index).step_by(num_buckets) {
+                let r = L::read(bucket);
+                if !self._is_current(buckets_ptr) {
+                    drop(r);
+                    continue 'retry;
+                }
+                if self.seed.load(Ordering::Acquire) != seed {
+                    return Err(io::Error::other("map reseeded while saving"));
+                }
+                let r = self._check_poison(bucket, r).map_err(io::Error::other)?;
+                let mut entries = r.iter().collect::<Vec<_>>();
+                if self.deterministic {
+                    entries.sort_by_cached_key(|(key, _)| self.hash(key));
+                }
+                for (key, value) in entries {
+                    frame.push(key, value);
+                }
+            }
+            frame.finish();
+            return Ok(());
+        }
+    }
+
+    /// Returns the hash of a fixed key, which tells whether two maps' hashers
+    /// hash alike.
+    fn _fingerprint(&self) -> u64 {
+        self._hash_seeded(self.seed.load(Ordering::Acquire), FINGERPRINT_PROBE)
+    }
+
     fn build(num_buckets: usize, hasher: S) -> Self {
-        let buckets: Vec<ProtectedBucket<K, V>> =
-            (0..num_buckets).map(|_| RwLock::new(vec![])).collect();
+        let buckets: Vec<ProtectedBucket<K, V, L>> =
+            (0..num_buckets).map(|_| L::new(vec![])).collect();
 
         let wrapped_buckets = Box::new(buckets);
         let bucket_ptr = Box::into_raw(wrapped_buckets);
@@ -107,21 +1083,28 @@ where
         StripedHashMap {
             buckets: CachePadded::new(AtomicPtr::new(bucket_ptr)),
             max_bucket_size: DEFAULT_MAX_BUCKET_SIZE,
+            poison_policy: PoisonPolicy::default(),
+            deterministic: false,
             resize_in_progress: CachePadded::new(AtomicBool::new(false)),
             state: hasher,
+            seed: AtomicU64::new(0),
+            counters: Counters::new(),
+            changes: Changes::new(),
+            _locking: PhantomData,
         }
     }
-}
 
-impl<K, V, S> StripedHashMap<K, V, S>
-where
-    K: Hash + PartialEq,
-    S: BuildHasher,
-{
     fn hash(&self, key: &K) -> usize {
-        let mut hasher = self.state.build_hasher();
-        key.hash(&mut hasher);
-        hasher.finish() as usize
+        self._hash_seeded(self.seed.load(Ordering::Acquire), key) as usize
+    }
+
+    /// Hashes `value` as keys are hashed under `seed`, which stays 0 until
+    /// the map first reseeds, so that keys hash as by `S` alone until then.
+    fn _hash_seeded<T: Hash + ?Sized>(&self, seed: u64, value: &T) -> u64 {
+        match seed {
+            0 => self.state.hash_one(value),
+            seed => self.state.hash_one((seed, value)),
+        }
     }
 
     #[allow(unused)]
@@ -129,79 +1112,267 @@ where
         unsafe { (*self.buckets.load(Ordering::Acquire)).len() }
     }
 
-    fn _get_read_bucket_by_key(&self, key: &K) -> RwLockReadGuard<Bucket<K, V>> {
-        let hash = self.hash(key);
+    /// Locks the bucket `key` maps to for reading.
+    ///
+    /// The caller must be pinned for as long as it holds the returned guard.
+    fn _get_read_bucket_by_key(&self, key: &K) -> Result<L::ReadGuard<'_, Bucket<K, V>>, Poisoned> {
         loop {
             self._guard_resize();
-            let buckets = unsafe { &*self.buckets.load(Ordering::Acquire) };
+            let buckets_ptr = self.buckets.load(Ordering::Acquire);
+            // SAFETY: bucket arrays are only freed through the epoch collector,
+            // and the caller is pinned.
+            let buckets = unsafe { &*buckets_ptr };
             if self.resize_in_progress.load(Ordering::Acquire) {
                 continue;
             }
-            let bucket_index = hash % buckets.len();
-            let r = buckets[bucket_index].read().unwrap();
-            if self.resize_in_progress.load(Ordering::Acquire) {
+            // Hashed after loading the array, as reseeding replaces both.
+            let bucket_index = self.hash(key) % buckets.len();
+            let r = L::read(&buckets[bucket_index]);
+            if !self._is_current(buckets_ptr) {
                 drop(r);
+                event!(trace, "bucket array replaced while locking, retrying");
                 continue;
             }
-            return r;
+            let r = self._check_poison(&buckets[bucket_index], r)?;
+            self._check_scan_len(r.len());
+            return Ok(r);
         }
     }
 
-    fn _get_write_bucket_by_key(&self, key: &K) -> (usize, RwLockWriteGuard<Bucket<K, V>>) {
-        let hash = self.hash(key);
+    /// Locks the bucket `key` maps to for writing.
+    ///
+    /// The caller must be pinned for as long as it holds the returned guard.
+    fn _get_write_bucket_by_key(
+        &self,
+        key: &K,
+    ) -> Result<(usize, BucketWriteGuard<'_, K, V, L>), Poisoned> {
         loop {
             self._guard_resize();
-            let buckets = unsafe { &*self.buckets.load(Ordering::Acquire) };
+            let buckets_ptr = self.buckets.load(Ordering::Acquire);
+            // SAFETY: bucket arrays are only freed through the epoch collector,
+            // and the caller is pinned.
+            let buckets = unsafe { &*buckets_ptr };
             if self.resize_in_progress.load(Ordering::Acquire) {
                 continue;
             }
-            let bucket_index = hash % buckets.len();
-            let w = buckets[bucket_index].write().unwrap();
-            if self.resize_in_progress.load(Ordering::Acquire) {
+            let bucket_index = self.hash(key) % buckets.len();
+            let w = L::write(&buckets[bucket_index]);
+            if !self._is_current(buckets_ptr) {
                 drop(w);
+                event!(trace, "bucket array replaced while locking, retrying");
                 continue;
             }
-            return (bucket_index, w);
+            let w = self._check_poison(&buckets[bucket_index], w)?;
+            self._check_scan_len(w.len());
+            return Ok((bucket_index, w));
         }
     }
 
-    fn _resize(&self) {
-        let buckets = unsafe { Box::from_raw(self.buckets.load(Ordering::Acquire)) };
+    /// Handles the result of locking `bucket` as set by the poison policy.
+    fn _check_poison<G>(
+        &self,
+        bucket: &ProtectedBucket<K, V, L>,
+        result: LockResult<G>,
+    ) -> Result<G, Poisoned> {
+        result.or_else(|err| match self.poison_policy {
+            PoisonPolicy::Propagate => Err(Poisoned),
+            PoisonPolicy::Recover => {
+                event!(debug, "clearing poisoned bucket lock");
+                L::clear_poison(bucket);
+                Ok(err.into_inner())
+            }
+        })
+    }
+
+    /// Locks for writing every bucket one of `keys` maps to, in order of
+    /// bucket index, returning each index with its lock.
+    ///
+    /// The caller must be pinned for as long as it holds the returned guards.
+    fn _get_write_buckets_by_keys(
+        &self,
+        keys: &[K],
+    ) -> Result<LockedBuckets<'_, K, V, L>, Poisoned> {
+        loop {
+            self._guard_resize();
+            let buckets_ptr = self.buckets.load(Ordering::Acquire);
+            // SAFETY: bucket arrays are only freed through the epoch collector,
+            // and the caller is pinned.
+            let buckets = unsafe { &*buckets_ptr };
+            let mut indices = keys
+                .iter()
+                .map(|key| self.hash(key) % buckets.len())
+                .collect::<Vec<_>>();
+            indices.sort_unstable();
+            indices.dedup();
+            // Every thread locking several buckets at once does so in order of
+            // index, so none waits on another for a lock it holds.
+            let locked = indices
+                .iter()
+                .map(|&index| (index, L::write(&buckets[index])))
+                .collect::<Vec<_>>();
+            if !self._is_current(buckets_ptr) {
+                drop(locked);
+                event!(trace, "bucket array replaced while locking, retrying");
+                continue;
+            }
+            return locked
+                .into_iter()
+                .map(|(index, w)| Ok((index, self._check_poison(&buckets[index], w)?)))
+                .collect();
+        }
+    }
+
+    /// Checks, while holding a bucket lock from `buckets_ptr`, that the bucket
+    /// array has not been or is not being replaced.
+    ///
+    /// A resize drains every bucket under its write lock before swapping the
+    /// arrays, so a bucket locked from the current array outside of a resize
+    /// holds the live entries.
+    fn _is_current(&self, buckets_ptr: *mut Vec<ProtectedBucket<K, V, L>>) -> bool {
+        !self.resize_in_progress.load(Ordering::Acquire)
+            && self.buckets.load(Ordering::Acquire) == buckets_ptr
+    }
+
+    /// Replaces the bucket array with one twice as long, or longer still if
+    /// needed to hold at least `min_len` buckets.
+    ///
+    /// A resize to no minimum length, called for an overfull bucket, instead
+    /// reseeds the map if it looks flooded and a fresh seed spreads its keys.
+    ///
+    /// The caller must have set `resize_in_progress`.
+    fn _resize(&self, min_len: usize, guard: &Guard) {
+        let buckets_ptr = self.buckets.load(Ordering::Acquire);
+        // SAFETY: only the resizing thread replaces the bucket array, so it
+        // stays alive until we retire it below.
+        let buckets = unsafe { &*buckets_ptr };
         let old_len = buckets.len();
-        let new_len = old_len * 2;
+        let mut new_len = (old_len * 2).max(1);
+        while new_len < min_len {
+            new_len *= 2;
+        }
+        enter_span!("striped_map_resize", old_len, new_len);
         let mut new_buckets: Vec<Bucket<K, V>> = (0..new_len).map(|_| Vec::new()).collect();
+        let seed = self.seed.load(Ordering::Acquire);
+        let mut len = 0;
+        let mut longest = 0;
 
-        // flush out all pending readers/writers.
-        // this allows us to safely move data from the old buckets to the new.
+        // Drain each bucket under its write lock, which waits out pending
+        // readers/writers. Operations arriving later see the resize flag and
+        // retry against the new array.
         for bucket in buckets.iter() {
-            drop(bucket.write().unwrap());
-        }
-
-        for locked_bucket in buckets.into_iter() {
-            let bucket = locked_bucket.into_inner().unwrap();
-            for (k, v) in bucket {
-                let hash = self.hash(&k);
+            // the old locks are dropped along with any poison
+            let entries =
+                std::mem::take(&mut *L::write(bucket).unwrap_or_else(PoisonError::into_inner));
+            len += entries.len();
+            longest = longest.max(entries.len());
+            for (k, v) in entries {
+                let hash = self._hash_seeded(seed, &k) as usize;
                 let new_bucket_idx = hash % new_len;
                 new_buckets[new_bucket_idx].push((k, v));
             }
         }
 
-        let new_buckets_locked = new_buckets.into_iter().map(RwLock::new).collect();
+        // Keys picked to collide stay together however the map grows, but a
+        // fresh seed may split them.
+        if min_len == 0 && longest > FLOOD_FACTOR * len.div_ceil(old_len) {
+            if let Some(seed) = self._reseed(&mut new_buckets, old_len) {
+                event!(warn, longest, "flooded bucket, reseeded the hasher");
+                // Stored before the new array, so that threads loading it
+                // hash with its seed.
+                self.seed.store(seed, Ordering::Release);
+            } else {
+                event!(
+                    warn,
+                    longest,
+                    "flooded bucket, which reseeding did not spread"
+                );
+            }
+        }
+
+        let new_buckets_locked = new_buckets.into_iter().map(L::new).collect();
         let new_buckets_wrapped = Box::new(new_buckets_locked);
         let new_buckets_ptr = Box::into_raw(new_buckets_wrapped);
         self.buckets.swap(new_buckets_ptr, Ordering::Release);
+        self.counters.resize();
+
+        // Threads that loaded the old array may still be about to lock one of
+        // its buckets, so it is only freed once they have unpinned.
+        //
+        // SAFETY: the old array is unreachable and was allocated with `Box`.
+        // All of its buckets are empty, so dropping it later never touches
+        // keys or values.
+        unsafe { epoch::retire(guard, buckets_ptr) };
+    }
+
+    /// Moves the entries of `buckets` into `len` buckets under a fresh seed,
+    /// returning the seed, unless that leaves a bucket overfull.
+    fn _reseed(&self, buckets: &mut Vec<Bucket<K, V>>, len: usize) -> Option<u64> {
+        let seed = if self.deterministic {
+            self._hash_seeded(self.seed.load(Ordering::Acquire), &len)
+        } else {
+            RandomState::new().build_hasher().finish()
+        };
+        // Any seed but 0, which stands for none.
+        let seed = seed.max(1);
+        let mut lens = vec![0; len];
+        for (key, _) in buckets.iter().flatten() {
+            lens[self._hash_seeded(seed, key) as usize % len] += 1;
+        }
+        if lens.iter().any(|&n| n > self.max_bucket_size) {
+            return None;
+        }
+        let mut reseeded: Vec<Bucket<K, V>> = lens.into_iter().map(Vec::with_capacity).collect();
+        for (key, value) in buckets.drain(..).flatten() {
+            let index = self._hash_seeded(seed, &key) as usize % len;
+            reseeded[index].push((key, value));
+        }
+        *buckets = reseeded;
+        Some(seed)
+    }
+
+    /// Starts a resize if `bucket`, just grown, holds more than the maximum
+    /// bucket size, and no other resize is running.
+    fn _resize_if_overfull(&self, bucket: L::WriteGuard<'_, Bucket<K, V>>, guard: &Guard) {
+        if bucket.len() > self.max_bucket_size {
+            drop(bucket);
+            self._try_resize(guard);
+        }
+    }
+
+    /// Resizes the map unless another resize is running.
+    ///
+    /// The caller must hold no bucket lock.
+    fn _try_resize(&self, guard: &Guard) {
+        if self
+            .resize_in_progress
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            self._resize(0, guard);
+            self.resize_in_progress.swap(false, Ordering::Release);
+        }
+    }
+
+    /// Reports a bucket about to be scanned that has outgrown the maximum
+    /// bucket size, which happens while a resize is pending.
+    fn _check_scan_len(&self, len: usize) {
+        if len > self.max_bucket_size {
+            event!(debug, len, "long bucket scan");
+        }
     }
 
     fn _guard_resize(&self) {
+        let backoff = Backoff::new();
         while self.resize_in_progress.load(Ordering::Acquire) {
-            std::hint::spin_loop()
+            backoff.snooze();
         }
     }
 }
 
-impl<K, V, S> Drop for StripedHashMap<K, V, S>
+impl<K, V, S, L> Drop for StripedHashMap<K, V, S, L>
 where
     K: Hash + PartialEq,
+    L: BucketLocking,
 {
     fn drop(&mut self) {
         let buckets_ptr = self.buckets.load(Ordering::Acquire);
@@ -210,55 +1381,218 @@ where
     }
 }
 
-impl<K, V, S> Map for StripedHashMap<K, V, S>
+impl<K, V, S, L> Map for StripedHashMap<K, V, S, L>
 where
     K: Hash + PartialEq,
     S: BuildHasher,
+    L: BucketLocking,
 {
     type Key = K;
     type Val = V;
-    type ValueRef<'a> = ElemRef<'a, K, V> where K: 'a, V: 'a, S: 'a;
+    type ValueRef<'a> = ElemRef<'a, K, V, L> where K: 'a, V: 'a, S: 'a, L: 'a;
 
-    fn get(&self, key: &K) -> Option<ElemRef<'_, K, V>> {
-        let searcher = MaybeElemRef {
-            guard: self._get_read_bucket_by_key(key),
-        };
-        searcher.find(key)
+    fn get(&self, key: &K) -> Option<ElemRef<'_, K, V, L>> {
+        self.try_get(key).unwrap()
     }
 
     fn contains(&self, key: &K) -> bool {
-        self.get(key).is_some()
+        self.try_contains(key).unwrap()
     }
 
     fn put(&self, key: K, value: V) {
-        let (_, mut bucket) = self._get_write_bucket_by_key(&key);
-        bucket.push((key, value));
+        self.try_put(key, value).unwrap()
+    }
 
-        #[allow(clippy::collapsible_if)]
-        if bucket.len() > self.max_bucket_size {
+    fn remove(&self, key: &K) -> bool {
+        self.try_remove(key).unwrap()
+    }
+}
+
+/// Values are updated under their bucket's write lock, and subscribers are
+/// told of an update once the guard is dropped, whether or not the value was
+/// changed.
+impl<K, V, S, L> MapMut for StripedHashMap<K, V, S, L>
+where
+    K: Hash + PartialEq,
+    S: BuildHasher,
+    L: BucketLocking,
+{
+    type ValueRefMut<'a> = ElemRefMut<'a, K, V, L> where K: 'a, V: 'a, S: 'a, L: 'a;
+
+    fn get_mut(&self, key: &K) -> Option<ElemRefMut<'_, K, V, L>> {
+        self.try_get_mut(key).unwrap()
+    }
+
+    fn take(&self, key: &K) -> Option<V> {
+        self.try_take(key).unwrap()
+    }
+}
+
+impl<K, V, S, L> Statistics for StripedHashMap<K, V, S, L>
+where
+    K: Hash + PartialEq,
+    L: BucketLocking,
+{
+    fn stats(&self) -> Stats {
+        self.counters.snapshot()
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<K, V, S, L> StripedHashMap<K, V, S, L>
+where
+    K: Hash + PartialEq,
+    S: BuildHasher,
+    L: BucketLocking,
+{
+    /// Grows the bucket array, if needed, to take `additional` more pairs
+    /// without resizing, sized as by [`StripedHashMap::with_capacity`].
+    fn _reserve(&self, additional: usize) {
+        let min_len = (additional / DEFAULT_MAX_BUCKET_SIZE) * 2;
+        let guard = epoch::pin();
+        while self.num_buckets() < min_len {
             if self
                 .resize_in_progress
                 .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
                 .is_ok()
             {
-                drop(bucket);
-                self._resize();
+                // another resize may have grown the array enough meanwhile
+                if self.num_buckets() < min_len {
+                    self._resize(min_len, &guard);
+                }
                 self.resize_in_progress.swap(false, Ordering::Release);
+            } else {
+                self._guard_resize();
             }
         }
     }
 
-    fn remove(&self, key: &K) -> bool {
-        let (_, mut bucket) = self._get_write_bucket_by_key(key);
-        let itr = bucket.iter();
-        for (i, entry) in itr.enumerate() {
-            if entry.0 == *key {
-                bucket.remove(i);
-                return true;
+    /// Puts `entries`, each with its key's hash and sorted by bucket, locking
+    /// each bucket once for the run of entries that map to it.
+    ///
+    /// The hashes only order the entries: the map may have been reseeded
+    /// since, so keys are hashed again under the bucket lock.
+    fn _put_sorted(&self, entries: Vec<(usize, K, V)>) {
+        let guard = epoch::pin();
+        let mut entries = entries.into_iter().peekable();
+        while let Some((hash, key, value)) = entries.next() {
+            let (bucket_index, mut bucket) = self._get_write_bucket_by_key(&key).unwrap();
+            // The array cannot be replaced while we hold one of its buckets,
+            // as a resize drains them all first.
+            let num_buckets = self.num_buckets();
+            let run = std::iter::once((hash, key, value)).chain(std::iter::from_fn(|| {
+                entries.next_if(|(_, key, _)| self.hash(key) % num_buckets == bucket_index)
+            }));
+            let mut changes = Vec::new();
+            for (_, key, value) in run {
+                self.counters.op();
+                if let Some(entry) = bucket.iter_mut().find(|entry| entry.0 == key) {
+                    entry.1 = value;
+                    changes.extend(
+                        self.changes
+                            .capture(ChangeKind::Update, &key, Some(&entry.1)),
+                    );
+                } else {
+                    changes.extend(self.changes.capture(ChangeKind::Insert, &key, Some(&value)));
+                    bucket.push((key, value));
+                }
+            }
+            self._resize_if_overfull(bucket, &guard);
+            for change in changes {
+                self.changes.send(Some(change));
             }
         }
+    }
+}
 
-        false
+/// Loads pairs from a parallel iterator, overwriting the values of keys
+/// already in the map.
+///
+/// The pairs are first partitioned by bucket into one shard per few Rayon
+/// workers, each covering a disjoint range of buckets, and each shard is then
+/// filled by a single worker that locks every bucket once. If the iterator's
+/// length is known, the map is grown up front as by
+/// [`StripedHashMap::with_capacity`] rather than resizing as it fills.
+///
+/// Other threads may use the map meanwhile; a resize while loading only
+/// costs the shards locking their buckets once.
+///
+/// Panics on finding a bucket poisoned under [`PoisonPolicy::Propagate`].
+#[cfg(feature = "rayon")]
+impl<K, V, S, L> ParallelExtend<(K, V)> for &StripedHashMap<K, V, S, L>
+where
+    K: Hash + PartialEq + Send,
+    V: Send,
+    S: BuildHasher + Sync,
+    L: BucketLocking + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        let map = *self;
+        let par_iter = par_iter.into_par_iter();
+        if let Some(len) = par_iter.opt_len() {
+            map._reserve(len);
+        }
+        let num_buckets = {
+            let _guard = epoch::pin();
+            map.num_buckets()
+        };
+        let num_shards = (rayon::current_num_threads() * 4).min(num_buckets);
+        let shard_of = |hash: usize| hash % num_buckets * num_shards / num_buckets;
+        let empty_shards = || (0..num_shards).map(|_| Vec::new()).collect::<Vec<_>>();
+
+        let shards = par_iter
+            .fold(empty_shards, |mut shards, (key, value)| {
+                let hash = map.hash(&key);
+                shards[shard_of(hash)].push((hash, key, value));
+                shards
+            })
+            .reduce(empty_shards, |mut shards, other| {
+                for (shard, other) in shards.iter_mut().zip(other) {
+                    shard.extend(other);
+                }
+                shards
+            });
+        shards.into_par_iter().for_each(|mut shard| {
+            shard.sort_unstable_by_key(|&(hash, _, _)| hash % num_buckets);
+            map._put_sorted(shard);
+        });
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<K, V, S, L> ParallelExtend<(K, V)> for StripedHashMap<K, V, S, L>
+where
+    K: Hash + PartialEq + Send,
+    V: Send,
+    S: BuildHasher + Sync,
+    L: BucketLocking + Sync,
+{
+    fn par_extend<I>(&mut self, par_iter: I)
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        (&*self).par_extend(par_iter);
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<K, V, S, L> FromParallelIterator<(K, V)> for StripedHashMap<K, V, S, L>
+where
+    K: Hash + PartialEq + Send,
+    V: Send,
+    S: BuildHasher + Default + Sync,
+    L: BucketLocking + Sync,
+{
+    fn from_par_iter<I>(par_iter: I) -> Self
+    where
+        I: IntoParallelIterator<Item = (K, V)>,
+    {
+        let mut map = StripedHashMap::build(DEFAULT_NUM_BUCKETS, S::default());
+        map.par_extend(par_iter);
+        map
     }
 }
 
@@ -274,5 +1608,488 @@ mod tests {
         map.put(key.clone(), val.clone());
         assert!(map.contains(&key));
         assert_eq!(*map.get(&key).unwrap(), val);
+
+        // a second put overwrites the first
+        map.put(key.clone(), "there".to_string());
+        assert_eq!(*map.get(&key).unwrap(), "there");
+        assert!(map.remove(&key));
+        assert!(!map.contains(&key));
+
+        map.put(key.clone(), val.clone());
+        assert_eq!(map.take(&key), Some(val));
+        assert_eq!(map.take(&key), None);
+    }
+
+    #[test]
+    fn test_get_or_insert_with() {
+        let map = StripedHashMap::new();
+        assert_eq!(map.get_or_insert_with(1, || "one"), "one");
+        assert_eq!(map.get_or_insert_with(1, || unreachable!()), "one");
+        assert_eq!(*map.get(&1).unwrap(), "one");
+    }
+
+    #[test]
+    fn test_get_with_modify_with() {
+        let map = StripedHashMap::new();
+        assert_eq!(map.get_with(&1, |v: &Vec<i32>| v.len()), None);
+        assert_eq!(map.modify_with(&1, |v: &mut Vec<i32>| v.push(1)), None);
+
+        map.put(1, vec![1]);
+        assert_eq!(
+            map.modify_with(&1, |v| {
+                v.push(2);
+                v.len()
+            }),
+            Some(2)
+        );
+        assert_eq!(map.get_with(&1, |v| v.iter().sum::<i32>()), Some(3));
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let map = StripedHashMap::new();
+        assert!(map.get_mut(&0).is_none());
+        map.put(0, 0);
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..1_000 {
+                        *map.get_mut(&0).unwrap() += 1;
+                    }
+                });
+            }
+        });
+        assert_eq!(*map.get(&0).unwrap(), 4_000);
+
+        // the update is reported once the guard is dropped
+        let mut changes = map.subscribe(16);
+        let mut value = map.get_mut(&0).unwrap();
+        *value = -1;
+        assert!(changes.try_recv().is_none());
+        drop(value);
+        let event = changes.try_recv().unwrap().unwrap();
+        assert_eq!((event.kind, event.value), (ChangeKind::Update, Some(-1)));
+    }
+
+    fn poison<L: BucketLocking + Sync>(map: &StripedHashMap<i32, i32, RandomState, L>) {
+        std::thread::scope(|s| {
+            let panicked = s.spawn(|| map.modify_with(&1, |_| panic!("poisoning bucket")));
+            assert!(panicked.join().is_err());
+        });
+    }
+
+    #[test]
+    fn test_poison_propagate() {
+        let map = StripedHashMap::new();
+        map.put(1, 1);
+        poison(&map);
+        assert!(map.try_get(&1).is_err());
+        assert_eq!(map.try_put(1, 2), Err(Poisoned));
+        assert_eq!(map.try_modify_with(&1, |v| *v), Err(Poisoned));
+    }
+
+    #[test]
+    fn test_poison_recover() {
+        let map = StripedHashMap::new().poison_policy(PoisonPolicy::Recover);
+        map.put(1, 1);
+        poison(&map);
+        assert_eq!(map.try_get_with(&1, |v| *v), Ok(Some(1)));
+        map.put(1, 2);
+        assert_eq!(*map.get(&1).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_poison_biased() {
+        // biased locks are never poisoned
+        let map = StripedHashMap::read_mostly();
+        map.put(1, 1);
+        poison(&map);
+        assert_eq!(map.try_remove(&1), Ok(true));
+    }
+
+    #[cfg(feature = "parking_lot")]
+    #[test]
+    fn test_poison_parking_lot() {
+        let map = StripedHashMap::with_parking_lot();
+        map.put(1, 1);
+        poison(&map);
+        assert_eq!(map.try_remove(&1), Ok(true));
+    }
+
+    #[test]
+    fn test_memory_usage() {
+        let map = StripedHashMap::with_capacity(100);
+        let empty = map.memory_usage();
+        assert_eq!(empty.entries, 0);
+        assert!(empty.buckets >= 20 * size_of::<RwLock<Bucket<u64, String>>>());
+
+        for key in 0..100 {
+            map.put(key, "x".repeat(key as usize));
+        }
+        let usage = map.memory_usage();
+        assert!(usage.entries >= 100 * size_of::<(u64, String)>());
+        assert_eq!(usage.values, 0);
+        let deep = map.deep_memory_usage();
+        assert!(deep.values >= (0..100).sum());
+        assert_eq!(deep.total(), usage.total() + deep.values);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let map = StripedHashMap::with_capacity(16);
+        for key in 0..1_000u64 {
+            map.put(key, key.to_string());
+        }
+        let mut bytes = Vec::new();
+        map.save_to(&mut bytes).unwrap();
+
+        let loaded: StripedHashMap<u64, String> = StripedHashMap::load_from(&bytes[..]).unwrap();
+        assert_eq!(loaded.num_buckets(), map.num_buckets());
+        for key in 0..1_000 {
+            assert_eq!(*loaded.get(&key).unwrap(), key.to_string());
+        }
+
+        assert!(StripedHashMap::<u64, String>::load_from(&bytes[1..]).is_err());
+        assert!(StripedHashMap::<u64, String>::load_from(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_same_hasher() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::BuildHasherDefault;
+
+        let hasher = BuildHasherDefault::<DefaultHasher>::default();
+        let map = StripedHashMap::with_hasher(hasher.clone());
+        for key in 0..1_000 {
+            map.put(key.to_string(), key);
+        }
+        let mut bytes = Vec::new();
+        map.save_to(&mut bytes).unwrap();
+
+        let loaded: StripedHashMap<String, i32, _> =
+            StripedHashMap::load_from_with_hasher(&bytes[..], hasher).unwrap();
+        assert_eq!(loaded._fingerprint(), map._fingerprint());
+        for key in 0..1_000 {
+            assert_eq!(*loaded.get(&key.to_string()).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn test_flooding_reseeds() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::BuildHasherDefault;
+
+        let hasher = BuildHasherDefault::<DefaultHasher>::default();
+        // keys that share a bucket in any array of up to 2^14 buckets
+        let keys = (0u64..)
+            .filter(|key| hasher.hash_one(key) % (1 << 14) == 0)
+            .take(20)
+            .collect::<Vec<_>>();
+        let map = StripedHashMap::with_hasher(hasher.clone());
+        for &key in &keys {
+            map.put(key, key);
+        }
+        assert_eq!(map.num_buckets(), DEFAULT_NUM_BUCKETS);
+        assert!(map.max_chain_len() <= DEFAULT_MAX_BUCKET_SIZE);
+        assert!(keys.iter().all(|key| map.contains(key)));
+
+        // the reseeded map no longer hashes as a fresh one does
+        let mut bytes = Vec::new();
+        map.save_to(&mut bytes).unwrap();
+        let loaded: StripedHashMap<u64, u64, _> =
+            StripedHashMap::load_from_with_hasher(&bytes[..], hasher).unwrap();
+        assert_ne!(loaded._fingerprint(), map._fingerprint());
+        assert!(keys.iter().all(|key| loaded.contains(key)));
+    }
+
+    #[test]
+    fn test_with_seed() {
+        let save = |map: &StripedHashMap<u32, u32, SeededState>| {
+            let mut bytes = Vec::new();
+            map.save_to(&mut bytes).unwrap();
+            bytes
+        };
+        let (a, b, c) = (
+            StripedHashMap::with_seed(1),
+            StripedHashMap::with_seed(1),
+            StripedHashMap::with_seed(2),
+        );
+        for key in 0..1_000 {
+            a.put(key, key);
+            c.put(key, key);
+        }
+        // the same entries, reached another way
+        for key in (0..2_000).rev() {
+            b.put(key, 0);
+        }
+        for key in 0..2_000 {
+            if key < 1_000 {
+                b.put(key, key);
+            } else {
+                b.remove(&key);
+            }
+        }
+        assert!(a.snapshot_iter().eq(b.snapshot_iter()));
+        assert_eq!(save(&a), save(&b));
+        assert_ne!(save(&a), save(&c));
+    }
+
+    #[test]
+    fn test_snapshot_iter() {
+        // keys are put in order, across resizes, so every snapshot must hold
+        // a prefix of them
+        let num_elems = 5_000;
+        let map = StripedHashMap::with_capacity(16);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for key in 0..num_elems {
+                    map.put(key, key);
+                }
+            });
+            for _ in 0..20 {
+                let mut keys = map.snapshot_iter().map(|(key, _)| key).collect::<Vec<_>>();
+                keys.sort_unstable();
+                assert!(keys.iter().copied().eq(0..keys.len()));
+            }
+        });
+        assert_eq!(map.snapshot_iter().len(), num_elems);
+    }
+
+    #[test]
+    fn test_subscribe() {
+        use crate::map::Lagged;
+
+        let map = StripedHashMap::new();
+        map.put(0, "zero".to_string());
+        let mut changes = map.subscribe(16);
+        let mut keys = map.subscribe_keys(2);
+
+        map.put(1, "one".to_string());
+        map.put(1, "uno".to_string());
+        map.modify_with(&1, |value| value.push('!'));
+        assert!(map.remove(&1));
+
+        let events = std::iter::from_fn(|| changes.try_recv())
+            .map(|event| {
+                let event = event.unwrap();
+                (event.kind, event.key, event.value)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                (ChangeKind::Insert, 1, Some("one".to_string())),
+                (ChangeKind::Update, 1, Some("uno".to_string())),
+                (ChangeKind::Update, 1, Some("uno!".to_string())),
+                (ChangeKind::Remove, 1, None),
+            ]
+        );
+
+        // the keys-only receiver lost the two oldest events
+        assert_eq!(keys.try_recv(), Some(Err(Lagged(2))));
+        let event = keys.try_recv().unwrap().unwrap();
+        assert_eq!((event.kind, event.value), (ChangeKind::Update, None));
+        assert_eq!(keys.try_recv().unwrap().unwrap().kind, ChangeKind::Remove);
+        assert_eq!(keys.try_recv(), None);
+
+        drop(map);
+        #[cfg(can_block)]
+        assert_eq!(changes.recv(), None);
+    }
+
+    #[test]
+    fn test_transact() {
+        let num_thrs = 4;
+        let num_accounts = 8;
+        let map = StripedHashMap::with_capacity(16);
+        for account in 0..num_accounts {
+            map.put(account, 100);
+        }
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let map = &map;
+                s.spawn(move || {
+                    for i in 0..1_000 {
+                        let from = (t + i) % num_accounts;
+                        let to = (t * 3 + i * 5 + 1) % num_accounts;
+                        if from == to {
+                            continue;
+                        }
+                        map.transact(&[from, to], |entries| {
+                            let balance = entries[0].get_mut().unwrap();
+                            if *balance > 0 {
+                                *balance -= 1;
+                                *entries[1].get_mut().unwrap() += 1;
+                            }
+                        });
+                    }
+                });
+            }
+            // no snapshot sees money in flight
+            for _ in 0..100 {
+                let total: i32 = map.snapshot_iter().map(|(_, balance)| balance).sum();
+                assert_eq!(total, num_accounts as i32 * 100);
+            }
+        });
+
+        // inserts and removals, by keys in and out of the map
+        map.transact(&[0, num_accounts], |entries| {
+            let balance = entries[0].remove().unwrap();
+            assert!(entries[1].get().is_none());
+            entries[1].insert(balance);
+        });
+        assert!(!map.contains(&0));
+        assert_eq!(map.snapshot_iter().len(), num_accounts);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_transact_duplicate_keys() {
+        let map = StripedHashMap::<i32, i32>::new();
+        map.transact(&[1, 2, 1], |_| {});
+    }
+
+    #[test]
+    fn test_concurrent_resize() {
+        let num_thrs = 8;
+        let num_elems = 2_000;
+        let map = StripedHashMap::with_capacity(16);
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let map = &map;
+                s.spawn(move || {
+                    for i in 0..num_elems {
+                        let key = t * num_elems + i;
+                        map.put(key, key);
+                        assert_eq!(*map.get(&key).unwrap(), key);
+                    }
+                });
+            }
+        });
+
+        assert!(map.num_buckets() > 16);
+        for key in 0..num_thrs * num_elems {
+            assert_eq!(*map.get(&key).unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn test_read_mostly() {
+        let num_thrs = 4;
+        let num_elems = 1_000;
+        let map = StripedHashMap::read_mostly();
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let map = &map;
+                s.spawn(move || {
+                    for i in 0..num_elems {
+                        let key = t * num_elems + i;
+                        map.put(key, key);
+                        assert_eq!(*map.get(&key).unwrap(), key);
+                        if i % 2 == 0 {
+                            assert!(map.remove(&key));
+                        }
+                    }
+                });
+            }
+        });
+
+        for key in 0..num_thrs * num_elems {
+            assert_eq!(map.get(&key).as_deref(), (key % 2 == 1).then_some(&key));
+        }
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_par_extend() {
+        use rayon::prelude::*;
+
+        let num_elems = 100_000;
+        let map: StripedHashMap<usize, usize> = (0..num_elems)
+            .into_par_iter()
+            .map(|key| (key, key))
+            .collect();
+        assert!(map.num_buckets() >= num_elems / DEFAULT_MAX_BUCKET_SIZE * 2);
+
+        // of unknown length, so the map resizes while it fills, and with
+        // puts from another thread racing the load
+        let map = StripedHashMap::with_capacity(16);
+        map.put(0, usize::MAX);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for key in num_elems..num_elems + 1_000 {
+                    map.put(key, key);
+                }
+            });
+            (&map).par_extend(
+                (0..num_elems)
+                    .into_par_iter()
+                    .filter(|_| true)
+                    .map(|key| (key, key)),
+            );
+        });
+        for key in 0..num_elems + 1_000 {
+            assert_eq!(*map.get(&key).unwrap(), key);
+        }
+    }
+
+    #[cfg(loom)]
+    #[test]
+    fn loom_striped_map_resize() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::BuildHasherDefault;
+
+        use loom::sync::Arc;
+
+        let mut builder = loom::model::Builder::new();
+        builder.preemption_bound = Some(3);
+        builder.check(|| {
+            // a single bucket that overflows on the second entry, so both
+            // threads' puts race to resize while the other reads and writes
+            let hasher = BuildHasherDefault::<DefaultHasher>::default();
+            let mut map = StripedHashMap::<_, _, _, RwLocking>::build(1, hasher);
+            map.max_bucket_size = 1;
+            map.put(0, 0);
+            let map = Arc::new(map);
+
+            let thread = {
+                let map = map.clone();
+                loom::thread::spawn(move || {
+                    map.put(1, 1);
+                    assert_eq!(*map.get(&1).unwrap(), 1);
+                    assert!(map.remove(&0));
+                })
+            };
+            map.put(2, 2);
+            assert_eq!(*map.get(&2).unwrap(), 2);
+            thread.join().unwrap();
+
+            assert!(!map.contains(&0));
+            assert_eq!(*map.get(&1).unwrap(), 1);
+            assert_eq!(*map.get(&2).unwrap(), 2);
+        });
+    }
+
+    #[test]
+    fn miri_striped_map() {
+        // few enough operations to run under Miri, with enough keys for two
+        // buckets to overflow
+        let map = StripedHashMap::with_capacity(DEFAULT_MAX_BUCKET_SIZE);
+        std::thread::scope(|s| {
+            for t in 0..2 {
+                let map = &map;
+                s.spawn(move || {
+                    for key in t * 12..t * 12 + 12 {
+                        map.put(key, key.to_string());
+                        assert_eq!(*map.get(&key).unwrap(), key.to_string());
+                    }
+                });
+            }
+        });
+        assert!(map.num_buckets() > 2);
+        assert!((0..24).all(|key| map.remove(&key)));
     }
 }
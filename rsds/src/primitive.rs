@@ -0,0 +1,28 @@
+//! The synchronization primitives that model-checked structures are built
+//! on: the standard library's, or loom's instrumented versions when built
+//! with `--cfg loom`.
+//!
+//! Structures that import their atomics and locks from here can be model
+//! checked with loom, which runs a test under every interleaving of its
+//! threads' synchronizing operations, up to a bound. The loom tests are named
+//! `loom_*`, and are run with
+//!
+//! ```text
+//! RUSTFLAGS="--cfg loom" cargo test --release --lib loom
+//! ```
+//!
+//! Under `--cfg loom`, these primitives may only be used inside a loom model,
+//! so the crate's other tests should not be run in that configuration.
+
+// Which primitives are used depends on the structures built.
+#![allow(unused_imports)]
+
+#[cfg(loom)]
+pub(crate) use loom::hint;
+#[cfg(loom)]
+pub(crate) use loom::sync::{atomic, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[cfg(not(loom))]
+pub(crate) use std::hint;
+#[cfg(not(loom))]
+pub(crate) use std::sync::{atomic, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
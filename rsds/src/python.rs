@@ -0,0 +1,329 @@
+//! Python bindings for the [`StripedHashMap`] and the queues, as a `rsds`
+//! extension module built with [PyO3].
+//!
+//! Every operation releases the GIL while it works on the structure, so that
+//! Python threads sharing a structure run its operations in parallel, and a
+//! thread blocked on a queue lets the others run. Keys are converted to
+//! Rust values before the GIL is released, which is why map keys are limited
+//! to `int`, `str` and `bytes`; values and queue elements can be any Python
+//! object.
+//!
+//! The module is built with the `python` feature. For an importable
+//! extension, also enable `pyo3/extension-module` and build a `cdylib`, as
+//! `maturin build --features python,pyo3/extension-module` does:
+//!
+//! ```text
+//! >>> import rsds
+//! >>> m = rsds.StripedHashMap()
+//! >>> m["answer"] = 42
+//! >>> m.get("answer"), "question" in m
+//! (42, False)
+//! >>> q = rsds.BoundedBlockingQueue(16)
+//! >>> q.push("task")
+//! >>> q.pop(timeout=1.0)
+//! 'task'
+//! ```
+//!
+//! [PyO3]: https://pyo3.rs
+
+// PyO3's generated wrappers convert errors that already are `PyErr`s.
+#![allow(clippy::useless_conversion)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use pyo3::exceptions::{PyKeyError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::map::{self, Map};
+use crate::queue::{self, Queue};
+
+/// A map key, converted from a Python `int`, `str` or `bytes`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Key {
+    Int(i64),
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+impl<'py> FromPyObject<'py> for Key {
+    fn extract_bound(ob: &Bound<'py, PyAny>) -> PyResult<Self> {
+        // `bool` keys become ints, as they compare equal to them.
+        if let Ok(int) = ob.extract() {
+            Ok(Key::Int(int))
+        } else if let Ok(string) = ob.extract() {
+            Ok(Key::Str(string))
+        } else if let Ok(bytes) = ob.extract() {
+            Ok(Key::Bytes(bytes))
+        } else {
+            Err(PyTypeError::new_err(format!(
+                "unsupported key type: {}",
+                ob.get_type().name()?
+            )))
+        }
+    }
+}
+
+/// A concurrent hash map from `int`, `str` or `bytes` keys to objects.
+///
+/// Values are reference counted on the Rust side, so that a lookup only
+/// holds the bucket's lock while it takes a reference.
+#[pyclass(frozen, name = "StripedHashMap", module = "rsds")]
+struct PyStripedHashMap {
+    // Boxed, as PyO3 does not align Python objects for the cache-padded
+    // parts of the structures.
+    map: Box<map::StripedHashMap<Key, Arc<PyObject>>>,
+}
+
+#[pymethods]
+impl PyStripedHashMap {
+    #[new]
+    fn new() -> Self {
+        Self {
+            map: Box::new(map::StripedHashMap::new()),
+        }
+    }
+
+    /// Returns the value for `key`, or `default` if there is none.
+    #[pyo3(signature = (key, default = None))]
+    fn get(&self, py: Python<'_>, key: Key, default: Option<PyObject>) -> Option<PyObject> {
+        self.lookup(py, &key).or(default)
+    }
+
+    fn __getitem__(&self, py: Python<'_>, key: Key) -> PyResult<PyObject> {
+        match self.lookup(py, &key) {
+            Some(value) => Ok(value),
+            None => Err(PyKeyError::new_err(key.into_py(py))),
+        }
+    }
+
+    fn __setitem__(&self, py: Python<'_>, key: Key, value: PyObject) {
+        let value = Arc::new(value);
+        py.allow_threads(|| self.map.put(key, value));
+    }
+
+    fn __delitem__(&self, py: Python<'_>, key: Key) -> PyResult<()> {
+        if py.allow_threads(|| self.map.remove(&key)) {
+            Ok(())
+        } else {
+            Err(PyKeyError::new_err(key.into_py(py)))
+        }
+    }
+
+    fn __contains__(&self, py: Python<'_>, key: Key) -> bool {
+        py.allow_threads(|| self.map.contains(&key))
+    }
+}
+
+impl PyStripedHashMap {
+    fn lookup(&self, py: Python<'_>, key: &Key) -> Option<PyObject> {
+        let value = py.allow_threads(|| self.map.get(key).map(|value| Arc::clone(&value)))?;
+        Some(value.clone_ref(py))
+    }
+}
+
+impl IntoPy<PyObject> for Key {
+    fn into_py(self, py: Python<'_>) -> PyObject {
+        match self {
+            Key::Int(int) => int.into_py(py),
+            Key::Str(string) => string.into_py(py),
+            Key::Bytes(bytes) => PyBytes::new_bound(py, &bytes).into_py(py),
+        }
+    }
+}
+
+/// An unbounded lock-free FIFO queue of objects.
+#[pyclass(frozen, name = "MsQueue", module = "rsds")]
+struct PyMsQueue {
+    queue: Box<queue::MsQueue<PyObject>>,
+}
+
+#[pymethods]
+impl PyMsQueue {
+    #[new]
+    fn new() -> Self {
+        Self {
+            queue: Box::new(queue::MsQueue::new()),
+        }
+    }
+
+    /// Appends `elem` to the back of the queue.
+    fn push(&self, py: Python<'_>, elem: PyObject) {
+        py.allow_threads(|| self.queue.push(elem));
+    }
+
+    /// Removes the element at the front of the queue, or returns `None` if
+    /// the queue is empty.
+    fn pop(&self, py: Python<'_>) -> Option<PyObject> {
+        py.allow_threads(|| self.queue.pop())
+    }
+
+    fn __bool__(&self, py: Python<'_>) -> bool {
+        !py.allow_threads(|| self.queue.is_empty())
+    }
+}
+
+/// A bounded FIFO queue of objects, whose producers wait for room and whose
+/// consumers wait for elements.
+#[pyclass(frozen, name = "BoundedBlockingQueue", module = "rsds")]
+struct PyBoundedBlockingQueue {
+    queue: Box<queue::BoundedBlockingQueue<PyObject>>,
+}
+
+#[pymethods]
+impl PyBoundedBlockingQueue {
+    #[new]
+    fn new(capacity: usize) -> PyResult<Self> {
+        if capacity == 0 {
+            return Err(PyValueError::new_err("capacity should be positive"));
+        }
+        Ok(Self {
+            queue: Box::new(queue::BoundedBlockingQueue::with_capacity(capacity)),
+        })
+    }
+
+    /// Appends `elem` to the back of the queue, waiting for room for at most
+    /// `timeout` seconds, or for as long as it takes if `timeout` is `None`.
+    /// Returns whether the element was appended.
+    #[pyo3(signature = (elem, timeout = None))]
+    fn push(&self, py: Python<'_>, elem: PyObject, timeout: Option<f64>) -> PyResult<bool> {
+        let Some(timeout) = timeout else {
+            py.allow_threads(|| self.queue.push(elem));
+            return Ok(true);
+        };
+        let timeout = to_duration(timeout)?;
+        // The element is dropped with the GIL held if there is no room.
+        let pushed = py.allow_threads(|| self.queue.push_timeout(elem, timeout));
+        Ok(pushed.is_ok())
+    }
+
+    /// Appends `elem` to the back of the queue if there is room, returning
+    /// whether it did.
+    fn try_push(&self, py: Python<'_>, elem: PyObject) -> bool {
+        py.allow_threads(|| self.queue.try_push(elem)).is_ok()
+    }
+
+    /// Removes the element at the front of the queue, waiting for one for at
+    /// most `timeout` seconds, or for as long as it takes if `timeout` is
+    /// `None`. Returns `None` if there is none by then.
+    #[pyo3(signature = (timeout = None))]
+    fn pop(&self, py: Python<'_>, timeout: Option<f64>) -> PyResult<Option<PyObject>> {
+        match timeout {
+            Some(timeout) => {
+                let timeout = to_duration(timeout)?;
+                Ok(py.allow_threads(|| self.queue.pop_timeout(timeout)))
+            }
+            None => Ok(Some(py.allow_threads(|| self.queue.pop_wait()))),
+        }
+    }
+
+    /// Removes the element at the front of the queue, or returns `None` if
+    /// the queue is empty.
+    fn try_pop(&self, py: Python<'_>) -> Option<PyObject> {
+        py.allow_threads(|| self.queue.pop())
+    }
+
+    /// The maximum number of elements the queue holds.
+    #[getter]
+    fn capacity(&self) -> usize {
+        self.queue.capacity()
+    }
+
+    fn __len__(&self, py: Python<'_>) -> usize {
+        py.allow_threads(|| self.queue.len())
+    }
+}
+
+/// Converts a timeout in seconds, as Python APIs take them.
+fn to_duration(secs: f64) -> PyResult<Duration> {
+    Duration::try_from_secs_f64(secs)
+        .map_err(|_| PyValueError::new_err("timeout should be a non-negative number"))
+}
+
+/// The `rsds` extension module.
+#[pymodule]
+fn rsds(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyStripedHashMap>()?;
+    m.add_class::<PyMsQueue>()?;
+    m.add_class::<PyBoundedBlockingQueue>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::types::PyDict;
+
+    use super::*;
+
+    #[test]
+    fn python() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new_bound(py, "rsds").unwrap();
+            rsds(&module).unwrap();
+            let globals = PyDict::new_bound(py);
+            globals.set_item("rsds", module).unwrap();
+            py.run_bound(
+                r#"
+import threading
+
+m = rsds.StripedHashMap()
+m[1] = "one"
+m["two"] = [2]
+m[b"three"] = 3
+assert m[True] == "one" and m.get("two") == [2] and b"three" in m
+assert m.get(4) is None and m.get(4, "four") == "four"
+del m[1]
+assert 1 not in m
+for missing in (lambda: m[1], lambda: m.__delitem__(1)):
+    try:
+        missing()
+        assert False
+    except KeyError as e:
+        assert e.args == (1,)
+try:
+    m[1.5] = 0
+    assert False
+except TypeError:
+    pass
+
+q = rsds.MsQueue()
+assert not q and q.pop() is None
+q.push(1)
+assert q and q.pop() == 1
+
+b = rsds.BoundedBlockingQueue(2)
+assert b.try_push(1) and b.push(2, timeout=0.01)
+assert not b.try_push(3) and not b.push(3, timeout=0.01)
+assert len(b) == 2 and b.capacity == 2
+assert b.pop() == 1 and b.try_pop() == 2 and b.pop(timeout=0.01) is None
+
+# threads wait on the queue without holding the GIL
+results = rsds.MsQueue()
+def work():
+    while (n := b.pop()) is not None:
+        m[n] = n * n
+        results.push(n)
+workers = [threading.Thread(target=work) for _ in range(4)]
+for w in workers:
+    w.start()
+for n in range(100):
+    b.push(n)
+for _ in workers:
+    b.push(None)
+for w in workers:
+    w.join()
+popped = []
+while (n := results.pop()) is not None:
+    popped.append(n)
+assert sorted(popped) == list(range(100))
+assert all(m[n] == n * n for n in range(100))
+"#,
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+        });
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+use crossbeam::utils::CachePadded;
+use futures_core::Stream;
+
+use super::Queue;
+
+/// Wakers of the tasks waiting for an element, oldest first.
+struct Waiters {
+    wakers: VecDeque<(u64, Waker)>,
+    /// The ID handed to the next waiter to register.
+    next_id: u64,
+}
+
+/// A [`Queue`] whose consumers can await an element from async code, while
+/// still serving threads through [`Queue::pop`].
+///
+/// A consumer that finds the queue empty registers its task's waker and
+/// returns [`Poll::Pending`], instead of blocking its thread. As with
+/// [`Blocking`], producers only take the waiters' lock when some consumer is
+/// waiting, and then wake up the one that has waited the longest.
+///
+/// A consumer that is woken up but dropped before taking an element passes
+/// the wakeup on to the next waiter, so that the element is not left behind
+/// with consumers waiting.
+///
+/// ```
+/// use futures::executor::block_on;
+/// use rsds::queue::{AsyncQueue, MsQueue, Queue};
+///
+/// let queue = AsyncQueue::new(MsQueue::new());
+/// std::thread::scope(|s| {
+///     s.spawn(|| assert_eq!(block_on(queue.pop_async()), 1));
+///     s.spawn(|| queue.push(1));
+/// });
+/// assert_eq!(queue.pop(), None);
+/// ```
+///
+/// [`Blocking`]: super::Blocking
+pub struct AsyncQueue<Q> {
+    queue: Q,
+    /// Number of registered waiters.
+    num_waiters: CachePadded<AtomicUsize>,
+    waiters: Mutex<Waiters>,
+}
+
+impl<Q> Default for AsyncQueue<Q>
+where
+    Q: Default,
+{
+    fn default() -> Self {
+        Self::new(Q::default())
+    }
+}
+
+impl<Q> AsyncQueue<Q> {
+    /// Wraps `queue` so that consumers can await it.
+    pub fn new(queue: Q) -> Self {
+        Self {
+            queue,
+            num_waiters: CachePadded::new(AtomicUsize::new(0)),
+            waiters: Mutex::new(Waiters {
+                wakers: VecDeque::new(),
+                next_id: 0,
+            }),
+        }
+    }
+
+    /// Returns a reference to the wrapped queue.
+    ///
+    /// Pushing to the wrapped queue directly does not wake up waiting
+    /// consumers.
+    pub fn inner(&self) -> &Q {
+        &self.queue
+    }
+
+    /// Consumes the wrapper, returning the wrapped queue.
+    pub fn into_inner(self) -> Q {
+        self.queue
+    }
+
+    /// Wakes up the consumer that has waited the longest, if any.
+    fn wake_one(&self) {
+        let mut waiters = self.waiters.lock().unwrap();
+        if let Some((_, waker)) = waiters.wakers.pop_front() {
+            self.num_waiters.fetch_sub(1, Ordering::Relaxed);
+            drop(waiters);
+            waker.wake();
+        }
+    }
+
+    /// Unregisters the waiter with the given ID, returning whether it was
+    /// still registered, i.e. not woken up yet.
+    fn unregister(&self, id: u64) -> bool {
+        let mut waiters = self.waiters.lock().unwrap();
+        let Some(pos) = waiters.wakers.iter().position(|(waiter, _)| *waiter == id) else {
+            return false;
+        };
+        waiters.wakers.remove(pos);
+        self.num_waiters.fetch_sub(1, Ordering::Relaxed);
+        true
+    }
+}
+
+impl<Q> AsyncQueue<Q>
+where
+    Q: Queue,
+{
+    /// Returns a future that removes an element from the front of the queue,
+    /// resolving once there is one.
+    pub fn pop_async(&self) -> Pop<'_, Q> {
+        Pop {
+            queue: self,
+            id: None,
+        }
+    }
+
+    /// Returns a never-ending stream of the elements popped from the front of
+    /// the queue.
+    pub fn stream(&self) -> PopStream<'_, Q> {
+        PopStream {
+            pop: self.pop_async(),
+        }
+    }
+
+    /// Pops an element, or registers the task as a waiter under `id`.
+    fn poll_pop(&self, id: &mut Option<u64>, cx: &mut Context<'_>) -> Poll<Q::Elem> {
+        if let Some(elem) = self.queue.pop() {
+            self.finish_wait(id);
+            return Poll::Ready(elem);
+        }
+
+        let mut waiters = self.waiters.lock().unwrap();
+        let registered =
+            id.and_then(|id| waiters.wakers.iter_mut().find(|(waiter, _)| *waiter == id));
+        match registered {
+            Some((_, waker)) => waker.clone_from(cx.waker()),
+            None => {
+                let new_id = waiters.next_id;
+                waiters.next_id += 1;
+                waiters.wakers.push_back((new_id, cx.waker().clone()));
+                *id = Some(new_id);
+                self.num_waiters.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+        // Producers check for waiters after pushing, so either they see us
+        // and wake us up, or we see their element here.
+        atomic::fence(Ordering::SeqCst);
+        let elem = self.queue.pop();
+        drop(waiters);
+        match elem {
+            Some(elem) => {
+                self.finish_wait(id);
+                Poll::Ready(elem)
+            }
+            None => Poll::Pending,
+        }
+    }
+
+    /// Unregisters a waiter that got an element.
+    fn finish_wait(&self, id: &mut Option<u64>) {
+        if let Some(id) = id.take() {
+            self.unregister(id);
+        }
+    }
+}
+
+impl<Q> Queue for AsyncQueue<Q>
+where
+    Q: Queue,
+{
+    type Elem = Q::Elem;
+
+    fn push(&self, elem: Q::Elem) {
+        self.queue.push(elem);
+        atomic::fence(Ordering::SeqCst);
+        if self.num_waiters.load(Ordering::SeqCst) > 0 {
+            self.wake_one();
+        }
+    }
+
+    fn pop(&self) -> Option<Q::Elem> {
+        self.queue.pop()
+    }
+}
+
+impl<Q> fmt::Debug for AsyncQueue<Q>
+where
+    Q: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncQueue")
+            .field("queue", &self.queue)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A future that pops an element from an [`AsyncQueue`], created by
+/// [`AsyncQueue::pop_async`].
+#[must_use = "futures do nothing unless polled"]
+pub struct Pop<'a, Q> {
+    queue: &'a AsyncQueue<Q>,
+    /// The ID the task is registered as a waiter under, if it is.
+    id: Option<u64>,
+}
+
+impl<Q> Future for Pop<'_, Q>
+where
+    Q: Queue,
+{
+    type Output = Q::Elem;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Q::Elem> {
+        let this = self.get_mut();
+        this.queue.poll_pop(&mut this.id, cx)
+    }
+}
+
+impl<Q> Drop for Pop<'_, Q> {
+    fn drop(&mut self) {
+        // A waiter that was woken up, but will not pop, passes the wakeup on.
+        if let Some(id) = self.id {
+            if !self.queue.unregister(id) {
+                self.queue.wake_one();
+            }
+        }
+    }
+}
+
+impl<Q> fmt::Debug for Pop<'_, Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pop")
+            .field("waiting", &self.id.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A stream of the elements popped from an [`AsyncQueue`], created by
+/// [`AsyncQueue::stream`].
+#[must_use = "streams do nothing unless polled"]
+pub struct PopStream<'a, Q> {
+    pop: Pop<'a, Q>,
+}
+
+impl<Q> Stream for PopStream<'_, Q>
+where
+    Q: Queue,
+{
+    type Item = Q::Elem;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Q::Elem>> {
+        Pin::new(&mut self.get_mut().pop).poll(cx).map(Some)
+    }
+}
+
+impl<Q> fmt::Debug for PopStream<'_, Q> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PopStream")
+            .field("waiting", &self.pop.id.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use futures::{FutureExt, StreamExt};
+
+    use super::*;
+    use crate::queue::{MsQueue, TwoLockQueue};
+
+    #[test]
+    fn async_queue() {
+        let queue = AsyncQueue::new(TwoLockQueue::new());
+        assert_eq!(queue.pop_async().now_or_never(), None);
+        queue.push(1);
+        assert_eq!(block_on(queue.pop_async()), 1);
+
+        // a woken-up waiter that is dropped passes the wakeup on
+        let mut dropped = queue.pop_async();
+        assert_eq!((&mut dropped).now_or_never(), None);
+        std::thread::scope(|s| {
+            s.spawn(|| assert_eq!(block_on(queue.pop_async()), 2));
+            while queue.num_waiters.load(Ordering::SeqCst) < 2 {
+                std::thread::yield_now();
+            }
+            queue.push(2);
+            drop(dropped);
+        });
+
+        (3..6).for_each(|i| queue.push(i));
+        let popped: Vec<_> = block_on(queue.stream().take(3).collect());
+        assert_eq!(popped, [3, 4, 5]);
+        assert_eq!(queue.num_waiters.load(Ordering::SeqCst), 0);
+        assert!(queue.into_inner().is_empty());
+    }
+
+    #[test]
+    fn async_queue_concurrent() {
+        let num_producers = 4;
+        let num_consumers = 4;
+        let num_elems = 10_000;
+        let queue = AsyncQueue::new(MsQueue::new());
+
+        let mut popped: Vec<_> = std::thread::scope(|s| {
+            let consumers: Vec<_> = (0..num_consumers)
+                .map(|c| {
+                    let queue = &queue;
+                    s.spawn(move || {
+                        let count = num_producers * num_elems / num_consumers;
+                        // consumers mix the async and sync APIs
+                        if c % 2 == 0 {
+                            block_on(queue.stream().take(count).collect::<Vec<_>>())
+                        } else {
+                            (0..count)
+                                .map(|_| loop {
+                                    if let Some(elem) = queue.pop() {
+                                        break elem;
+                                    }
+                                    std::thread::yield_now();
+                                })
+                                .collect()
+                        }
+                    })
+                })
+                .collect();
+            for p in 0..num_producers {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..num_elems {
+                        queue.push(p * num_elems + i);
+                        // pause now and then, so that consumers wait
+                        if i % 1000 == 0 {
+                            std::thread::sleep(std::time::Duration::from_millis(1));
+                        }
+                    }
+                });
+            }
+            consumers
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        });
+        popped.sort_unstable();
+        assert!(popped.into_iter().eq(0..num_producers * num_elems));
+    }
+}
@@ -0,0 +1,235 @@
+use std::fmt;
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+#[cfg(has_clock)]
+use std::time::{Duration, Instant};
+
+use crossbeam::utils::CachePadded;
+
+use super::Queue;
+use crate::sync::Backoff;
+
+/// A [`Queue`] whose consumers can block until an element arrives, instead
+/// of spinning on [`Queue::pop`].
+///
+/// Consumers that find the queue empty spin briefly, then park on a condition
+/// variable. Producers only take the condition variable's lock to wake them
+/// up when some consumer is parked, so pushes stay as cheap as the wrapped
+/// queue's while every consumer is busy.
+///
+/// ```
+/// use rsds::queue::{Blocking, MsQueue, Queue};
+///
+/// let queue = Blocking::new(MsQueue::new());
+/// std::thread::scope(|s| {
+///     s.spawn(|| assert_eq!(queue.pop_wait(), 1));
+///     s.spawn(|| queue.push(1));
+/// });
+/// assert_eq!(queue.pop(), None);
+/// ```
+pub struct Blocking<Q> {
+    queue: Q,
+    /// Number of consumers parked, or about to park.
+    waiters: CachePadded<AtomicUsize>,
+    lock: Mutex<()>,
+    not_empty: Condvar,
+}
+
+impl<Q> Default for Blocking<Q>
+where
+    Q: Default,
+{
+    fn default() -> Self {
+        Self::new(Q::default())
+    }
+}
+
+impl<Q> Blocking<Q> {
+    /// Wraps `queue` so that consumers can block on it.
+    pub fn new(queue: Q) -> Self {
+        Self {
+            queue,
+            waiters: CachePadded::new(AtomicUsize::new(0)),
+            lock: Mutex::new(()),
+            not_empty: Condvar::new(),
+        }
+    }
+
+    /// Returns a reference to the wrapped queue.
+    ///
+    /// Pushing to the wrapped queue directly does not wake up parked
+    /// consumers.
+    pub fn inner(&self) -> &Q {
+        &self.queue
+    }
+
+    /// Consumes the wrapper, returning the wrapped queue.
+    pub fn into_inner(self) -> Q {
+        self.queue
+    }
+}
+
+impl<Q> Blocking<Q>
+where
+    Q: Queue,
+{
+    /// Removes an element from the front of the queue, blocking until there
+    /// is one.
+    pub fn pop_wait(&self) -> Q::Elem {
+        loop {
+            if let Some(elem) = self.pop_spin() {
+                return elem;
+            }
+            let guard = self.lock.lock().unwrap();
+            if let Some(elem) = self.prepare_wait() {
+                return elem;
+            }
+            drop(self.not_empty.wait(guard).unwrap());
+            self.waiters.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Removes an element from the front of the queue, blocking for at most
+    /// `timeout` until there is one, and returning `None` if there is none by
+    /// then.
+    #[cfg(has_clock)]
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<Q::Elem> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(elem) = self.pop_spin() {
+                return Some(elem);
+            }
+            let guard = self.lock.lock().unwrap();
+            if let Some(elem) = self.prepare_wait() {
+                return Some(elem);
+            }
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                self.waiters.fetch_sub(1, Ordering::Relaxed);
+                return None;
+            };
+            drop(self.not_empty.wait_timeout(guard, remaining).unwrap());
+            self.waiters.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Pops an element, retrying with a backoff for a while if there is none.
+    fn pop_spin(&self) -> Option<Q::Elem> {
+        let backoff = Backoff::new();
+        loop {
+            if let Some(elem) = self.queue.pop() {
+                return Some(elem);
+            }
+            if backoff.is_completed() {
+                return None;
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Registers as a waiter, holding the lock, and checks the queue once
+    /// more. Returns an element, having unregistered, if there was one.
+    fn prepare_wait(&self) -> Option<Q::Elem> {
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        // Producers check for waiters after pushing, so either they see us
+        // and wake us up, or we see their element here.
+        atomic::fence(Ordering::SeqCst);
+        let elem = self.queue.pop()?;
+        self.waiters.fetch_sub(1, Ordering::Relaxed);
+        Some(elem)
+    }
+}
+
+impl<Q> Queue for Blocking<Q>
+where
+    Q: Queue,
+{
+    type Elem = Q::Elem;
+
+    fn push(&self, elem: Q::Elem) {
+        self.queue.push(elem);
+        atomic::fence(Ordering::SeqCst);
+        if self.waiters.load(Ordering::SeqCst) > 0 {
+            // Taking the lock waits for a consumer that registered to start
+            // waiting, so that the notification is not lost.
+            drop(self.lock.lock().unwrap());
+            self.not_empty.notify_one();
+        }
+    }
+
+    fn pop(&self) -> Option<Q::Elem> {
+        self.queue.pop()
+    }
+}
+
+impl<Q> fmt::Debug for Blocking<Q>
+where
+    Q: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Blocking")
+            .field("queue", &self.queue)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queue::{MsQueue, TwoLockQueue};
+
+    #[test]
+    fn blocking() {
+        let queue = Blocking::new(TwoLockQueue::new());
+        assert_eq!(queue.pop_timeout(Duration::from_millis(10)), None);
+        queue.push(1);
+        assert_eq!(queue.pop_timeout(Duration::from_millis(10)), Some(1));
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(50));
+                queue.push(2);
+            });
+            // outlasts the spinning, so the consumer parks
+            assert_eq!(queue.pop_timeout(Duration::from_secs(10)), Some(2));
+        });
+        assert!(queue.into_inner().is_empty());
+    }
+
+    #[test]
+    fn blocking_concurrent() {
+        let num_producers = 4;
+        let num_consumers = 4;
+        let num_elems = 10_000;
+        let queue = Blocking::new(MsQueue::new());
+
+        let mut popped: Vec<_> = std::thread::scope(|s| {
+            let consumers: Vec<_> = (0..num_consumers)
+                .map(|_| {
+                    let queue = &queue;
+                    s.spawn(move || {
+                        (0..num_producers * num_elems / num_consumers)
+                            .map(|_| queue.pop_wait())
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            for p in 0..num_producers {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..num_elems {
+                        queue.push(p * num_elems + i);
+                        // pause now and then, so that consumers park
+                        if i % 1000 == 0 {
+                            std::thread::sleep(Duration::from_millis(1));
+                        }
+                    }
+                });
+            }
+            consumers
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        });
+        popped.sort_unstable();
+        assert!(popped.into_iter().eq(0..num_producers * num_elems));
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Condvar, Mutex, MutexGuard};
+#[cfg(has_clock)]
+use std::time::{Duration, Instant};
+
+use super::Queue;
+
+struct State<T> {
+    elems: VecDeque<T>,
+    /// Number of producers waiting for room.
+    blocked_pushers: usize,
+    /// Number of consumers waiting for an element.
+    blocked_poppers: usize,
+}
+
+/// A bounded queue whose producers block while it is full, and whose
+/// consumers can block while it is empty.
+///
+/// Blocking producers apply backpressure between pipeline stages: a stage
+/// that outpaces the next one is slowed down to its pace, instead of growing
+/// the queue between them without bound. Producers and consumers wait on
+/// separate condition variables, and each push or pop wakes up at most one
+/// waiter on the other side, and only if there is one, so no thread is woken
+/// up just to find that it cannot make progress.
+///
+/// ```
+/// use rsds::queue::{BoundedBlockingQueue, Queue};
+///
+/// let queue = BoundedBlockingQueue::with_capacity(2);
+/// std::thread::scope(|s| {
+///     // the producer blocks until the consumer makes room
+///     s.spawn(|| (0..10).for_each(|i| queue.push(i)));
+///     s.spawn(|| {
+///         for i in 0..10 {
+///             assert_eq!(queue.pop_wait(), i);
+///         }
+///     });
+/// });
+/// assert!(queue.is_empty());
+/// ```
+pub struct BoundedBlockingQueue<T> {
+    state: Mutex<State<T>>,
+    not_full: Condvar,
+    not_empty: Condvar,
+    capacity: usize,
+}
+
+impl<T> BoundedBlockingQueue<T> {
+    /// Creates a new, empty [`BoundedBlockingQueue`] holding up to `capacity`
+    /// elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "capacity (is {}) should be positive",
+            capacity
+        );
+        Self {
+            state: Mutex::new(State {
+                elems: VecDeque::with_capacity(capacity),
+                blocked_pushers: 0,
+                blocked_poppers: 0,
+            }),
+            not_full: Condvar::new(),
+            not_empty: Condvar::new(),
+            capacity,
+        }
+    }
+
+    /// Appends an element to the back of the queue if there is room,
+    /// returning it back otherwise.
+    pub fn try_push(&self, elem: T) -> Result<(), T> {
+        let state = self.state.lock().unwrap();
+        if state.elems.len() == self.capacity {
+            return Err(elem);
+        }
+        self.push_locked(state, elem);
+        Ok(())
+    }
+
+    /// Appends an element to the back of the queue, blocking for at most
+    /// `timeout` until there is room, and returning it back if there is none
+    /// by then.
+    #[cfg(has_clock)]
+    pub fn push_timeout(&self, elem: T, timeout: Duration) -> Result<(), T> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        while state.elems.len() == self.capacity {
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                return Err(elem);
+            };
+            state.blocked_pushers += 1;
+            state = self.not_full.wait_timeout(state, remaining).unwrap().0;
+            state.blocked_pushers -= 1;
+        }
+        self.push_locked(state, elem);
+        Ok(())
+    }
+
+    /// Removes an element from the front of the queue, blocking until there
+    /// is one.
+    pub fn pop_wait(&self) -> T {
+        let mut state = self.state.lock().unwrap();
+        while state.elems.is_empty() {
+            state.blocked_poppers += 1;
+            state = self.not_empty.wait(state).unwrap();
+            state.blocked_poppers -= 1;
+        }
+        self.pop_locked(state).unwrap()
+    }
+
+    /// Removes an element from the front of the queue, blocking for at most
+    /// `timeout` until there is one, and returning `None` if there is none by
+    /// then.
+    #[cfg(has_clock)]
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.state.lock().unwrap();
+        while state.elems.is_empty() {
+            let remaining = deadline.checked_duration_since(Instant::now())?;
+            state.blocked_poppers += 1;
+            state = self.not_empty.wait_timeout(state, remaining).unwrap().0;
+            state.blocked_poppers -= 1;
+        }
+        self.pop_locked(state)
+    }
+
+    /// Returns the number of elements the queue can hold.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of elements in the queue at the time of the call.
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().elems.len()
+    }
+
+    /// Checks whether the queue is empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn push_locked(&self, mut state: MutexGuard<'_, State<T>>, elem: T) {
+        state.elems.push_back(elem);
+        let wake = state.blocked_poppers > 0;
+        drop(state);
+        if wake {
+            self.not_empty.notify_one();
+        }
+    }
+
+    fn pop_locked(&self, mut state: MutexGuard<'_, State<T>>) -> Option<T> {
+        let elem = state.elems.pop_front()?;
+        let wake = state.blocked_pushers > 0;
+        drop(state);
+        if wake {
+            self.not_full.notify_one();
+        }
+        Some(elem)
+    }
+}
+
+impl<T> Queue for BoundedBlockingQueue<T> {
+    type Elem = T;
+
+    /// Appends an element to the back of the queue, blocking until there is
+    /// room.
+    fn push(&self, elem: T) {
+        let mut state = self.state.lock().unwrap();
+        while state.elems.len() == self.capacity {
+            state.blocked_pushers += 1;
+            state = self.not_full.wait(state).unwrap();
+            state.blocked_pushers -= 1;
+        }
+        self.push_locked(state, elem);
+    }
+
+    fn pop(&self) -> Option<T> {
+        self.pop_locked(self.state.lock().unwrap())
+    }
+}
+
+impl<T> fmt::Debug for BoundedBlockingQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoundedBlockingQueue")
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounded_blocking_queue() {
+        let queue = BoundedBlockingQueue::with_capacity(2);
+        assert_eq!(queue.try_push(1), Ok(()));
+        queue.push(2);
+        assert_eq!(queue.try_push(3), Err(3));
+        assert_eq!(queue.push_timeout(3, Duration::from_millis(10)), Err(3));
+        assert_eq!(
+            format!("{:?}", queue),
+            "BoundedBlockingQueue { capacity: 2, len: 2, .. }"
+        );
+        assert_eq!(queue.pop(), Some(1));
+        assert_eq!(queue.pop_timeout(Duration::from_millis(10)), Some(2));
+        assert_eq!(queue.pop_timeout(Duration::from_millis(10)), None);
+        assert!(queue.is_empty());
+
+        std::thread::scope(|s| {
+            queue.push(1);
+            queue.push(2);
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(50));
+                assert_eq!(queue.pop_wait(), 1);
+            });
+            // blocks until the consumer makes room
+            assert_eq!(queue.push_timeout(3, Duration::from_secs(10)), Ok(()));
+        });
+        assert_eq!(queue.len(), queue.capacity());
+    }
+
+    #[test]
+    fn bounded_blocking_queue_concurrent() {
+        let num_producers = 4;
+        let num_consumers = 4;
+        let num_elems = 10_000;
+        let queue = BoundedBlockingQueue::with_capacity(8);
+
+        let mut popped: Vec<_> = std::thread::scope(|s| {
+            for p in 0..num_producers {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..num_elems {
+                        queue.push(p * num_elems + i);
+                    }
+                });
+            }
+            let consumers: Vec<_> = (0..num_consumers)
+                .map(|_| {
+                    let queue = &queue;
+                    s.spawn(move || {
+                        let mut popped = Vec::new();
+                        for _ in 0..num_producers * num_elems / num_consumers {
+                            let elem = queue.pop_wait();
+                            // the queue never grows past its capacity
+                            assert!(queue.len() <= queue.capacity());
+                            popped.push(elem);
+                        }
+                        popped
+                    })
+                })
+                .collect();
+            consumers
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        });
+        popped.sort_unstable();
+        assert!(popped.into_iter().eq(0..num_producers * num_elems));
+    }
+}
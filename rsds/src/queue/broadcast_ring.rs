@@ -0,0 +1,365 @@
+use std::fmt;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crossbeam::utils::CachePadded;
+
+use crate::reclaim::epoch;
+use crate::sync::Backoff;
+
+/// What the producer of a broadcast ring does when the slowest consumer is a
+/// full ring behind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlowConsumerPolicy {
+    /// Wait for the slowest consumer to catch up, so that no consumer misses
+    /// an element.
+    Block,
+    /// Overwrite the oldest element, so that consumers that fall too far
+    /// behind skip ahead to the oldest element still in the ring.
+    Overwrite,
+}
+
+struct Entry<T> {
+    seq: u64,
+    elem: T,
+}
+
+type Cursor = Arc<CachePadded<AtomicU64>>;
+
+/// State shared by the producer and the consumers of a ring.
+struct Ring<T> {
+    slots: Box<[AtomicPtr<Entry<T>>]>,
+    /// Number of elements published so far, which is also the sequence number
+    /// of the next element.
+    published: CachePadded<AtomicU64>,
+    /// The next sequence number each consumer reads.
+    cursors: Mutex<Vec<Cursor>>,
+    policy: SlowConsumerPolicy,
+    closed: AtomicBool,
+}
+
+impl<T> Ring<T> {
+    fn capacity(&self) -> u64 {
+        self.slots.len() as u64
+    }
+
+    fn min_cursor(&self) -> Option<u64> {
+        let cursors = self.cursors.lock().unwrap();
+        cursors.iter().map(|c| c.load(Ordering::Acquire)).min()
+    }
+}
+
+impl<T> Drop for Ring<T> {
+    fn drop(&mut self) {
+        for slot in self.slots.iter_mut() {
+            let entry = *slot.get_mut();
+            if !entry.is_null() {
+                // SAFETY: we have exclusive access, so no other thread holds
+                // a reference to the remaining entries.
+                drop(unsafe { Box::from_raw(entry) });
+            }
+        }
+    }
+}
+
+/// Creates a broadcast ring holding up to `capacity` elements, returning its
+/// producer.
+///
+/// One producer publishes elements in sequence, and every consumer sees each
+/// of them at its own pace, as with the LMAX Disruptor. Each consumer keeps
+/// its own cursor into the ring, so consumers never contend with one another,
+/// and elements are cloned out rather than removed. What happens when the
+/// slowest consumer falls a full ring behind is up to `policy`.
+///
+/// Elements are stored behind epoch-protected pointers, so a consumer reading
+/// an element the producer is overwriting still clones a whole element, never
+/// a torn one.
+///
+/// # Panics
+///
+/// Panics if `capacity` is 0.
+///
+/// ```
+/// use rsds::queue::{broadcast_ring, SlowConsumerPolicy};
+///
+/// let mut producer = broadcast_ring(16, SlowConsumerPolicy::Block);
+/// let consumers: Vec<_> = (0..4).map(|_| producer.subscribe()).collect();
+/// let handles: Vec<_> = consumers
+///     .into_iter()
+///     .map(|mut consumer| {
+///         std::thread::spawn(move || std::iter::from_fn(|| consumer.recv()).count())
+///     })
+///     .collect();
+///
+/// for i in 0..100 {
+///     producer.publish(i);
+/// }
+/// drop(producer);
+/// for h in handles {
+///     assert_eq!(h.join().unwrap(), 100);
+/// }
+/// ```
+pub fn broadcast_ring<T>(capacity: usize, policy: SlowConsumerPolicy) -> BroadcastProducer<T> {
+    assert!(
+        capacity > 0,
+        "capacity (is {}) should be positive",
+        capacity
+    );
+    let ring = Ring {
+        slots: (0..capacity)
+            .map(|_| AtomicPtr::new(ptr::null_mut()))
+            .collect(),
+        published: CachePadded::new(AtomicU64::new(0)),
+        cursors: Mutex::new(Vec::new()),
+        policy,
+        closed: AtomicBool::new(false),
+    };
+    BroadcastProducer {
+        ring: Arc::new(ring),
+        min_cursor: 0,
+    }
+}
+
+/// The producer of a ring created by [`broadcast_ring`].
+pub struct BroadcastProducer<T> {
+    ring: Arc<Ring<T>>,
+    /// The slowest consumer's cursor when last checked, which only grows.
+    min_cursor: u64,
+}
+
+// SAFETY: elements are moved in by the producer and cloned out by consumers
+// on other threads.
+unsafe impl<T: Send + Sync> Send for BroadcastProducer<T> {}
+unsafe impl<T: Send + Sync> Sync for BroadcastProducer<T> {}
+
+impl<T> BroadcastProducer<T> {
+    /// Creates a consumer that receives every element published from now on.
+    pub fn subscribe(&self) -> BroadcastConsumer<T> {
+        let mut cursors = self.ring.cursors.lock().unwrap();
+        let cursor = Arc::new(CachePadded::new(AtomicU64::new(
+            self.ring.published.load(Ordering::Relaxed),
+        )));
+        cursors.push(cursor.clone());
+        BroadcastConsumer {
+            ring: self.ring.clone(),
+            cursor,
+        }
+    }
+
+    /// Returns the maximum number of elements the ring holds.
+    pub fn capacity(&self) -> usize {
+        self.ring.slots.len()
+    }
+}
+
+impl<T> BroadcastProducer<T>
+where
+    T: Send + 'static,
+{
+    /// Publishes an element to every consumer, returning its sequence number.
+    ///
+    /// With [`SlowConsumerPolicy::Block`], this waits while the slowest
+    /// consumer is a full ring behind.
+    pub fn publish(&mut self, elem: T) -> u64 {
+        let ring = &*self.ring;
+        let seq = ring.published.load(Ordering::Relaxed);
+        if ring.policy == SlowConsumerPolicy::Block {
+            let backoff = Backoff::new();
+            while seq >= self.min_cursor + ring.capacity() {
+                match ring.min_cursor() {
+                    Some(min_cursor) => self.min_cursor = min_cursor,
+                    None => self.min_cursor = seq,
+                }
+                if seq >= self.min_cursor + ring.capacity() {
+                    backoff.snooze();
+                }
+            }
+        }
+
+        let guard = epoch::pin();
+        let slot = &ring.slots[(seq % ring.capacity()) as usize];
+        let entry = Box::into_raw(Box::new(Entry { seq, elem }));
+        let old = slot.swap(entry, Ordering::AcqRel);
+        if !old.is_null() {
+            // SAFETY: the old entry is unlinked and was allocated with `Box`,
+            // consumers only read it while pinned, and `T: Send + 'static`
+            // makes dropping it later, on any thread, sound.
+            unsafe { epoch::retire(&guard, old) };
+        }
+        ring.published.store(seq + 1, Ordering::Release);
+        seq
+    }
+}
+
+impl<T> Drop for BroadcastProducer<T> {
+    fn drop(&mut self) {
+        self.ring.closed.store(true, Ordering::Release);
+    }
+}
+
+impl<T> fmt::Debug for BroadcastProducer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BroadcastProducer")
+            .field("capacity", &self.capacity())
+            .field("policy", &self.ring.policy)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A consumer of a ring created by [`broadcast_ring`], reading elements at
+/// its own cursor.
+///
+/// Cloning a consumer creates another one at the same position.
+pub struct BroadcastConsumer<T> {
+    ring: Arc<Ring<T>>,
+    cursor: Cursor,
+}
+
+// SAFETY: as for `BroadcastProducer`.
+unsafe impl<T: Send + Sync> Send for BroadcastConsumer<T> {}
+unsafe impl<T: Send + Sync> Sync for BroadcastConsumer<T> {}
+
+impl<T> BroadcastConsumer<T>
+where
+    T: Clone,
+{
+    /// Receives the next element and its sequence number without blocking,
+    /// returning `None` if the consumer has seen every published element.
+    ///
+    /// With [`SlowConsumerPolicy::Overwrite`], a consumer that has fallen a
+    /// full ring behind skips to the oldest element still in the ring, which
+    /// shows as a gap in the sequence numbers.
+    pub fn try_recv(&mut self) -> Option<(u64, T)> {
+        let ring = &*self.ring;
+        let _guard = epoch::pin();
+        let mut seq = self.cursor.load(Ordering::Relaxed);
+        loop {
+            let published = ring.published.load(Ordering::Acquire);
+            if seq >= published {
+                return None;
+            }
+            seq = seq.max(published.saturating_sub(ring.capacity()));
+            let slot = &ring.slots[(seq % ring.capacity()) as usize];
+            // SAFETY: entries are only destroyed once unlinked and no pinned
+            // thread can see them, and every slot below `published` is set.
+            let entry = unsafe { &*slot.load(Ordering::Acquire) };
+            if entry.seq == seq {
+                let elem = entry.elem.clone();
+                self.cursor.store(seq + 1, Ordering::Release);
+                return Some((seq, elem));
+            }
+            // The producer has lapped us since we loaded `published`.
+        }
+    }
+
+    /// Receives the next element and its sequence number, blocking until one
+    /// is published.
+    ///
+    /// Returns `None` once the producer has been dropped and the consumer has
+    /// seen every element it published.
+    pub fn recv(&mut self) -> Option<(u64, T)> {
+        let backoff = Backoff::new();
+        loop {
+            if let Some(next) = self.try_recv() {
+                return Some(next);
+            }
+            if self.ring.closed.load(Ordering::Acquire) {
+                return self.try_recv();
+            }
+            backoff.snooze();
+        }
+    }
+}
+
+impl<T> BroadcastConsumer<T> {
+    /// Returns the number of published elements the consumer has not read
+    /// yet, counting those that it will skip for having been overwritten.
+    pub fn lag(&self) -> u64 {
+        let published = self.ring.published.load(Ordering::Acquire);
+        published.saturating_sub(self.cursor.load(Ordering::Relaxed))
+    }
+}
+
+impl<T> Clone for BroadcastConsumer<T> {
+    fn clone(&self) -> Self {
+        let mut cursors = self.ring.cursors.lock().unwrap();
+        let cursor = Arc::new(CachePadded::new(AtomicU64::new(
+            self.cursor.load(Ordering::Relaxed),
+        )));
+        cursors.push(cursor.clone());
+        Self {
+            ring: self.ring.clone(),
+            cursor,
+        }
+    }
+}
+
+impl<T> Drop for BroadcastConsumer<T> {
+    fn drop(&mut self) {
+        let mut cursors = self.ring.cursors.lock().unwrap();
+        cursors.retain(|cursor| !Arc::ptr_eq(cursor, &self.cursor));
+    }
+}
+
+impl<T> fmt::Debug for BroadcastConsumer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BroadcastConsumer")
+            .field("cursor", &self.cursor.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broadcast_ring_overwrite() {
+        let mut producer = broadcast_ring(4, SlowConsumerPolicy::Overwrite);
+        let mut consumer = producer.subscribe();
+        assert_eq!(consumer.try_recv(), None);
+
+        for i in 0..10 {
+            assert_eq!(producer.publish(i.to_string()), i);
+        }
+        assert_eq!(consumer.lag(), 10);
+        // the first six elements have been overwritten
+        assert_eq!(consumer.try_recv(), Some((6, "6".to_string())));
+        let mut late = consumer.clone();
+        producer.publish("10".to_string());
+        assert_eq!(consumer.try_recv(), Some((7, "7".to_string())));
+        assert_eq!(late.try_recv(), Some((7, "7".to_string())));
+        drop(producer);
+        assert_eq!(consumer.recv().map(|(seq, _)| seq), Some(8));
+        assert_eq!(std::iter::from_fn(|| late.recv()).count(), 3);
+    }
+
+    #[test]
+    fn broadcast_ring_block() {
+        let num_consumers = 4;
+        let num_elems = 10_000;
+        let mut producer = broadcast_ring(8, SlowConsumerPolicy::Block);
+
+        let consumers: Vec<_> = (0..num_consumers)
+            .map(|_| {
+                let mut consumer = producer.subscribe();
+                std::thread::spawn(move || {
+                    // no element is skipped, however slow the consumer
+                    for i in 0..num_elems {
+                        assert_eq!(consumer.recv(), Some((i, i)));
+                    }
+                    assert_eq!(consumer.recv(), None);
+                })
+            })
+            .collect();
+
+        for i in 0..num_elems {
+            producer.publish(i);
+        }
+        drop(producer);
+        for h in consumers {
+            h.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use std::cmp::Ordering as CmpOrdering;
+use std::fmt;
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use super::PriorityQueue;
+
+/// An element of a [`DelayQueue`], ordered by its deadline only.
+struct Delayed<T> {
+    deadline: Instant,
+    elem: T,
+}
+
+impl<T> PartialEq for Delayed<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl<T> Eq for Delayed<T> {}
+
+impl<T> PartialOrd for Delayed<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Delayed<T> {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        self.deadline.cmp(&other.deadline)
+    }
+}
+
+/// An unbounded queue whose elements can only be popped once their deadline
+/// has passed, such as retries with a backoff or expiring sessions.
+///
+/// Elements are kept in a [`PriorityQueue`] ordered by deadline, and popped
+/// only if the earliest deadline has passed, so consumers that do not wait
+/// only take the skip list's locks on the nodes around the element they
+/// remove. Elements with the same deadline are popped in the order they were
+/// pushed.
+///
+/// Consumers of [`DelayQueue::pop_expired`] sleep until the earliest
+/// deadline, or until a push that may have brought it forward. To that end,
+/// every push also takes a lock that sleeping consumers wait on, for as long
+/// as it takes to bump a counter, so producers do contend with each other
+/// there.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use rsds::queue::DelayQueue;
+///
+/// let queue = DelayQueue::new();
+/// queue.push_after("later", Duration::from_millis(20));
+/// queue.push_after("sooner", Duration::from_millis(10));
+/// assert_eq!(queue.try_pop_expired(), None);
+/// assert_eq!(queue.pop_expired(), "sooner");
+/// assert_eq!(queue.pop_expired(), "later");
+/// ```
+pub struct DelayQueue<T> {
+    queue: PriorityQueue<Delayed<T>>,
+    /// Number of pushes so far, which consumers check before sleeping so as
+    /// not to miss an earlier deadline.
+    pushes: Mutex<u64>,
+    pushed: Condvar,
+}
+
+impl<T> Default for DelayQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> DelayQueue<T> {
+    /// Creates a new, empty [`DelayQueue`].
+    pub fn new() -> Self {
+        Self {
+            queue: PriorityQueue::new(),
+            pushes: Mutex::new(0),
+            pushed: Condvar::new(),
+        }
+    }
+
+    /// Returns the number of elements in the queue, expired or not, at the
+    /// time of the call.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Checks whether the queue is empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Adds an element that expires at `deadline`.
+    pub fn push_at(&self, elem: T, deadline: Instant) {
+        self.queue.push(Delayed { deadline, elem });
+        *self.pushes.lock().unwrap() += 1;
+        self.pushed.notify_one();
+    }
+
+    /// Adds an element that expires after `delay`.
+    pub fn push_after(&self, elem: T, delay: Duration) {
+        self.push_at(elem, Instant::now() + delay);
+    }
+
+    /// Removes the element with the earliest deadline if it has passed,
+    /// returning `None` otherwise.
+    pub fn try_pop_expired(&self) -> Option<T> {
+        self.pop_before(Instant::now()).ok()
+    }
+
+    /// Removes the element with the earliest deadline, blocking until it has
+    /// passed.
+    pub fn pop_expired(&self) -> T {
+        loop {
+            if let Some(elem) = self.wait_expired(None) {
+                return elem;
+            }
+        }
+    }
+
+    /// Removes the element with the earliest deadline, blocking for at most
+    /// `timeout` until it has passed, and returning `None` if no element has
+    /// expired by then.
+    pub fn pop_expired_timeout(&self, timeout: Duration) -> Option<T> {
+        let give_up = Instant::now() + timeout;
+        loop {
+            if let Some(elem) = self.wait_expired(Some(give_up)) {
+                return Some(elem);
+            }
+            if Instant::now() >= give_up {
+                return self.try_pop_expired();
+            }
+        }
+    }
+
+    /// Pops an expired element, or otherwise sleeps until the earliest
+    /// deadline, a push, or `give_up`, whichever comes first.
+    fn wait_expired(&self, give_up: Option<Instant>) -> Option<T> {
+        let seen = *self.pushes.lock().unwrap();
+        let next_deadline = match self.pop_before(Instant::now()) {
+            Ok(elem) => return Some(elem),
+            Err(next_deadline) => next_deadline,
+        };
+        let wake_at = match (next_deadline, give_up) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let pushes = self.pushes.lock().unwrap();
+        // An element pushed since we looked may expire sooner.
+        if *pushes != seen {
+            return None;
+        }
+        match wake_at {
+            Some(wake_at) => {
+                let timeout = wake_at.saturating_duration_since(Instant::now());
+                drop(self.pushed.wait_timeout(pushes, timeout).unwrap());
+            }
+            None => drop(self.pushed.wait(pushes).unwrap()),
+        }
+        None
+    }
+
+    /// Pops the element with the earliest deadline if it is at or before
+    /// `now`, or returns the earliest deadline, if any, otherwise.
+    fn pop_before(&self, now: Instant) -> Result<T, Option<Instant>> {
+        let mut next_deadline = None;
+        let delayed = self.queue.pop_min_if(|delayed| {
+            next_deadline = Some(delayed.deadline);
+            delayed.deadline <= now
+        });
+        delayed.map(|delayed| delayed.elem).ok_or(next_deadline)
+    }
+}
+
+impl<T> fmt::Debug for DelayQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DelayQueue")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_queue() {
+        let queue = DelayQueue::new();
+        assert_eq!(queue.pop_expired_timeout(Duration::from_millis(10)), None);
+
+        let start = Instant::now();
+        queue.push_at(3, start + Duration::from_millis(30));
+        queue.push_at(1, start);
+        queue.push_at(2, start + Duration::from_millis(30));
+        assert_eq!(queue.len(), 3);
+        assert_eq!(format!("{:?}", queue), "DelayQueue { len: 3, .. }");
+        assert_eq!(queue.try_pop_expired(), Some(1));
+        assert_eq!(queue.try_pop_expired(), None);
+        // equal deadlines come out in the order they were pushed
+        assert_eq!(queue.pop_expired(), 3);
+        assert!(start.elapsed() >= Duration::from_millis(30));
+        assert_eq!(queue.pop_expired_timeout(Duration::ZERO), Some(2));
+        assert!(queue.is_empty());
+
+        std::thread::scope(|s| {
+            queue.push_after(5, Duration::from_secs(60));
+            s.spawn(|| {
+                std::thread::sleep(Duration::from_millis(20));
+                queue.push_after(4, Duration::ZERO);
+            });
+            // the push wakes up the consumer sleeping until the later deadline
+            assert_eq!(queue.pop_expired_timeout(Duration::from_secs(10)), Some(4));
+        });
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn delay_queue_concurrent() {
+        let num_producers = 4;
+        let num_consumers = 4;
+        let num_elems = 1_000;
+        let queue = DelayQueue::new();
+
+        let mut popped: Vec<_> = std::thread::scope(|s| {
+            let consumers: Vec<_> = (0..num_consumers)
+                .map(|_| {
+                    let queue = &queue;
+                    s.spawn(move || {
+                        (0..num_producers * num_elems / num_consumers)
+                            .map(|_| {
+                                let (deadline, elem) = queue.pop_expired();
+                                // nothing comes out before its deadline
+                                assert!(Instant::now() >= deadline);
+                                elem
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            for p in 0..num_producers {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..num_elems {
+                        let deadline = Instant::now() + Duration::from_micros((i % 7) as u64 * 500);
+                        queue.push_at((deadline, p * num_elems + i), deadline);
+                    }
+                });
+            }
+            consumers
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        });
+        popped.sort_unstable();
+        assert!(popped.into_iter().eq(0..num_producers * num_elems));
+        assert!(queue.is_empty());
+    }
+}
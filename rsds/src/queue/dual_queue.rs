@@ -0,0 +1,511 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::thread::{self, Thread};
+#[cfg(has_clock)]
+use std::time::{Duration, Instant};
+
+use crossbeam::utils::CachePadded;
+
+use super::Queue;
+use crate::reclaim::epoch::{self, Guard};
+#[cfg(has_clock)]
+use crate::sync::Backoff;
+
+/// The reservation is waiting for an element.
+const WAITING: u8 = 0;
+/// A producer is handing an element over.
+const FILLING: u8 = 1;
+/// The element has been handed over.
+const FULL: u8 = 2;
+/// The consumer gave up waiting.
+#[cfg(has_clock)]
+const CANCELLED: u8 = 3;
+
+/// Where a producer hands an element over to a waiting consumer.
+///
+/// It is shared between the reservation node and the consumer, so that the
+/// consumer does not have to stay pinned while it waits.
+struct Handoff<T> {
+    state: AtomicU8,
+    elem: UnsafeCell<MaybeUninit<T>>,
+    waiter: Thread,
+}
+
+enum Payload<T> {
+    /// The initial sentinel.
+    Empty,
+    /// An element pushed while no consumer was waiting, which is moved out
+    /// by the consumer that sets `taken`.
+    Data {
+        elem: MaybeUninit<T>,
+        taken: AtomicBool,
+    },
+    /// A consumer waiting for an element.
+    Reservation(Arc<Handoff<T>>),
+}
+
+struct Node<T> {
+    payload: Payload<T>,
+    next: AtomicPtr<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn new(payload: Payload<T>) -> Self {
+        Self {
+            payload,
+            next: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    fn is_reservation(&self) -> bool {
+        matches!(self.payload, Payload::Reservation(_))
+    }
+
+    /// Returns the next node, if any.
+    fn next_ptr(&self) -> Option<*mut Node<T>> {
+        let next = self.next.load(Ordering::Acquire);
+        (!next.is_null()).then_some(next)
+    }
+}
+
+/// An unbounded lock-free dual queue, in which consumers that find the queue
+/// empty enqueue a reservation that a later producer fulfills directly,
+/// implemented with the Scherer–Scott algorithm.
+///
+/// Like an [`MsQueue`], the queue is a linked list with a sentinel at the
+/// head, but its nodes are either all elements or all reservations. A
+/// producer that finds reservations hands its element to the oldest one and
+/// wakes up its consumer, and otherwise appends the element. A consumer of
+/// [`DualQueue::pop_wait`] that finds elements takes the oldest one, and
+/// otherwise appends a reservation and parks until it is fulfilled, so that
+/// consumers are served in the order they arrived.
+///
+/// Nodes are reclaimed with the crate's
+/// [epoch-based reclamation](crate::reclaim::epoch). A waiting
+/// consumer is not pinned, as its reservation's handoff slot is shared with
+/// it through an [`Arc`].
+///
+/// ```
+/// use rsds::queue::{DualQueue, Queue};
+///
+/// let queue = DualQueue::new();
+/// std::thread::scope(|s| {
+///     // the consumer reserves its element before it is pushed
+///     s.spawn(|| assert_eq!(queue.pop_wait(), 1));
+///     s.spawn(|| queue.push(1));
+/// });
+/// assert_eq!(queue.pop(), None);
+/// ```
+///
+/// [`MsQueue`]: super::MsQueue
+pub struct DualQueue<T> {
+    head: CachePadded<AtomicPtr<Node<T>>>,
+    tail: CachePadded<AtomicPtr<Node<T>>>,
+}
+
+// SAFETY: elements are moved between threads only through push/pop, and nodes
+// are only freed once no thread can observe them.
+unsafe impl<T: Send> Send for DualQueue<T> {}
+unsafe impl<T: Send> Sync for DualQueue<T> {}
+
+impl<T> Default for DualQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> DualQueue<T> {
+    /// Creates a new, empty [`DualQueue`].
+    pub fn new() -> Self {
+        let sentinel = Box::into_raw(Box::new(Node::new(Payload::Empty)));
+        Self {
+            head: CachePadded::new(AtomicPtr::new(sentinel)),
+            tail: CachePadded::new(AtomicPtr::new(sentinel)),
+        }
+    }
+
+    /// Removes an element from the front of the queue, blocking until there
+    /// is one.
+    ///
+    /// If the queue holds no elements, the consumer reserves the next element
+    /// pushed after the consumers that reserved before it.
+    pub fn pop_wait(&self) -> T {
+        match self.take_or_reserve() {
+            Ok(elem) => elem,
+            Err(handoff) => {
+                while handoff.state.load(Ordering::Acquire) != FULL {
+                    thread::park();
+                }
+                // SAFETY: the producer that filled the handoff wrote the
+                // element, and only we move it out.
+                unsafe { (*handoff.elem.get()).assume_init_read() }
+            }
+        }
+    }
+
+    /// Removes an element from the front of the queue, blocking for at most
+    /// `timeout` until there is one, and returning `None` if there is none by
+    /// then.
+    ///
+    /// A consumer that times out cancels its reservation, which producers
+    /// then skip.
+    #[cfg(has_clock)]
+    pub fn pop_timeout(&self, timeout: Duration) -> Option<T> {
+        let deadline = Instant::now() + timeout;
+        let handoff = match self.take_or_reserve() {
+            Ok(elem) => return Some(elem),
+            Err(handoff) => handoff,
+        };
+        loop {
+            match handoff.state.load(Ordering::Acquire) {
+                FULL => break,
+                WAITING => match deadline.checked_duration_since(Instant::now()) {
+                    Some(remaining) => thread::park_timeout(remaining),
+                    None => {
+                        if handoff
+                            .state
+                            .compare_exchange(
+                                WAITING,
+                                CANCELLED,
+                                Ordering::AcqRel,
+                                Ordering::Acquire,
+                            )
+                            .is_ok()
+                        {
+                            return None;
+                        }
+                    }
+                },
+                // A producer is filling the handoff, which will not take long.
+                _ => Backoff::new().snooze(),
+            }
+        }
+        // SAFETY: as in `pop_wait`.
+        Some(unsafe { (*handoff.elem.get()).assume_init_read() })
+    }
+
+    /// Checks whether the queue holds no elements at the time of the call,
+    /// which it does not while consumers wait.
+    pub fn is_empty(&self) -> bool {
+        let _guard = epoch::pin();
+        let mut curr = self.head.load(Ordering::Acquire);
+        // SAFETY: nodes cannot be reclaimed while we are pinned, and the
+        // head is never null.
+        while let Some(next) = unsafe { &*curr }.next_ptr() {
+            match &unsafe { &*next }.payload {
+                Payload::Data { taken, .. } if !taken.load(Ordering::Acquire) => return false,
+                Payload::Data { .. } => curr = next,
+                _ => return true,
+            }
+        }
+        true
+    }
+
+    /// Takes the oldest element if the queue holds elements, and otherwise
+    /// appends a reservation, returning its handoff slot.
+    fn take_or_reserve(&self) -> Result<T, Arc<Handoff<T>>> {
+        let guard = &epoch::pin();
+        let mut reservation = None;
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            let tail = self.tail.load(Ordering::Acquire);
+            // SAFETY: the tail is never null, and cannot be reclaimed while
+            // we are pinned.
+            if head == tail || unsafe { &*tail }.is_reservation() {
+                let node = reservation.take().unwrap_or_else(|| {
+                    Box::new(Node::new(Payload::Reservation(Arc::new(Handoff {
+                        state: AtomicU8::new(WAITING),
+                        elem: UnsafeCell::new(MaybeUninit::uninit()),
+                        waiter: thread::current(),
+                    }))))
+                });
+                match self.append(tail, node, guard) {
+                    Ok(node) => match &node.payload {
+                        Payload::Reservation(handoff) => return Err(handoff.clone()),
+                        _ => unreachable!(),
+                    },
+                    Err(node) => reservation = Some(node),
+                }
+                continue;
+            }
+
+            // SAFETY: the head is never null, and cannot be reclaimed while we
+            // are pinned.
+            let Some(next) = (unsafe { &*head }).next_ptr() else {
+                continue;
+            };
+            // SAFETY: as for the head.
+            if let Payload::Data { elem, taken } = &unsafe { &*next }.payload {
+                if !taken.swap(true, Ordering::AcqRel) {
+                    self.advance_head(head, next, guard);
+                    // SAFETY: the element was initialized by `push`, and only
+                    // the consumer that set `taken` moves it out.
+                    return Ok(unsafe { elem.assume_init_read() });
+                }
+            }
+            self.advance_head(head, next, guard);
+        }
+    }
+
+    /// Appends `node` after `tail`, or returns it if `tail` is not the last
+    /// node, after helping to move the tail forward.
+    fn append<'g>(
+        &self,
+        tail: *mut Node<T>,
+        node: Box<Node<T>>,
+        _guard: &'g Guard,
+    ) -> Result<&'g Node<T>, Box<Node<T>>> {
+        // SAFETY: the tail is never null, and cannot be reclaimed while we are
+        // pinned.
+        let tail_ref = unsafe { &*tail };
+        let next = tail_ref.next.load(Ordering::Acquire);
+        if !next.is_null() {
+            // The tail is lagging behind; help move it forward.
+            let _ = self
+                .tail
+                .compare_exchange(tail, next, Ordering::Release, Ordering::Relaxed);
+            return Err(node);
+        }
+        let new = Box::into_raw(node);
+        // The release ordering publishes the node's payload to other threads.
+        match tail_ref.next.compare_exchange(
+            ptr::null_mut(),
+            new,
+            Ordering::Release,
+            Ordering::Relaxed,
+        ) {
+            Ok(_) => {
+                // Failing here is fine: another thread has already helped.
+                let _ = self
+                    .tail
+                    .compare_exchange(tail, new, Ordering::Release, Ordering::Relaxed);
+                // SAFETY: the node was just published, and cannot be reclaimed
+                // while we are pinned.
+                Ok(unsafe { &*new })
+            }
+            // SAFETY: the node was never published, so we still own it.
+            Err(_) => Err(unsafe { Box::from_raw(new) }),
+        }
+    }
+
+    /// Makes `next` the sentinel in place of `head`, retiring `head`.
+    fn advance_head(&self, head: *mut Node<T>, next: *mut Node<T>, guard: &Guard) {
+        // Keep the tail from pointing at a node we are about to retire.
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail == head {
+            let _ = self
+                .tail
+                .compare_exchange(tail, next, Ordering::Release, Ordering::Relaxed);
+        }
+        if self
+            .head
+            .compare_exchange(head, next, Ordering::Release, Ordering::Relaxed)
+            .is_ok()
+        {
+            // SAFETY: the old sentinel is unreachable from the queue and was
+            // allocated with `Box`, and its element, if any, has been moved
+            // out, so dropping it later on another thread drops no `T`.
+            unsafe { epoch::retire(guard, head) };
+        }
+    }
+}
+
+impl<T> Queue for DualQueue<T> {
+    type Elem = T;
+
+    /// Hands `elem` to the consumer that has waited the longest, or appends
+    /// it to the back of the queue if no consumer is waiting.
+    fn push(&self, elem: T) {
+        let guard = &epoch::pin();
+        let mut node = Box::new(Node::new(Payload::Data {
+            elem: MaybeUninit::new(elem),
+            taken: AtomicBool::new(false),
+        }));
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            let tail = self.tail.load(Ordering::Acquire);
+            // SAFETY: the tail is never null, and cannot be reclaimed while
+            // we are pinned.
+            if head == tail || !unsafe { &*tail }.is_reservation() {
+                match self.append(tail, node, guard) {
+                    Ok(_) => return,
+                    Err(returned) => node = returned,
+                }
+                continue;
+            }
+
+            // SAFETY: the head is never null, and cannot be reclaimed while we
+            // are pinned.
+            let Some(next) = (unsafe { &*head }).next_ptr() else {
+                continue;
+            };
+            // SAFETY: as for the head.
+            if let Payload::Reservation(handoff) = &unsafe { &*next }.payload {
+                if handoff
+                    .state
+                    .compare_exchange(WAITING, FILLING, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    let Payload::Data { elem, .. } = &mut node.payload else {
+                        unreachable!()
+                    };
+                    // SAFETY: the FILLING state excludes other producers, and
+                    // the consumer only reads the element once it is FULL. Our
+                    // node is dropped without dropping the element.
+                    unsafe {
+                        (*handoff.elem.get()).write(elem.assume_init_read());
+                    }
+                    handoff.state.store(FULL, Ordering::Release);
+                    handoff.waiter.unpark();
+                    self.advance_head(head, next, guard);
+                    return;
+                }
+            }
+            // The reservation was fulfilled or cancelled; skip over it.
+            self.advance_head(head, next, guard);
+        }
+    }
+
+    /// Removes the element at the front of the queue, returning `None`
+    /// without reserving one if there is none.
+    fn pop(&self) -> Option<T> {
+        let guard = &epoch::pin();
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            // SAFETY: the head is never null, and cannot be reclaimed while we
+            // are pinned.
+            let next = unsafe { &*head }.next_ptr()?;
+            // SAFETY: as for the head.
+            match &unsafe { &*next }.payload {
+                Payload::Data { elem, taken } => {
+                    if !taken.swap(true, Ordering::AcqRel) {
+                        self.advance_head(head, next, guard);
+                        // SAFETY: as in `take_or_reserve`.
+                        return Some(unsafe { elem.assume_init_read() });
+                    }
+                    self.advance_head(head, next, guard);
+                }
+                _ => return None,
+            }
+        }
+    }
+}
+
+impl<T> Drop for DualQueue<T> {
+    fn drop(&mut self) {
+        // SAFETY: we have exclusive access, so no other thread holds a
+        // reference to the remaining nodes, and no consumer is waiting.
+        unsafe {
+            let mut curr = *self.head.get_mut();
+            let mut is_sentinel = true;
+            while !curr.is_null() {
+                let mut node = Box::from_raw(curr);
+                curr = *node.next.get_mut();
+                if let Payload::Data { elem, taken } = &mut node.payload {
+                    if !is_sentinel && !*taken.get_mut() {
+                        elem.assume_init_drop();
+                    }
+                }
+                is_sentinel = false;
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for DualQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DualQueue")
+            .field("is_empty", &self.is_empty())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dual_queue() {
+        let queue = DualQueue::new();
+        assert_eq!(queue.pop(), None);
+        assert_eq!(queue.pop_timeout(Duration::from_millis(10)), None);
+        for i in 0..4 {
+            queue.push(i.to_string());
+        }
+        assert_eq!(format!("{:?}", queue), "DualQueue { is_empty: false, .. }");
+        assert_eq!(queue.pop(), Some("0".to_string()));
+        assert_eq!(queue.pop_wait(), "1");
+        assert_eq!(queue.pop_timeout(Duration::ZERO), Some("2".to_string()));
+
+        std::thread::scope(|s| {
+            assert_eq!(queue.pop(), Some("3".to_string()));
+            let consumer = s.spawn(|| queue.pop_timeout(Duration::from_secs(10)));
+            // wait for the consumer's reservation, then fulfill it
+            loop {
+                let _guard = epoch::pin();
+                // SAFETY: the tail is never null, and cannot be reclaimed
+                // while we are pinned.
+                if unsafe { &*queue.tail.load(Ordering::Acquire) }.is_reservation() {
+                    break;
+                }
+                thread::yield_now();
+            }
+            assert!(queue.is_empty());
+            queue.push("4".to_string());
+            assert_eq!(consumer.join().unwrap(), Some("4".to_string()));
+        });
+
+        // the cancelled reservation is skipped
+        queue.push("5".to_string());
+        assert_eq!(queue.pop(), Some("5".to_string()));
+        queue.push("6".to_string());
+        // the remaining element is freed when the queue is dropped
+    }
+
+    #[test]
+    fn dual_queue_concurrent() {
+        let num_producers = 4;
+        let num_consumers = 4;
+        let num_elems = 10_000;
+        let queue = DualQueue::new();
+
+        let mut popped: Vec<_> = std::thread::scope(|s| {
+            let consumers: Vec<_> = (0..num_consumers)
+                .map(|c| {
+                    let queue = &queue;
+                    s.spawn(move || {
+                        let mut popped = Vec::new();
+                        while popped.len() < num_producers * num_elems / num_consumers {
+                            // some reservations time out and are cancelled
+                            if c % 2 == 0 {
+                                popped.push(queue.pop_wait());
+                            } else {
+                                popped.extend(queue.pop_timeout(Duration::from_micros(50)));
+                            }
+                        }
+                        popped
+                    })
+                })
+                .collect();
+            for p in 0..num_producers {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..num_elems {
+                        queue.push(p * num_elems + i);
+                    }
+                });
+            }
+            consumers
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        });
+        popped.sort_unstable();
+        assert!(popped.into_iter().eq(0..num_producers * num_elems));
+        assert!(queue.is_empty());
+    }
+}
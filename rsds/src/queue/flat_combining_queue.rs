@@ -0,0 +1,55 @@
+use std::collections::VecDeque;
+
+use super::Queue;
+use crate::sync::FlatCombiner;
+
+/// An operation on a [`FlatCombiningQueue`], published for the combiner.
+enum QueueOp<T> {
+    Push(T),
+    Pop,
+}
+
+fn apply<T>(queue: &mut VecDeque<T>, op: QueueOp<T>) -> Option<T> {
+    match op {
+        QueueOp::Push(elem) => {
+            queue.push_back(elem);
+            None
+        }
+        QueueOp::Pop => queue.pop_front(),
+    }
+}
+
+/// An unbounded queue implemented with flat combining.
+///
+/// Threads publish their pushes and pops, and whichever thread holds the lock
+/// applies all of them to a sequential queue in one batch.
+pub struct FlatCombiningQueue<T> {
+    combiner: FlatCombiner<VecDeque<T>, QueueOp<T>, Option<T>>,
+}
+
+impl<T> Default for FlatCombiningQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FlatCombiningQueue<T> {
+    /// Creates a new, empty [`FlatCombiningQueue`].
+    pub fn new() -> Self {
+        Self {
+            combiner: FlatCombiner::new(VecDeque::new(), apply),
+        }
+    }
+}
+
+impl<T> Queue for FlatCombiningQueue<T> {
+    type Elem = T;
+
+    fn push(&self, elem: T) {
+        self.combiner.execute(QueueOp::Push(elem));
+    }
+
+    fn pop(&self) -> Option<T> {
+        self.combiner.execute(QueueOp::Pop)
+    }
+}
@@ -0,0 +1,362 @@
+use std::fmt;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicU64, Ordering};
+
+use crossbeam::utils::CachePadded;
+
+use super::{rng, Queue};
+use crate::reclaim::epoch::{self, Guard};
+
+/// The address marking a slot whose element has been taken, or that was
+/// closed before receiving one. Boxed elements never live in a static.
+static TAKEN_MARKER: u8 = 0;
+
+fn taken<T>() -> *mut T {
+    ptr::addr_of!(TAKEN_MARKER) as *mut T
+}
+
+struct Segment<T> {
+    /// Each slot goes from null to an element to taken, and never back.
+    slots: Box<[AtomicPtr<T>]>,
+    next: AtomicPtr<Segment<T>>,
+}
+
+impl<T> Segment<T> {
+    fn new(k: usize) -> Self {
+        Self {
+            slots: (0..k).map(|_| AtomicPtr::new(ptr::null_mut())).collect(),
+            next: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+}
+
+/// An unbounded lock-free queue that relaxes FIFO order by up to `k`
+/// positions in exchange for scalability, implemented as a segment queue in
+/// the style of Kirsch, Lippautz and Payer's k-FIFO queue.
+///
+/// The queue is a linked list of segments of `k` slots each. Producers put
+/// elements in any free slot of the tail segment, and consumers take them
+/// from any full slot of the head segment, starting at a random slot, so
+/// that up to `k` producers and `k` consumers operate on different slots
+/// instead of all contending on the same end. Segments are FIFO with respect
+/// to each other, so an element is popped at most `k - 1` positions away
+/// from its position in strict FIFO order. With `k` of 1, the queue is a
+/// strict FIFO queue.
+///
+/// Every slot is used once: a consumer that finds the head segment empty,
+/// while later segments exist, closes its free slots before moving on to the
+/// next segment, so that no element is left behind in a segment that is
+/// unlinked. Unlinked segments are reclaimed with the crate's
+/// [epoch-based reclamation](crate::reclaim::epoch).
+///
+/// ```
+/// use rsds::queue::{KFifoQueue, Queue};
+///
+/// let queue = KFifoQueue::with_k(4);
+/// (0..8).for_each(|i| queue.push(i));
+/// // the first segment's elements come out in some order, before the
+/// // second segment's
+/// let mut first: Vec<_> = (0..4).map(|_| queue.pop().unwrap()).collect();
+/// first.sort_unstable();
+/// assert_eq!(first, [0, 1, 2, 3]);
+/// ```
+pub struct KFifoQueue<T> {
+    head: CachePadded<AtomicPtr<Segment<T>>>,
+    tail: CachePadded<AtomicPtr<Segment<T>>>,
+    /// Number of segments allocated, for reporting.
+    segments: AtomicU64,
+    k: usize,
+}
+
+// SAFETY: elements are moved between threads only through push/pop, and
+// segments are only freed once no thread can observe them.
+unsafe impl<T: Send> Send for KFifoQueue<T> {}
+unsafe impl<T: Send> Sync for KFifoQueue<T> {}
+
+impl<T> KFifoQueue<T> {
+    /// Creates a new, empty [`KFifoQueue`] that reorders elements by fewer
+    /// than `k` positions.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is zero.
+    pub fn with_k(k: usize) -> Self {
+        assert!(k > 0, "k (is {}) should be positive", k);
+        let segment = Box::into_raw(Box::new(Segment::new(k)));
+        Self {
+            head: CachePadded::new(AtomicPtr::new(segment)),
+            tail: CachePadded::new(AtomicPtr::new(segment)),
+            segments: AtomicU64::new(1),
+            k,
+        }
+    }
+
+    /// Returns the bound on reordering, which is the size of a segment.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Checks whether the queue is empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        let _guard = epoch::pin();
+        let mut segment = self.head.load(Ordering::Acquire);
+        // SAFETY: the head is never null, and segments cannot be reclaimed
+        // while we are pinned.
+        while let Some(segment_ref) = unsafe { segment.as_ref() } {
+            let has_elem = segment_ref.slots.iter().any(|slot| {
+                let elem = slot.load(Ordering::Acquire);
+                !elem.is_null() && elem != taken()
+            });
+            if has_elem {
+                return false;
+            }
+            segment = segment_ref.next.load(Ordering::Acquire);
+        }
+        true
+    }
+
+    /// Moves the tail from `tail` to the segment after it, appending a new
+    /// segment if there is none.
+    fn advance_tail(&self, tail: *mut Segment<T>, _guard: &Guard) {
+        // SAFETY: the tail is never null, and cannot be reclaimed while we are
+        // pinned.
+        let tail_ref = unsafe { &*tail };
+        let mut next = tail_ref.next.load(Ordering::Acquire);
+        if next.is_null() {
+            let new = Box::into_raw(Box::new(Segment::new(self.k)));
+            match tail_ref.next.compare_exchange(
+                ptr::null_mut(),
+                new,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    self.segments.fetch_add(1, Ordering::Relaxed);
+                    next = new;
+                }
+                Err(current) => {
+                    // SAFETY: the segment was never published, so we still
+                    // own it.
+                    drop(unsafe { Box::from_raw(new) });
+                    next = current;
+                }
+            }
+        }
+        // Failing here is fine: another thread has already helped.
+        let _ = self
+            .tail
+            .compare_exchange(tail, next, Ordering::Release, Ordering::Relaxed);
+    }
+}
+
+impl<T> Queue for KFifoQueue<T> {
+    type Elem = T;
+
+    fn push(&self, elem: T) {
+        let guard = &epoch::pin();
+        let elem = Box::into_raw(Box::new(elem));
+        loop {
+            let tail = self.tail.load(Ordering::Acquire);
+            // SAFETY: the tail is never null, and cannot be reclaimed while
+            // we are pinned.
+            let slots = &unsafe { &*tail }.slots;
+            let start = rng::next_index(self.k);
+            let stored = (0..self.k).any(|i| {
+                let slot = &slots[(start + i) % self.k];
+                // The release ordering publishes the element to consumers.
+                slot.load(Ordering::Relaxed).is_null()
+                    && slot
+                        .compare_exchange(
+                            ptr::null_mut(),
+                            elem,
+                            Ordering::Release,
+                            Ordering::Relaxed,
+                        )
+                        .is_ok()
+            });
+            if stored {
+                return;
+            }
+            // Every slot is used, so move on to the next segment.
+            self.advance_tail(tail, guard);
+        }
+    }
+
+    fn pop(&self) -> Option<T> {
+        let guard = &epoch::pin();
+        'segments: loop {
+            let head = self.head.load(Ordering::Acquire);
+            // SAFETY: the head is never null, and cannot be reclaimed while
+            // we are pinned.
+            let head_ref = unsafe { &*head };
+            let start = rng::next_index(self.k);
+            for i in 0..self.k {
+                let slot = &head_ref.slots[(start + i) % self.k];
+                let elem = slot.load(Ordering::Acquire);
+                if !elem.is_null()
+                    && elem != taken()
+                    && slot
+                        .compare_exchange(elem, taken(), Ordering::Acquire, Ordering::Relaxed)
+                        .is_ok()
+                {
+                    // SAFETY: the element was boxed by `push`, and only the
+                    // consumer that took it from its slot owns it.
+                    return Some(*unsafe { Box::from_raw(elem) });
+                }
+            }
+
+            // The head segment holds no element. If it is the last segment,
+            // the queue is empty; otherwise, its remaining free slots are
+            // closed so that it can be unlinked.
+            let next = head_ref.next.load(Ordering::Acquire);
+            if next.is_null() {
+                return None;
+            }
+            for slot in head_ref.slots.iter() {
+                if let Err(elem) = slot.compare_exchange(
+                    ptr::null_mut(),
+                    taken(),
+                    Ordering::Acquire,
+                    Ordering::Acquire,
+                ) {
+                    if elem != taken() {
+                        // A late producer got an element in; go take it.
+                        continue 'segments;
+                    }
+                }
+            }
+            // Keep the tail from pointing at a segment we are about to retire.
+            if self.tail.load(Ordering::Acquire) == head {
+                self.advance_tail(head, guard);
+            }
+            if self
+                .head
+                .compare_exchange(head, next, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                // SAFETY: the segment is unreachable from the queue and was
+                // allocated with `Box`, and all of its elements have been
+                // taken, so dropping it later on another thread drops no `T`.
+                unsafe { epoch::retire(guard, head) };
+            }
+        }
+    }
+}
+
+impl<T> Drop for KFifoQueue<T> {
+    fn drop(&mut self) {
+        // SAFETY: we have exclusive access, so no other thread holds a
+        // reference to the remaining segments or their elements.
+        unsafe {
+            let mut segment = *self.head.get_mut();
+            while !segment.is_null() {
+                let mut segment_owned = Box::from_raw(segment);
+                for slot in segment_owned.slots.iter_mut() {
+                    let elem = *slot.get_mut();
+                    if !elem.is_null() && elem != taken() {
+                        drop(Box::from_raw(elem));
+                    }
+                }
+                segment = *segment_owned.next.get_mut();
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for KFifoQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KFifoQueue")
+            .field("k", &self.k)
+            .field("segments", &self.segments.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn k_fifo_queue() {
+        // a single slot per segment makes the queue strictly FIFO
+        let queue = KFifoQueue::with_k(1);
+        (0..10).for_each(|i| queue.push(i.to_string()));
+        for i in 0..10 {
+            assert_eq!(queue.pop(), Some(i.to_string()));
+        }
+        assert_eq!(queue.pop(), None);
+
+        let queue = KFifoQueue::with_k(4);
+        assert!(queue.is_empty());
+        (0..100).for_each(|i| queue.push(i));
+        assert!(!queue.is_empty());
+        for pos in 0..90 {
+            let elem: usize = queue.pop().unwrap();
+            assert!(elem.abs_diff(pos) < queue.k(), "{} at {}", elem, pos);
+        }
+        assert_eq!(
+            format!("{:?}", queue),
+            "KFifoQueue { k: 4, segments: 25, .. }"
+        );
+        // the remaining elements are freed when the queue is dropped
+    }
+
+    #[test]
+    fn k_fifo_queue_concurrent() {
+        let num_producers = 4;
+        let num_consumers = 4;
+        let num_elems = 10_000;
+        let queue = KFifoQueue::with_k(8);
+
+        let mut popped: Vec<_> = std::thread::scope(|s| {
+            let consumers: Vec<_> = (0..num_consumers)
+                .map(|_| {
+                    let queue = &queue;
+                    s.spawn(move || {
+                        let mut popped = Vec::new();
+                        while popped.len() < num_producers * num_elems / num_consumers {
+                            match queue.pop() {
+                                Some(elem) => popped.push(elem),
+                                None => std::thread::yield_now(),
+                            }
+                        }
+                        popped
+                    })
+                })
+                .collect();
+            for p in 0..num_producers {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..num_elems {
+                        queue.push(p * num_elems + i);
+                    }
+                });
+            }
+            consumers
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect()
+        });
+        popped.sort_unstable();
+        assert!(popped.into_iter().eq(0..num_producers * num_elems));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn miri_k_fifo_queue() {
+        // few enough operations to run under Miri, over several segments
+        let queue = KFifoQueue::with_k(2);
+        std::thread::scope(|s| {
+            for t in 0..2 {
+                let queue = &queue;
+                s.spawn(move || {
+                    for i in 0..4 {
+                        queue.push((t, i.to_string()));
+                        assert!(queue.pop().is_some());
+                    }
+                });
+            }
+        });
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+//! This module contains concurrent queue implementations.
+
+#[cfg(feature = "futures")]
+mod async_queue;
+#[cfg(can_block)]
+mod blocking;
+#[cfg(can_block)]
+mod bounded_blocking_queue;
+mod broadcast_ring;
+#[cfg(all(can_block, has_clock))]
+mod delay_queue;
+#[cfg(can_block)]
+mod dual_queue;
+mod flat_combining_queue;
+mod k_fifo_queue;
+mod mpsc_queue;
+mod ms_queue;
+mod multi_queue;
+mod priority_queue;
+mod priority_work_queue;
+mod ring_queue;
+mod rng;
+mod sampling_ring;
+mod two_lock_deque;
+mod two_lock_queue;
+
+#[cfg(feature = "futures")]
+pub use async_queue::{AsyncQueue, Pop, PopStream};
+#[cfg(can_block)]
+pub use blocking::Blocking;
+#[cfg(can_block)]
+pub use bounded_blocking_queue::BoundedBlockingQueue;
+pub use broadcast_ring::{
+    broadcast_ring, BroadcastConsumer, BroadcastProducer, SlowConsumerPolicy,
+};
+#[cfg(all(can_block, has_clock))]
+pub use delay_queue::DelayQueue;
+#[cfg(can_block)]
+pub use dual_queue::DualQueue;
+pub use flat_combining_queue::FlatCombiningQueue;
+pub use k_fifo_queue::KFifoQueue;
+pub use mpsc_queue::{mpsc_channel, MpscReceiver, MpscSender};
+pub use ms_queue::MsQueue;
+pub use multi_queue::MultiQueue;
+pub use priority_queue::PriorityQueue;
+pub use priority_work_queue::PriorityWorkQueue;
+pub use ring_queue::RingQueue;
+pub use sampling_ring::SamplingRing;
+pub use two_lock_deque::TwoLockDeque;
+pub use two_lock_queue::TwoLockQueue;
+
+/// Defines common behavior for a FIFO queue.
+pub trait Queue {
+    /// Type of element contained in a queue.
+    type Elem;
+
+    /// Appends an element to the back of the queue.
+    fn push(&self, elem: Self::Elem);
+
+    /// Removes an element from the front of the queue, returning `None` if the
+    /// queue is empty.
+    fn pop(&self) -> Option<Self::Elem>;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn test_queue<Q>(queue: Q, num_producers: usize, num_consumers: usize, num_elems: usize)
+    where
+        Q: Queue<Elem = (usize, usize)> + Send + Sync + 'static,
+    {
+        let queue = Arc::new(queue);
+
+        let producers: Vec<_> = (0..num_producers)
+            .map(|p| {
+                let q = queue.clone();
+                std::thread::spawn(move || {
+                    for i in 0..num_elems {
+                        q.push((p, i));
+                    }
+                })
+            })
+            .collect();
+
+        let consumers: Vec<_> = (0..num_consumers)
+            .map(|_| {
+                let q = queue.clone();
+                let total = num_producers * num_elems;
+                std::thread::spawn(move || {
+                    let mut popped = Vec::new();
+                    while popped.len() < total / num_consumers {
+                        if let Some(elem) = q.pop() {
+                            popped.push(elem);
+                        }
+                    }
+                    popped
+                })
+            })
+            .collect();
+
+        for h in producers {
+            h.join().unwrap();
+        }
+
+        let mut seen = vec![Vec::new(); num_producers];
+        for h in consumers {
+            let mut last = vec![None; num_producers];
+            for (p, i) in h.join().unwrap() {
+                // elements from the same producer are dequeued in FIFO order
+                if let Some(l) = last[p] {
+                    assert!(l < i);
+                }
+                last[p] = Some(i);
+                seen[p].push(i);
+            }
+        }
+        while let Some((p, i)) = queue.pop() {
+            seen[p].push(i);
+        }
+
+        for mut s in seen {
+            s.sort_unstable();
+            assert!(s.into_iter().eq(0..num_elems));
+        }
+    }
+
+    #[cfg(can_block)]
+    mod bounded_blocking_queue {
+        use crate::queue::BoundedBlockingQueue;
+
+        #[test]
+        fn bounded_blocking_queue() {
+            super::test_queue(BoundedBlockingQueue::with_capacity(64), 4, 4, 10_000);
+        }
+    }
+
+    #[cfg(can_block)]
+    mod dual_queue {
+        use crate::queue::DualQueue;
+
+        #[test]
+        fn dual_queue() {
+            super::test_queue(DualQueue::new(), 4, 4, 10_000);
+        }
+    }
+
+    mod flat_combining_queue {
+        use crate::queue::FlatCombiningQueue;
+
+        #[test]
+        fn flat_combining_queue() {
+            super::test_queue(FlatCombiningQueue::new(), 4, 4, 10_000);
+        }
+    }
+
+    mod ms_queue {
+        use crate::queue::MsQueue;
+
+        #[test]
+        fn ms_queue() {
+            super::test_queue(MsQueue::new(), 4, 4, 10_000);
+        }
+    }
+
+    mod ring_queue {
+        use crate::queue::RingQueue;
+
+        #[test]
+        fn ring_queue() {
+            super::test_queue(RingQueue::with_capacity(64), 4, 4, 10_000);
+        }
+    }
+
+    mod two_lock_deque {
+        use crate::queue::TwoLockDeque;
+
+        #[test]
+        fn two_lock_deque() {
+            super::test_queue(TwoLockDeque::new(), 4, 4, 10_000);
+        }
+    }
+
+    mod two_lock_queue {
+        use crate::queue::TwoLockQueue;
+
+        #[test]
+        fn two_lock_queue() {
+            super::test_queue(TwoLockQueue::new(), 4, 4, 10_000);
+        }
+    }
+}
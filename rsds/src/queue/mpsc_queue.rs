@@ -0,0 +1,382 @@
+use std::cell::{Cell, UnsafeCell};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+#[cfg(can_block)]
+use std::thread;
+use std::thread::Thread;
+
+use crossbeam::utils::CachePadded;
+
+#[cfg(can_block)]
+use crate::sync::Backoff;
+
+struct Node<T> {
+    next: AtomicPtr<Node<T>>,
+    // Uninitialized for the stub node, which never carries an element.
+    elem: MaybeUninit<T>,
+}
+
+impl<T> Node<T> {
+    fn new(elem: MaybeUninit<T>) -> *mut Self {
+        Box::into_raw(Box::new(Node {
+            next: AtomicPtr::new(ptr::null_mut()),
+            elem,
+        }))
+    }
+}
+
+/// Result of a single attempt to pop from the queue.
+enum Pop<T> {
+    Elem(T),
+    Empty,
+    /// A producer has claimed the head but not linked its node yet, so the
+    /// queue is not empty, but its next element cannot be reached either.
+    Inconsistent,
+}
+
+/// State shared by the senders and the receiver of a channel.
+struct Channel<T> {
+    /// The most recently pushed node, swapped in by producers.
+    head: CachePadded<AtomicPtr<Node<T>>>,
+    /// The oldest node, only touched by the receiver.
+    tail: CachePadded<UnsafeCell<*mut Node<T>>>,
+    stub: *mut Node<T>,
+    senders: AtomicUsize,
+    /// Set while the receiver is, or is about to be, parked in `recv`.
+    parked: AtomicBool,
+    receiver: Mutex<Option<Thread>>,
+}
+
+impl<T> Channel<T> {
+    fn new() -> Self {
+        let stub = Node::new(MaybeUninit::uninit());
+        Self {
+            head: CachePadded::new(AtomicPtr::new(stub)),
+            tail: CachePadded::new(UnsafeCell::new(stub)),
+            stub,
+            senders: AtomicUsize::new(1),
+            parked: AtomicBool::new(false),
+            receiver: Mutex::new(None),
+        }
+    }
+
+    fn push_node(&self, node: *mut Node<T>) {
+        // SAFETY: the node is ours until it is linked below.
+        unsafe { (*node).next.store(ptr::null_mut(), Ordering::Relaxed) };
+        let prev = self.head.swap(node, Ordering::SeqCst);
+        // SAFETY: nodes are only freed by the receiver once their successor
+        // has been linked, which has not happened to `prev` yet.
+        unsafe { (*prev).next.store(node, Ordering::Release) };
+    }
+
+    /// Pops the oldest element.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by one thread at a time.
+    unsafe fn pop(&self) -> Pop<T> {
+        let tail = &mut *self.tail.get();
+        let mut next = (**tail).next.load(Ordering::Acquire);
+        if *tail == self.stub {
+            if next.is_null() {
+                return Pop::Empty;
+            }
+            // Skip the stub, which is pushed back whenever the queue drains.
+            *tail = next;
+            next = (*next).next.load(Ordering::Acquire);
+        }
+        if next.is_null() {
+            if *tail != self.head.load(Ordering::SeqCst) {
+                return Pop::Inconsistent;
+            }
+            // The tail is the last node; push the stub behind it so that the
+            // tail can be unlinked without racing with producers.
+            self.push_node(self.stub);
+            next = (**tail).next.load(Ordering::Acquire);
+            if next.is_null() {
+                return Pop::Inconsistent;
+            }
+        }
+        let node = Box::from_raw(std::mem::replace(tail, next));
+        Pop::Elem(node.elem.assume_init())
+    }
+
+    fn wake(&self) {
+        if self.parked.swap(false, Ordering::SeqCst) {
+            if let Some(receiver) = &*self.receiver.lock().unwrap() {
+                receiver.unpark();
+            }
+        }
+    }
+}
+
+impl<T> Drop for Channel<T> {
+    fn drop(&mut self) {
+        // SAFETY: we have exclusive access, so there are no producers left to
+        // leave the queue inconsistent.
+        while let Pop::Elem(_) = unsafe { self.pop() } {}
+        // SAFETY: the stub is the only node left.
+        drop(unsafe { Box::from_raw(self.stub) });
+    }
+}
+
+/// Creates an unbounded multi-producer, single-consumer channel, returning
+/// its sending and receiving halves.
+///
+/// The channel is a lock-free intrusive queue by Dmitry Vyukov. Sending takes
+/// a single atomic swap of the queue head, after which the sender links the
+/// previous head to its node; it never waits for the receiver or other
+/// senders. Receiving follows the links from the tail and needs no atomic
+/// read-modify-write operations at all, as only one thread ever receives. A
+/// stub node is pushed whenever the queue drains, so that the receiver can
+/// unlink the last node without racing with senders.
+///
+/// The receiver can block in [`MpscReceiver::recv`] until an element arrives,
+/// which makes the channel suitable for actor-style mailboxes.
+///
+/// ```
+/// use rsds::queue::mpsc_channel;
+///
+/// let (sender, receiver) = mpsc_channel();
+/// let handles: Vec<_> = (0..4)
+///     .map(|i| {
+///         let sender = sender.clone();
+///         std::thread::spawn(move || sender.send(i))
+///     })
+///     .collect();
+/// drop(sender);
+///
+/// let mut received: Vec<_> = std::iter::from_fn(|| receiver.recv()).collect();
+/// received.sort_unstable();
+/// assert_eq!(received, [0, 1, 2, 3]);
+/// # for h in handles { h.join().unwrap(); }
+/// ```
+pub fn mpsc_channel<T>() -> (MpscSender<T>, MpscReceiver<T>) {
+    let channel = Arc::new(Channel::new());
+    let sender = MpscSender {
+        channel: channel.clone(),
+    };
+    let receiver = MpscReceiver {
+        channel,
+        _not_sync: PhantomData,
+    };
+    (sender, receiver)
+}
+
+/// The sending half of a channel created by [`mpsc_channel`], which can be
+/// cloned to send from many threads.
+pub struct MpscSender<T> {
+    channel: Arc<Channel<T>>,
+}
+
+// SAFETY: elements are moved to the receiver's thread, and senders only touch
+// the atomic head of the queue.
+unsafe impl<T: Send> Send for MpscSender<T> {}
+unsafe impl<T: Send> Sync for MpscSender<T> {}
+
+impl<T> MpscSender<T> {
+    /// Sends an element to the receiver, waking it up if it is blocked.
+    ///
+    /// The element is dropped along with the channel if the receiver has been
+    /// dropped already.
+    pub fn send(&self, elem: T) {
+        self.channel.push_node(Node::new(MaybeUninit::new(elem)));
+        self.channel.wake();
+    }
+}
+
+impl<T> Clone for MpscSender<T> {
+    fn clone(&self) -> Self {
+        self.channel.senders.fetch_add(1, Ordering::Relaxed);
+        Self {
+            channel: self.channel.clone(),
+        }
+    }
+}
+
+impl<T> Drop for MpscSender<T> {
+    fn drop(&mut self) {
+        if self.channel.senders.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // Let a blocked receiver notice that no more elements can arrive.
+            self.channel.wake();
+        }
+    }
+}
+
+impl<T> fmt::Debug for MpscSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MpscSender").finish_non_exhaustive()
+    }
+}
+
+/// The receiving half of a channel created by [`mpsc_channel`].
+///
+/// The receiver can be moved to another thread, but not shared between
+/// threads.
+pub struct MpscReceiver<T> {
+    channel: Arc<Channel<T>>,
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+// SAFETY: the receiver is not `Sync`, so only the thread owning it can pop.
+unsafe impl<T: Send> Send for MpscReceiver<T> {}
+
+impl<T> MpscReceiver<T> {
+    /// Receives the oldest element without blocking, returning `None` if the
+    /// channel is empty.
+    ///
+    /// This may also return `None` while a sender is halfway through sending,
+    /// even if other elements have been sent after it.
+    pub fn try_recv(&self) -> Option<T> {
+        // SAFETY: the receiver is the only consumer, and is not `Sync`.
+        match unsafe { self.channel.pop() } {
+            Pop::Elem(elem) => Some(elem),
+            Pop::Empty | Pop::Inconsistent => None,
+        }
+    }
+
+    /// Receives the oldest element, blocking until one arrives.
+    ///
+    /// Returns `None` once the channel is empty and every sender has been
+    /// dropped.
+    #[cfg(can_block)]
+    pub fn recv(&self) -> Option<T> {
+        let channel = &*self.channel;
+        let backoff = Backoff::new();
+        loop {
+            // SAFETY: the receiver is the only consumer, and is not `Sync`.
+            match unsafe { channel.pop() } {
+                Pop::Elem(elem) => return Some(elem),
+                // A sender is about to link its node.
+                Pop::Inconsistent => {
+                    if backoff.is_completed() {
+                        thread::yield_now();
+                    } else {
+                        backoff.snooze();
+                    }
+                    continue;
+                }
+                Pop::Empty => {}
+            }
+            if channel.senders.load(Ordering::SeqCst) == 0 {
+                // Every send happened before the last sender was dropped.
+                // SAFETY: as above.
+                return match unsafe { channel.pop() } {
+                    Pop::Elem(elem) => Some(elem),
+                    Pop::Empty | Pop::Inconsistent => None,
+                };
+            }
+            if !backoff.is_completed() {
+                backoff.snooze();
+                continue;
+            }
+
+            // The receiver may have moved since it last blocked.
+            *channel.receiver.lock().unwrap() = Some(thread::current());
+            channel.parked.store(true, Ordering::SeqCst);
+            // Senders check `parked` after pushing, so either they see it set
+            // and wake us up, or we see their element here.
+            if channel.head.load(Ordering::SeqCst) == unsafe { *channel.tail.get() }
+                && channel.senders.load(Ordering::SeqCst) != 0
+            {
+                thread::park();
+            }
+            channel.parked.store(false, Ordering::SeqCst);
+            backoff.reset();
+        }
+    }
+}
+
+impl<T> fmt::Debug for MpscReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MpscReceiver").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn mpsc_channel() {
+        let (sender, receiver) = super::mpsc_channel();
+        assert_eq!(receiver.try_recv(), None);
+        for i in 0..10 {
+            sender.send(i);
+        }
+        assert!((0..10).all(|i| receiver.try_recv() == Some(i)));
+        assert_eq!(receiver.try_recv(), None);
+
+        let (sender, receiver) = super::mpsc_channel();
+        let counted = Arc::new(());
+        sender.send(counted.clone());
+        drop(receiver);
+        drop(sender);
+        assert_eq!(Arc::strong_count(&counted), 1);
+    }
+
+    #[test]
+    fn mpsc_channel_blocking() {
+        let num_senders = 4;
+        let num_elems = 10_000;
+        let (sender, receiver) = super::mpsc_channel();
+
+        let handles: Vec<_> = (0..num_senders)
+            .map(|s| {
+                let sender = sender.clone();
+                std::thread::spawn(move || {
+                    for i in 0..num_elems {
+                        sender.send((s, i));
+                        if i % 1_000 == 0 {
+                            // give the receiver a chance to block
+                            std::thread::sleep(Duration::from_millis(1));
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(sender);
+
+        // the receiver can be moved to another thread
+        let receiver = std::thread::spawn(move || {
+            let mut last = vec![None; num_senders];
+            let mut count = 0;
+            while let Some((s, i)) = receiver.recv() {
+                // elements from the same sender are received in FIFO order
+                assert!(last[s] < Some(i));
+                last[s] = Some(i);
+                count += 1;
+            }
+            count
+        });
+        for h in handles {
+            h.join().unwrap();
+        }
+        assert_eq!(receiver.join().unwrap(), num_senders * num_elems);
+    }
+
+    #[test]
+    fn miri_mpsc_channel() {
+        // few enough operations to run under Miri
+        let (sender, receiver) = super::mpsc_channel();
+        let senders: Vec<_> = (0..2)
+            .map(|s| {
+                let sender = sender.clone();
+                std::thread::spawn(move || (0..4).for_each(|i| sender.send((s, i))))
+            })
+            .collect();
+        drop(sender);
+        let mut received = Vec::new();
+        while let Some(elem) = receiver.recv() {
+            received.push(elem);
+        }
+        senders.into_iter().for_each(|h| h.join().unwrap());
+        received.sort_unstable();
+        assert!(received.into_iter().eq((0..2).flat_map(|s| (0..4).map(move |i| (s, i)))));
+    }
+}
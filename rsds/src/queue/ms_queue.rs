@@ -0,0 +1,180 @@
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+use crossbeam::utils::CachePadded;
+
+use super::Queue;
+use crate::reclaim::epoch;
+
+struct Node<T> {
+    // Uninitialized for the sentinel node, whose element has either never
+    // existed or has already been dequeued.
+    elem: MaybeUninit<T>,
+    next: AtomicPtr<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn new(elem: MaybeUninit<T>) -> *mut Self {
+        Box::into_raw(Box::new(Node {
+            elem,
+            next: AtomicPtr::new(ptr::null_mut()),
+        }))
+    }
+}
+
+/// An unbounded lock-free queue, implemented with the Michael–Scott algorithm.
+///
+/// Both ends are advanced with compare-and-swap. As in [`TwoLockQueue`], the
+/// head always points at a sentinel node. The tail may lag one node behind
+/// the real end of the queue; any thread that notices this helps swing it
+/// forward before retrying its own operation.
+///
+/// Dequeued nodes may still be read by concurrent operations, so they are
+/// retired through the crate's [epoch-based reclamation](crate::reclaim::epoch)
+/// rather than freed immediately.
+///
+/// [`TwoLockQueue`]: super::TwoLockQueue
+pub struct MsQueue<T> {
+    head: CachePadded<AtomicPtr<Node<T>>>,
+    tail: CachePadded<AtomicPtr<Node<T>>>,
+}
+
+// SAFETY: elements are moved between threads only through push/pop, and nodes
+// are only freed once no thread can observe them.
+unsafe impl<T: Send> Send for MsQueue<T> {}
+unsafe impl<T: Send> Sync for MsQueue<T> {}
+
+impl<T> Default for MsQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> MsQueue<T> {
+    /// Creates a new, empty [`MsQueue`].
+    pub fn new() -> Self {
+        let sentinel = Node::new(MaybeUninit::uninit());
+        Self {
+            head: CachePadded::new(AtomicPtr::new(sentinel)),
+            tail: CachePadded::new(AtomicPtr::new(sentinel)),
+        }
+    }
+
+    /// Checks whether the queue is empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        let _guard = epoch::pin();
+        let head = self.head.load(Ordering::Acquire);
+        // SAFETY: the head is never null, and is only retired after being
+        // unlinked while we are pinned.
+        unsafe { &*head }.next.load(Ordering::Acquire).is_null()
+    }
+}
+
+impl<T> Queue for MsQueue<T> {
+    type Elem = T;
+
+    fn push(&self, elem: T) {
+        let _guard = epoch::pin();
+        let node = Node::new(MaybeUninit::new(elem));
+
+        loop {
+            let tail = self.tail.load(Ordering::Acquire);
+            // SAFETY: the tail is never null, and cannot be reclaimed while we
+            // are pinned.
+            let tail_ref = unsafe { &*tail };
+            let next = tail_ref.next.load(Ordering::Acquire);
+
+            if !next.is_null() {
+                // The tail is lagging behind; help move it forward.
+                let _ =
+                    self.tail
+                        .compare_exchange(tail, next, Ordering::Release, Ordering::Relaxed);
+                continue;
+            }
+
+            // The release ordering publishes the node's element to dequeuers.
+            if tail_ref
+                .next
+                .compare_exchange(ptr::null_mut(), node, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                // Failing here is fine: another thread has already helped.
+                let _ =
+                    self.tail
+                        .compare_exchange(tail, node, Ordering::Release, Ordering::Relaxed);
+                return;
+            }
+        }
+    }
+
+    fn pop(&self) -> Option<T> {
+        let guard = epoch::pin();
+        loop {
+            let head = self.head.load(Ordering::Acquire);
+            // SAFETY: the head is never null, and cannot be reclaimed while we
+            // are pinned.
+            let next = unsafe { &*head }.next.load(Ordering::Acquire);
+            // SAFETY: a non-null successor cannot be reclaimed while we are
+            // pinned either.
+            let next_ref = unsafe { next.as_ref() }?;
+
+            // Keep the tail from pointing at a node we are about to retire.
+            let tail = self.tail.load(Ordering::Relaxed);
+            if tail == head {
+                let _ =
+                    self.tail
+                        .compare_exchange(tail, next, Ordering::Release, Ordering::Relaxed);
+            }
+
+            if self
+                .head
+                .compare_exchange(head, next, Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                // `next` is now the sentinel. Only the thread that swung the
+                // head moves its element out, and it is never read again.
+                //
+                // SAFETY: `next` was fully initialized before being published
+                // by `push`, and the old sentinel is unreachable from the queue
+                // and was allocated with `Box`. Its element is uninitialized,
+                // so dropping it later on another thread drops no `T`.
+                unsafe {
+                    let elem = next_ref.elem.assume_init_read();
+                    epoch::retire(&guard, head);
+                    return Some(elem);
+                }
+            }
+        }
+    }
+}
+
+impl<T> Drop for MsQueue<T> {
+    fn drop(&mut self) {
+        while self.pop().is_some() {}
+        // SAFETY: we have exclusive access, so no other thread holds a
+        // reference to the remaining sentinel.
+        unsafe { drop(Box::from_raw(*self.head.get_mut())) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ms_queue() {
+        let queue = MsQueue::new();
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+
+        for i in 0..10 {
+            queue.push(i.to_string());
+        }
+        assert!(!queue.is_empty());
+        for i in 0..5 {
+            assert_eq!(queue.pop(), Some(i.to_string()));
+        }
+        // the remaining elements are freed when the queue is dropped
+    }
+}
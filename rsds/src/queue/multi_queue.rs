@@ -0,0 +1,207 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crossbeam::utils::CachePadded;
+
+use super::rng;
+
+/// Number of random attempts `pop_min` makes before falling back to scanning
+/// every heap.
+const MAX_POP_ATTEMPTS: usize = 16;
+
+/// A min-heap, padded to keep neighboring heaps' locks on separate cache
+/// lines.
+type Heap<T> = CachePadded<Mutex<BinaryHeap<Reverse<T>>>>;
+
+/// A relaxed concurrent priority queue, implemented as a MultiQueue: a set of
+/// independently locked binary heaps.
+///
+/// `push` inserts into a random heap, and `pop_min` picks two random heaps and
+/// pops from the one whose minimum is smaller. Locks are only ever taken with
+/// `try_lock`, so threads spread out over the heaps instead of queueing up on
+/// a busy one.
+///
+/// The queue trades exact priority order for throughput: `pop_min` does not
+/// necessarily return the smallest element. With `c` heaps, the rank of the
+/// returned element (its position in the sorted order of all elements) is
+/// `O(c)` in expectation and `O(c log c)` with high probability. Using two to
+/// four heaps per thread is a good balance between scalability and quality.
+pub struct MultiQueue<T> {
+    heaps: Box<[Heap<T>]>,
+    len: AtomicUsize,
+}
+
+impl<T> MultiQueue<T>
+where
+    T: Ord,
+{
+    /// Creates a new, empty [`MultiQueue`] over `num_heaps` heaps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_heaps` is less than 2.
+    pub fn with_heaps(num_heaps: usize) -> Self {
+        assert!(
+            num_heaps >= 2,
+            "number of heaps (is {}) should be >= 2",
+            num_heaps
+        );
+        Self {
+            heaps: (0..num_heaps)
+                .map(|_| CachePadded::new(Mutex::new(BinaryHeap::new())))
+                .collect(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Inserts an element into the queue.
+    pub fn push(&self, elem: T) {
+        // Counted before it becomes visible, so `len` never underflows.
+        self.len.fetch_add(1, Ordering::Relaxed);
+        loop {
+            let idx = rng::next_index(self.heaps.len());
+            if let Ok(mut heap) = self.heaps[idx].try_lock() {
+                heap.push(Reverse(elem));
+                return;
+            }
+        }
+    }
+
+    /// Removes a small element from the queue, returning `None` if the queue
+    /// is empty.
+    ///
+    /// The element is the smaller minimum of two random heaps, so it is close
+    /// to, but not necessarily, the smallest element in the queue.
+    pub fn pop_min(&self) -> Option<T> {
+        for _ in 0..MAX_POP_ATTEMPTS {
+            if self.is_empty() {
+                return None;
+            }
+
+            let i = rng::next_index(self.heaps.len());
+            let j = rng::next_index(self.heaps.len() - 1);
+            // Make the two picks distinct.
+            let j = if j >= i { j + 1 } else { j };
+            let (Ok(mut first), Ok(mut second)) =
+                (self.heaps[i].try_lock(), self.heaps[j].try_lock())
+            else {
+                continue;
+            };
+
+            // `Reverse` flips the order, so the greater top holds the smaller
+            // element.
+            let pop_first = match (first.peek(), second.peek()) {
+                (None, None) => continue,
+                (Some(a), Some(b)) => a >= b,
+                (first_top, _) => first_top.is_some(),
+            };
+            let heap = if pop_first { &mut first } else { &mut second };
+            let Reverse(elem) = heap.pop().unwrap();
+            self.len.fetch_sub(1, Ordering::Relaxed);
+            return Some(elem);
+        }
+
+        // The queue is nearly empty or heavily contended; settle for any
+        // element rather than report a non-empty queue as empty.
+        for heap in self.heaps.iter() {
+            if let Some(Reverse(elem)) = heap.lock().unwrap().pop() {
+                self.len.fetch_sub(1, Ordering::Relaxed);
+                return Some(elem);
+            }
+        }
+        None
+    }
+}
+
+impl<T> MultiQueue<T> {
+    /// Returns the number of elements in the queue at the time of the call.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Checks whether the queue is empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of heaps the queue is spread over.
+    pub fn num_heaps(&self) -> usize {
+        self.heaps.len()
+    }
+}
+
+impl<T> Default for MultiQueue<T>
+where
+    T: Ord,
+{
+    /// Creates a new, empty [`MultiQueue`] with two heaps per available
+    /// thread.
+    fn default() -> Self {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self::with_heaps(2 * threads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn multi_queue() {
+        let queue = MultiQueue::with_heaps(4);
+        assert_eq!(queue.pop_min(), None);
+
+        let num_elems = 1_000;
+        for i in (0..num_elems).rev() {
+            queue.push(i);
+        }
+        assert_eq!(queue.len(), num_elems);
+
+        // every element is popped exactly once, roughly in order
+        let mut popped = Vec::new();
+        while let Some(elem) = queue.pop_min() {
+            popped.push(elem);
+        }
+        let early = popped[..num_elems / 2].iter().sum::<usize>();
+        let late = popped[num_elems / 2..].iter().sum::<usize>();
+        assert!(early < late);
+        popped.sort_unstable();
+        assert!(popped.into_iter().eq(0..num_elems));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn multi_queue_concurrent() {
+        let num_thrs = 8;
+        let num_elems = 5_000;
+        let queue = Arc::new(MultiQueue::default());
+
+        let handles: Vec<_> = (0..num_thrs)
+            .map(|t| {
+                let queue = queue.clone();
+                std::thread::spawn(move || {
+                    let mut popped = Vec::new();
+                    for i in 0..num_elems {
+                        queue.push(i * num_thrs + t);
+                        if i % 2 == 0 {
+                            popped.extend(queue.pop_min());
+                        }
+                    }
+                    popped
+                })
+            })
+            .collect();
+
+        let mut popped: Vec<_> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        popped.extend(std::iter::from_fn(|| queue.pop_min()));
+        popped.sort_unstable();
+        assert!(popped.into_iter().eq(0..num_thrs * num_elems));
+    }
+}
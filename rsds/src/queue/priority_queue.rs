@@ -0,0 +1,441 @@
+use std::cmp::Ordering as CmpOrdering;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use crossbeam::epoch::{self, Atomic, Guard, Owned, Shared};
+
+use super::rng;
+use crate::sync::Backoff;
+
+/// Maximum number of levels in the skip list.
+const MAX_LEVEL: usize = 32;
+
+struct Node<T> {
+    // Uninitialized for the head sentinel, and moved out once `taken` is set.
+    elem: MaybeUninit<T>,
+    // Breaks ties between equal elements, so that every node has a unique key
+    // and equal elements are popped in insertion order.
+    seq: u64,
+    next: Box<[Atomic<Node<T>>]>,
+    lock: Mutex<()>,
+    // Set by the `pop_min` that claims this node, before it is unlinked.
+    marked: AtomicBool,
+    fully_linked: AtomicBool,
+    // Set once the node is unlinked and its element is about to be moved out.
+    taken: AtomicBool,
+    // Number of threads currently comparing against this node's element.
+    readers: AtomicUsize,
+}
+
+impl<T> Node<T> {
+    fn new(elem: MaybeUninit<T>, seq: u64, height: usize) -> Self {
+        Self {
+            elem,
+            seq,
+            next: (0..height).map(|_| Atomic::null()).collect(),
+            lock: Mutex::new(()),
+            marked: AtomicBool::new(false),
+            fully_linked: AtomicBool::new(false),
+            taken: AtomicBool::new(false),
+            readers: AtomicUsize::new(0),
+        }
+    }
+
+    fn top_level(&self) -> usize {
+        self.next.len() - 1
+    }
+
+    /// Checks whether this node's key is smaller than `(elem, seq)`, or
+    /// returns `None` if the element has been moved out and the caller has to
+    /// restart its traversal.
+    fn precedes(&self, elem: &T, seq: u64) -> Option<bool>
+    where
+        T: Ord,
+    {
+        self.read(|node_elem| match node_elem.cmp(elem) {
+            CmpOrdering::Equal => self.seq < seq,
+            ord => ord == CmpOrdering::Less,
+        })
+    }
+
+    /// Calls `f` on this node's element, or returns `None` if the element
+    /// has been moved out.
+    fn read<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        // Announcing the read before checking `taken` pairs with `pop_min`
+        // setting `taken` before waiting for `readers` to drop to zero: at
+        // least one side is guaranteed to see the other.
+        self.readers.fetch_add(1, Ordering::SeqCst);
+        if self.taken.load(Ordering::SeqCst) {
+            self.readers.fetch_sub(1, Ordering::Release);
+            return None;
+        }
+        // SAFETY: the element cannot be moved out while `readers` is non-zero.
+        let result = f(unsafe { self.elem.assume_init_ref() });
+        self.readers.fetch_sub(1, Ordering::Release);
+        Some(result)
+    }
+}
+
+/// Picks a level in `[0, MAX_LEVEL)`, where each level is half as likely as
+/// the one below it.
+fn random_level() -> usize {
+    (rng::next_u64().trailing_ones() as usize).min(MAX_LEVEL - 1)
+}
+
+type Path<'g, T> = ([&'g Node<T>; MAX_LEVEL], [Shared<'g, Node<T>>; MAX_LEVEL]);
+
+/// An unbounded concurrent priority queue, implemented as a lock-based skip
+/// list.
+///
+/// Elements are kept sorted in a lazy skip list: searches take no locks, and
+/// insertions and removals only lock the nodes whose links they change. The
+/// smallest element is popped by walking the bottom level and claiming the
+/// first unclaimed node with a flag, which logically deletes it; the node is
+/// then unlinked like a regular removal. Equal elements are popped in the
+/// order they were pushed.
+///
+/// Unlinked nodes are reclaimed with crossbeam's epoch-based reclamation.
+pub struct PriorityQueue<T> {
+    head: Box<Node<T>>,
+    seq: AtomicU64,
+    len: AtomicUsize,
+}
+
+// SAFETY: elements are moved between threads only through push/pop_min, and
+// are only shared for comparisons, which require `T: Sync`.
+unsafe impl<T: Send> Send for PriorityQueue<T> {}
+unsafe impl<T: Send + Sync> Sync for PriorityQueue<T> {}
+
+impl<T> Default for PriorityQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> PriorityQueue<T> {
+    /// Creates a new, empty [`PriorityQueue`].
+    pub fn new() -> Self {
+        Self {
+            head: Box::new(Node::new(MaybeUninit::uninit(), 0, MAX_LEVEL)),
+            seq: AtomicU64::new(0),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of elements in the queue at the time of the call.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Checks whether the queue is empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> PriorityQueue<T>
+where
+    T: Ord,
+{
+    /// Inserts an element into the queue.
+    pub fn push(&self, elem: T) {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let top_level = random_level();
+        let guard = &epoch::pin();
+        let node = Owned::new(Node::new(MaybeUninit::new(elem), seq, top_level + 1));
+
+        loop {
+            // SAFETY: the element is only moved out of the node by `pop_min`,
+            // which cannot claim it before it is linked.
+            let elem = unsafe { node.elem.assume_init_ref() };
+            let Some((preds, succs)) = self.find(elem, seq, guard) else {
+                continue;
+            };
+            let _locks = lock_preds(&preds, top_level);
+
+            let valid = (0..=top_level).all(|level| {
+                let pred = preds[level];
+                let succ = succs[level];
+                // SAFETY: nodes cannot be reclaimed while we are pinned.
+                let succ_marked = unsafe { succ.as_ref() }
+                    .is_some_and(|succ| succ.marked.load(Ordering::Acquire));
+                !pred.marked.load(Ordering::Acquire)
+                    && !succ_marked
+                    && pred.next[level].load(Ordering::Acquire, guard) == succ
+            });
+            if !valid {
+                continue;
+            }
+
+            for (level, succ) in succs.iter().enumerate().take(top_level + 1) {
+                node.next[level].store(*succ, Ordering::Relaxed);
+            }
+            let node = node.into_shared(guard);
+            for (level, pred) in preds.iter().enumerate().take(top_level + 1) {
+                pred.next[level].store(node, Ordering::Release);
+            }
+            // Counted before it becomes claimable, so `len` never underflows.
+            self.len.fetch_add(1, Ordering::Relaxed);
+            // SAFETY: we just allocated the node, and it is not yet claimable.
+            unsafe { node.deref() }
+                .fully_linked
+                .store(true, Ordering::Release);
+            return;
+        }
+    }
+
+    /// Removes the smallest element from the queue, returning `None` if the
+    /// queue is empty.
+    pub fn pop_min(&self) -> Option<T> {
+        self.pop_min_if(|_| true)
+    }
+
+    /// Removes the smallest element from the queue if `pred` holds for it,
+    /// returning `None` if it does not or the queue is empty.
+    ///
+    /// `pred` may be called more than once, if other threads pop the
+    /// elements it is called on first.
+    pub fn pop_min_if(&self, mut pred: impl FnMut(&T) -> bool) -> Option<T> {
+        let guard = &epoch::pin();
+        let victim = self.claim_min(&mut pred, guard)?;
+        self.len.fetch_sub(1, Ordering::Relaxed);
+        self.unlink(victim, guard);
+
+        // SAFETY: the node is no longer reachable from the queue.
+        let node = unsafe { victim.deref() };
+        node.taken.store(true, Ordering::SeqCst);
+        let backoff = Backoff::new();
+        while node.readers.load(Ordering::SeqCst) != 0 {
+            backoff.snooze();
+        }
+        // SAFETY: no thread is reading the element, and any thread that still
+        // reaches the node will see `taken` and leave it alone. We claimed the
+        // node, so no other thread moves the element out.
+        unsafe {
+            let elem = node.elem.assume_init_read();
+            guard.defer_destroy(victim);
+            Some(elem)
+        }
+    }
+
+    /// Claims the first unclaimed node on the bottom level, if `pred` holds
+    /// for its element.
+    fn claim_min<'g>(
+        &self,
+        pred: &mut impl FnMut(&T) -> bool,
+        guard: &'g Guard,
+    ) -> Option<Shared<'g, Node<T>>> {
+        let mut curr = self.head.next[0].load(Ordering::Acquire, guard);
+        // SAFETY: nodes cannot be reclaimed while we are pinned, and unlinked
+        // nodes keep pointing forward into the list.
+        while let Some(node) = unsafe { curr.as_ref() } {
+            if node.fully_linked.load(Ordering::Acquire) && !node.marked.load(Ordering::Acquire) {
+                // A node popped in the meantime is skipped, as is one claimed
+                // below by another thread.
+                match node.read(&mut *pred) {
+                    Some(false) => return None,
+                    Some(true)
+                        if node
+                            .marked
+                            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                            .is_ok() =>
+                    {
+                        return Some(curr);
+                    }
+                    _ => {}
+                }
+            }
+            curr = node.next[0].load(Ordering::Acquire, guard);
+        }
+        None
+    }
+
+    /// Unlinks a claimed node from every level it is linked on.
+    fn unlink<'g>(&'g self, victim: Shared<'g, Node<T>>, guard: &'g Guard) {
+        // SAFETY: we claimed the node, so only we move its element out.
+        let node = unsafe { victim.deref() };
+        let elem = unsafe { node.elem.assume_init_ref() };
+        let top_level = node.top_level();
+        // Holding the victim's lock keeps new nodes from being linked after it.
+        let _victim_lock = node.lock.lock().unwrap();
+
+        loop {
+            let Some((preds, _)) = self.find(elem, node.seq, guard) else {
+                continue;
+            };
+            let _locks = lock_preds(&preds, top_level);
+
+            let valid = (0..=top_level).all(|level| {
+                let pred = preds[level];
+                !pred.marked.load(Ordering::Acquire)
+                    && pred.next[level].load(Ordering::Acquire, guard) == victim
+            });
+            if !valid {
+                continue;
+            }
+
+            for level in (0..=top_level).rev() {
+                let next = node.next[level].load(Ordering::Acquire, guard);
+                preds[level].next[level].store(next, Ordering::Release);
+            }
+            return;
+        }
+    }
+
+    /// Finds, on every level, the last node preceding `(elem, seq)` and the
+    /// node following it.
+    ///
+    /// Returns `None` if the traversal ran into a node whose element has been
+    /// moved out, in which case it has to be restarted.
+    fn find<'g>(&'g self, elem: &T, seq: u64, guard: &'g Guard) -> Option<Path<'g, T>> {
+        let mut preds = [&*self.head; MAX_LEVEL];
+        let mut succs = [Shared::null(); MAX_LEVEL];
+        let mut pred: &Node<T> = &self.head;
+
+        for level in (0..MAX_LEVEL).rev() {
+            let mut curr = pred.next[level].load(Ordering::Acquire, guard);
+            // SAFETY: nodes cannot be reclaimed while we are pinned.
+            while let Some(node) = unsafe { curr.as_ref() } {
+                if !node.precedes(elem, seq)? {
+                    break;
+                }
+                pred = node;
+                curr = node.next[level].load(Ordering::Acquire, guard);
+            }
+            preds[level] = pred;
+            succs[level] = curr;
+        }
+        Some((preds, succs))
+    }
+}
+
+/// Locks the distinct predecessors on levels `0..=top_level`, bottom-up.
+fn lock_preds<'g, T>(
+    preds: &[&'g Node<T>; MAX_LEVEL],
+    top_level: usize,
+) -> Vec<MutexGuard<'g, ()>> {
+    let mut locks = Vec::with_capacity(top_level + 1);
+    let mut prev: *const Node<T> = std::ptr::null();
+    for &pred in preds.iter().take(top_level + 1) {
+        if !std::ptr::eq(prev, pred) {
+            locks.push(pred.lock.lock().unwrap());
+            prev = pred;
+        }
+    }
+    locks
+}
+
+impl<T> Drop for PriorityQueue<T> {
+    fn drop(&mut self) {
+        // SAFETY: we have exclusive access, so every linked node is owned by
+        // the queue and still holds its element.
+        unsafe {
+            let guard = epoch::unprotected();
+            let mut curr = self.head.next[0].load(Ordering::Relaxed, guard);
+            while !curr.is_null() {
+                let mut node = curr.into_owned();
+                curr = node.next[0].load(Ordering::Relaxed, guard);
+                node.elem.assume_init_drop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn priority_queue() {
+        let queue = PriorityQueue::new();
+        assert_eq!(queue.pop_min(), None);
+
+        for i in [5, 3, 8, 1, 9, 2, 7] {
+            queue.push(i.to_string());
+        }
+        assert_eq!(queue.len(), 7);
+        for i in [1, 2, 3] {
+            assert_eq!(queue.pop_min(), Some(i.to_string()));
+        }
+        assert_eq!(queue.pop_min_if(|s| s.as_str() < "5"), None);
+        assert_eq!(
+            queue.pop_min_if(|s| s.as_str() == "5"),
+            Some("5".to_string())
+        );
+        // the remaining elements are freed when the queue is dropped
+    }
+
+    #[test]
+    fn priority_queue_ties() {
+        let queue = PriorityQueue::new();
+        for i in 0..10 {
+            queue.push(Tagged(i % 2, i));
+        }
+        let popped: Vec<_> = std::iter::from_fn(|| queue.pop_min())
+            .map(|t| t.1)
+            .collect();
+        assert_eq!(popped, [0, 2, 4, 6, 8, 1, 3, 5, 7, 9]);
+    }
+
+    #[test]
+    fn priority_queue_concurrent() {
+        let num_thrs = 8;
+        let num_elems = 5_000;
+        let queue = Arc::new(PriorityQueue::new());
+
+        let handles: Vec<_> = (0..num_thrs)
+            .map(|t| {
+                let queue = queue.clone();
+                std::thread::spawn(move || {
+                    let mut popped = Vec::new();
+                    for i in 0..num_elems {
+                        queue.push(i * num_thrs + t);
+                        if i % 2 == 0 {
+                            popped.extend(queue.pop_min());
+                        }
+                    }
+                    popped
+                })
+            })
+            .collect();
+
+        let mut popped: Vec<_> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        let mut rest: Vec<_> = std::iter::from_fn(|| queue.pop_min()).collect();
+        assert!(rest.windows(2).all(|w| w[0] < w[1]));
+
+        popped.append(&mut rest);
+        popped.sort_unstable();
+        assert!(popped.into_iter().eq(0..num_thrs * num_elems));
+        assert!(queue.is_empty());
+    }
+
+    /// Compares by its first field only.
+    #[derive(Debug)]
+    struct Tagged(usize, usize);
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Tagged {}
+
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Self) -> CmpOrdering {
+            self.0.cmp(&other.0)
+        }
+    }
+}
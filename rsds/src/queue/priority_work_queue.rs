@@ -0,0 +1,231 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crossbeam::utils::CachePadded;
+
+use super::TwoLockDeque;
+
+/// A work-stealing scheduler queue whose tasks are split into priority bands,
+/// with band 0 the most urgent.
+///
+/// Every worker owns a [`TwoLockDeque`] per band. A worker pushes its tasks
+/// to the back of its own deques and pops them from there, most recent
+/// first, while idle workers steal from the front, oldest first, so that
+/// owners and thieves mostly lock different halves of a deque.
+///
+/// Stealing respects the bands: a worker only settles for a task from a band
+/// once its own deque for the band, and then every other worker's, came up
+/// empty, and it always looks at more urgent bands first. Each band keeps a
+/// count of its tasks, so that empty bands are skipped without visiting every
+/// worker's deque.
+///
+/// ```
+/// use rsds::queue::PriorityWorkQueue;
+///
+/// let queue = PriorityWorkQueue::new(2, 3);
+/// queue.push(0, 2, "background");
+/// queue.push(1, 0, "urgent");
+/// // worker 0 steals the urgent task before running its own
+/// assert_eq!(queue.pop(0), Some("urgent"));
+/// assert_eq!(queue.pop(1), Some("background"));
+/// assert_eq!(queue.pop(0), None);
+/// ```
+pub struct PriorityWorkQueue<T> {
+    /// The deques of each worker, one per band.
+    workers: Box<[Box<[TwoLockDeque<T>]>]>,
+    /// Number of tasks in each band, which may run ahead of the tasks in its
+    /// deques while they are being pushed.
+    band_lens: Box<[CachePadded<AtomicUsize>]>,
+}
+
+impl<T> PriorityWorkQueue<T> {
+    /// Creates a new, empty [`PriorityWorkQueue`] for `num_workers` workers,
+    /// with `num_bands` priority bands.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_workers` or `num_bands` is zero.
+    pub fn new(num_workers: usize, num_bands: usize) -> Self {
+        assert!(
+            num_workers > 0,
+            "number of workers (is {}) should be positive",
+            num_workers
+        );
+        assert!(
+            num_bands > 0,
+            "number of bands (is {}) should be positive",
+            num_bands
+        );
+        Self {
+            workers: (0..num_workers)
+                .map(|_| (0..num_bands).map(|_| TwoLockDeque::new()).collect())
+                .collect(),
+            band_lens: (0..num_bands)
+                .map(|_| CachePadded::new(AtomicUsize::new(0)))
+                .collect(),
+        }
+    }
+
+    /// Returns the number of workers.
+    pub fn num_workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Returns the number of priority bands.
+    pub fn num_bands(&self) -> usize {
+        self.band_lens.len()
+    }
+
+    /// Pushes `task` onto `worker`'s deque for band `band`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `worker` or `band` is out of range.
+    pub fn push(&self, worker: usize, band: usize, task: T) {
+        let deque = &self.workers[worker][band];
+        // Counting the task first keeps the count from dropping below zero
+        // when a thief takes it right away.
+        self.band_lens[band].fetch_add(1, Ordering::SeqCst);
+        deque.push_back(task);
+    }
+
+    /// Takes a task for `worker` from the most urgent band that has one,
+    /// preferring `worker`'s own deque, or returns `None` if every deque
+    /// appears empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `worker` is out of range.
+    pub fn pop(&self, worker: usize) -> Option<T> {
+        let num_workers = self.num_workers();
+        assert!(
+            worker < num_workers,
+            "worker (is {}) should be less than the number of workers (is {})",
+            worker,
+            num_workers
+        );
+        (0..self.num_bands()).find_map(|band| {
+            if self.band_lens[band].load(Ordering::SeqCst) == 0 {
+                return None;
+            }
+            // Steal from the other workers in turn, starting after ourselves,
+            // so that thieves spread over the victims.
+            let task = self.workers[worker][band].pop_back().or_else(|| {
+                (1..num_workers)
+                    .map(|i| &self.workers[(worker + i) % num_workers][band])
+                    .find_map(TwoLockDeque::pop_front)
+            })?;
+            self.band_lens[band].fetch_sub(1, Ordering::SeqCst);
+            Some(task)
+        })
+    }
+
+    /// Returns the number of tasks in band `band` at the time of the call.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `band` is out of range.
+    pub fn band_len(&self, band: usize) -> usize {
+        self.band_lens[band].load(Ordering::SeqCst)
+    }
+
+    /// Returns the number of tasks in the queue at the time of the call.
+    pub fn len(&self) -> usize {
+        (0..self.num_bands()).map(|band| self.band_len(band)).sum()
+    }
+
+    /// Checks whether the queue is empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> fmt::Debug for PriorityWorkQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PriorityWorkQueue")
+            .field("num_workers", &self.num_workers())
+            .field("num_bands", &self.num_bands())
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+
+    #[test]
+    fn priority_work_queue() {
+        let queue = PriorityWorkQueue::new(3, 2);
+        for i in 0..3 {
+            queue.push(0, 1, i);
+        }
+        queue.push(2, 0, 10);
+        assert_eq!((queue.band_len(0), queue.len()), (1, 4));
+        assert_eq!(
+            format!("{:?}", queue),
+            "PriorityWorkQueue { num_workers: 3, num_bands: 2, len: 4, .. }"
+        );
+        // the urgent band comes first, even from another worker's deque
+        assert_eq!(queue.pop(0), Some(10));
+        // owners take their latest task, and thieves the oldest
+        assert_eq!(queue.pop(0), Some(2));
+        assert_eq!(queue.pop(1), Some(0));
+        assert_eq!(queue.pop(2), Some(1));
+        assert_eq!(queue.pop(1), None);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn priority_work_queue_concurrent() {
+        let num_workers = 4;
+        let num_bands = 3;
+        let num_tasks = 5_000;
+        let queue = PriorityWorkQueue::new(num_workers, num_bands);
+        let done = AtomicBool::new(false);
+
+        let mut popped: Vec<_> = std::thread::scope(|s| {
+            // half the workers produce tasks, and all of them run tasks
+            let producers: Vec<_> = (0..num_workers / 2)
+                .map(|w| {
+                    let queue = &queue;
+                    s.spawn(move || {
+                        let mut popped = Vec::new();
+                        for i in 0..num_tasks {
+                            queue.push(w, i % num_bands, w * num_tasks + i);
+                            if i % 2 == 0 {
+                                popped.extend(queue.pop(w));
+                            }
+                        }
+                        popped
+                    })
+                })
+                .collect();
+            let thieves: Vec<_> = (num_workers / 2..num_workers)
+                .map(|w| {
+                    let (queue, done) = (&queue, &done);
+                    s.spawn(move || {
+                        let mut popped = Vec::new();
+                        while !done.load(Ordering::Relaxed) {
+                            popped.extend(queue.pop(w));
+                        }
+                        popped.extend(std::iter::from_fn(|| queue.pop(w)));
+                        popped
+                    })
+                })
+                .collect();
+            let mut popped: Vec<_> = producers
+                .into_iter()
+                .flat_map(|h| h.join().unwrap())
+                .collect();
+            done.store(true, Ordering::Relaxed);
+            popped.extend(thieves.into_iter().flat_map(|h| h.join().unwrap()));
+            popped
+        });
+        popped.sort_unstable();
+        assert!(popped.into_iter().eq(0..num_workers / 2 * num_tasks));
+        assert!(queue.is_empty());
+    }
+}
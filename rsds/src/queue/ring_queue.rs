@@ -0,0 +1,216 @@
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crossbeam::utils::CachePadded;
+
+use super::Queue;
+use crate::sync::Backoff;
+
+struct Slot<T> {
+    // The position this slot is next ready for. A slot at index `i` is ready
+    // to be written by the push at position `pos` when `seq == pos`, and ready
+    // to be read by the pop at position `pos` when `seq == pos + 1`.
+    seq: AtomicUsize,
+    elem: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A bounded, allocation-free queue backed by a ring buffer, implemented with
+/// Dmitry Vyukov's sequence-numbered slots.
+///
+/// Producers and consumers each claim a position by advancing their own
+/// cache-padded index with compare-and-swap, and then hand the slot over to
+/// the other side by bumping its sequence number. No memory is allocated after
+/// construction.
+///
+/// Through the [`Queue`] trait, `push` spins until there is room and `pop`
+/// returns `None` when the queue is empty. [`try_push`](RingQueue::try_push)
+/// hands the element back instead of waiting when the queue is full.
+pub struct RingQueue<T> {
+    buffer: Box<[Slot<T>]>,
+    mask: usize,
+    enqueue_pos: CachePadded<AtomicUsize>,
+    dequeue_pos: CachePadded<AtomicUsize>,
+}
+
+// SAFETY: a slot's element is only accessed by the single thread that claimed
+// its position, and elements are moved between threads only through push/pop.
+unsafe impl<T: Send> Send for RingQueue<T> {}
+unsafe impl<T: Send> Sync for RingQueue<T> {}
+
+impl<T> RingQueue<T> {
+    /// Creates a new, empty [`RingQueue`] holding at most `capacity` elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is not a power of two, or is less than 2.
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(
+            capacity >= 2 && capacity.is_power_of_two(),
+            "capacity (is {}) should be a power of two >= 2",
+            capacity
+        );
+
+        let buffer = (0..capacity)
+            .map(|i| Slot {
+                seq: AtomicUsize::new(i),
+                elem: UnsafeCell::new(MaybeUninit::uninit()),
+            })
+            .collect();
+        Self {
+            buffer,
+            mask: capacity - 1,
+            enqueue_pos: CachePadded::new(AtomicUsize::new(0)),
+            dequeue_pos: CachePadded::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Attempts to append an element to the back of the queue, handing it
+    /// back if the queue is full.
+    pub fn try_push(&self, elem: T) -> Result<(), T> {
+        let mut pos = self.enqueue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.buffer[pos & self.mask];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let diff = (seq as isize).wrapping_sub(pos as isize);
+
+            if diff == 0 {
+                match self.enqueue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: claiming `pos` gives us exclusive access to
+                        // the slot until its sequence number is bumped.
+                        unsafe { (*slot.elem.get()).write(elem) };
+                        // The release store publishes the element to the pop
+                        // that claims this position.
+                        slot.seq.store(pos.wrapping_add(1), Ordering::Release);
+                        return Ok(());
+                    }
+                    Err(curr) => pos = curr,
+                }
+            } else if diff < 0 {
+                // The slot still holds the element pushed one lap ago.
+                return Err(elem);
+            } else {
+                // Another producer claimed this position; catch up.
+                pos = self.enqueue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Attempts to remove an element from the front of the queue, returning
+    /// `None` if the queue is empty.
+    pub fn try_pop(&self) -> Option<T> {
+        let mut pos = self.dequeue_pos.load(Ordering::Relaxed);
+        loop {
+            let slot = &self.buffer[pos & self.mask];
+            let seq = slot.seq.load(Ordering::Acquire);
+            let diff = (seq as isize).wrapping_sub(pos.wrapping_add(1) as isize);
+
+            if diff == 0 {
+                match self.dequeue_pos.compare_exchange_weak(
+                    pos,
+                    pos.wrapping_add(1),
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => {
+                        // SAFETY: claiming `pos` gives us exclusive access to
+                        // the slot, whose element was initialized by the push
+                        // at the same position.
+                        let elem = unsafe { (*slot.elem.get()).assume_init_read() };
+                        // Hand the slot to the push one lap ahead.
+                        slot.seq
+                            .store(pos.wrapping_add(self.mask + 1), Ordering::Release);
+                        return Some(elem);
+                    }
+                    Err(curr) => pos = curr,
+                }
+            } else if diff < 0 {
+                // The push for this position has not completed yet.
+                return None;
+            } else {
+                // Another consumer claimed this position; catch up.
+                pos = self.dequeue_pos.load(Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns the maximum number of elements the queue can hold.
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns the number of elements in the queue at the time of the call.
+    ///
+    /// This is only a snapshot when other threads are pushing or popping.
+    pub fn len(&self) -> usize {
+        let tail = self.enqueue_pos.load(Ordering::Relaxed);
+        let head = self.dequeue_pos.load(Ordering::Relaxed);
+        tail.wrapping_sub(head).min(self.capacity())
+    }
+
+    /// Checks whether the queue is empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Queue for RingQueue<T> {
+    type Elem = T;
+
+    fn push(&self, mut elem: T) {
+        let backoff = Backoff::new();
+        while let Err(rejected) = self.try_push(elem) {
+            elem = rejected;
+            backoff.snooze();
+        }
+    }
+
+    fn pop(&self) -> Option<T> {
+        self.try_pop()
+    }
+}
+
+impl<T> Drop for RingQueue<T> {
+    fn drop(&mut self) {
+        while self.try_pop().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring_queue() {
+        let queue = RingQueue::with_capacity(4);
+        assert!(queue.is_empty());
+        assert_eq!(queue.try_pop(), None);
+
+        for lap in 0..3 {
+            for i in 0..4 {
+                assert!(queue.try_push((lap, i)).is_ok());
+            }
+            assert_eq!(queue.len(), 4);
+            assert_eq!(queue.try_push((lap, 4)), Err((lap, 4)));
+            for i in 0..4 {
+                assert_eq!(queue.try_pop(), Some((lap, i)));
+            }
+        }
+
+        queue.push((3, 0));
+        assert_eq!(queue.len(), 1);
+        // the remaining element is freed when the queue is dropped
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_ring_queue_capacity() {
+        let _ = RingQueue::<usize>::with_capacity(3);
+    }
+}
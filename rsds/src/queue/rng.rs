@@ -0,0 +1,28 @@
+//! A small, fast, thread-local random number generator for randomized
+//! structures. It is not suitable for anything security-sensitive.
+
+use std::cell::Cell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+thread_local! {
+    static RNG: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+}
+
+/// Returns the next pseudo-random number of the calling thread's xorshift64
+/// generator.
+pub(super) fn next_u64() -> u64 {
+    RNG.with(|rng| {
+        let mut x = rng.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        rng.set(x);
+        x
+    })
+}
+
+/// Returns a pseudo-random index in `[0, bound)`.
+pub(super) fn next_index(bound: usize) -> usize {
+    (next_u64() % bound as u64) as usize
+}
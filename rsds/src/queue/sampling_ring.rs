@@ -0,0 +1,233 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{self, AtomicU64, Ordering};
+
+use crossbeam::utils::CachePadded;
+
+use crate::sync::Backoff;
+
+struct Slot<T> {
+    /// Zero until the slot is first written, and otherwise `2 * ticket + 1`
+    /// while the sample with the given ticket is being written, and
+    /// `2 * ticket + 2` once it is.
+    stamp: AtomicU64,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+/// A fixed-size ring of the most recent samples recorded by any number of
+/// threads, for always-on telemetry such as latency sampling.
+///
+/// Recording a sample claims the next slot with a single atomic increment,
+/// overwriting the oldest sample once the ring is full, and never waits for
+/// readers. [`SamplingRing::snapshot`] copies out the samples in the ring
+/// without stopping producers: as with a [`SeqLock`], each slot is stamped
+/// before and after it is written, and samples that are overwritten while
+/// being copied are left out of the snapshot.
+///
+/// A producer only waits if a producer that claimed the same slot a full
+/// ring earlier is still writing it, and drops its sample if one that
+/// claimed it a ring later already has.
+///
+/// ```
+/// use rsds::queue::SamplingRing;
+///
+/// let ring = SamplingRing::with_capacity(4);
+/// std::thread::scope(|s| {
+///     s.spawn(|| (0..5).for_each(|i| ring.record(i)));
+///     s.spawn(|| assert!(ring.snapshot().len() <= 4));
+/// });
+/// assert_eq!(ring.snapshot(), [1, 2, 3, 4]);
+/// assert_eq!(ring.recorded(), 5);
+/// ```
+///
+/// [`SeqLock`]: crate::sync::SeqLock
+pub struct SamplingRing<T> {
+    slots: Box<[Slot<T>]>,
+    /// The ticket of the next sample, which is also the number of samples
+    /// recorded so far.
+    next: CachePadded<AtomicU64>,
+}
+
+// SAFETY: samples are copied in and out by whichever threads record and
+// snapshot them.
+unsafe impl<T: Copy + Send> Send for SamplingRing<T> {}
+unsafe impl<T: Copy + Send> Sync for SamplingRing<T> {}
+
+impl<T> SamplingRing<T>
+where
+    T: Copy,
+{
+    /// Creates a new, empty [`SamplingRing`] keeping the last `capacity`
+    /// samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "capacity (is {}) should be positive",
+            capacity
+        );
+        Self {
+            slots: (0..capacity)
+                .map(|_| Slot {
+                    stamp: AtomicU64::new(0),
+                    value: UnsafeCell::new(MaybeUninit::uninit()),
+                })
+                .collect(),
+            next: CachePadded::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Records `sample`, overwriting the oldest sample if the ring is full.
+    pub fn record(&self, sample: T) {
+        let ticket = self.next.fetch_add(1, Ordering::Relaxed);
+        let slot = &self.slots[(ticket % self.capacity() as u64) as usize];
+        let backoff = Backoff::new();
+        loop {
+            let stamp = slot.stamp.load(Ordering::Relaxed);
+            if stamp > 2 * ticket {
+                // a later sample got here first, and ours is already stale
+                return;
+            }
+            if stamp & 1 == 0
+                && slot
+                    .stamp
+                    .compare_exchange_weak(
+                        stamp,
+                        2 * ticket + 1,
+                        Ordering::Acquire,
+                        Ordering::Relaxed,
+                    )
+                    .is_ok()
+            {
+                break;
+            }
+            backoff.snooze();
+        }
+        // Order the odd stamp before our write to the value, so that a reader
+        // seeing the write sees the slot being written on re-check.
+        atomic::fence(Ordering::Release);
+        // SAFETY: the odd stamp excludes other producers, and readers discard
+        // whatever they copy in the meantime.
+        unsafe { (*slot.value.get()).write(sample) };
+        slot.stamp.store(2 * ticket + 2, Ordering::Release);
+    }
+
+    /// Returns the samples in the ring, oldest first.
+    ///
+    /// Samples that are still being recorded, or that are overwritten while
+    /// being copied, are left out, so the snapshot may hold fewer samples
+    /// than the ring while producers are active.
+    pub fn snapshot(&self) -> Vec<T> {
+        let end = self.next.load(Ordering::Acquire);
+        let start = end.saturating_sub(self.capacity() as u64);
+        (start..end)
+            .filter_map(|ticket| {
+                let slot = &self.slots[(ticket % self.capacity() as u64) as usize];
+                let stamp = slot.stamp.load(Ordering::Acquire);
+                if stamp != 2 * ticket + 2 {
+                    return None;
+                }
+                // SAFETY: the copy may race with a producer and be torn, so it
+                // stays possibly uninitialized bytes, and is thrown away below
+                // unless the stamp is unchanged.
+                let value = unsafe { ptr::read_volatile(slot.value.get()) };
+                // Order the copy before re-checking the stamp.
+                atomic::fence(Ordering::Acquire);
+                if slot.stamp.load(Ordering::Relaxed) != stamp {
+                    return None;
+                }
+                // SAFETY: the stamp shows that the sample was fully written,
+                // and that no producer touched it while it was copied.
+                Some(unsafe { value.assume_init() })
+            })
+            .collect()
+    }
+
+    /// Returns the number of samples recorded so far, including those that
+    /// have since been overwritten.
+    pub fn recorded(&self) -> u64 {
+        self.next.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of samples the ring keeps.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+}
+
+impl<T> fmt::Debug for SamplingRing<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SamplingRing")
+            .field("capacity", &self.slots.len())
+            .field("recorded", &self.next.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+
+    #[test]
+    fn sampling_ring() {
+        let ring = SamplingRing::with_capacity(4);
+        assert!(ring.snapshot().is_empty());
+        ring.record(0);
+        ring.record(1);
+        assert_eq!(ring.snapshot(), [0, 1]);
+        (2..10).for_each(|i| ring.record(i));
+        assert_eq!(ring.snapshot(), [6, 7, 8, 9]);
+        assert_eq!(
+            format!("{:?}", ring),
+            "SamplingRing { capacity: 4, recorded: 10, .. }"
+        );
+    }
+
+    #[test]
+    fn sampling_ring_concurrent() {
+        let num_thrs = 4;
+        let num_samples = 20_000u64;
+        let ring = SamplingRing::with_capacity(16);
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|s| {
+            let producers: Vec<_> = (0..num_thrs)
+                .map(|t| {
+                    let ring = &ring;
+                    s.spawn(move || {
+                        for i in 0..num_samples {
+                            // a sample large enough to be torn if copied
+                            // while being written
+                            ring.record([t, i, t + i, t * i]);
+                        }
+                    })
+                })
+                .collect();
+            s.spawn(|| {
+                while !done.load(Ordering::Relaxed) {
+                    let mut last = vec![None; num_thrs as usize];
+                    for [t, i, sum, product] in ring.snapshot() {
+                        assert_eq!((sum, product), (t + i, t * i));
+                        // a producer's samples come out in order
+                        assert!(last[t as usize] < Some(i));
+                        last[t as usize] = Some(i);
+                    }
+                }
+            });
+            for producer in producers {
+                producer.join().unwrap();
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+
+        assert_eq!(ring.recorded(), num_thrs * num_samples);
+        assert_eq!(ring.snapshot().len(), 16);
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard};
+
+use crossbeam::utils::CachePadded;
+
+use super::Queue;
+
+/// An unbounded double-ended queue implemented with two locks, one guarding
+/// each half.
+///
+/// The deque is the front half followed by the back half. Operations on an end
+/// only lock that end's half, as long as it is not empty. When it is, the
+/// operation locks both halves and moves half of the elements over from the
+/// other side, so that a run of operations draining one end does not keep
+/// taking both locks.
+///
+/// Any thread may operate on either end. Both locks are always taken front
+/// first, so operations on opposite ends never deadlock.
+pub struct TwoLockDeque<T> {
+    front: CachePadded<Mutex<VecDeque<T>>>,
+    back: CachePadded<Mutex<VecDeque<T>>>,
+}
+
+impl<T> Default for TwoLockDeque<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TwoLockDeque<T> {
+    /// Creates a new, empty [`TwoLockDeque`].
+    pub fn new() -> Self {
+        Self {
+            front: CachePadded::new(Mutex::new(VecDeque::new())),
+            back: CachePadded::new(Mutex::new(VecDeque::new())),
+        }
+    }
+
+    /// Prepends an element to the front of the deque.
+    pub fn push_front(&self, elem: T) {
+        self.front.lock().unwrap().push_front(elem)
+    }
+
+    /// Appends an element to the back of the deque.
+    pub fn push_back(&self, elem: T) {
+        self.back.lock().unwrap().push_back(elem)
+    }
+
+    /// Removes the first element and returns it, or `None` if the deque is
+    /// empty.
+    pub fn pop_front(&self) -> Option<T> {
+        let mut front = self.front.lock().unwrap();
+        if let Some(elem) = front.pop_front() {
+            return Some(elem);
+        }
+
+        let mut back = self.back.lock().unwrap();
+        // Take over the first half of the back, which comes right after the
+        // (empty) front.
+        let take = back.len().div_ceil(2);
+        front.extend(back.drain(..take));
+        front.pop_front()
+    }
+
+    /// Removes the last element and returns it, or `None` if the deque is
+    /// empty.
+    pub fn pop_back(&self) -> Option<T> {
+        if let Some(elem) = self.back.lock().unwrap().pop_back() {
+            return Some(elem);
+        }
+
+        // Both locks are taken front first, so the back lock has to be
+        // released and taken again.
+        let (mut front, mut back) = self.lock_both();
+        if let Some(elem) = back.pop_back() {
+            return Some(elem);
+        }
+        // Take over the second half of the front, which comes right before
+        // the (empty) back.
+        let keep = front.len() / 2;
+        back.extend(front.drain(keep..));
+        back.pop_back()
+    }
+
+    /// Returns the number of elements in the deque at the time of the call.
+    pub fn len(&self) -> usize {
+        let (front, back) = self.lock_both();
+        front.len() + back.len()
+    }
+
+    /// Checks whether the deque is empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock_both(&self) -> (MutexGuard<'_, VecDeque<T>>, MutexGuard<'_, VecDeque<T>>) {
+        let front = self.front.lock().unwrap();
+        let back = self.back.lock().unwrap();
+        (front, back)
+    }
+}
+
+impl<T> Queue for TwoLockDeque<T> {
+    type Elem = T;
+
+    fn push(&self, elem: T) {
+        self.push_back(elem)
+    }
+
+    fn pop(&self) -> Option<T> {
+        self.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn two_lock_deque() {
+        let deque = TwoLockDeque::new();
+        assert_eq!(deque.pop_front(), None);
+        assert_eq!(deque.pop_back(), None);
+
+        for i in 0..5 {
+            deque.push_back(i);
+            deque.push_front(10 + i);
+        }
+        assert_eq!(deque.len(), 10);
+        // 14 13 12 11 10 0 1 2 3 4
+        assert_eq!(deque.pop_back(), Some(4));
+        assert_eq!(deque.pop_front(), Some(14));
+        for i in (0..4).rev() {
+            assert_eq!(deque.pop_back(), Some(i));
+        }
+        // drains into the front half
+        assert_eq!(deque.pop_back(), Some(10));
+        for i in (11..14).rev() {
+            assert_eq!(deque.pop_front(), Some(i));
+        }
+        assert!(deque.is_empty());
+
+        for i in 0..10 {
+            deque.push_back(i);
+        }
+        for i in 0..10 {
+            assert_eq!(deque.pop_front(), Some(i));
+        }
+        assert!(deque.is_empty());
+    }
+
+    #[test]
+    fn two_lock_deque_both_ends() {
+        let num_thrs = 8;
+        let num_elems = 10_000;
+        let deque = Arc::new(TwoLockDeque::new());
+
+        let handles: Vec<_> = (0..num_thrs)
+            .map(|t| {
+                let deque = deque.clone();
+                std::thread::spawn(move || {
+                    let mut popped = Vec::new();
+                    for i in 0..num_elems {
+                        let elem = t * num_elems + i;
+                        if (t + i) % 2 == 0 {
+                            deque.push_front(elem);
+                        } else {
+                            deque.push_back(elem);
+                        }
+                        if i % 3 == 0 {
+                            popped.extend(deque.pop_front());
+                        } else if i % 3 == 1 {
+                            popped.extend(deque.pop_back());
+                        }
+                    }
+                    popped
+                })
+            })
+            .collect();
+
+        let mut popped: Vec<_> = handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap())
+            .collect();
+        popped.extend(std::iter::from_fn(|| deque.pop_back()));
+        popped.sort_unstable();
+        assert!(popped.into_iter().eq(0..num_thrs * num_elems));
+    }
+}
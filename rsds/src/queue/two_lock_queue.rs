@@ -0,0 +1,157 @@
+//! A two-lock queue, after Michael and Scott.
+//!
+//! The queue keeps its own nodes rather than building on the list set's
+//! `ListInner`. That list keeps its head, tail and length together and
+//! changes them only through `&mut self`, so it can sit behind one lock but
+//! not be split between two. Its nodes also own their successors: a push
+//! rewrites the last node in place to link the new one, while popping the
+//! last element moves that same node out. Splitting the two ends needs a
+//! sentinel node and atomic `next` links, so that a push and a pop never
+//! touch the same node, and `ListInner` has neither.
+
+use std::mem::MaybeUninit;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Mutex;
+
+use crossbeam::utils::CachePadded;
+
+use super::Queue;
+use crate::reclaim::free_list::FreeList;
+
+struct Node<T> {
+    // Uninitialized for the sentinel node, whose element has either never
+    // existed or has already been dequeued.
+    elem: MaybeUninit<T>,
+    next: AtomicPtr<Node<T>>,
+}
+
+impl<T> Node<T> {
+    fn new(nodes: &FreeList<Self>, elem: MaybeUninit<T>) -> *mut Self {
+        nodes
+            .alloc(Node {
+                elem,
+                next: AtomicPtr::new(ptr::null_mut()),
+            })
+            .as_ptr()
+    }
+}
+
+/// An unbounded queue implemented with two locks, one guarding each end.
+///
+/// `push` only contends on the tail lock and `pop` only contends on the head
+/// lock, so a producer and a consumer never block each other. The head always
+/// points at a sentinel node, which keeps the two ends from ever touching the
+/// same node's links at the same time.
+///
+/// Dequeued nodes are recycled through a [`FreeList`], so a queue whose length
+/// stays bounded stops allocating once it has warmed up.
+pub struct TwoLockQueue<T> {
+    head: CachePadded<Mutex<*mut Node<T>>>,
+    tail: CachePadded<Mutex<*mut Node<T>>>,
+    nodes: FreeList<Node<T>>,
+}
+
+// SAFETY: the raw pointers are only dereferenced while holding the lock that
+// guards them, and elements are moved between threads only through push/pop.
+unsafe impl<T: Send> Send for TwoLockQueue<T> {}
+unsafe impl<T: Send> Sync for TwoLockQueue<T> {}
+
+impl<T> Default for TwoLockQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> TwoLockQueue<T> {
+    /// Creates a new, empty [`TwoLockQueue`].
+    pub fn new() -> Self {
+        let nodes = FreeList::new();
+        let sentinel = Node::new(&nodes, MaybeUninit::uninit());
+        Self {
+            head: CachePadded::new(Mutex::new(sentinel)),
+            tail: CachePadded::new(Mutex::new(sentinel)),
+            nodes,
+        }
+    }
+
+    /// Checks whether the queue is empty at the time of the call.
+    pub fn is_empty(&self) -> bool {
+        let head = self.head.lock().unwrap();
+        // SAFETY: the sentinel is only freed by a dequeuer holding the head lock.
+        unsafe { (**head).next.load(Ordering::Acquire).is_null() }
+    }
+}
+
+impl<T> Queue for TwoLockQueue<T> {
+    type Elem = T;
+
+    fn push(&self, elem: T) {
+        let node = Node::new(&self.nodes, MaybeUninit::new(elem));
+        let mut tail = self.tail.lock().unwrap();
+        // SAFETY: the tail node is never freed while it is the tail, since the
+        // sentinel always precedes it.
+        //
+        // The release store pairs with the acquire load in `pop`, publishing
+        // the node's element to the dequeuer.
+        unsafe { (**tail).next.store(node, Ordering::Release) };
+        *tail = node;
+    }
+
+    fn pop(&self) -> Option<T> {
+        let mut head = self.head.lock().unwrap();
+        let sentinel = *head;
+        // SAFETY: only dequeuers, which hold the head lock, free the sentinel.
+        let next = unsafe { (*sentinel).next.load(Ordering::Acquire) };
+        if next.is_null() {
+            return None;
+        }
+
+        // `next` becomes the new sentinel. Its element is moved out here and
+        // is never read again.
+        //
+        // SAFETY: `next` was fully initialized before being published by
+        // `push`.
+        let elem = unsafe { (*next).elem.assume_init_read() };
+        *head = next;
+        drop(head);
+
+        // SAFETY: the old sentinel is unreachable from both ends of the queue.
+        unsafe { self.nodes.recycle(NonNull::new_unchecked(sentinel)) };
+        Some(elem)
+    }
+}
+
+impl<T> Drop for TwoLockQueue<T> {
+    fn drop(&mut self) {
+        // the nodes themselves are freed along with the free list
+        while self.pop().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_two_lock_queue() {
+        let queue = TwoLockQueue::new();
+        assert!(queue.is_empty());
+        assert_eq!(queue.pop(), None);
+
+        for i in 0..10 {
+            queue.push(i.to_string());
+        }
+        assert!(!queue.is_empty());
+        for i in 0..5 {
+            assert_eq!(queue.pop(), Some(i.to_string()));
+        }
+        // dequeued nodes are reused for later elements
+        for i in 0..100 {
+            queue.push(i.to_string());
+            queue.pop();
+        }
+        assert_eq!(queue.nodes.capacity(), 11);
+        // the remaining elements are freed when the queue is dropped
+    }
+}
@@ -0,0 +1,127 @@
+//! Epoch-based memory reclamation, as thin wrappers over `crossbeam-epoch`.
+//!
+//! A thread [`pin`]s itself before it loads shared pointers, and stays pinned
+//! for as long as it uses them. Unlinked nodes are handed to [`retire`] (or
+//! any cleanup to [`defer`]) instead of being freed, and are only freed once
+//! every thread that was pinned at that time has unpinned.
+//!
+//! Compared to [hazard pointers](super::hazard), pinning is much cheaper than
+//! protecting every pointer, but a single thread that stays pinned holds back
+//! reclamation for everyone.
+//!
+//! ```
+//! use std::sync::atomic::{AtomicPtr, Ordering};
+//!
+//! use rsds::reclaim::epoch;
+//!
+//! let shared = AtomicPtr::new(Box::into_raw(Box::new(1)));
+//!
+//! let guard = epoch::pin();
+//! // SAFETY: pointers swapped out of `shared` are only retired, and we are
+//! // pinned, so this one cannot be freed while we read it.
+//! assert_eq!(unsafe { *shared.load(Ordering::Acquire) }, 1);
+//!
+//! let old = shared.swap(Box::into_raw(Box::new(2)), Ordering::AcqRel);
+//! // SAFETY: `old` is unlinked and was allocated with `Box`.
+//! unsafe { epoch::retire(&guard, old) };
+//! drop(guard);
+//! # unsafe { drop(Box::from_raw(shared.load(Ordering::Relaxed))) };
+//! ```
+
+use crossbeam::epoch as cb_epoch;
+
+pub use crossbeam::epoch::Guard;
+
+/// Pins the current thread, returning a guard that keeps it pinned until it
+/// is dropped.
+///
+/// Pinning is reentrant: a thread pinned by several guards stays pinned until
+/// all of them are dropped.
+pub fn pin() -> Guard {
+    cb_epoch::pin()
+}
+
+/// Checks whether the current thread is pinned.
+pub fn is_pinned() -> bool {
+    cb_epoch::is_pinned()
+}
+
+/// Retires a node unlinked from a shared structure, freeing it once no thread
+/// pinned at the time of the call is still pinned.
+///
+/// # Safety
+///
+/// `ptr` must have been allocated with [`Box`], must no longer be reachable
+/// from the shared structure, and must not be retired twice. Dropping the node
+/// must be sound at any later point in time, on any thread.
+pub unsafe fn retire<T>(guard: &Guard, ptr: *mut T) {
+    guard.defer_unchecked(move || drop(Box::from_raw(ptr)));
+}
+
+/// Schedules `f` to run once no thread pinned at the time of the call is
+/// still pinned.
+pub fn defer<F>(guard: &Guard, f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    guard.defer(f)
+}
+
+/// Pushes the current thread's deferred functions to the global queue and
+/// runs any that are ready.
+///
+/// Deferred functions are otherwise only collected every so often as threads
+/// pin themselves, so this is mostly useful for tests and for freeing memory
+/// eagerly after a burst of retirements.
+pub fn collect() {
+    pin().flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+    use super::*;
+
+    struct DropCounter(&'static AtomicUsize);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn epoch_retire() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        let num_swaps = 1_000;
+        let shared = AtomicPtr::new(Box::into_raw(Box::new(DropCounter(&DROPS))));
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..num_swaps {
+                        let guard = pin();
+                        assert!(is_pinned());
+                        let new = Box::into_raw(Box::new(DropCounter(&DROPS)));
+                        let old = shared.swap(new, Ordering::AcqRel);
+                        unsafe { retire(&guard, old) };
+                    }
+                });
+            }
+        });
+        assert!(!is_pinned());
+
+        // reclamation is lazy, and threads of other tests may hold the epoch
+        // back for a while, so give the collector a few rounds
+        for _ in 0..10_000 {
+            if DROPS.load(Ordering::Relaxed) == 4 * num_swaps {
+                break;
+            }
+            collect();
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert_eq!(DROPS.load(Ordering::Relaxed), 4 * num_swaps);
+        unsafe { drop(Box::from_raw(shared.load(Ordering::Relaxed))) };
+    }
+}
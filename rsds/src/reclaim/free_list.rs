@@ -0,0 +1,250 @@
+//! A lock-free free list, recycling fixed-size blocks between the nodes of
+//! a structure instead of returning them to the allocator.
+//!
+//! Blocks live in buckets of doubling sizes, which are only freed along with
+//! the list, so a thread may safely read a block that another thread has just
+//! taken off the list. The list itself is a Treiber stack of block indices,
+//! whose head packs the index of the top block with a tag that every update
+//! bumps, so that a stale compare-and-swap fails even if the same block has
+//! been popped and pushed back in the meantime.
+//!
+//! ```
+//! use rsds::reclaim::free_list::FreeList;
+//!
+//! let list = FreeList::new();
+//! let node = list.alloc(String::from("node"));
+//! // SAFETY: `node` came from `list`, and is not used again.
+//! assert_eq!(unsafe { list.recycle(node) }, "node");
+//! // the block is reused, without touching the allocator
+//! assert_eq!(list.alloc(String::new()), node);
+//! assert_eq!(list.capacity(), 1);
+//! ```
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ptr::NonNull;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+use crossbeam::utils::CachePadded;
+
+use crate::buckets::Buckets;
+
+#[repr(C)]
+struct Block<T> {
+    /// The block's value, first so that a pointer to it points at the block.
+    value: UnsafeCell<MaybeUninit<T>>,
+    /// The index of the next free block plus one, or zero for none.
+    next: AtomicU32,
+    index: u32,
+}
+
+impl<T> Block<T> {
+    fn new(index: usize) -> Self {
+        Self {
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            next: AtomicU32::new(0),
+            index: index as u32,
+        }
+    }
+}
+
+/// A lock-free free list of blocks holding values of type `T`.
+///
+/// See the [module-level documentation](self) for more.
+pub struct FreeList<T> {
+    /// The tag in the high half, and the index of the top block plus one, or
+    /// zero for an empty list, in the low half.
+    head: CachePadded<AtomicU64>,
+    blocks: Buckets<Block<T>>,
+    capacity: AtomicUsize,
+}
+
+// SAFETY: values are moved in and out of blocks by whichever thread allocates
+// or recycles them.
+unsafe impl<T: Send> Send for FreeList<T> {}
+unsafe impl<T: Send> Sync for FreeList<T> {}
+
+impl<T> Default for FreeList<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> FreeList<T> {
+    /// Creates a new, empty [`FreeList`].
+    pub fn new() -> Self {
+        Self {
+            head: CachePadded::new(AtomicU64::new(0)),
+            blocks: Buckets::new(),
+            capacity: AtomicUsize::new(0),
+        }
+    }
+
+    /// Moves `value` into a block, recycling a free block if there is one and
+    /// allocating a new one otherwise, and returns a pointer to it.
+    ///
+    /// The pointer stays valid until it is handed back to
+    /// [`FreeList::recycle`], or the list is dropped. Values still in blocks
+    /// when the list is dropped are leaked.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the list would hold more than `u32::MAX` blocks.
+    pub fn alloc(&self, value: T) -> NonNull<T> {
+        self.alloc_indexed(value).1
+    }
+
+    /// Like [`FreeList::alloc`], but also returns the index of the block,
+    /// which [`FreeList::get`] looks it up by.
+    pub(crate) fn alloc_indexed(&self, value: T) -> (usize, NonNull<T>) {
+        let block = self.pop().unwrap_or_else(|| self.grow());
+        // SAFETY: a block off the list, or a new one, is ours alone.
+        unsafe { (*block.value.get()).write(value) };
+        (block.index as usize, NonNull::from(block).cast())
+    }
+
+    /// Returns a pointer to the block with the given index, or `None` if no
+    /// block with that index or a later one in its bucket has been allocated.
+    ///
+    /// The block's value is only initialized while the block is in use.
+    // Only the slab looks blocks up by index.
+    #[cfg_attr(not(feature = "slab"), allow(dead_code))]
+    pub(crate) fn get(&self, index: usize) -> Option<NonNull<T>> {
+        let block = self.blocks.get(index)?;
+        Some(NonNull::from(block).cast())
+    }
+
+    /// Moves the value out of the block `ptr` points at, and puts the block
+    /// back on the list.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by [`FreeList::alloc`] on this list, and
+    /// not recycled since. It must not be used again afterwards.
+    pub unsafe fn recycle(&self, ptr: NonNull<T>) -> T {
+        let block = &*ptr.cast::<Block<T>>().as_ptr();
+        let value = (*block.value.get()).assume_init_read();
+        self.push(block);
+        value
+    }
+
+    /// Returns the number of blocks allocated, whether free or in use.
+    pub fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    fn block(&self, index: u32) -> &Block<T> {
+        // A block's bucket is installed before its index is handed out.
+        self.blocks.get(index as usize).unwrap()
+    }
+
+    fn pop(&self) -> Option<&Block<T>> {
+        let mut head = self.head.load(Ordering::Acquire);
+        loop {
+            let index = (head as u32).checked_sub(1)?;
+            let block = self.block(index);
+            // The block may be popped and reused by another thread before our
+            // compare-and-swap, making this stale, but the tag will have moved
+            // on by then.
+            let next = block.next.load(Ordering::Relaxed);
+            let tag = (head >> 32) as u32;
+            let new = (tag.wrapping_add(1) as u64) << 32 | next as u64;
+            match self
+                .head
+                .compare_exchange_weak(head, new, Ordering::Acquire, Ordering::Acquire)
+            {
+                Ok(_) => return Some(block),
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    fn push(&self, block: &Block<T>) {
+        let mut head = self.head.load(Ordering::Relaxed);
+        loop {
+            block.next.store(head as u32, Ordering::Relaxed);
+            let tag = (head >> 32) as u32;
+            let new = (tag.wrapping_add(1) as u64) << 32 | (block.index + 1) as u64;
+            // The release pairs with the acquire in `pop`, handing the block
+            // over once its last user is done with it.
+            match self
+                .head
+                .compare_exchange_weak(head, new, Ordering::Release, Ordering::Relaxed)
+            {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    /// Allocates a new block.
+    fn grow(&self) -> &Block<T> {
+        let index = self.capacity.fetch_add(1, Ordering::Relaxed);
+        assert!(
+            index < u32::MAX as usize,
+            "number of blocks (is {}) should fit in a u32",
+            index + 1
+        );
+        self.blocks.get_or_alloc(index, Block::new)
+    }
+}
+
+impl<T> fmt::Debug for FreeList<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FreeList")
+            .field("capacity", &self.capacity())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn free_list() {
+        let list = FreeList::new();
+        let a = list.alloc(1);
+        let b = list.alloc(2);
+        let c = list.alloc(3);
+        assert_eq!(list.capacity(), 3);
+        unsafe {
+            assert_eq!(*b.as_ref(), 2);
+            assert_eq!(list.recycle(a), 1);
+            assert_eq!(list.recycle(c), 3);
+        }
+        // freed blocks come back last in, first out
+        assert_eq!(list.alloc(4), c);
+        assert_eq!(list.alloc(5), a);
+        assert_ne!(list.alloc(6), b);
+        assert_eq!(list.capacity(), 4);
+    }
+
+    #[test]
+    fn free_list_concurrent() {
+        let num_thrs = 8;
+        let live = 4;
+        let list = FreeList::new();
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let list = &list;
+                s.spawn(move || {
+                    let mut blocks = Vec::new();
+                    for i in 0..10_000 {
+                        blocks.push((list.alloc((t, i)), (t, i)));
+                        if blocks.len() == live {
+                            for (block, value) in blocks.drain(..) {
+                                // no one else wrote to our blocks meanwhile
+                                assert_eq!(unsafe { list.recycle(block) }, value);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+        // steady state never needs more blocks than are in use at once
+        assert!(list.capacity() <= num_thrs * live);
+    }
+}
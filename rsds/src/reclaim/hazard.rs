@@ -0,0 +1,330 @@
+//! Hazard-pointer based memory reclamation.
+//!
+//! Before dereferencing a shared pointer, a thread publishes it in a hazard
+//! pointer with [`HazardPointer::protect`]. Unlinked nodes are handed to
+//! [`retire`] instead of being freed, and are only freed by a [`scan`] once no
+//! hazard pointer protects them.
+//!
+//! Retired nodes are kept in a per-thread list, which is scanned automatically
+//! once it grows past a threshold. Nodes still protected when a thread exits
+//! are handed over to the next thread that scans.
+//!
+//! The crate's own lock-free structures reclaim through [`epoch`](super::epoch)
+//! instead, as pinning once per operation is cheaper than protecting every
+//! pointer they traverse. Hazard pointers suit structures built outside the
+//! crate where a stalled thread must not hold back reclamation for everyone,
+//! since it only keeps the few nodes it protects alive.
+//!
+//! ```
+//! use std::sync::atomic::{AtomicPtr, Ordering};
+//!
+//! use rsds::reclaim::hazard::{self, HazardPointer};
+//!
+//! let shared = AtomicPtr::new(Box::into_raw(Box::new(1)));
+//!
+//! let mut hp = HazardPointer::new();
+//! let ptr = hp.protect(&shared);
+//! // SAFETY: `ptr` is protected, so it cannot be freed while we read it.
+//! assert_eq!(unsafe { *ptr }, 1);
+//!
+//! let old = shared.swap(Box::into_raw(Box::new(2)), Ordering::AcqRel);
+//! // SAFETY: `old` is unlinked and was allocated with `Box`.
+//! unsafe { hazard::retire(old) };
+//! hp.reset();
+//! hazard::scan();
+//! # unsafe { drop(Box::from_raw(shared.load(Ordering::Relaxed))) };
+//! ```
+
+use std::cell::RefCell;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicBool, AtomicPtr, Ordering};
+use std::sync::Mutex;
+
+/// Number of nodes a thread retires before it scans them automatically.
+const RETIRE_THRESHOLD: usize = 64;
+
+/// A published hazard pointer. Slots are never freed, and are reused once
+/// released, so the list only grows to the peak number of live hazard
+/// pointers.
+struct Slot {
+    ptr: AtomicPtr<()>,
+    active: AtomicBool,
+    next: *const Slot,
+}
+
+/// Head of the global list of slots.
+static SLOTS: AtomicPtr<Slot> = AtomicPtr::new(ptr::null_mut());
+
+/// Nodes left over by threads that exited while the nodes were protected.
+static ORPHANS: Mutex<Vec<Retired>> = Mutex::new(Vec::new());
+
+/// Iterates over every slot ever allocated.
+fn slots() -> impl Iterator<Item = &'static Slot> {
+    // SAFETY: slots are leaked, so every pointer in the list stays valid.
+    let mut curr = unsafe { SLOTS.load(Ordering::Acquire).as_ref() };
+    std::iter::from_fn(move || {
+        let slot = curr?;
+        // SAFETY: as above.
+        curr = unsafe { slot.next.as_ref() };
+        Some(slot)
+    })
+}
+
+/// A hazard pointer, protecting a single pointer at a time from being freed.
+///
+/// Dropping the hazard pointer releases its slot for reuse.
+pub struct HazardPointer {
+    slot: &'static Slot,
+}
+
+impl Default for HazardPointer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HazardPointer {
+    /// Acquires a new hazard pointer, which protects nothing yet.
+    pub fn new() -> Self {
+        for slot in slots() {
+            if !slot.active.load(Ordering::Relaxed)
+                && slot
+                    .active
+                    .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            {
+                return Self { slot };
+            }
+        }
+
+        let slot = Box::leak(Box::new(Slot {
+            ptr: AtomicPtr::new(ptr::null_mut()),
+            active: AtomicBool::new(true),
+            next: ptr::null(),
+        }));
+        let mut head = SLOTS.load(Ordering::Relaxed);
+        loop {
+            slot.next = head;
+            match SLOTS.compare_exchange_weak(head, slot, Ordering::Release, Ordering::Relaxed) {
+                Ok(_) => return Self { slot },
+                Err(curr) => head = curr,
+            }
+        }
+    }
+
+    /// Loads the pointer stored in `src` and protects it, replacing whatever
+    /// this hazard pointer protected before.
+    ///
+    /// The returned pointer will not be freed by [`scan`] until this hazard
+    /// pointer is reset, protects something else, or is dropped. It is only
+    /// safe to dereference if every thread retires pointers it unlinks from
+    /// `src` through [`retire`].
+    pub fn protect<T>(&mut self, src: &AtomicPtr<T>) -> *mut T {
+        let mut ptr = src.load(Ordering::Relaxed);
+        loop {
+            self.slot.ptr.store(ptr.cast(), Ordering::SeqCst);
+            // The pointer may have been unlinked and scanned before it was
+            // published, so check that it is still current.
+            let curr = src.load(Ordering::SeqCst);
+            if curr == ptr {
+                return ptr;
+            }
+            ptr = curr;
+        }
+    }
+
+    /// Protects a pointer that is already known to be safe to access, e.g.
+    /// because another hazard pointer protects it.
+    pub fn protect_raw<T>(&mut self, ptr: *mut T) {
+        self.slot.ptr.store(ptr.cast(), Ordering::SeqCst);
+    }
+
+    /// Stops protecting the current pointer.
+    pub fn reset(&mut self) {
+        self.slot.ptr.store(ptr::null_mut(), Ordering::Release);
+    }
+}
+
+impl Drop for HazardPointer {
+    fn drop(&mut self) {
+        self.reset();
+        self.slot.active.store(false, Ordering::Release);
+    }
+}
+
+/// A node waiting to be freed.
+struct Retired {
+    ptr: *mut (),
+    deleter: unsafe fn(*mut ()),
+}
+
+// SAFETY: retired nodes are unreachable, so whichever thread frees them has
+// exclusive access.
+unsafe impl Send for Retired {}
+
+/// The calling thread's retired nodes. Nodes still protected when the thread
+/// exits are orphaned.
+struct RetiredList(Vec<Retired>);
+
+impl Drop for RetiredList {
+    fn drop(&mut self) {
+        scan_list(&mut self.0);
+        if !self.0.is_empty() {
+            ORPHANS.lock().unwrap().append(&mut self.0);
+        }
+    }
+}
+
+thread_local! {
+    static RETIRED: RefCell<RetiredList> = const { RefCell::new(RetiredList(Vec::new())) };
+}
+
+/// Retires a node unlinked from a shared structure, freeing it once no hazard
+/// pointer protects it.
+///
+/// # Safety
+///
+/// `ptr` must have been allocated with [`Box`], must no longer be reachable
+/// from the shared structure, and must not be retired twice. Dropping the node
+/// must be sound at any later point in time, on any thread, as it is freed by
+/// whichever thread scans it next, possibly after the retiring thread exits.
+pub unsafe fn retire<T>(ptr: *mut T) {
+    unsafe fn delete<T>(ptr: *mut ()) {
+        drop(Box::from_raw(ptr.cast::<T>()));
+    }
+
+    let node = Retired {
+        ptr: ptr.cast(),
+        deleter: delete::<T>,
+    };
+    let mut node = Some(node);
+    let full = RETIRED.try_with(|retired| {
+        let mut retired = retired.borrow_mut();
+        retired.0.extend(node.take());
+        retired.0.len() >= RETIRE_THRESHOLD
+    });
+    match full {
+        Ok(true) => scan(),
+        Ok(false) => {}
+        // The thread is exiting and its list is gone; leave the node to
+        // another thread.
+        Err(_) => ORPHANS.lock().unwrap().extend(node),
+    }
+}
+
+/// Frees every node retired by the calling thread, or orphaned by an exited
+/// thread, that no hazard pointer protects.
+pub fn scan() {
+    let mut retired = RETIRED.with(|retired| std::mem::take(&mut retired.borrow_mut().0));
+    retired.append(&mut ORPHANS.lock().unwrap());
+    scan_list(&mut retired);
+    // Nodes retired by a deleter while we were scanning were pushed onto the
+    // thread's (then empty) list, so merge them back in.
+    RETIRED.with(|list| list.borrow_mut().0.append(&mut retired));
+}
+
+fn scan_list(retired: &mut Vec<Retired>) {
+    // Pairs with the store in `protect`: a pointer published before this
+    // fence is seen below, and one published after it fails validation
+    // because the node was already unlinked.
+    fence(Ordering::SeqCst);
+    let mut hazards: Vec<*mut ()> = slots()
+        .map(|slot| slot.ptr.load(Ordering::Acquire))
+        .filter(|ptr| !ptr.is_null())
+        .collect();
+    hazards.sort_unstable();
+
+    retired.retain(|node| {
+        if hazards.binary_search(&node.ptr).is_ok() {
+            return true;
+        }
+        // SAFETY: the node is unreachable and no thread protects it, and
+        // `retire`'s contract guarantees `deleter` matches its allocation.
+        unsafe { (node.deleter)(node.ptr) };
+        false
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    use super::*;
+
+    struct DropCounter<'a>(&'a AtomicUsize);
+
+    impl Drop for DropCounter<'_> {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn hazard_protects() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        let shared = AtomicPtr::new(Box::into_raw(Box::new(DropCounter(&DROPS))));
+
+        let mut hp = HazardPointer::new();
+        let ptr = hp.protect(&shared);
+        shared.store(ptr::null_mut(), Ordering::Release);
+        unsafe { retire(ptr) };
+        scan();
+        assert_eq!(DROPS.load(Ordering::Relaxed), 0);
+
+        hp.reset();
+        scan();
+        assert_eq!(DROPS.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn hazard_concurrent() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+        let num_readers = 4;
+        let num_swaps = 10_000;
+        let shared = Arc::new(AtomicPtr::new(Box::into_raw(Box::new((
+            0,
+            DropCounter(&DROPS),
+        )))));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..num_readers)
+            .map(|_| {
+                let shared = shared.clone();
+                let done = done.clone();
+                std::thread::spawn(move || {
+                    let mut hp = HazardPointer::new();
+                    let mut last = 0;
+                    while !done.load(Ordering::Relaxed) {
+                        let ptr = hp.protect(&shared);
+                        // SAFETY: the pointer is protected.
+                        let val = unsafe { (*ptr).0 };
+                        assert!(val >= last);
+                        last = val;
+                    }
+                })
+            })
+            .collect();
+
+        let writer = {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                for i in 1..=num_swaps {
+                    let new = Box::into_raw(Box::new((i, DropCounter(&DROPS))));
+                    let old = shared.swap(new, Ordering::AcqRel);
+                    unsafe { retire(old) };
+                }
+            })
+        };
+
+        writer.join().unwrap();
+        done.store(true, Ordering::Relaxed);
+        for h in readers {
+            h.join().unwrap();
+        }
+        // the writer's leftovers were orphaned when it exited
+        scan();
+        assert_eq!(DROPS.load(Ordering::Relaxed), num_swaps);
+        unsafe { drop(Box::from_raw(shared.load(Ordering::Relaxed))) };
+    }
+}
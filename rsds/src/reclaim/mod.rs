@@ -0,0 +1,11 @@
+//! This module contains memory reclamation schemes for lock-free structures.
+//!
+//! A node unlinked from a lock-free structure cannot be freed right away,
+//! since other threads may have loaded a pointer to it before it was unlinked
+//! and still be reading it. The schemes here defer freeing such nodes until no
+//! thread can be reading them any more. The free list recycles the memory of
+//! such nodes once they are safe to reuse.
+
+pub mod epoch;
+pub mod free_list;
+pub mod hazard;
@@ -0,0 +1,289 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Smallest supported precision.
+const MIN_PRECISION: u32 = 1;
+/// Largest supported precision.
+const MAX_PRECISION: u32 = 14;
+
+/// A high-dynamic-range histogram of `u64` values, such as latencies in
+/// nanoseconds, that many threads can record into at once.
+///
+/// Values are counted in logarithmic buckets, each split linearly into
+/// `2^precision` sub-buckets, so that every value is counted in a bucket at
+/// most `2^-precision` times its size wide, e.g. within 0.1% for a precision
+/// of 10, across the whole range of `u64`. Values below `2^(precision + 1)`
+/// are counted exactly.
+///
+/// Recording a value is an atomic increment of its bucket, plus an atomic
+/// min and max, so recording never blocks. Readers scan the buckets, and
+/// histograms with the same precision can be [merged](HdrHistogram::merge),
+/// e.g. per-thread or per-interval histograms into a total.
+///
+/// ```
+/// use rsds::sketch::HdrHistogram;
+///
+/// let histogram = HdrHistogram::new(9);
+/// std::thread::scope(|s| {
+///     for t in 0..4 {
+///         let histogram = &histogram;
+///         s.spawn(move || (1..=250).for_each(|i| histogram.record(i * 4 - t)));
+///     }
+/// });
+/// assert_eq!(histogram.count(), 1000);
+/// assert_eq!(histogram.percentile(50.0), Some(500));
+/// assert_eq!(histogram.max(), Some(1000));
+/// ```
+pub struct HdrHistogram {
+    counts: Box<[AtomicU64]>,
+    min: AtomicU64,
+    max: AtomicU64,
+    precision: u32,
+}
+
+impl HdrHistogram {
+    /// Creates a new, empty [`HdrHistogram`] whose buckets are split into
+    /// `2^precision` sub-buckets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is not between 1 and 14.
+    pub fn new(precision: u32) -> Self {
+        assert!(
+            (MIN_PRECISION..=MAX_PRECISION).contains(&precision),
+            "precision (is {}) should be between {} and {}",
+            precision,
+            MIN_PRECISION,
+            MAX_PRECISION
+        );
+        let num_buckets = (u64::BITS - precision + 1) << precision;
+        Self {
+            counts: (0..num_buckets).map(|_| AtomicU64::new(0)).collect(),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+            precision,
+        }
+    }
+
+    /// Records `value` once.
+    pub fn record(&self, value: u64) {
+        self.record_n(value, 1);
+    }
+
+    /// Records `value` `n` times.
+    pub fn record_n(&self, value: u64, n: u64) {
+        if n == 0 {
+            return;
+        }
+        self.counts[self.index_of(value)].fetch_add(n, Ordering::Relaxed);
+        self.min.fetch_min(value, Ordering::Relaxed);
+        self.max.fetch_max(value, Ordering::Relaxed);
+    }
+
+    /// Returns the number of values recorded.
+    pub fn count(&self) -> u64 {
+        self.counts
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Returns the smallest value recorded, or `None` if there is none.
+    pub fn min(&self) -> Option<u64> {
+        let min = self.min.load(Ordering::Relaxed);
+        (self.max.load(Ordering::Relaxed) >= min).then_some(min)
+    }
+
+    /// Returns the largest value recorded, or `None` if there is none.
+    pub fn max(&self) -> Option<u64> {
+        let max = self.max.load(Ordering::Relaxed);
+        (max >= self.min.load(Ordering::Relaxed)).then_some(max)
+    }
+
+    /// Returns the value that `percentile` percent of the recorded values are
+    /// at or below, up to the precision of the buckets, or `None` if there
+    /// are no values.
+    ///
+    /// Values recorded concurrently with the call may or may not be taken
+    /// into account.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `percentile` is not between 0 and 100.
+    pub fn percentile(&self, percentile: f64) -> Option<u64> {
+        assert!(
+            (0.0..=100.0).contains(&percentile),
+            "percentile (is {}) should be between 0 and 100",
+            percentile
+        );
+        let counts: Vec<_> = self
+            .counts
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((percentile / 100.0 * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        let index = counts.iter().position(|&count| {
+            seen += count;
+            seen >= rank
+        })?;
+        // Report the bucket's highest value, but no more than was recorded.
+        Some(self.highest_in(index).min(self.max.load(Ordering::Relaxed)))
+    }
+
+    /// Adds the values recorded in `other` to this histogram.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the histograms have different precisions.
+    pub fn merge(&self, other: &HdrHistogram) {
+        assert_eq!(
+            self.precision, other.precision,
+            "precisions of merged histograms should match"
+        );
+        for (count, other) in self.counts.iter().zip(other.counts.iter()) {
+            let other = other.load(Ordering::Relaxed);
+            if other > 0 {
+                count.fetch_add(other, Ordering::Relaxed);
+            }
+        }
+        self.min
+            .fetch_min(other.min.load(Ordering::Relaxed), Ordering::Relaxed);
+        self.max
+            .fetch_max(other.max.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+
+    /// Forgets every recorded value.
+    ///
+    /// Values recorded concurrently with the call may or may not be kept.
+    pub fn clear(&self) {
+        for count in self.counts.iter() {
+            count.store(0, Ordering::Relaxed);
+        }
+        self.min.store(u64::MAX, Ordering::Relaxed);
+        self.max.store(0, Ordering::Relaxed);
+    }
+
+    /// Returns the precision of the histogram.
+    pub fn precision(&self) -> u32 {
+        self.precision
+    }
+
+    /// Returns the index of the sub-bucket counting `value`.
+    ///
+    /// Values below `2^(precision + 1)` are their own index. Above that, each
+    /// power of two gets `2^precision` sub-buckets, indexed by the bits
+    /// following the value's leading one.
+    fn index_of(&self, value: u64) -> usize {
+        let p = self.precision;
+        let exponent = (u64::BITS - 1).saturating_sub(value.leading_zeros());
+        if exponent <= p {
+            return value as usize;
+        }
+        let shift = exponent - p;
+        let block = (shift + 1) as usize;
+        let sub = (value >> shift) as usize & ((1 << p) - 1);
+        block << p | sub
+    }
+
+    /// Returns the largest value counted in the sub-bucket at `index`.
+    fn highest_in(&self, index: usize) -> u64 {
+        let p = self.precision;
+        let block = index >> p;
+        if block <= 1 {
+            return index as u64;
+        }
+        let shift = block as u32 - 1;
+        let sub = (index & ((1 << p) - 1)) as u64;
+        let lowest = ((1 << p) + sub) << shift;
+        lowest + ((1 << shift) - 1)
+    }
+}
+
+impl fmt::Debug for HdrHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HdrHistogram")
+            .field("precision", &self.precision)
+            .field("count", &self.count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hdr_histogram() {
+        let histogram = HdrHistogram::new(3);
+        assert_eq!((histogram.min(), histogram.percentile(50.0)), (None, None));
+        // values below 16 are exact
+        (0..16).for_each(|i| histogram.record(i));
+        assert_eq!(histogram.percentile(0.0), Some(0));
+        assert_eq!(histogram.percentile(50.0), Some(7));
+        assert_eq!(histogram.percentile(100.0), Some(15));
+
+        // larger values share buckets an eighth of their size wide
+        histogram.record_n(1000, 16);
+        assert_eq!(histogram.percentile(75.0), Some(1000));
+        histogram.record(1001);
+        assert_eq!(histogram.percentile(100.0), Some(1001));
+        histogram.record(u64::MAX);
+        assert_eq!(histogram.percentile(100.0), Some(u64::MAX));
+        assert_eq!(histogram.percentile(90.0), Some(1023));
+        assert_eq!(
+            (histogram.min(), histogram.max(), histogram.count()),
+            (Some(0), Some(u64::MAX), 34)
+        );
+        assert_eq!(
+            format!("{:?}", histogram),
+            "HdrHistogram { precision: 3, count: 34 }"
+        );
+
+        histogram.clear();
+        assert_eq!((histogram.max(), histogram.count()), (None, 0));
+    }
+
+    #[test]
+    fn hdr_histogram_precision() {
+        let histogram = HdrHistogram::new(10);
+        let values = (0..64).flat_map(|shift| [1u64 << shift, (1 << shift) + 12_345]);
+        for value in values.chain([999_999, u64::MAX]) {
+            let highest = histogram.highest_in(histogram.index_of(value));
+            assert!(highest >= value);
+            assert!(highest - value <= value >> 10, "{} for {}", highest, value);
+        }
+    }
+
+    #[test]
+    fn hdr_histogram_concurrent() {
+        let num_thrs = 4;
+        let num_values = 25_000;
+        let histograms: Vec<_> = (0..2).map(|_| HdrHistogram::new(7)).collect();
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let histogram = &histograms[t as usize % 2];
+                s.spawn(move || {
+                    for i in 0..num_values {
+                        histogram.record(i * num_thrs + t);
+                    }
+                });
+            }
+        });
+        let total = HdrHistogram::new(7);
+        histograms
+            .iter()
+            .for_each(|histogram| total.merge(histogram));
+        assert_eq!(total.count(), num_thrs * num_values);
+        assert_eq!(total.min(), Some(0));
+        assert_eq!(total.max(), Some(num_thrs * num_values - 1));
+        let median = total.percentile(50.0).unwrap();
+        let expected = num_thrs * num_values / 2;
+        assert!(median.abs_diff(expected) <= expected >> 7);
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{BuildHasher, BuildHasherDefault, Hash};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// Smallest supported precision.
+const MIN_PRECISION: u32 = 4;
+/// Largest supported precision.
+const MAX_PRECISION: u32 = 18;
+
+/// A HyperLogLog cardinality estimator that many threads can feed at once.
+///
+/// The estimator keeps `2^precision` one-byte registers, each holding the
+/// longest run of leading zeros seen among the hashes routed to it. Inserting
+/// is a hash and an atomic max into a single register, so inserts never block
+/// each other. The relative standard error of [`HyperLogLog::estimate`] is
+/// about `1.04 / sqrt(2^precision)`, e.g. 0.8% for a precision of 14.
+///
+/// The default hasher has fixed keys, so estimators with the same precision
+/// can be [merged](HyperLogLog::merge) even if they were fed separately.
+pub struct HyperLogLog<S = BuildHasherDefault<DefaultHasher>> {
+    registers: Box<[AtomicU8]>,
+    precision: u32,
+    state: S,
+}
+
+impl HyperLogLog {
+    /// Creates a new, empty [`HyperLogLog`] with `2^precision` registers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is not between 4 and 18.
+    pub fn new(precision: u32) -> Self {
+        Self::with_hasher(precision, BuildHasherDefault::default())
+    }
+}
+
+impl<S> HyperLogLog<S>
+where
+    S: BuildHasher,
+{
+    /// Creates a new, empty [`HyperLogLog`] with `2^precision` registers and
+    /// the given hasher.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `precision` is not between 4 and 18.
+    pub fn with_hasher(precision: u32, hasher: S) -> Self {
+        assert!(
+            (MIN_PRECISION..=MAX_PRECISION).contains(&precision),
+            "precision (is {}) should be between {} and {}",
+            precision,
+            MIN_PRECISION,
+            MAX_PRECISION
+        );
+        Self {
+            registers: (0..1 << precision).map(|_| AtomicU8::new(0)).collect(),
+            precision,
+            state: hasher,
+        }
+    }
+
+    /// Records `item` in the estimator.
+    pub fn insert<T>(&self, item: &T)
+    where
+        T: Hash + ?Sized,
+    {
+        let hash = self.state.hash_one(item);
+
+        // The top bits pick the register, and the rest count leading zeros.
+        // The guard bit caps the count for hashes whose rest is all zeros.
+        let index = (hash >> (64 - self.precision)) as usize;
+        let rest = (hash << self.precision) | (1 << (self.precision - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[index].fetch_max(rank, Ordering::Relaxed);
+    }
+
+    /// Returns the estimated number of distinct items inserted so far.
+    ///
+    /// Items inserted concurrently with the call may or may not be counted.
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let (sum, zeros) = self
+            .registers
+            .iter()
+            .map(|register| register.load(Ordering::Relaxed))
+            .fold((0.0, 0), |(sum, zeros), rank| {
+                (
+                    sum + 2f64.powi(-(rank as i32)),
+                    zeros + (rank == 0) as usize,
+                )
+            });
+
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let raw = alpha * m * m / sum;
+        if raw <= 2.5 * m && zeros > 0 {
+            // Small cardinalities are estimated better by linear counting.
+            m * (m / zeros as f64).ln()
+        } else {
+            raw
+        }
+    }
+
+    /// Folds `other` into this estimator, which then estimates the number of
+    /// distinct items inserted into either.
+    ///
+    /// Both estimators must hash items the same way for the result to be
+    /// meaningful.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the estimators have different precisions.
+    pub fn merge<S2>(&self, other: &HyperLogLog<S2>) {
+        assert_eq!(
+            self.precision, other.precision,
+            "precisions of merged estimators should match"
+        );
+        for (register, other) in self.registers.iter().zip(other.registers.iter()) {
+            register.fetch_max(other.load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+    }
+
+    /// Forgets every inserted item.
+    ///
+    /// Items inserted concurrently with the call may or may not be kept.
+    pub fn clear(&self) {
+        for register in self.registers.iter() {
+            register.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the precision of the estimator.
+    pub fn precision(&self) -> u32 {
+        self.precision
+    }
+}
+
+impl<S> fmt::Debug for HyperLogLog<S>
+where
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HyperLogLog")
+            .field("precision", &self.precision)
+            .field("estimate", &self.estimate())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(estimate: f64, actual: usize) {
+        let error = (estimate - actual as f64).abs() / actual as f64;
+        assert!(error < 0.05, "estimate {} of {}", estimate, actual);
+    }
+
+    #[test]
+    fn hyper_log_log() {
+        let hll = HyperLogLog::new(14);
+        assert_eq!(hll.estimate(), 0.0);
+        for i in 0..10 {
+            hll.insert(&i);
+            hll.insert(&i);
+        }
+        assert_eq!(hll.estimate().round(), 10.0);
+        hll.clear();
+        assert_eq!(hll.estimate(), 0.0);
+    }
+
+    #[test]
+    fn hyper_log_log_concurrent() {
+        let hll = HyperLogLog::new(14);
+        let num_items = 100_000;
+
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let hll = &hll;
+                // threads overlap on half of their items
+                s.spawn(move || {
+                    for i in t * num_items / 8..(t + 2) * num_items / 8 {
+                        hll.insert(&i);
+                    }
+                });
+            }
+        });
+        assert_close(hll.estimate(), 5 * num_items / 8);
+    }
+
+    #[test]
+    fn hyper_log_log_merge() {
+        let (a, b) = (HyperLogLog::new(12), HyperLogLog::new(12));
+        for i in 0..50_000 {
+            a.insert(&i);
+            b.insert(&(i + 25_000));
+        }
+        a.merge(&b);
+        assert_close(a.estimate(), 75_000);
+        assert_close(b.estimate(), 50_000);
+    }
+}
@@ -0,0 +1,10 @@
+//! This module contains concurrent summaries of large streams, such as
+//! probabilistic sketches, which trade some accuracy for little memory.
+
+mod hdr_histogram;
+mod hyper_log_log;
+mod top_k;
+
+pub use hdr_histogram::HdrHistogram;
+pub use hyper_log_log::HyperLogLog;
+pub use top_k::TopK;
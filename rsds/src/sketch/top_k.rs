@@ -0,0 +1,251 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Mutex;
+
+use crossbeam::utils::CachePadded;
+
+use crate::reclaim::epoch::{self, Guard};
+use crate::sync::thread_index;
+
+/// A min-heap of the largest items routed to a stripe.
+type Stripe<T> = CachePadded<Mutex<BinaryHeap<Reverse<T>>>>;
+
+/// A tracker of the `k` largest items seen, such as the top scores of a
+/// leaderboard, that many threads can feed at once.
+///
+/// Items are inserted into one of several stripes, each a mutex-protected
+/// min-heap of the `k` largest items routed to it, which the stripes' union
+/// always contains the overall top `k` of.
+///
+/// Once a stripe is full, no item below its smallest one can make the top
+/// `k`, so the largest such minimum is published as a threshold. Most items
+/// of a long stream fall below the threshold, and are rejected by comparing
+/// against it without taking any lock.
+///
+/// ```
+/// use rsds::sketch::TopK;
+///
+/// let top = TopK::new(3);
+/// std::thread::scope(|s| {
+///     for t in 0..4 {
+///         let top = &top;
+///         s.spawn(move || {
+///             for i in 0..100 {
+///                 top.insert(i * 4 + t);
+///             }
+///         });
+///     }
+/// });
+/// assert_eq!(top.top(), [399, 398, 397]);
+/// ```
+pub struct TopK<T> {
+    stripes: Box<[Stripe<T>]>,
+    /// The largest minimum of a full stripe, or null while none is full.
+    threshold: AtomicPtr<T>,
+    k: usize,
+}
+
+// SAFETY: inserting threads share `&T` through the threshold, and old
+// thresholds are dropped on whichever thread collects them.
+unsafe impl<T: Send + Sync> Send for TopK<T> {}
+unsafe impl<T: Send + Sync> Sync for TopK<T> {}
+
+impl<T> TopK<T>
+where
+    T: Ord + Clone,
+{
+    /// Creates a new, empty [`TopK`] keeping the `k` largest items, with a
+    /// stripe per available core.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is zero.
+    pub fn new(k: usize) -> Self {
+        let num_stripes = std::thread::available_parallelism().map_or(8, |n| n.get());
+        Self::with_stripes(k, num_stripes)
+    }
+
+    /// Creates a new, empty [`TopK`] keeping the `k` largest items, with
+    /// `num_stripes` stripes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` or `num_stripes` is zero.
+    pub fn with_stripes(k: usize, num_stripes: usize) -> Self {
+        assert!(k > 0, "k (is {}) should be positive", k);
+        assert!(
+            num_stripes > 0,
+            "number of stripes (is {}) should be positive",
+            num_stripes
+        );
+        Self {
+            stripes: (0..num_stripes)
+                .map(|_| CachePadded::new(Mutex::new(BinaryHeap::with_capacity(k + 1))))
+                .collect(),
+            threshold: AtomicPtr::new(ptr::null_mut()),
+            k,
+        }
+    }
+
+    /// Returns the `k` largest items seen so far, or all of them if there
+    /// are fewer, largest first.
+    ///
+    /// Items inserted concurrently with the call may or may not be included.
+    pub fn top(&self) -> Vec<T> {
+        let mut items: Vec<_> = self
+            .stripes
+            .iter()
+            .flat_map(|stripe| {
+                let heap = stripe.lock().unwrap();
+                heap.iter().map(|item| item.0.clone()).collect::<Vec<_>>()
+            })
+            .collect();
+        items.sort_unstable_by(|a, b| b.cmp(a));
+        items.truncate(self.k);
+        items
+    }
+
+    /// Returns the number of items kept.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+}
+
+impl<T> TopK<T>
+where
+    T: Ord + Clone + Send + 'static,
+{
+    /// Offers `item` to the tracker, returning whether it was kept.
+    ///
+    /// A kept item may still be pushed out of the top `k` by larger items
+    /// later on.
+    pub fn insert(&self, item: T) -> bool {
+        let guard = &epoch::pin();
+        let threshold = self.threshold.load(Ordering::Acquire);
+        // SAFETY: thresholds are only freed once no thread is pinned.
+        if let Some(threshold) = unsafe { threshold.as_ref() } {
+            if item <= *threshold {
+                return false;
+            }
+        }
+
+        let mut heap = self.stripes[thread_index() % self.stripes.len()]
+            .lock()
+            .unwrap();
+        if heap.len() == self.k {
+            let mut min = heap.peek_mut().unwrap();
+            if item <= min.0 {
+                return false;
+            }
+            *min = Reverse(item);
+        } else {
+            heap.push(Reverse(item));
+            if heap.len() < self.k {
+                return true;
+            }
+        }
+        let min = heap.peek().unwrap().0.clone();
+        drop(heap);
+        self.raise_threshold(min, guard);
+        true
+    }
+
+    /// Raises the threshold to `min`, the minimum of a full stripe, unless it
+    /// is already at least that.
+    fn raise_threshold(&self, min: T, guard: &Guard) {
+        let new = Box::into_raw(Box::new(min));
+        // SAFETY: `new` stays ours until the exchange below publishes it.
+        let min = unsafe { &*new };
+        let mut current = self.threshold.load(Ordering::Acquire);
+        loop {
+            // SAFETY: as in `insert`.
+            if matches!(unsafe { current.as_ref() }, Some(current) if current >= min) {
+                // SAFETY: `new` was never published, so we still own it.
+                drop(unsafe { Box::from_raw(new) });
+                return;
+            }
+            match self
+                .threshold
+                .compare_exchange(current, new, Ordering::AcqRel, Ordering::Acquire)
+            {
+                Ok(_) => {
+                    if !current.is_null() {
+                        // SAFETY: the old threshold is unlinked, was allocated
+                        // with `Box`, and `T: Send + 'static` makes dropping it
+                        // later, on any thread, sound.
+                        unsafe { epoch::retire(guard, current) };
+                    }
+                    return;
+                }
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+impl<T> Drop for TopK<T> {
+    fn drop(&mut self) {
+        let threshold = *self.threshold.get_mut();
+        if !threshold.is_null() {
+            // SAFETY: we have exclusive access, so no other thread holds a
+            // reference to the threshold.
+            drop(unsafe { Box::from_raw(threshold) });
+        }
+    }
+}
+
+impl<T> fmt::Debug for TopK<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TopK")
+            .field("k", &self.k)
+            .field("num_stripes", &self.stripes.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn top_k() {
+        let top = TopK::with_stripes(3, 1);
+        assert!(top.top().is_empty());
+        assert!(top.insert("b".to_string()));
+        assert!(top.insert("d".to_string()));
+        assert_eq!(top.top(), ["d", "b"]);
+        assert!(top.insert("a".to_string()));
+        // the stripe is full, so anything up to its minimum is rejected
+        assert!(!top.insert("a".to_string()));
+        assert!(top.insert("c".to_string()));
+        assert!(top.insert("e".to_string()));
+        assert_eq!(top.top(), ["e", "d", "c"]);
+        assert_eq!(format!("{:?}", top), "TopK { k: 3, num_stripes: 1, .. }");
+    }
+
+    #[test]
+    fn top_k_concurrent() {
+        let num_thrs = 4;
+        let num_items = 20_000;
+        let top = TopK::with_stripes(10, 3);
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let top = &top;
+                s.spawn(move || {
+                    // interleave the threads' items, some ascending and some
+                    // descending, so that thresholds keep rising
+                    for i in 0..num_items {
+                        let i = if t % 2 == 0 { i } else { num_items - 1 - i };
+                        top.insert(i * num_thrs + t);
+                    }
+                });
+            }
+        });
+        let expected: Vec<_> = (0..num_thrs * num_items).rev().take(10).collect();
+        assert_eq!(top.top(), expected);
+    }
+}
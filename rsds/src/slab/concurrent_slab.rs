@@ -0,0 +1,384 @@
+use std::fmt;
+use std::ops::Deref;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::buckets::Buckets;
+use crate::reclaim::free_list::FreeList;
+use crate::sync::{thread_index, Backoff};
+
+// A slot's state packs, from the top, a flag set while it holds a value, the
+// slot's generation, bumped every time its value is removed, and the number of
+// references to the value handed out by `get`.
+const OCCUPIED: u64 = 1 << 63;
+const GENERATION_SHIFT: u32 = 32;
+/// The largest generation. A slot whose generation reaches it is retired
+/// rather than reused, so that generations never wrap around.
+const MAX_GENERATION: u32 = (1 << 31) - 1;
+const REFS: u64 = (1 << GENERATION_SHIFT) - 1;
+
+fn generation(state: u64) -> u32 {
+    (state >> GENERATION_SHIFT) as u32 & MAX_GENERATION
+}
+
+/// The slots whose index is congruent to the shard's number, modulo the
+/// number of shards.
+///
+/// A slot's value lives in the block of `values` with the slot's local
+/// index, and its state in `states` at the same index, which outlives the
+/// value so that the generation carries over to the next one.
+struct Shard<T> {
+    values: FreeList<T>,
+    states: Buckets<AtomicU64>,
+    len: AtomicUsize,
+}
+
+impl<T> Shard<T> {
+    fn new() -> Self {
+        Self {
+            values: FreeList::new(),
+            states: Buckets::new(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Moves `value` into a free slot, or a fresh one, returning the slot's
+    /// local index and state.
+    fn claim(&self, value: T) -> (usize, &AtomicU64) {
+        let (index, _) = self.values.alloc_indexed(value);
+        (
+            index,
+            self.states.get_or_alloc(index, |_| AtomicU64::new(0)),
+        )
+    }
+
+    /// Returns the state of the slot at local index `index`, if it has been
+    /// handed out. A slot whose state is still being allocated is not
+    /// occupied yet, so is looked up as missing.
+    fn state(&self, index: usize) -> Option<&AtomicU64> {
+        self.states.get(index)
+    }
+
+    /// Returns a pointer to the value of the slot at local index `index`,
+    /// which must be occupied.
+    fn value(&self, index: usize) -> NonNull<T> {
+        // The slot's block was allocated before it was made occupied.
+        self.values.get(index).unwrap()
+    }
+}
+
+impl<T> Drop for Shard<T> {
+    fn drop(&mut self) {
+        for (index, state) in self.states.iter_mut() {
+            if *state.get_mut() & OCCUPIED != 0 {
+                // SAFETY: we have exclusive access, and occupied slots hold an
+                // initialized value, which the free list would leak.
+                unsafe { ptr::drop_in_place(self.values.get(index).unwrap().as_ptr()) };
+            }
+        }
+    }
+}
+
+/// A slab that many threads can insert into, read from, and remove from at
+/// once, handing out small integer keys that stay valid until removed.
+///
+/// Slots are spread over shards, one per available core by default, and a
+/// thread inserts into a shard of its own, so that inserting threads mostly
+/// do not contend. Each shard keeps its values in a
+/// [`FreeList`](crate::reclaim::free_list::FreeList), whose blocks never move
+/// while the slab is alive and are recycled when removed, so that keys stay
+/// small and dense.
+///
+/// Insertion and lookup are lock-free. Removal waits for the references that
+/// [`ConcurrentSlab::get`] handed out to the removed value to be dropped, so
+/// a thread must not remove a key while holding a reference to its value.
+///
+/// ```
+/// use rsds::slab::ConcurrentSlab;
+///
+/// let slab = ConcurrentSlab::new();
+/// let keys: Vec<_> = std::thread::scope(|s| {
+///     let slab = &slab;
+///     let handles: Vec<_> = (0..4).map(|i| s.spawn(move || slab.insert(i))).collect();
+///     handles.into_iter().map(|h| h.join().unwrap()).collect()
+/// });
+/// assert_eq!(*slab.get(keys[2]).unwrap(), 2);
+/// assert_eq!(slab.remove(keys[2]), Some(2));
+/// assert!(slab.get(keys[2]).is_none());
+/// assert_eq!(slab.len(), 3);
+/// ```
+pub struct ConcurrentSlab<T> {
+    shards: Box<[Shard<T>]>,
+}
+
+/// A reference to a value in a [`ConcurrentSlab`], which keeps the value from
+/// being removed while it is alive.
+#[must_use = "the reference keeps the value from being removed until dropped"]
+pub struct SlabRef<'a, T> {
+    state: &'a AtomicU64,
+    value: NonNull<T>,
+}
+
+// SAFETY: values are shared between threads through `get`, and moved in and
+// out from other threads through `insert` and `remove`.
+unsafe impl<T: Send> Send for ConcurrentSlab<T> {}
+unsafe impl<T: Send + Sync> Sync for ConcurrentSlab<T> {}
+
+// SAFETY: the reference only hands out `&T`.
+unsafe impl<T: Sync> Send for SlabRef<'_, T> {}
+unsafe impl<T: Sync> Sync for SlabRef<'_, T> {}
+
+impl<T> Default for ConcurrentSlab<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ConcurrentSlab<T> {
+    /// Creates a new, empty [`ConcurrentSlab`] with a shard per available
+    /// core.
+    pub fn new() -> Self {
+        let num_shards = std::thread::available_parallelism().map_or(8, |n| n.get());
+        Self::with_shards(num_shards)
+    }
+
+    /// Creates a new, empty [`ConcurrentSlab`] with `num_shards` shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_shards` is zero.
+    pub fn with_shards(num_shards: usize) -> Self {
+        assert!(
+            num_shards > 0,
+            "number of shards (is {}) should be positive",
+            num_shards
+        );
+        Self {
+            shards: (0..num_shards).map(|_| Shard::new()).collect(),
+        }
+    }
+
+    /// Inserts `value`, returning the key it can be looked up and removed by.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a shard would hold more than `u32::MAX` slots.
+    pub fn insert(&self, value: T) -> usize {
+        self.insert_with_generation(value).0
+    }
+
+    /// Inserts `value`, returning its key and the generation of its slot.
+    pub(super) fn insert_with_generation(&self, value: T) -> (usize, u32) {
+        let shard_index = thread_index() % self.shards.len();
+        let shard = &self.shards[shard_index];
+        let (index, state) = shard.claim(value);
+        let generation = generation(state.load(Ordering::Relaxed));
+        state.store(
+            OCCUPIED | (generation as u64) << GENERATION_SHIFT,
+            Ordering::Release,
+        );
+        shard.len.fetch_add(1, Ordering::Relaxed);
+        (index * self.shards.len() + shard_index, generation)
+    }
+
+    /// Returns a reference to the value inserted with `key`, or `None` if
+    /// there is none.
+    pub fn get(&self, key: usize) -> Option<SlabRef<'_, T>> {
+        self.get_with_generation(key, None)
+    }
+
+    /// Returns a reference to the value inserted with `key`, if there is one
+    /// and, if `expected` is given, its slot is of that generation.
+    pub(super) fn get_with_generation(
+        &self,
+        key: usize,
+        expected: Option<u32>,
+    ) -> Option<SlabRef<'_, T>> {
+        let (shard, index) = self.locate(key);
+        let slot_state = shard.state(index)?;
+        let mut state = slot_state.load(Ordering::Relaxed);
+        loop {
+            if state & OCCUPIED == 0 || matches!(expected, Some(g) if g != generation(state)) {
+                return None;
+            }
+            match slot_state.compare_exchange_weak(
+                state,
+                state + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => {
+                    return Some(SlabRef {
+                        state: slot_state,
+                        value: shard.value(index),
+                    })
+                }
+                Err(current) => state = current,
+            }
+        }
+    }
+
+    /// Checks whether the slab holds a value inserted with `key`.
+    pub fn contains(&self, key: usize) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes and returns the value inserted with `key`, or `None` if there
+    /// is none, freeing the key for reuse.
+    ///
+    /// A key can be reused about two billion times, after which its slot is
+    /// retired.
+    ///
+    /// Waits for the references to the value handed out by
+    /// [`ConcurrentSlab::get`] to be dropped.
+    pub fn remove(&self, key: usize) -> Option<T> {
+        self.remove_with_generation(key, None)
+    }
+
+    /// Removes and returns the value inserted with `key`, if there is one
+    /// and, if `expected` is given, its slot is of that generation.
+    pub(super) fn remove_with_generation(&self, key: usize, expected: Option<u32>) -> Option<T> {
+        let (shard, index) = self.locate(key);
+        let slot_state = shard.state(index)?;
+        let mut state = slot_state.load(Ordering::Relaxed);
+        loop {
+            if state & OCCUPIED == 0 || matches!(expected, Some(g) if g != generation(state)) {
+                return None;
+            }
+            match slot_state.compare_exchange_weak(
+                state,
+                state & !OCCUPIED,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => break,
+                Err(current) => state = current,
+            }
+        }
+        // No new references can be taken now, so wait out the existing ones.
+        let backoff = Backoff::new();
+        while state & REFS != 0 {
+            backoff.snooze();
+            state = slot_state.load(Ordering::Acquire);
+        }
+        shard.len.fetch_sub(1, Ordering::Relaxed);
+        let value = shard.value(index);
+        let generation = generation(state);
+        if generation == MAX_GENERATION {
+            // SAFETY: we cleared the occupied flag, so the value is ours
+            // alone. Its block is never recycled.
+            return Some(unsafe { value.as_ptr().read() });
+        }
+        // The next generation is published along with the block when it is
+        // recycled.
+        let next = (generation as u64 + 1) << GENERATION_SHIFT;
+        slot_state.store(next, Ordering::Relaxed);
+        // SAFETY: as above, and the block was allocated from the shard's
+        // free list and is recycled only once.
+        Some(unsafe { shard.values.recycle(value) })
+    }
+
+    /// Returns the number of values in the slab.
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.len.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Checks whether the slab is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the shard `key` is in, and the key's index in it.
+    fn locate(&self, key: usize) -> (&Shard<T>, usize) {
+        let num_shards = self.shards.len();
+        (&self.shards[key % num_shards], key / num_shards)
+    }
+}
+
+impl<T> fmt::Debug for ConcurrentSlab<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConcurrentSlab")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Deref for SlabRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: the slot stays occupied while we hold a reference.
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T> Drop for SlabRef<'_, T> {
+    fn drop(&mut self) {
+        self.state.fetch_sub(1, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_slab() {
+        let slab = ConcurrentSlab::with_shards(1);
+        let a = slab.insert("a");
+        let b = slab.insert("b");
+        assert_eq!((a, b), (0, 1));
+        assert_eq!(*slab.get(b).unwrap(), "b");
+        assert!(slab.get(2).is_none());
+        assert_eq!(slab.remove(a), Some("a"));
+        assert_eq!(slab.remove(a), None);
+        assert!(!slab.contains(a));
+        // the freed key is reused
+        assert_eq!(slab.insert("c"), a);
+        assert_eq!(slab.len(), 2);
+        assert_eq!(format!("{:?}", slab), "ConcurrentSlab { len: 2, .. }");
+    }
+
+    #[test]
+    fn concurrent_slab_concurrent() {
+        let num_thrs = 8;
+        let num_iters = 10_000;
+        let live = 4;
+        let slab = ConcurrentSlab::with_shards(3);
+        let counted = Arc::new(());
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let (slab, counted) = (&slab, &counted);
+                s.spawn(move || {
+                    let mut keys = Vec::new();
+                    for i in 0..num_iters {
+                        keys.push((slab.insert(((t, i), counted.clone())), (t, i)));
+                        if keys.len() == live {
+                            for (key, value) in keys.drain(..) {
+                                // no one else wrote to our keys meanwhile
+                                assert_eq!(slab.get(key).unwrap().0, value);
+                                assert_eq!(slab.remove(key).unwrap().0, value);
+                            }
+                        }
+                        // peek at others' keys, which may come and go
+                        if let Some(entry) = slab.get(i % 64) {
+                            assert!(entry.0 .0 < num_thrs);
+                        }
+                    }
+                });
+            }
+        });
+        assert!(slab.is_empty());
+        // keys are recycled, so they stay small
+        let key = slab.insert(((0, 0), counted.clone()));
+        assert!(key < 3 * num_thrs * live);
+        drop(slab);
+        assert_eq!(Arc::strong_count(&counted), 1);
+    }
+}
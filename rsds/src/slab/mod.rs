@@ -0,0 +1,8 @@
+//! This module contains slabs, which store values under small integer keys
+//! that they hand out themselves.
+
+mod concurrent_slab;
+mod slot_map;
+
+pub use concurrent_slab::{ConcurrentSlab, SlabRef};
+pub use slot_map::{ConcurrentSlotMap, SlotKey};
@@ -0,0 +1,188 @@
+use std::fmt;
+
+use super::{ConcurrentSlab, SlabRef};
+
+/// A key into a [`ConcurrentSlotMap`], pairing a slot's index with the
+/// generation the slot was in when the key was handed out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SlotKey {
+    index: usize,
+    generation: u32,
+}
+
+impl SlotKey {
+    /// Returns the index of the key's slot, which may be shared with keys
+    /// handed out before or after this one.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the generation of the key's slot when the key was handed out.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// A [`ConcurrentSlab`] whose keys carry a generation, so that a key stops
+/// working once its value is removed, even after the slot is reused.
+///
+/// Every slot counts how many times its value has been removed, and a key
+/// only matches the slot in the generation it was handed out in. The
+/// generation is checked in the same atomic operation that takes a reference
+/// to a value or removes it, so a stale key can never reach a value inserted
+/// after its own was removed, however operations interleave.
+///
+/// ```
+/// use rsds::slab::ConcurrentSlotMap;
+///
+/// let map = ConcurrentSlotMap::with_shards(1);
+/// let old = map.insert("old");
+/// assert_eq!(map.remove(old), Some("old"));
+/// let new = map.insert("new");
+/// // the slot is reused, but the old key no longer reaches it
+/// assert_eq!(new.index(), old.index());
+/// assert!(map.get(old).is_none());
+/// assert_eq!(map.remove(old), None);
+/// assert_eq!(*map.get(new).unwrap(), "new");
+/// ```
+pub struct ConcurrentSlotMap<T> {
+    slab: ConcurrentSlab<T>,
+}
+
+impl<T> Default for ConcurrentSlotMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ConcurrentSlotMap<T> {
+    /// Creates a new, empty [`ConcurrentSlotMap`] with a shard per available
+    /// core.
+    pub fn new() -> Self {
+        Self {
+            slab: ConcurrentSlab::new(),
+        }
+    }
+
+    /// Creates a new, empty [`ConcurrentSlotMap`] with `num_shards` shards.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_shards` is zero.
+    pub fn with_shards(num_shards: usize) -> Self {
+        Self {
+            slab: ConcurrentSlab::with_shards(num_shards),
+        }
+    }
+
+    /// Inserts `value`, returning the key it can be looked up and removed by.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a shard would hold more than `u32::MAX` slots.
+    pub fn insert(&self, value: T) -> SlotKey {
+        let (index, generation) = self.slab.insert_with_generation(value);
+        SlotKey { index, generation }
+    }
+
+    /// Returns a reference to the value inserted with `key`, or `None` if it
+    /// has been removed.
+    pub fn get(&self, key: SlotKey) -> Option<SlabRef<'_, T>> {
+        self.slab
+            .get_with_generation(key.index, Some(key.generation))
+    }
+
+    /// Checks whether the value inserted with `key` is still in the map.
+    pub fn contains(&self, key: SlotKey) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Removes and returns the value inserted with `key`, or `None` if it has
+    /// already been removed.
+    ///
+    /// Waits for the references to the value handed out by
+    /// [`ConcurrentSlotMap::get`] to be dropped.
+    pub fn remove(&self, key: SlotKey) -> Option<T> {
+        self.slab
+            .remove_with_generation(key.index, Some(key.generation))
+    }
+
+    /// Returns the number of values in the map.
+    pub fn len(&self) -> usize {
+        self.slab.len()
+    }
+
+    /// Checks whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.slab.is_empty()
+    }
+}
+
+impl<T> fmt::Debug for ConcurrentSlotMap<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConcurrentSlotMap")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_slot_map() {
+        let map = ConcurrentSlotMap::with_shards(1);
+        let a = map.insert(1);
+        assert_eq!(map.remove(a), Some(1));
+        let b = map.insert(2);
+        assert_eq!((b.index(), b.generation()), (a.index(), a.generation() + 1));
+        assert!(!map.contains(a));
+        assert_eq!(map.remove(a), None);
+        assert_eq!(map.remove(b), Some(2));
+        assert!(map.is_empty());
+        assert_eq!(format!("{:?}", map), "ConcurrentSlotMap { len: 0, .. }");
+    }
+
+    #[test]
+    fn concurrent_slot_map_stale_keys() {
+        let num_thrs = 4;
+        let num_iters = 10_000;
+        let map = ConcurrentSlotMap::with_shards(1);
+        // keys whose values were removed, whose slots others keep reusing
+        let stale = Mutex::new(Vec::new());
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let (map, stale) = (&map, &stale);
+                s.spawn(move || {
+                    for i in 0..num_iters {
+                        let key = map.insert((t, i));
+                        assert_eq!(*map.get(key).unwrap(), (t, i));
+                        let old = {
+                            let stale = stale.lock().unwrap();
+                            stale.get(i % (stale.len() + 1)).copied()
+                        };
+                        if let Some(old) = old {
+                            assert!(map.get(old).is_none());
+                            assert_eq!(map.remove(old), None);
+                        }
+                        assert_eq!(map.remove(key), Some((t, i)));
+                        if i % 16 == 0 {
+                            stale.lock().unwrap().push(key);
+                        }
+                    }
+                });
+            }
+        });
+        assert!(map.is_empty());
+        // the few slots were reused many times over
+        assert!(stale
+            .into_inner()
+            .unwrap()
+            .iter()
+            .any(|key| key.generation() > 100));
+    }
+}
@@ -0,0 +1,246 @@
+//! Operation statistics for the crate's maps, sets, and caches.
+//!
+//! Structures implementing [`Statistics`] count their operations, how many
+//! lookups hit or missed, and how often they resized or evicted an entry.
+//! Counting is only compiled in with the `stats` feature; without it the
+//! counters are zero-sized, every update compiles to nothing, and
+//! [`Statistics::stats`] always returns zeros.
+//!
+//! The counters are striped across cores, so that counting does not add
+//! contention of its own, and a snapshot taken while operations are running
+//! may miss some of them.
+//!
+//! ```
+//! use rsds::map::{Map, StripedHashMap};
+//! use rsds::stats::{self, Statistics};
+//!
+//! let map = StripedHashMap::new();
+//! map.put(1, "one");
+//! map.get(&1);
+//! map.get(&2);
+//!
+//! let stats = map.stats();
+//! if stats::ENABLED {
+//!     assert_eq!(stats.ops, 3);
+//!     assert_eq!(stats.hit_ratio(), Some(0.5));
+//! } else {
+//!     assert_eq!(stats, Default::default());
+//! }
+//! ```
+
+// The maps, sets and caches each update some of the counters.
+#![cfg_attr(
+    not(all(feature = "cache", feature = "list-set", feature = "map")),
+    allow(dead_code)
+)]
+
+#[cfg(feature = "stats")]
+use crate::counter::StripedCounter;
+
+/// Whether the crate was built with the `stats` feature, and so counts
+/// operations.
+pub const ENABLED: bool = cfg!(feature = "stats");
+
+/// A snapshot of a structure's statistics.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Number of operations run.
+    pub ops: usize,
+    /// Number of lookups that found their key.
+    pub hits: usize,
+    /// Number of lookups that did not find their key.
+    pub misses: usize,
+    /// Number of times the structure grew its storage.
+    pub resizes: usize,
+    /// Number of entries evicted to make room for others.
+    pub evictions: usize,
+}
+
+impl Stats {
+    /// Returns the fraction of lookups that found their key, or `None` if
+    /// there were no lookups.
+    pub fn hit_ratio(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+
+    /// Returns the fraction of lookups that did not find their key, or `None`
+    /// if there were no lookups.
+    pub fn miss_ratio(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.misses as f64 / lookups as f64)
+    }
+}
+
+/// Structures that keep operation statistics.
+///
+/// See the [module-level documentation](self) for more.
+pub trait Statistics {
+    /// Returns a snapshot of the structure's statistics, which are all zero
+    /// unless the crate was built with the `stats` feature.
+    fn stats(&self) -> Stats;
+}
+
+/// The counters behind a structure's [`Stats`].
+#[cfg(feature = "stats")]
+pub(crate) struct Counters {
+    ops: StripedCounter,
+    hits: StripedCounter,
+    misses: StripedCounter,
+    resizes: StripedCounter,
+    evictions: StripedCounter,
+}
+
+#[cfg(feature = "stats")]
+impl Counters {
+    pub(crate) fn new() -> Self {
+        Self {
+            ops: StripedCounter::new(),
+            hits: StripedCounter::new(),
+            misses: StripedCounter::new(),
+            resizes: StripedCounter::new(),
+            evictions: StripedCounter::new(),
+        }
+    }
+
+    /// Counts an operation.
+    pub(crate) fn op(&self) {
+        self.ops.increment();
+    }
+
+    /// Counts a lookup, and returns whether it found its key.
+    pub(crate) fn lookup(&self, found: bool) -> bool {
+        self.op();
+        if found {
+            self.hits.increment();
+        } else {
+            self.misses.increment();
+        }
+        found
+    }
+
+    /// Counts a resize.
+    pub(crate) fn resize(&self) {
+        self.resizes.increment();
+    }
+
+    /// Counts an eviction.
+    pub(crate) fn evict(&self) {
+        self.evictions.increment();
+    }
+
+    pub(crate) fn snapshot(&self) -> Stats {
+        Stats {
+            ops: self.ops.sum(),
+            hits: self.hits.sum(),
+            misses: self.misses.sum(),
+            resizes: self.resizes.sum(),
+            evictions: self.evictions.sum(),
+        }
+    }
+}
+
+/// The counters behind a structure's [`Stats`], which count nothing without
+/// the `stats` feature.
+#[cfg(not(feature = "stats"))]
+pub(crate) struct Counters;
+
+#[cfg(not(feature = "stats"))]
+impl Counters {
+    #[inline(always)]
+    pub(crate) fn new() -> Self {
+        Self
+    }
+
+    #[inline(always)]
+    pub(crate) fn op(&self) {}
+
+    #[inline(always)]
+    pub(crate) fn lookup(&self, found: bool) -> bool {
+        found
+    }
+
+    #[inline(always)]
+    pub(crate) fn resize(&self) {}
+
+    #[inline(always)]
+    pub(crate) fn evict(&self) {}
+
+    #[inline(always)]
+    pub(crate) fn snapshot(&self) -> Stats {
+        Stats::default()
+    }
+}
+
+impl Default for Counters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "cache", feature = "list-set", feature = "map"))]
+mod tests {
+    use super::*;
+    use crate::cache::{Cache, LfuCache};
+    use crate::list_set::{FineGrainedSet, Set};
+    use crate::map::{CoarseMap, Map};
+
+    /// Returns `stats` if counting is compiled in, and zeros otherwise.
+    fn expected(stats: Stats) -> Stats {
+        if ENABLED {
+            stats
+        } else {
+            Stats::default()
+        }
+    }
+
+    #[test]
+    fn stats() {
+        let map = CoarseMap::<_, _>::new();
+        for i in 0..100 {
+            map.put(i, i);
+        }
+        assert!(map.contains(&0));
+        assert!(map.get(&100).is_none());
+        assert!(map.remove(&0));
+        let stats = map.stats();
+        let counted = Stats {
+            ops: 103,
+            hits: 1,
+            misses: 1,
+            ..stats
+        };
+        assert_eq!(stats, expected(counted));
+        assert_eq!(stats.hit_ratio(), ENABLED.then_some(0.5));
+        // the map grows its table a few times along the way
+        assert_eq!(stats.resizes > 0, ENABLED);
+
+        let set = FineGrainedSet::default();
+        set.add(1);
+        set.add(1);
+        set.contains(&1);
+        set.contains(&2);
+        set.contains(&3);
+        let stats = Stats {
+            ops: 5,
+            hits: 1,
+            misses: 2,
+            ..Stats::default()
+        };
+        assert_eq!(set.stats(), expected(stats));
+        assert_eq!(set.stats().miss_ratio(), ENABLED.then_some(2.0 / 3.0));
+
+        let cache = LfuCache::with_shards(2, 1);
+        for i in 0..5 {
+            cache.insert(i, i);
+            cache.get(&i);
+        }
+        let stats = Stats {
+            ops: 10,
+            hits: 5,
+            evictions: 3,
+            ..Stats::default()
+        };
+        assert_eq!(cache.stats(), expected(stats));
+    }
+}
@@ -0,0 +1,478 @@
+//! A stress-testing harness that runs randomized schedules of conflicting
+//! operations against any [`Map`] or [`Set`], and shrinks the schedules it
+//! finds failing.
+//!
+//! Each run draws a script of operations on a handful of keys, so threads
+//! keep running into each other, and a schedule that fixes the order in which
+//! the operations start. An operation may be started while the one before it
+//! is still running, or only once it has finished, so runs mix overlapping
+//! and sequential stretches. Once the threads finish, every key is read once
+//! more, and the run's recorded history is checked with the
+//! [linearizability checker](crate::linearizability).
+//!
+//! When a run fails, the harness shrinks it, dropping operations and turning
+//! overlapping starts into sequential ones for as long as the run keeps
+//! failing, and reports the smallest failing schedule it found.
+//!
+//! The harness is behind the `stress` feature.
+//!
+//! ```
+//! use rsds::map::StripedHashMap;
+//! use rsds::stress::Stress;
+//!
+//! let result = Stress::new().seed(7).iterations(10).check_map(StripedHashMap::new);
+//! if let Err(failure) = result {
+//!     panic!("{}", failure);
+//! }
+//! ```
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::linearizability::{RecordedMap, RecordedSet};
+use crate::list_set::Set;
+use crate::map::Map;
+use crate::sync::Backoff;
+
+/// An operation in a stress-test script.
+///
+/// For a [`Set`], `Get` checks whether the key is in the set, and `Put` adds
+/// it, ignoring the value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    /// Looks up a key.
+    Get(u64),
+    /// Maps a key to a value.
+    Put(u64, u64),
+    /// Removes a key.
+    Remove(u64),
+}
+
+impl Op {
+    fn key(self) -> u64 {
+        match self {
+            Op::Get(key) | Op::Put(key, _) | Op::Remove(key) => key,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Op::Get(key) => write!(f, "get({})", key),
+            Op::Put(key, value) => write!(f, "put({}, {})", key, value),
+            Op::Remove(key) => write!(f, "remove({})", key),
+        }
+    }
+}
+
+/// A step of a schedule: a thread starting its next operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    /// The thread running the operation.
+    pub thread: usize,
+    /// The operation.
+    pub op: Op,
+    /// Whether the next step may start before this one finishes.
+    pub overlap: bool,
+}
+
+/// A failing schedule, shrunk as far as the harness could.
+#[derive(Clone, Debug)]
+pub struct Failure {
+    /// The seed of the run that first failed.
+    pub seed: u64,
+    /// The steps of the shrunk schedule, in the order they start.
+    pub schedule: Vec<Step>,
+    /// What went wrong in the last failing run of the shrunk schedule.
+    pub message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} (seed {})", self.message, self.seed)?;
+        for step in &self.schedule {
+            let overlap = if step.overlap { ", overlapping" } else { "" };
+            writeln!(f, "  thread {}: {}{}", step.thread, step.op, overlap)?;
+        }
+        Ok(())
+    }
+}
+
+/// A structure under test, recording the operations run against it.
+trait Target: Sync {
+    fn apply(&self, op: Op);
+
+    /// Checks that the recorded history is linearizable.
+    fn check(self) -> Result<(), String>;
+}
+
+impl<M> Target for RecordedMap<M>
+where
+    M: Map<Key = u64, Val = u64> + Sync,
+{
+    fn apply(&self, op: Op) {
+        match op {
+            Op::Get(key) => drop(self.get(&key)),
+            Op::Put(key, value) => self.put(key, value),
+            Op::Remove(key) => drop(self.remove(&key)),
+        }
+    }
+
+    fn check(self) -> Result<(), String> {
+        self.into_history().check().map_err(|v| v.to_string())
+    }
+}
+
+impl<S> Target for RecordedSet<S>
+where
+    S: Set<Elem = u64> + Sync,
+{
+    fn apply(&self, op: Op) {
+        match op {
+            Op::Get(key) => drop(self.contains(&key)),
+            Op::Put(key, _) => drop(self.add(key)),
+            Op::Remove(key) => drop(self.remove(&key)),
+        }
+    }
+
+    fn check(self) -> Result<(), String> {
+        self.into_history().check().map_err(|v| v.to_string())
+    }
+}
+
+/// A xorshift64* generator, seeded so that runs can be reproduced.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // splitmix64, so that nearby seeds give unrelated streams
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Self((z ^ (z >> 31)) | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+}
+
+/// Configures and runs stress tests.
+#[derive(Clone, Debug)]
+pub struct Stress {
+    threads: usize,
+    ops_per_thread: usize,
+    keys: u64,
+    seed: u64,
+    iterations: usize,
+    attempts: usize,
+}
+
+impl Default for Stress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Stress {
+    /// Creates a harness running 100 schedules of 4 threads doing 20
+    /// operations each on 4 keys, seeded from the clock.
+    pub fn new() -> Self {
+        let seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        Self {
+            threads: 4,
+            ops_per_thread: 20,
+            keys: 4,
+            seed,
+            iterations: 100,
+            attempts: 20,
+        }
+    }
+
+    /// Sets the number of threads in each schedule.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threads` is zero.
+    pub fn threads(mut self, threads: usize) -> Self {
+        assert!(threads > 0, "threads (is {}) should be positive", threads);
+        self.threads = threads;
+        self
+    }
+
+    /// Sets the number of operations each thread runs.
+    pub fn ops_per_thread(mut self, ops_per_thread: usize) -> Self {
+        self.ops_per_thread = ops_per_thread;
+        self
+    }
+
+    /// Sets the number of distinct keys the operations use.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `keys` is zero.
+    pub fn keys(mut self, keys: u64) -> Self {
+        assert!(keys > 0, "keys (is {}) should be positive", keys);
+        self.keys = keys;
+        self
+    }
+
+    /// Sets the seed of the first schedule; each later one increments it.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the number of schedules to run.
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Sets how many times a candidate schedule is rerun while shrinking,
+    /// since a schedule that failed once may need several runs to fail again.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `attempts` is zero.
+    pub fn attempts(mut self, attempts: usize) -> Self {
+        assert!(
+            attempts > 0,
+            "attempts (is {}) should be positive",
+            attempts
+        );
+        self.attempts = attempts;
+        self
+    }
+
+    /// Runs schedules against maps created by `new_map`, one per run.
+    pub fn check_map<M, F>(&self, new_map: F) -> Result<(), Failure>
+    where
+        M: Map<Key = u64, Val = u64> + Sync,
+        F: Fn() -> M,
+    {
+        self.check(|| RecordedMap::new(new_map()))
+    }
+
+    /// Runs schedules against sets created by `new_set`, one per run.
+    pub fn check_set<S, F>(&self, new_set: F) -> Result<(), Failure>
+    where
+        S: Set<Elem = u64> + Sync,
+        F: Fn() -> S,
+    {
+        self.check(|| RecordedSet::new(new_set()))
+    }
+
+    fn check<T: Target, F: Fn() -> T>(&self, new_target: F) -> Result<(), Failure> {
+        for i in 0..self.iterations {
+            let seed = self.seed.wrapping_add(i as u64);
+            let schedule = self.schedule(seed);
+            if let Err(message) = run(new_target(), &schedule) {
+                let (schedule, message) = self.shrink(&new_target, schedule, message);
+                return Err(Failure {
+                    seed,
+                    schedule,
+                    message,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn schedule(&self, seed: u64) -> Vec<Step> {
+        let mut rng = Rng::new(seed);
+        let mut left = vec![self.ops_per_thread; self.threads];
+        let mut schedule = Vec::with_capacity(self.threads * self.ops_per_thread);
+        let mut next_value = 0;
+        while left.iter().any(|&n| n > 0) {
+            let thread = loop {
+                let thread = rng.below(self.threads as u64) as usize;
+                if left[thread] > 0 {
+                    break thread;
+                }
+            };
+            left[thread] -= 1;
+            let key = rng.below(self.keys);
+            let op = match rng.below(10) {
+                0..=4 => Op::Get(key),
+                5..=7 => {
+                    // values are unique, so reads tell which write they saw
+                    next_value += 1;
+                    Op::Put(key, next_value)
+                }
+                _ => Op::Remove(key),
+            };
+            let overlap = rng.below(2) == 0;
+            schedule.push(Step {
+                thread,
+                op,
+                overlap,
+            });
+        }
+        schedule
+    }
+
+    /// Shrinks a failing schedule, dropping steps and making steps sequential
+    /// for as long as some run of the result still fails.
+    fn shrink<T: Target, F: Fn() -> T>(
+        &self,
+        new_target: &F,
+        mut schedule: Vec<Step>,
+        mut message: String,
+    ) -> (Vec<Step>, String) {
+        let fails =
+            |schedule: &[Step]| (0..self.attempts).find_map(|_| run(new_target(), schedule).err());
+        // Making a step sequential may let steps that were needed to fail
+        // overlapping be dropped, so go on until neither finds anything.
+        let mut shrunk = true;
+        while shrunk {
+            shrunk = false;
+            let mut i = 0;
+            while i < schedule.len() {
+                let mut candidate = schedule.clone();
+                candidate.remove(i);
+                match fails(&candidate) {
+                    Some(m) => (schedule, message, shrunk) = (candidate, m, true),
+                    None => i += 1,
+                }
+            }
+            for i in 0..schedule.len() {
+                if schedule[i].overlap {
+                    let mut candidate = schedule.clone();
+                    candidate[i].overlap = false;
+                    if let Some(m) = fails(&candidate) {
+                        (schedule, message, shrunk) = (candidate, m, true);
+                    }
+                }
+            }
+        }
+        (schedule, message)
+    }
+}
+
+/// Runs a schedule against a target, and checks the results.
+fn run<T: Target>(target: T, schedule: &[Step]) -> Result<(), String> {
+    let num_threads = schedule
+        .iter()
+        .map(|step| step.thread + 1)
+        .max()
+        .unwrap_or(0);
+    let turn = AtomicUsize::new(0);
+
+    std::thread::scope(|s| {
+        for thread in 0..num_threads {
+            let (target, turn) = (&target, &turn);
+            s.spawn(move || {
+                let steps = schedule.iter().enumerate();
+                for (i, step) in steps.filter(|(_, step)| step.thread == thread) {
+                    let backoff = Backoff::new();
+                    while turn.load(Ordering::Acquire) != i {
+                        backoff.snooze();
+                    }
+                    if step.overlap {
+                        turn.store(i + 1, Ordering::Release);
+                    }
+                    target.apply(step.op);
+                    if !step.overlap {
+                        turn.store(i + 1, Ordering::Release);
+                    }
+                }
+            });
+        }
+    });
+
+    // once quiescent, read every key the schedule touched
+    let mut keys: Vec<_> = schedule.iter().map(|step| step.op.key()).collect();
+    keys.sort_unstable();
+    keys.dedup();
+    for key in keys {
+        target.apply(Op::Get(key));
+    }
+    target.check()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::list_set::{CoarseSet, FineGrainedSet};
+    use crate::map::{CoarseMap, StripedHashMap};
+
+    /// A map that forgets an update to a key that is already present.
+    #[derive(Default)]
+    struct LostUpdateMap(Mutex<HashMap<u64, u64>>);
+
+    struct Value(u64);
+
+    impl std::ops::Deref for Value {
+        type Target = u64;
+
+        fn deref(&self) -> &u64 {
+            &self.0
+        }
+    }
+
+    impl Map for LostUpdateMap {
+        type Key = u64;
+        type Val = u64;
+        type ValueRef<'a> = Value;
+
+        fn get(&self, key: &u64) -> Option<Value> {
+            self.0.lock().unwrap().get(key).copied().map(Value)
+        }
+
+        fn contains(&self, key: &u64) -> bool {
+            self.0.lock().unwrap().contains_key(key)
+        }
+
+        fn put(&self, key: u64, value: u64) {
+            self.0.lock().unwrap().entry(key).or_insert(value);
+        }
+
+        fn remove(&self, key: &u64) -> bool {
+            self.0.lock().unwrap().remove(key).is_some()
+        }
+    }
+
+    #[test]
+    fn stress_maps() {
+        let stress = Stress::new().seed(0).iterations(50);
+        stress.check_map(StripedHashMap::new).unwrap();
+        stress.check_map(CoarseMap::<_, _>::new).unwrap();
+    }
+
+    #[test]
+    fn stress_sets() {
+        let stress = Stress::new().seed(0).iterations(50);
+        stress.check_set(CoarseSet::default).unwrap();
+        stress.check_set(FineGrainedSet::default).unwrap();
+    }
+
+    #[test]
+    fn stress_shrinks_failures() {
+        let failure = Stress::new()
+            .seed(0)
+            .check_map(LostUpdateMap::default)
+            .unwrap_err();
+        // two puts to the same key, and the read after the run, are all it
+        // takes
+        assert_eq!(failure.schedule.len(), 2, "{}", failure);
+        assert!(failure.schedule.iter().all(|step| !step.overlap));
+        assert!(failure
+            .schedule
+            .iter()
+            .all(|step| matches!(step.op, Op::Put(_, _))));
+    }
+}
@@ -0,0 +1,206 @@
+use std::cell::Cell;
+#[cfg(all(can_block, has_clock, not(loom)))]
+use std::time::Duration;
+
+const SPIN_LIMIT: u32 = 6;
+const YIELD_LIMIT: u32 = 10;
+/// The longest a parked waiter sleeps before checking again, as a power of
+/// two microseconds.
+#[cfg(all(can_block, has_clock, not(loom)))]
+const PARK_LIMIT: u32 = 10;
+
+/// Waits between attempts of a retry loop, for longer the more attempts
+/// fail.
+///
+/// [`Backoff::snooze`] first spins for exponentially many iterations, then
+/// yields the thread. [`Backoff::spin`] only ever spins, for loops that wait
+/// on other threads that are running.
+///
+/// The limits are counted in steps, one per call, and default to those of
+/// crossbeam's `Backoff`, which this is a drop-in for unless made
+/// [`parking`](Backoff::parking).
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// use rsds::sync::Backoff;
+///
+/// let ready = AtomicBool::new(false);
+/// std::thread::scope(|s| {
+///     s.spawn(|| ready.store(true, Ordering::Release));
+///     let backoff = Backoff::with_limits(4, 8).parking();
+///     while !ready.load(Ordering::Acquire) {
+///         backoff.snooze();
+///     }
+/// });
+/// ```
+#[derive(Debug)]
+pub struct Backoff {
+    step: Cell<u32>,
+    spin_limit: u32,
+    yield_limit: u32,
+    /// Whether snoozing parks the thread once past the yield limit.
+    park: bool,
+}
+
+impl Backoff {
+    /// Creates a backoff with the default limits.
+    pub const fn new() -> Self {
+        Self::with_limits(SPIN_LIMIT, YIELD_LIMIT)
+    }
+
+    /// Creates a backoff that spins for its first `spin_limit` steps, and
+    /// yields after that. `yield_limit` is the step after which
+    /// [`Backoff::is_completed`] holds, and a [`parking`](Backoff::parking)
+    /// backoff parks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `spin_limit` is over `yield_limit`, or over 31.
+    pub const fn with_limits(spin_limit: u32, yield_limit: u32) -> Self {
+        assert!(
+            spin_limit <= yield_limit,
+            "spin limit should not exceed yield limit"
+        );
+        assert!(spin_limit < u32::BITS, "spin limit should be under 32");
+        Backoff {
+            step: Cell::new(0),
+            spin_limit,
+            yield_limit,
+            park: false,
+        }
+    }
+
+    /// Makes [`Backoff::snooze`] park the thread once past the yield limit,
+    /// for exponentially longer timeouts up to about a millisecond, rather
+    /// than keep yielding. Parking takes a clock and threads that can block;
+    /// without them, it keeps yielding.
+    ///
+    /// A parked snooze uses up a pending [`unpark`](std::thread::Thread::unpark)
+    /// of the thread, so loops that also park the thread themselves, to be
+    /// woken by other threads, must not use a parking backoff.
+    pub const fn parking(mut self) -> Self {
+        self.park = true;
+        self
+    }
+
+    /// Starts over from the first step.
+    pub fn reset(&self) {
+        self.step.set(0);
+    }
+
+    /// Spins for exponentially many iterations, up to the spin limit.
+    pub fn spin(&self) {
+        let step = self.step.get().min(self.spin_limit);
+        Self::spin_for(step);
+        if self.step.get() <= self.spin_limit {
+            self.step.set(self.step.get() + 1);
+        }
+    }
+
+    /// Spins or yields the thread, depending on how many steps it has backed
+    /// off for, or parks it past the yield limit if the backoff is
+    /// [`parking`](Backoff::parking).
+    pub fn snooze(&self) {
+        let step = self.step.get();
+        if step <= self.spin_limit {
+            Self::spin_for(step);
+        } else if step <= self.yield_limit || !self.park {
+            yield_now();
+        } else {
+            park(step - self.yield_limit);
+        }
+        self.step.set(step.saturating_add(1));
+    }
+
+    /// Returns whether the backoff has gone past the yield limit, after which
+    /// a caller that can wait to be woken should do that instead.
+    pub fn is_completed(&self) -> bool {
+        self.step.get() > self.yield_limit
+    }
+
+    #[cfg(not(loom))]
+    fn spin_for(step: u32) {
+        for _ in 0..1u32 << step {
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Under loom, every spin is a yield, so that the model moves on to
+    /// another thread.
+    #[cfg(loom)]
+    fn spin_for(_step: u32) {
+        loom::thread::yield_now();
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(not(loom))]
+fn yield_now() {
+    std::thread::yield_now();
+}
+
+#[cfg(loom)]
+fn yield_now() {
+    loom::thread::yield_now();
+}
+
+/// Parks the thread for the `parks`-th time in a row.
+#[cfg(all(can_block, has_clock, not(loom)))]
+fn park(parks: u32) {
+    let micros = 1 << parks.min(PARK_LIMIT);
+    std::thread::park_timeout(Duration::from_micros(micros));
+}
+
+#[cfg(not(all(can_block, has_clock, not(loom))))]
+fn park(_parks: u32) {
+    yield_now();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_steps() {
+        let backoff = Backoff::with_limits(2, 4).parking();
+        for _ in 0..5 {
+            assert!(!backoff.is_completed());
+            backoff.snooze();
+        }
+        assert!(backoff.is_completed());
+        // parks, which returns within its timeout without an unpark
+        backoff.snooze();
+        backoff.reset();
+        assert!(!backoff.is_completed());
+
+        // spinning stops counting at the spin limit
+        for _ in 0..10 {
+            backoff.spin();
+        }
+        assert!(!backoff.is_completed());
+    }
+
+    #[cfg(all(can_block, has_clock, not(loom)))]
+    #[test]
+    fn test_backoff_keeps_unpark() {
+        use std::time::Instant;
+
+        // past the yield limit, snoozing only yields, leaving the token of
+        // an earlier unpark for the thread's own park
+        let backoff = Backoff::with_limits(0, 0);
+        std::thread::current().unpark();
+        for _ in 0..100 {
+            backoff.snooze();
+        }
+        assert!(backoff.is_completed());
+        let start = Instant::now();
+        std::thread::park_timeout(Duration::from_secs(10));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,255 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crossbeam::utils::CachePadded;
+
+use super::{thread_index, Backoff};
+
+/// A reader-writer lock biased towards readers, for data that is read far
+/// more often than it is written.
+///
+/// Readers mark themselves in a slot of their own, picked by thread, and only
+/// read the writer's flag, so readers on different threads never write to
+/// the same cache line. A writer raises its flag, then scans every slot and
+/// waits for the readers it finds to leave. Readers that see the flag back
+/// out and wait for the writer, so a waiting writer is not starved by a
+/// stream of readers.
+///
+/// Reads are thus cheaper than with [`std::sync::RwLock`], which has every
+/// reader update a shared counter, but writes cost a scan of all the slots,
+/// and each slot takes a cache line. Threads beyond the number of slots share
+/// them.
+///
+/// As with [`std::sync::RwLock`], a thread that takes a read lock while
+/// already holding one may deadlock with a waiting writer.
+///
+/// ```
+/// use rsds::sync::BiasedRwLock;
+///
+/// let lock = BiasedRwLock::new(vec![1, 2, 3]);
+/// std::thread::scope(|s| {
+///     s.spawn(|| assert!(lock.read().len() >= 3));
+///     s.spawn(|| lock.write().push(4));
+/// });
+/// assert_eq!(*lock.read(), [1, 2, 3, 4]);
+/// ```
+pub struct BiasedRwLock<T> {
+    writer: CachePadded<AtomicBool>,
+    /// Number of readers holding the lock through each slot.
+    readers: Box<[CachePadded<AtomicUsize>]>,
+    data: UnsafeCell<T>,
+}
+
+/// A read lock on a [`BiasedRwLock`], which is released when the guard is
+/// dropped.
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct BiasedReadGuard<'a, T> {
+    lock: &'a BiasedRwLock<T>,
+    slot: &'a AtomicUsize,
+}
+
+/// A write lock on a [`BiasedRwLock`], which is released when the guard is
+/// dropped.
+#[must_use = "the lock is released as soon as the guard is dropped"]
+pub struct BiasedWriteGuard<'a, T> {
+    lock: &'a BiasedRwLock<T>,
+}
+
+// SAFETY: the lock hands out `&T` to any number of threads at once, or
+// `&mut T` to a single one.
+unsafe impl<T: Send> Send for BiasedRwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for BiasedRwLock<T> {}
+
+// SAFETY: the guards hand out `&T` to whichever threads share them.
+unsafe impl<T: Sync> Sync for BiasedReadGuard<'_, T> {}
+unsafe impl<T: Sync> Sync for BiasedWriteGuard<'_, T> {}
+
+impl<T> Default for BiasedRwLock<T>
+where
+    T: Default,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> BiasedRwLock<T> {
+    /// Creates a new, unlocked [`BiasedRwLock`] guarding `data`, with a
+    /// reader slot per available core.
+    pub fn new(data: T) -> Self {
+        let num_slots = std::thread::available_parallelism().map_or(8, |n| n.get());
+        Self::with_slots(num_slots, data)
+    }
+
+    /// Creates a new, unlocked [`BiasedRwLock`] guarding `data`, with
+    /// `num_slots` reader slots.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_slots` is zero.
+    pub fn with_slots(num_slots: usize, data: T) -> Self {
+        assert!(
+            num_slots > 0,
+            "number of slots (is {}) should be positive",
+            num_slots
+        );
+        Self {
+            writer: CachePadded::new(AtomicBool::new(false)),
+            readers: (0..num_slots)
+                .map(|_| CachePadded::new(AtomicUsize::new(0)))
+                .collect(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Acquires a read lock, blocking while a writer holds or waits for the
+    /// lock.
+    pub fn read(&self) -> BiasedReadGuard<'_, T> {
+        let slot = &*self.readers[thread_index() % self.readers.len()];
+        loop {
+            // Announcing ourselves before checking for a writer pairs with the
+            // writer raising its flag before scanning the slots: at least one
+            // of us sees the other.
+            slot.fetch_add(1, Ordering::SeqCst);
+            if !self.writer.load(Ordering::SeqCst) {
+                return BiasedReadGuard { lock: self, slot };
+            }
+            slot.fetch_sub(1, Ordering::Release);
+            let backoff = Backoff::new();
+            while self.writer.load(Ordering::Relaxed) {
+                backoff.snooze();
+            }
+        }
+    }
+
+    /// Acquires the write lock, blocking until every reader and other writer
+    /// has left.
+    pub fn write(&self) -> BiasedWriteGuard<'_, T> {
+        let backoff = Backoff::new();
+        while self
+            .writer
+            .compare_exchange_weak(false, true, Ordering::SeqCst, Ordering::Relaxed)
+            .is_err()
+        {
+            backoff.snooze();
+        }
+        for slot in self.readers.iter() {
+            let backoff = Backoff::new();
+            while slot.load(Ordering::SeqCst) != 0 {
+                backoff.snooze();
+            }
+        }
+        BiasedWriteGuard { lock: self }
+    }
+
+    /// Returns a mutable reference to the data.
+    ///
+    /// This is safe since the mutable borrow guarantees no other threads are
+    /// accessing the lock.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Consumes the lock, returning the data.
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+
+    /// Returns the bytes the reader slots take on the heap.
+    #[cfg(feature = "map")]
+    pub(crate) fn heap_size(&self) -> usize {
+        std::mem::size_of_val(&*self.readers)
+    }
+}
+
+impl<T> fmt::Debug for BiasedRwLock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BiasedRwLock")
+            .field("num_slots", &self.readers.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Deref for BiasedReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: we hold a read lock, so there is no writer.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for BiasedReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.slot.fetch_sub(1, Ordering::Release);
+    }
+}
+
+impl<T> Deref for BiasedWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: we hold the write lock.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<T> DerefMut for BiasedWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: we hold the write lock.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<T> Drop for BiasedWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.writer.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn biased_rw_lock() {
+        let mut lock = BiasedRwLock::with_slots(2, 0);
+        let (a, b) = (lock.read(), lock.read());
+        assert_eq!(*a + *b, 0);
+        drop((a, b));
+        *lock.write() += 1;
+        *lock.get_mut() += 1;
+        assert_eq!(format!("{:?}", lock), "BiasedRwLock { num_slots: 2, .. }");
+        assert_eq!(lock.into_inner(), 2);
+    }
+
+    #[test]
+    fn biased_rw_lock_concurrent() {
+        let num_thrs = 4;
+        let num_iters = 10_000;
+        // fewer slots than threads, so that some share
+        let lock = BiasedRwLock::with_slots(3, (0, 0));
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let lock = &lock;
+                s.spawn(move || {
+                    for i in 0..num_iters {
+                        if (t + i) % 8 == 0 {
+                            let mut guard = lock.write();
+                            guard.0 += 1;
+                            guard.1 += 1;
+                        } else {
+                            let guard = lock.read();
+                            assert_eq!(guard.0, guard.1);
+                        }
+                    }
+                });
+            }
+        });
+        let (a, b) = lock.into_inner();
+        assert_eq!(a, b);
+        assert_eq!(a, num_thrs * num_iters / 8);
+    }
+}
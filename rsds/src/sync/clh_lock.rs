@@ -0,0 +1,215 @@
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicPtr, Ordering};
+#[cfg(has_clock)]
+use std::time::{Duration, Instant};
+
+use crossbeam::utils::CachePadded;
+
+#[cfg(has_clock)]
+use super::RawTimedLock;
+use super::{Backoff, Lock, RawLock, RawTryLock};
+
+struct Node {
+    /// Null while the owner holds or waits for the lock, `AVAILABLE` once it
+    /// has released the lock, and the owner's predecessor once it has given
+    /// up waiting.
+    pred: AtomicPtr<CachePadded<Node>>,
+}
+
+/// Marks a node whose owner has released the lock.
+static AVAILABLE: CachePadded<Node> = CachePadded::new(Node {
+    pred: AtomicPtr::new(ptr::null_mut()),
+});
+
+fn available() -> *mut CachePadded<Node> {
+    &AVAILABLE as *const _ as *mut _
+}
+
+/// A queue lock by Craig, Landin and Hagersten, in the abortable variant
+/// described by Herlihy and Shavit, which lets waiters time out.
+///
+/// Waiters append a node of their own to a queue and spin on the node of the
+/// waiter before them, so the lock is granted in order and each release only
+/// touches one waiter's cache line. Unlike with an MCS lock, a waiter can
+/// leave the queue when it times out: it points its node at its predecessor,
+/// and its successor skips over it to wait on that predecessor instead.
+///
+/// Each node is freed by its owner's successor, once the successor is done
+/// with it, or by its owner if it turns out to have no successor.
+#[derive(Debug)]
+pub struct RawClhLock {
+    tail: AtomicPtr<CachePadded<Node>>,
+}
+
+/// Token for a held [`RawClhLock`], pointing at the holder's queue node.
+#[derive(Debug)]
+pub struct ClhToken(NonNull<CachePadded<Node>>);
+
+/// Data guarded by a [`RawClhLock`].
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use rsds::sync::ClhLock;
+///
+/// let lock = ClhLock::new(0);
+/// let guard = lock.lock();
+/// std::thread::scope(|s| {
+///     // gives up while the lock is held
+///     s.spawn(|| assert!(lock.try_lock_for(Duration::from_millis(10)).is_none()));
+/// });
+/// drop(guard);
+/// *lock.try_lock_for(Duration::from_millis(10)).unwrap() += 1;
+/// assert_eq!(lock.into_inner(), 1);
+/// ```
+pub type ClhLock<T> = Lock<RawClhLock, T>;
+
+impl Default for RawClhLock {
+    fn default() -> Self {
+        Self {
+            tail: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+}
+
+impl RawClhLock {
+    /// Joins the queue and waits for the lock until `give_up` returns true.
+    fn acquire(&self, give_up: impl Fn() -> bool) -> Option<ClhToken> {
+        let node = Box::into_raw(Box::new(CachePadded::new(Node {
+            pred: AtomicPtr::new(ptr::null_mut()),
+        })));
+        let mut pred = self.tail.swap(node, Ordering::AcqRel);
+        // SAFETY: the node was just allocated.
+        let token = ClhToken(unsafe { NonNull::new_unchecked(node) });
+        if pred.is_null() {
+            return Some(token);
+        }
+
+        let backoff = Backoff::new();
+        loop {
+            // SAFETY: nodes are only freed by their successor, which is us.
+            let pred_pred = unsafe { &*pred }.pred.load(Ordering::Acquire);
+            if pred_pred == available() {
+                // SAFETY: as above, and no one else refers to the node.
+                drop(unsafe { Box::from_raw(pred) });
+                return Some(token);
+            }
+            if !pred_pred.is_null() {
+                // The predecessor has given up; wait on its predecessor.
+                // SAFETY: as above.
+                drop(unsafe { Box::from_raw(pred) });
+                pred = pred_pred;
+                continue;
+            }
+            if give_up() {
+                break;
+            }
+            backoff.snooze();
+        }
+
+        // Leave the queue: drop out of the tail if we are last, or else let
+        // our successor skip over us.
+        if self
+            .tail
+            .compare_exchange(node, pred, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
+        {
+            // SAFETY: no one else refers to the node.
+            drop(unsafe { Box::from_raw(node) });
+        } else {
+            // SAFETY: the node is freed by our successor once it reads this.
+            unsafe { &*node }.pred.store(pred, Ordering::Release);
+        }
+        None
+    }
+}
+
+impl Drop for RawClhLock {
+    fn drop(&mut self) {
+        let tail = *self.tail.get_mut();
+        if !tail.is_null() {
+            // SAFETY: a waiter that gave up may have left a released node at
+            // the tail, which has no successor to free it.
+            drop(unsafe { Box::from_raw(tail) });
+        }
+    }
+}
+
+// SAFETY: a waiter only proceeds once its predecessor has released the lock,
+// which it does with a release store the waiter acquires.
+unsafe impl RawLock for RawClhLock {
+    type Token = ClhToken;
+
+    fn lock(&self) -> ClhToken {
+        self.acquire(|| false).unwrap()
+    }
+
+    unsafe fn unlock(&self, token: ClhToken) {
+        let node = token.0.as_ptr();
+        if self
+            .tail
+            .compare_exchange(node, ptr::null_mut(), Ordering::Release, Ordering::Relaxed)
+            .is_ok()
+        {
+            drop(Box::from_raw(node));
+        } else {
+            // The successor frees the node once it reads this.
+            (&*node).pred.store(available(), Ordering::Release);
+        }
+    }
+}
+
+// SAFETY: as above.
+unsafe impl RawTryLock for RawClhLock {
+    fn try_lock(&self) -> Option<ClhToken> {
+        self.acquire(|| true)
+    }
+}
+
+// SAFETY: as above.
+#[cfg(has_clock)]
+unsafe impl RawTimedLock for RawClhLock {
+    fn try_lock_for(&self, timeout: Duration) -> Option<ClhToken> {
+        let deadline = Instant::now() + timeout;
+        self.acquire(|| Instant::now() >= deadline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clh_lock_timeout() {
+        let num_thrs = 4;
+        let lock = ClhLock::new(0);
+        let guard = lock.lock();
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let lock = &lock;
+                s.spawn(move || {
+                    // waiters give up at different times, some of them while
+                    // their predecessors are still waiting
+                    let timeout = Duration::from_millis(10 * (t % 2 + 1));
+                    assert!(lock.try_lock_for(timeout).is_none());
+                });
+            }
+        });
+        drop(guard);
+
+        std::thread::scope(|s| {
+            for _ in 0..num_thrs {
+                s.spawn(|| {
+                    for _ in 0..1_000 {
+                        match lock.try_lock_for(Duration::from_micros(10)) {
+                            Some(mut guard) => *guard += 1,
+                            None => *lock.lock() += 1,
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(lock.into_inner(), num_thrs * 1_000);
+    }
+}
@@ -0,0 +1,189 @@
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+use crossbeam::utils::CachePadded;
+
+use super::Backoff;
+
+/// Number of passes a combiner makes over the publication list before handing
+/// the lock back. Later passes pick up requests published while it was busy.
+const COMBINING_PASSES: usize = 3;
+
+// Slot states.
+const FREE: u8 = 0;
+const WRITING: u8 = 1;
+const PENDING: u8 = 2;
+const DONE: u8 = 3;
+
+/// A slot of the publication list, through which a thread hands a request to
+/// the combiner and gets the response back.
+struct Slot<Op, R> {
+    state: AtomicU8,
+    op: UnsafeCell<Option<Op>>,
+    response: UnsafeCell<Option<R>>,
+}
+
+/// A sequential data structure made concurrent with flat combining.
+///
+/// Rather than every thread taking the lock to apply its own operation, each
+/// thread publishes its operation in a slot of a publication list. Whichever
+/// thread gets the lock becomes the combiner: it applies every published
+/// operation in one go and hands back the responses, while the other threads
+/// wait on their own slots. Under heavy contention this replaces a convoy of
+/// lock handoffs with a single thread working through a batch with a hot
+/// cache.
+///
+/// Operations are applied by `apply`, which is fixed per combiner since the
+/// combiner runs it on behalf of other threads.
+pub(crate) struct FlatCombiner<T, Op, R> {
+    data: Mutex<T>,
+    slots: Box<[CachePadded<Slot<Op, R>>]>,
+    apply: fn(&mut T, Op) -> R,
+}
+
+// SAFETY: a slot's operation and response are only accessed by the thread
+// owning the slot and by the combiner, with the slot's state handing them
+// over. Operations and responses move between threads, hence `Op: Send` and
+// `R: Send`.
+unsafe impl<T: Send, Op: Send, R: Send> Sync for FlatCombiner<T, Op, R> {}
+
+impl<T, Op, R> FlatCombiner<T, Op, R> {
+    /// Creates a new combiner over `data`, whose operations are applied by
+    /// `apply`.
+    pub fn new(data: T, apply: fn(&mut T, Op) -> R) -> Self {
+        let num_slots = std::thread::available_parallelism().map_or(8, |n| n.get() * 2);
+        Self {
+            data: Mutex::new(data),
+            slots: (0..num_slots)
+                .map(|_| {
+                    CachePadded::new(Slot {
+                        state: AtomicU8::new(FREE),
+                        op: UnsafeCell::new(None),
+                        response: UnsafeCell::new(None),
+                    })
+                })
+                .collect(),
+            apply,
+        }
+    }
+
+    /// Applies `op` to the data structure, either directly or through another
+    /// thread acting as the combiner, and returns its response.
+    pub fn execute(&self, op: Op) -> R {
+        let slot = self.publish(op);
+        let backoff = Backoff::new();
+        loop {
+            if slot.state.load(Ordering::Acquire) == DONE {
+                // SAFETY: the combiner is done with the slot, and it is ours
+                // until we set it free.
+                let response = unsafe { (*slot.response.get()).take().unwrap() };
+                slot.state.store(FREE, Ordering::Release);
+                return response;
+            }
+
+            if let Some(mut data) = self.try_lock() {
+                self.combine(&mut data);
+                // Our own request was served by the first pass.
+                continue;
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data structure.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut().unwrap()
+    }
+
+    /// Consumes the combiner, returning the underlying data structure.
+    pub fn into_inner(self) -> T {
+        self.data.into_inner().unwrap()
+    }
+
+    /// Claims a free slot and publishes `op` in it.
+    fn publish(&self, op: Op) -> &Slot<Op, R> {
+        // Start at a slot of our own, so that threads mostly stick to
+        // different slots.
+        let start = super::thread_index();
+        let backoff = Backoff::new();
+        loop {
+            for i in 0..self.slots.len() {
+                let slot = &self.slots[(start + i) % self.slots.len()];
+                if slot
+                    .state
+                    .compare_exchange(FREE, WRITING, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+                {
+                    // SAFETY: claiming the slot gives us exclusive access
+                    // until it is published.
+                    unsafe { *slot.op.get() = Some(op) };
+                    slot.state.store(PENDING, Ordering::Release);
+                    return slot;
+                }
+            }
+            // More threads than slots; wait for one to free up.
+            backoff.snooze();
+        }
+    }
+
+    fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        match self.data.try_lock() {
+            Ok(data) => Some(data),
+            Err(TryLockError::WouldBlock) => None,
+            Err(TryLockError::Poisoned(err)) => panic!("{}", err),
+        }
+    }
+
+    /// Serves every pending request. Must be called with the lock held.
+    fn combine(&self, data: &mut T) {
+        for _ in 0..COMBINING_PASSES {
+            let mut served = false;
+            for slot in self.slots.iter() {
+                if slot.state.load(Ordering::Acquire) != PENDING {
+                    continue;
+                }
+                // SAFETY: a pending slot is handed over to the combiner, and
+                // we hold the lock.
+                unsafe {
+                    let op = (*slot.op.get()).take().unwrap();
+                    *slot.response.get() = Some((self.apply)(data, op));
+                }
+                slot.state.store(DONE, Ordering::Release);
+                served = true;
+            }
+            if !served {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flat_combiner() {
+        let combiner = FlatCombiner::new(0usize, |count, delta: usize| {
+            *count += delta;
+            *count
+        });
+
+        let num_thrs = 8;
+        let num_ops = 10_000;
+        std::thread::scope(|s| {
+            for _ in 0..num_thrs {
+                s.spawn(|| {
+                    let mut last = 0;
+                    for _ in 0..num_ops {
+                        let count = combiner.execute(1);
+                        assert!(count > last);
+                        last = count;
+                    }
+                });
+            }
+        });
+        assert_eq!(combiner.execute(0), num_thrs * num_ops);
+    }
+}
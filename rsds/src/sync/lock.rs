@@ -0,0 +1,252 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::ManuallyDrop;
+use std::ops::{Deref, DerefMut};
+use std::time::Duration;
+
+/// A mutual exclusion lock that guards no data of its own.
+///
+/// Acquiring the lock hands out a token, which is handed back to release it.
+/// Queue locks such as [`RawMcsLock`] use the token to find the waiter to
+/// pass the lock to. Wrap a raw lock in a [`Lock`] to guard data with it.
+///
+/// # Safety
+///
+/// Implementations must ensure that between `lock` returning a token and
+/// `unlock` being called with it, no other call to `lock` returns. Releasing
+/// the lock must synchronize with the next acquisition.
+///
+/// [`RawMcsLock`]: super::RawMcsLock
+pub unsafe trait RawLock: Default + Send + Sync {
+    /// Token proving that the lock is held.
+    type Token;
+
+    /// Acquires the lock, blocking until it is available.
+    fn lock(&self) -> Self::Token;
+
+    /// Releases the lock.
+    ///
+    /// # Safety
+    ///
+    /// `token` must have been returned by acquiring this lock.
+    unsafe fn unlock(&self, token: Self::Token);
+}
+
+/// A [`RawLock`] that can be acquired without blocking.
+///
+/// # Safety
+///
+/// The same requirements as for [`RawLock`] apply to tokens returned by
+/// `try_lock`.
+pub unsafe trait RawTryLock: RawLock {
+    /// Attempts to acquire the lock, returning `None` if it is held.
+    fn try_lock(&self) -> Option<Self::Token>;
+}
+
+/// A [`RawLock`] whose waiters can give up after a timeout.
+///
+/// # Safety
+///
+/// The same requirements as for [`RawLock`] apply to tokens returned by
+/// `try_lock_for`.
+pub unsafe trait RawTimedLock: RawLock {
+    /// Attempts to acquire the lock, blocking for at most `timeout`, and
+    /// returning `None` if it could not be acquired in time.
+    fn try_lock_for(&self, timeout: Duration) -> Option<Self::Token>;
+}
+
+/// Data guarded by a [`RawLock`], with an interface like [`Mutex`]'s.
+///
+/// The raw lock decides how waiting threads are ordered and how they wait.
+/// Swapping it, as with the [`SpinLock`], [`TicketLock`] and [`McsLock`]
+/// aliases, changes the locking algorithm without touching the code using
+/// the lock.
+///
+/// [`Mutex`]: std::sync::Mutex
+/// [`SpinLock`]: super::SpinLock
+/// [`TicketLock`]: super::TicketLock
+/// [`McsLock`]: super::McsLock
+pub struct Lock<L, T> {
+    lock: L,
+    data: UnsafeCell<T>,
+}
+
+/// A held [`Lock`], which is released when the guard is dropped.
+pub struct LockGuard<'a, L: RawLock, T> {
+    lock: &'a Lock<L, T>,
+    token: ManuallyDrop<L::Token>,
+}
+
+// SAFETY: the lock hands the data to one thread at a time.
+unsafe impl<L: Send, T: Send> Send for Lock<L, T> {}
+unsafe impl<L: Sync, T: Send> Sync for Lock<L, T> {}
+
+// SAFETY: the guard hands out `&T` to whichever threads share it.
+unsafe impl<L: RawLock, T: Sync> Sync for LockGuard<'_, L, T> where L::Token: Sync {}
+
+impl<L, T> Default for Lock<L, T>
+where
+    L: RawLock,
+    T: Default,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<L, T> Lock<L, T>
+where
+    L: RawLock,
+{
+    /// Creates a new, unlocked [`Lock`] guarding `data`.
+    pub fn new(data: T) -> Self {
+        Self {
+            lock: L::default(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    /// Acquires the lock, blocking until it is available.
+    pub fn lock(&self) -> LockGuard<'_, L, T> {
+        LockGuard {
+            lock: self,
+            token: ManuallyDrop::new(self.lock.lock()),
+        }
+    }
+
+    /// Returns a mutable reference to the data.
+    ///
+    /// This is safe since the mutable borrow guarantees no other threads are
+    /// accessing the lock.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.data.get_mut()
+    }
+
+    /// Consumes the lock, returning the data.
+    pub fn into_inner(self) -> T {
+        self.data.into_inner()
+    }
+}
+
+impl<L, T> Lock<L, T>
+where
+    L: RawTryLock,
+{
+    /// Attempts to acquire the lock, returning `None` if it is held.
+    pub fn try_lock(&self) -> Option<LockGuard<'_, L, T>> {
+        self.lock.try_lock().map(|token| LockGuard {
+            lock: self,
+            token: ManuallyDrop::new(token),
+        })
+    }
+}
+
+impl<L, T> Lock<L, T>
+where
+    L: RawTimedLock,
+{
+    /// Attempts to acquire the lock, blocking for at most `timeout`, and
+    /// returning `None` if it could not be acquired in time.
+    pub fn try_lock_for(&self, timeout: Duration) -> Option<LockGuard<'_, L, T>> {
+        self.lock.try_lock_for(timeout).map(|token| LockGuard {
+            lock: self,
+            token: ManuallyDrop::new(token),
+        })
+    }
+}
+
+impl<L, T> fmt::Debug for Lock<L, T>
+where
+    L: RawTryLock,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("Lock");
+        match self.try_lock() {
+            Some(guard) => d.field("data", &&*guard),
+            None => d.field("data", &format_args!("<locked>")),
+        };
+        d.finish()
+    }
+}
+
+impl<L: RawLock, T> Deref for LockGuard<'_, L, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: we hold the lock.
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<L: RawLock, T> DerefMut for LockGuard<'_, L, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: we hold the lock.
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<L: RawLock, T> Drop for LockGuard<'_, L, T> {
+    fn drop(&mut self) {
+        // SAFETY: the token is not used again, and came from this lock.
+        unsafe {
+            let token = ManuallyDrop::take(&mut self.token);
+            self.lock.lock.unlock(token);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{RawClhLock, RawMcsLock, RawSpinLock, RawTicketLock};
+
+    fn test_lock<L: RawTryLock>() {
+        let num_thrs = 8;
+        let num_iters = 10_000;
+        let lock = Lock::<L, _>::new(0);
+
+        let guard = lock.lock();
+        assert!(lock.try_lock().is_none());
+        assert_eq!(format!("{:?}", lock), "Lock { data: <locked> }");
+        drop(guard);
+        assert_eq!(format!("{:?}", lock), "Lock { data: 0 }");
+
+        std::thread::scope(|s| {
+            for _ in 0..num_thrs {
+                s.spawn(|| {
+                    for i in 0..num_iters {
+                        if i % 2 == 0 {
+                            if let Some(mut guard) = lock.try_lock() {
+                                *guard += 1;
+                                continue;
+                            }
+                        }
+                        *lock.lock() += 1;
+                    }
+                });
+            }
+        });
+        assert_eq!(lock.into_inner(), num_thrs * num_iters);
+    }
+
+    #[test]
+    fn spin_lock() {
+        test_lock::<RawSpinLock>();
+    }
+
+    #[test]
+    fn ticket_lock() {
+        test_lock::<RawTicketLock>();
+    }
+
+    #[test]
+    fn mcs_lock() {
+        test_lock::<RawMcsLock>();
+    }
+
+    #[test]
+    fn clh_lock() {
+        test_lock::<RawClhLock>();
+    }
+}
@@ -0,0 +1,118 @@
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+use crossbeam::utils::CachePadded;
+
+use super::{Backoff, Lock, RawLock, RawTryLock};
+
+struct Node {
+    locked: AtomicBool,
+    next: AtomicPtr<CachePadded<Node>>,
+}
+
+/// A queue lock by Mellor-Crummey and Scott.
+///
+/// Waiters append a node of their own to a queue and spin on a flag in it,
+/// which the previous holder clears to pass the lock on. Like a ticket lock,
+/// the lock is granted in the order it was requested, but each release only
+/// touches the next waiter's cache line, so it scales to many waiters.
+#[derive(Debug)]
+pub struct RawMcsLock {
+    tail: AtomicPtr<CachePadded<Node>>,
+}
+
+/// Token for a held [`RawMcsLock`], pointing at the holder's queue node.
+#[derive(Debug)]
+pub struct McsToken(NonNull<CachePadded<Node>>);
+
+/// Data guarded by a [`RawMcsLock`].
+pub type McsLock<T> = Lock<RawMcsLock, T>;
+
+impl Default for RawMcsLock {
+    fn default() -> Self {
+        Self {
+            tail: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+}
+
+fn new_node() -> *mut CachePadded<Node> {
+    Box::into_raw(Box::new(CachePadded::new(Node {
+        locked: AtomicBool::new(true),
+        next: AtomicPtr::new(ptr::null_mut()),
+    })))
+}
+
+// SAFETY: a node's flag is only cleared by the holder before it, which passes
+// the lock on with a release store the waiter acquires.
+unsafe impl RawLock for RawMcsLock {
+    type Token = McsToken;
+
+    fn lock(&self) -> McsToken {
+        let node = new_node();
+        let prev = self.tail.swap(node, Ordering::AcqRel);
+        if !prev.is_null() {
+            // SAFETY: the previous node is only freed by its owner after it
+            // has handed the lock to its successor, which is us, and our node
+            // is only freed by us.
+            let (prev, node) = unsafe { (&*prev, &*node) };
+            prev.next
+                .store(node as *const _ as *mut _, Ordering::Release);
+            let backoff = Backoff::new();
+            while node.locked.load(Ordering::Acquire) {
+                backoff.snooze();
+            }
+        }
+        // SAFETY: the node was just allocated.
+        McsToken(unsafe { NonNull::new_unchecked(node) })
+    }
+
+    unsafe fn unlock(&self, token: McsToken) {
+        let node = token.0.as_ptr();
+        let node_ref = &*node;
+        let mut next = node_ref.next.load(Ordering::Acquire);
+        if next.is_null() {
+            if self
+                .tail
+                .compare_exchange(node, ptr::null_mut(), Ordering::Release, Ordering::Relaxed)
+                .is_ok()
+            {
+                drop(Box::from_raw(node));
+                return;
+            }
+            // A successor has swapped itself in, but not linked itself yet.
+            let backoff = Backoff::new();
+            loop {
+                next = node_ref.next.load(Ordering::Acquire);
+                if !next.is_null() {
+                    break;
+                }
+                backoff.snooze();
+            }
+        }
+        (&*next).locked.store(false, Ordering::Release);
+        drop(Box::from_raw(node));
+    }
+}
+
+// SAFETY: as above.
+unsafe impl RawTryLock for RawMcsLock {
+    fn try_lock(&self) -> Option<McsToken> {
+        if !self.tail.load(Ordering::Relaxed).is_null() {
+            return None;
+        }
+        let node = new_node();
+        match self
+            .tail
+            .compare_exchange(ptr::null_mut(), node, Ordering::AcqRel, Ordering::Relaxed)
+        {
+            // SAFETY: the node was just allocated.
+            Ok(_) => Some(McsToken(unsafe { NonNull::new_unchecked(node) })),
+            Err(_) => {
+                // SAFETY: the node was never published.
+                drop(unsafe { Box::from_raw(node) });
+                None
+            }
+        }
+    }
+}
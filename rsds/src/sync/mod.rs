@@ -0,0 +1,55 @@
+//! This module contains synchronization primitives that the data structures
+//! are built on, exposed for use on their own.
+
+mod backoff;
+mod biased_rw_lock;
+mod clh_lock;
+mod combining_lock;
+mod flat_combining;
+mod lock;
+mod mcs_lock;
+#[cfg(can_block)]
+mod phaser;
+#[cfg(has_clock)]
+mod rate_limiter;
+mod rcu_cell;
+mod seq_lock;
+mod snzi;
+mod spin_lock;
+#[cfg(can_block)]
+mod striped_semaphore;
+mod ticket_lock;
+
+pub use backoff::Backoff;
+pub use biased_rw_lock::{BiasedReadGuard, BiasedRwLock, BiasedWriteGuard};
+pub use clh_lock::{ClhLock, ClhToken, RawClhLock};
+pub use combining_lock::CombiningLock;
+pub(crate) use flat_combining::FlatCombiner;
+pub use lock::{Lock, LockGuard, RawLock, RawTimedLock, RawTryLock};
+pub use mcs_lock::{McsLock, McsToken, RawMcsLock};
+#[cfg(can_block)]
+pub use phaser::Phaser;
+#[cfg(has_clock)]
+pub use rate_limiter::RateLimiter;
+pub use rcu_cell::{RcuCell, RcuGuard};
+pub use seq_lock::SeqLock;
+pub use snzi::{Arrival, ReadGuard, ReadIndicator, Snzi};
+pub use spin_lock::{RawSpinLock, SpinLock};
+#[cfg(can_block)]
+pub use striped_semaphore::StripedSemaphore;
+pub use ticket_lock::{RawTicketLock, TicketLock};
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_THREAD_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static THREAD_INDEX: usize = NEXT_THREAD_INDEX.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Returns a number identifying the calling thread, handed out in order of
+/// first use. Structures use it to spread threads over slots, cells and the
+/// like, so that threads mostly stick to different ones.
+pub(crate) fn thread_index() -> usize {
+    THREAD_INDEX.with(|index| *index)
+}
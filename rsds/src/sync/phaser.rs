@@ -0,0 +1,223 @@
+use std::fmt;
+use std::sync::{Condvar, Mutex};
+
+struct State {
+    phase: u64,
+    parties: usize,
+    arrived: usize,
+}
+
+impl State {
+    /// Advances to the next phase once every registered party has arrived.
+    fn try_advance(&mut self, cvar: &Condvar) {
+        if self.arrived == self.parties {
+            self.phase += 1;
+            self.arrived = 0;
+            cvar.notify_all();
+        }
+    }
+}
+
+/// A reusable barrier for a changing set of parties, in the style of Java's
+/// `Phaser`.
+///
+/// Unlike [`std::sync::Barrier`], the number of parties can change between
+/// and during phases, and arriving is separate from waiting: a party can
+/// arrive without waiting for the others, and wait for a phase to end without
+/// arriving. Each time every registered party has arrived, the phaser moves
+/// on to the next phase, numbered from 0.
+///
+/// ```
+/// use rsds::sync::Phaser;
+///
+/// let phaser = Phaser::new(1);
+/// std::thread::scope(|s| {
+///     for _ in 0..4 {
+///         phaser.register();
+///         s.spawn(|| {
+///             for phase in 0..3 {
+///                 assert_eq!(phaser.arrive_and_await_advance(), phase + 1);
+///             }
+///             phaser.arrive_and_deregister();
+///         });
+///     }
+///     // the spawning thread takes part in the first phase only
+///     phaser.arrive_and_deregister();
+/// });
+/// assert_eq!(phaser.phase(), 4);
+/// ```
+pub struct Phaser {
+    state: Mutex<State>,
+    cvar: Condvar,
+}
+
+impl Default for Phaser {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl Phaser {
+    /// Creates a new [`Phaser`] at phase 0, with `parties` registered parties.
+    pub fn new(parties: usize) -> Self {
+        Self {
+            state: Mutex::new(State {
+                phase: 0,
+                parties,
+                arrived: 0,
+            }),
+            cvar: Condvar::new(),
+        }
+    }
+
+    /// Registers a new party, which takes part from the current phase on, and
+    /// returns the current phase.
+    pub fn register(&self) -> u64 {
+        self.bulk_register(1)
+    }
+
+    /// Registers `parties` new parties, and returns the current phase.
+    pub fn bulk_register(&self, parties: usize) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.parties += parties;
+        state.phase
+    }
+
+    /// Arrives at the current phase without waiting for the other parties,
+    /// returning the phase arrived at.
+    ///
+    /// # Panics
+    ///
+    /// Panics if every registered party has arrived already.
+    pub fn arrive(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        assert!(
+            state.arrived < state.parties,
+            "arrived parties (is {}) should be fewer than registered parties",
+            state.arrived
+        );
+        let phase = state.phase;
+        state.arrived += 1;
+        state.try_advance(&self.cvar);
+        phase
+    }
+
+    /// Deregisters a party without waiting for the others, counting as its
+    /// arrival at the current phase, and returns that phase.
+    ///
+    /// # Panics
+    ///
+    /// Panics if every registered party has arrived already.
+    pub fn arrive_and_deregister(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        assert!(
+            state.arrived < state.parties,
+            "arrived parties (is {}) should be fewer than registered parties",
+            state.arrived
+        );
+        let phase = state.phase;
+        state.parties -= 1;
+        state.try_advance(&self.cvar);
+        phase
+    }
+
+    /// Arrives at the current phase and waits for the other parties, returning
+    /// the phase that follows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if every registered party has arrived already.
+    pub fn arrive_and_await_advance(&self) -> u64 {
+        let phase = self.arrive();
+        self.await_advance(phase)
+    }
+
+    /// Waits until the phaser has moved past `phase`, without arriving, and
+    /// returns the current phase.
+    pub fn await_advance(&self, phase: u64) -> u64 {
+        let state = self.state.lock().unwrap();
+        let state = self
+            .cvar
+            .wait_while(state, |state| state.phase <= phase)
+            .unwrap();
+        state.phase
+    }
+
+    /// Returns the current phase.
+    pub fn phase(&self) -> u64 {
+        self.state.lock().unwrap().phase
+    }
+
+    /// Returns the number of registered parties.
+    pub fn registered_parties(&self) -> usize {
+        self.state.lock().unwrap().parties
+    }
+
+    /// Returns the number of parties that have arrived at the current phase.
+    pub fn arrived_parties(&self) -> usize {
+        self.state.lock().unwrap().arrived
+    }
+}
+
+impl fmt::Debug for Phaser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+        f.debug_struct("Phaser")
+            .field("phase", &state.phase)
+            .field("parties", &state.parties)
+            .field("arrived", &state.arrived)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[test]
+    fn phaser() {
+        let phaser = Phaser::new(2);
+        assert_eq!(phaser.arrive(), 0);
+        assert_eq!(phaser.arrived_parties(), 1);
+        assert_eq!(phaser.arrive(), 0);
+        assert_eq!(phaser.phase(), 1);
+        // the phase is over already, so this returns at once
+        assert_eq!(phaser.await_advance(0), 1);
+
+        assert_eq!(phaser.arrive(), 1);
+        // deregistering the last party to arrive ends the phase
+        assert_eq!(phaser.arrive_and_deregister(), 1);
+        assert_eq!(phaser.registered_parties(), 1);
+        assert_eq!(phaser.arrive(), 2);
+        assert_eq!(phaser.phase(), 3);
+    }
+
+    #[test]
+    fn phaser_concurrent() {
+        let num_thrs = 4;
+        let rounds = |t: usize| 10 * (t + 1);
+        let phaser = Phaser::new(num_thrs);
+        let counter = AtomicUsize::new(0);
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let (phaser, counter) = (&phaser, &counter);
+                s.spawn(move || {
+                    // threads leave one by one, each after its own rounds
+                    for round in 0..rounds(t) {
+                        counter.fetch_add(1, Ordering::Relaxed);
+                        phaser.arrive_and_await_advance();
+                        // every thread still taking part has counted this round
+                        let expected: usize = (0..num_thrs).map(|t| rounds(t).min(round + 1)).sum();
+                        assert_eq!(counter.load(Ordering::Relaxed), expected);
+                        phaser.arrive_and_await_advance();
+                    }
+                    phaser.arrive_and_deregister();
+                });
+            }
+        });
+        assert_eq!(phaser.registered_parties(), 0);
+    }
+}
@@ -0,0 +1,212 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crossbeam::utils::CachePadded;
+
+use super::thread_index;
+
+struct Bucket {
+    /// The time, in nanoseconds since the limiter was created, at which the
+    /// bucket will be full again if no more tokens are taken.
+    full_at: AtomicU64,
+    /// The number of tokens the bucket holds when full.
+    burst: u64,
+}
+
+/// A token-bucket rate limiter, striped over cache-padded buckets.
+///
+/// Tokens are added at a fixed rate up to a maximum burst, and every permit
+/// acquired takes a token. The tokens are split between several buckets,
+/// each refilling at its share of the rate, and threads take from a bucket
+/// of their own first, only turning to the others when theirs runs dry, so
+/// threads acquiring concurrently rarely touch the same cache line.
+///
+/// A bucket holds no token count to top up: it stores the time it will be
+/// full again, in the style of the generic cell rate algorithm, so refilling
+/// is implied by the clock, and taking tokens is a single compare-and-swap.
+///
+/// ```
+/// use rsds::sync::RateLimiter;
+///
+/// // a burst of 10, then one permit a second
+/// let limiter = RateLimiter::new(1, 10);
+/// assert!(limiter.try_acquire(10));
+/// assert!(!limiter.try_acquire(1));
+/// ```
+pub struct RateLimiter {
+    buckets: Box<[CachePadded<Bucket>]>,
+    /// Nanoseconds it takes a bucket to gain a token.
+    interval: u64,
+    start: Instant,
+}
+
+impl RateLimiter {
+    /// Creates a new [`RateLimiter`] granting `rate` permits per second, and
+    /// up to `burst` at once, with a bucket per available core.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` or `burst` is zero.
+    pub fn new(rate: u64, burst: u64) -> Self {
+        let num_cells = std::thread::available_parallelism().map_or(8, |n| n.get());
+        Self::with_cells(rate, burst, num_cells)
+    }
+
+    /// Creates a new [`RateLimiter`] granting `rate` permits per second, and
+    /// up to `burst` at once, with up to `num_cells` buckets.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate`, `burst` or `num_cells` is zero.
+    pub fn with_cells(rate: u64, burst: u64, num_cells: usize) -> Self {
+        assert!(rate > 0, "rate (is {}) should be positive", rate);
+        assert!(burst > 0, "burst (is {}) should be positive", burst);
+        assert!(
+            num_cells > 0,
+            "number of cells (is {}) should be positive",
+            num_cells
+        );
+        // every bucket holds at least one token
+        let num_cells = (num_cells as u64).min(burst);
+        let buckets = (0..num_cells)
+            .map(|i| {
+                CachePadded::new(Bucket {
+                    full_at: AtomicU64::new(0),
+                    burst: burst / num_cells + u64::from(i < burst % num_cells),
+                })
+            })
+            .collect();
+        Self {
+            buckets,
+            interval: (num_cells * 1_000_000_000 / rate).max(1),
+            start: Instant::now(),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_nanos() as u64
+    }
+
+    /// Takes up to `wanted` tokens from a bucket, returning how many it took.
+    fn take(&self, bucket: &Bucket, wanted: u64, now: u64) -> u64 {
+        let mut full_at = bucket.full_at.load(Ordering::Relaxed);
+        loop {
+            let base = full_at.max(now);
+            let missing = base - now;
+            let available = (bucket.burst * self.interval).saturating_sub(missing) / self.interval;
+            let taken = available.min(wanted);
+            if taken == 0 {
+                return 0;
+            }
+            match bucket.full_at.compare_exchange_weak(
+                full_at,
+                base + taken * self.interval,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return taken,
+                Err(current) => full_at = current,
+            }
+        }
+    }
+
+    /// Tries to acquire `permits` permits without waiting, returning whether
+    /// it succeeded.
+    ///
+    /// Taking tokens from several buckets is not atomic, so this may fail if
+    /// other threads are taking tokens at the same time, even though enough
+    /// tokens are available in total.
+    pub fn try_acquire(&self, permits: u64) -> bool {
+        let now = self.now();
+        let home = thread_index() % self.buckets.len();
+        let mut taken = Vec::new();
+        let mut total = 0;
+        for i in (home..self.buckets.len()).chain(0..home) {
+            let n = self.take(&self.buckets[i], permits - total, now);
+            if n > 0 {
+                total += n;
+                taken.push((i, n));
+            }
+            if total == permits {
+                return true;
+            }
+        }
+        // give back what was taken
+        for (i, n) in taken {
+            self.buckets[i]
+                .full_at
+                .fetch_sub(n * self.interval, Ordering::Relaxed);
+        }
+        false
+    }
+
+    /// Acquires `permits` permits, sleeping until enough tokens are available.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `permits` is more than the burst, which could never be
+    /// granted.
+    pub fn acquire(&self, permits: u64) {
+        assert!(
+            permits <= self.burst(),
+            "permits (is {}) should be at most the burst ({})",
+            permits,
+            self.burst()
+        );
+        while !self.try_acquire(permits) {
+            std::thread::sleep(Duration::from_nanos(self.interval));
+        }
+    }
+
+    /// Returns the maximum number of permits that can be granted at once.
+    pub fn burst(&self) -> u64 {
+        self.buckets.iter().map(|bucket| bucket.burst).sum()
+    }
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("burst", &self.burst())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rate_limiter() {
+        let limiter = RateLimiter::with_cells(1, 5, 4);
+        assert_eq!(limiter.burst(), 5);
+        // more than any one bucket holds
+        assert!(limiter.try_acquire(3));
+        assert!(!limiter.try_acquire(3));
+        assert!(limiter.try_acquire(2));
+        assert!(!limiter.try_acquire(1));
+    }
+
+    #[test]
+    fn rate_limiter_concurrent() {
+        let num_thrs = 4;
+        let per_thr = 50;
+        let (rate, burst) = (2_000, 20);
+        let start = Instant::now();
+        let limiter = RateLimiter::with_cells(rate, burst, 4);
+        std::thread::scope(|s| {
+            for _ in 0..num_thrs {
+                s.spawn(|| {
+                    for _ in 0..per_thr {
+                        limiter.acquire(1);
+                    }
+                });
+            }
+        });
+        // all but the initial burst had to wait for tokens
+        let refilled = (num_thrs * per_thr) as u64 - burst;
+        let expected = Duration::from_nanos(refilled * 1_000_000_000 / rate);
+        assert!(start.elapsed() >= expected);
+    }
+}
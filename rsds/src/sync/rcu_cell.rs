@@ -0,0 +1,204 @@
+use std::fmt;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Mutex;
+
+use crate::reclaim::epoch::{self, Guard};
+
+/// A read-mostly cell in the style of read-copy-update (RCU).
+///
+/// Readers get the current version without taking any lock and without ever
+/// waiting for writers. Writers build a new version from the current one and
+/// install it with a single pointer swap; writers are serialized among
+/// themselves by a mutex. Old versions are freed through epoch-based
+/// reclamation once no reader can still be looking at them.
+///
+/// This suits configuration blobs, routing tables and other data that is read
+/// constantly and replaced rarely.
+pub struct RcuCell<T> {
+    ptr: AtomicPtr<T>,
+    writer: Mutex<()>,
+}
+
+/// A reference to a version of the value in an [`RcuCell`].
+///
+/// The version stays alive for as long as the reference does, even if a writer
+/// replaces it in the meantime. Holding on to it also holds back reclamation
+/// for every epoch-based structure, so it should be short-lived.
+pub struct RcuGuard<'a, T> {
+    value: &'a T,
+    _epoch: Guard,
+}
+
+impl<'a, T> Deref for RcuGuard<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+// SAFETY: readers on any thread share `&T`, and versions are dropped on
+// whichever thread collects them.
+unsafe impl<T: Send + Sync> Send for RcuCell<T> {}
+unsafe impl<T: Send + Sync> Sync for RcuCell<T> {}
+
+impl<T> RcuCell<T> {
+    /// Creates a new [`RcuCell`] holding `value`.
+    pub fn new(value: T) -> Self {
+        Self {
+            ptr: AtomicPtr::new(Box::into_raw(Box::new(value))),
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// Returns a reference to the current version of the value.
+    pub fn read(&self) -> RcuGuard<'_, T> {
+        let guard = epoch::pin();
+        // SAFETY: versions are only freed through the epoch collector, and we
+        // are pinned for as long as the reference lives.
+        let value = unsafe { &*self.ptr.load(Ordering::Acquire) };
+        RcuGuard {
+            value,
+            _epoch: guard,
+        }
+    }
+
+    /// Returns a mutable reference to the value.
+    ///
+    /// This is safe since the mutable borrow guarantees no readers exist.
+    pub fn get_mut(&mut self) -> &mut T {
+        // SAFETY: we have exclusive access, and the pointer is always valid.
+        unsafe { &mut *self.ptr.load(Ordering::Relaxed) }
+    }
+
+    /// Consumes the cell, returning the current value.
+    pub fn into_inner(self) -> T {
+        let ptr = self.ptr.load(Ordering::Relaxed);
+        std::mem::forget(self);
+        // SAFETY: the cell owned the current version and has been forgotten.
+        *unsafe { Box::from_raw(ptr) }
+    }
+}
+
+impl<T> RcuCell<T>
+where
+    T: Send + 'static,
+{
+    /// Installs a new version computed from the current one.
+    ///
+    /// `f` runs exactly once, under the writer lock, so concurrent updates are
+    /// never lost. Readers keep seeing the old version until the new one is
+    /// installed.
+    pub fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&T) -> T,
+    {
+        let _writer = self.writer.lock().unwrap();
+        // SAFETY: only writers replace the version, and we hold the writer
+        // lock.
+        let old = unsafe { &*self.ptr.load(Ordering::Acquire) };
+        let new = f(old);
+        self.install(new);
+    }
+
+    /// Replaces the value with a new version.
+    pub fn store(&self, value: T) {
+        let _writer = self.writer.lock().unwrap();
+        self.install(value);
+    }
+
+    /// Swaps in a new version and retires the old one. Must be called with
+    /// the writer lock held.
+    fn install(&self, value: T) {
+        let guard = epoch::pin();
+        let new = Box::into_raw(Box::new(value));
+        let old = self.ptr.swap(new, Ordering::AcqRel);
+        // SAFETY: the old version is unreachable from the cell, was allocated
+        // with `Box`, and `T: Send + 'static` makes dropping it later, on any
+        // thread, sound.
+        unsafe { epoch::retire(&guard, old) };
+    }
+}
+
+impl<T> Default for RcuCell<T>
+where
+    T: Default,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> fmt::Debug for RcuCell<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RcuCell").field(&*self.read()).finish()
+    }
+}
+
+impl<T> Drop for RcuCell<T> {
+    fn drop(&mut self) {
+        // SAFETY: we have exclusive access, so no reader holds the current
+        // version. Retired versions are freed by the epoch collector.
+        drop(unsafe { Box::from_raw(self.ptr.load(Ordering::Relaxed)) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rcu_cell() {
+        let mut cell = RcuCell::new(vec![1, 2, 3]);
+        let before = cell.read();
+        cell.update(|old| old.iter().map(|i| i * 2).collect());
+        // readers keep the version they started with
+        assert_eq!(*before, [1, 2, 3]);
+        assert_eq!(*cell.read(), [2, 4, 6]);
+        drop(before);
+
+        cell.store(vec![7]);
+        cell.get_mut().push(8);
+        assert_eq!(format!("{:?}", cell), "RcuCell([7, 8])");
+        assert_eq!(cell.into_inner(), [7, 8]);
+    }
+
+    #[test]
+    fn rcu_cell_concurrent() {
+        let num_writers = 4;
+        let num_updates = 1_000;
+        let cell = RcuCell::new((0, 0));
+
+        std::thread::scope(|s| {
+            for _ in 0..num_writers {
+                s.spawn(|| {
+                    for _ in 0..num_updates {
+                        cell.update(|&(a, b)| (a + 1, b + 2));
+                    }
+                });
+            }
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let mut last = 0;
+                    while last < num_writers * num_updates {
+                        let value = cell.read();
+                        // every version is internally consistent, and versions
+                        // only move forward
+                        assert_eq!(value.1, 2 * value.0);
+                        assert!(value.0 >= last);
+                        last = value.0;
+                    }
+                });
+            }
+        });
+
+        assert_eq!(
+            *cell.read(),
+            (num_writers * num_updates, 2 * num_writers * num_updates)
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{self, AtomicUsize, Ordering};
+
+use crossbeam::utils::CachePadded;
+
+use super::Backoff;
+
+struct Inner<T> {
+    /// Odd while a writer is updating the value.
+    seq: AtomicUsize,
+    value: UnsafeCell<T>,
+}
+
+/// A sequence lock guarding a small `Copy` value.
+///
+/// Readers never write to shared memory: they copy the value optimistically,
+/// and retry if the sequence number shows that a writer was active in the
+/// meantime. Writers make the sequence number odd for the duration of their
+/// update, and are serialized among themselves by it. Reads are therefore
+/// very cheap and never block writers, but may starve while writes are
+/// frequent.
+///
+/// The lock and its value are cache-padded, so that they share no cache line
+/// with neighbouring data.
+///
+/// ```
+/// use rsds::sync::SeqLock;
+///
+/// let lock = SeqLock::new((0, 0));
+/// std::thread::scope(|s| {
+///     s.spawn(|| {
+///         for _ in 0..100 {
+///             lock.write(|(a, b)| {
+///                 *a += 1;
+///                 *b += 1;
+///             });
+///         }
+///     });
+///     s.spawn(|| {
+///         let (a, b) = lock.read();
+///         assert_eq!(a, b);
+///     });
+/// });
+/// assert_eq!(lock.read(), (100, 100));
+/// ```
+pub struct SeqLock<T> {
+    inner: CachePadded<Inner<T>>,
+}
+
+// SAFETY: readers only ever copy the value out, and writers are serialized.
+unsafe impl<T: Copy + Send> Send for SeqLock<T> {}
+unsafe impl<T: Copy + Send> Sync for SeqLock<T> {}
+
+impl<T> Default for SeqLock<T>
+where
+    T: Copy + Default,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> SeqLock<T>
+where
+    T: Copy,
+{
+    /// Creates a new [`SeqLock`] holding `value`.
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: CachePadded::new(Inner {
+                seq: AtomicUsize::new(0),
+                value: UnsafeCell::new(value),
+            }),
+        }
+    }
+
+    /// Returns a copy of the value, retrying until it reads one that no
+    /// writer touched while it was being copied.
+    pub fn read(&self) -> T {
+        let backoff = Backoff::new();
+        loop {
+            if let Some(value) = self.try_read() {
+                return value;
+            }
+            backoff.snooze();
+        }
+    }
+
+    /// Attempts to read a copy of the value once, returning `None` if a
+    /// writer was active at the time.
+    pub fn try_read(&self) -> Option<T> {
+        let seq = self.inner.seq.load(Ordering::Acquire);
+        if seq & 1 == 1 {
+            return None;
+        }
+        // SAFETY: the copy may race with a writer and be torn, so it is only
+        // read as possibly uninitialized bytes, and thrown away below unless
+        // the sequence number shows no writer was active. Volatile reads keep
+        // the compiler from assuming the value is stable.
+        let value = unsafe { ptr::read_volatile(self.inner.value.get().cast::<MaybeUninit<T>>()) };
+        // Order the copy before re-checking the sequence number.
+        atomic::fence(Ordering::Acquire);
+        if self.inner.seq.load(Ordering::Relaxed) != seq {
+            return None;
+        }
+        // SAFETY: no writer touched the value while it was copied, so the
+        // copy is a valid `T`.
+        Some(unsafe { value.assume_init() })
+    }
+
+    /// Updates the value with `f`, waiting for other writers first, and
+    /// returns what `f` returns.
+    ///
+    /// Concurrent readers retry until the update is complete. If `f` panics,
+    /// the lock is released with the value as `f` left it.
+    pub fn write<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        let backoff = Backoff::new();
+        let seq = loop {
+            let seq = self.inner.seq.load(Ordering::Relaxed);
+            if seq & 1 == 0
+                && self
+                    .inner
+                    .seq
+                    .compare_exchange_weak(seq, seq + 1, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            {
+                break seq;
+            }
+            backoff.snooze();
+        };
+        // Order the odd sequence number before our writes to the value, so
+        // that a reader seeing any of them sees the lock held on re-check.
+        atomic::fence(Ordering::Release);
+
+        struct Unlock<'a>(&'a AtomicUsize, usize);
+
+        impl Drop for Unlock<'_> {
+            fn drop(&mut self) {
+                self.0.store(self.1 + 2, Ordering::Release);
+            }
+        }
+
+        let _unlock = Unlock(&self.inner.seq, seq);
+        // SAFETY: the odd sequence number excludes other writers, and readers
+        // discard whatever they copy in the meantime.
+        f(unsafe { &mut *self.inner.value.get() })
+    }
+
+    /// Replaces the value, returning the old one.
+    pub fn replace(&self, value: T) -> T {
+        self.write(|old| std::mem::replace(old, value))
+    }
+
+    /// Returns a mutable reference to the value.
+    ///
+    /// This is safe since the mutable borrow guarantees no other threads are
+    /// accessing the lock.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.value.get_mut()
+    }
+
+    /// Consumes the lock, returning the value.
+    pub fn into_inner(self) -> T {
+        CachePadded::into_inner(self.inner).value.into_inner()
+    }
+}
+
+impl<T> fmt::Debug for SeqLock<T>
+where
+    T: Copy + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SeqLock")
+            .field("value", &self.read())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::AssertUnwindSafe;
+
+    use super::*;
+
+    #[test]
+    fn seq_lock() {
+        let mut lock = SeqLock::new([0u64; 8]);
+        assert_eq!(lock.write(|value| value[0] += 1), ());
+        assert_eq!(lock.replace([1; 8]), [1, 0, 0, 0, 0, 0, 0, 0]);
+        *lock.get_mut() = [2; 8];
+        assert_eq!(lock.try_read(), Some([2; 8]));
+
+        let panicked = std::panic::catch_unwind(AssertUnwindSafe(|| lock.write(|_| panic!())));
+        assert!(panicked.is_err());
+        // the lock is released after a panicking write
+        assert_eq!(lock.into_inner(), [2; 8]);
+    }
+
+    #[test]
+    fn seq_lock_concurrent() {
+        let lock = SeqLock::new([0u64; 8]);
+        std::thread::scope(|s| {
+            for _ in 0..2 {
+                s.spawn(|| {
+                    for _ in 0..10_000 {
+                        lock.write(|value| value.iter_mut().for_each(|v| *v += 1));
+                    }
+                });
+            }
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..10_000 {
+                        // reads never see a write half-applied
+                        let value = lock.read();
+                        assert!(value.iter().all(|&v| v == value[0]));
+                    }
+                });
+            }
+        });
+        assert_eq!(lock.read(), [20_000; 8]);
+    }
+}
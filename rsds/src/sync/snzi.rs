@@ -0,0 +1,329 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crossbeam::utils::CachePadded;
+
+use super::Backoff;
+
+// A node's state packs its surplus, counted in halves, in the low half of the
+// word and a version number in the high half.
+const HALF: u64 = 1;
+const ONE: u64 = 2;
+
+fn surplus(state: u64) -> u64 {
+    state & 0xffff_ffff
+}
+
+fn version(state: u64) -> u64 {
+    state >> 32
+}
+
+fn pack(surplus: u64, version: u64) -> u64 {
+    (version & 0xffff_ffff) << 32 | surplus
+}
+
+/// A scalable non-zero indicator (SNZI), after Ellen et al.
+///
+/// A SNZI tracks whether the number of threads that have arrived and not yet
+/// departed is non-zero, without every thread hammering a single counter.
+/// Threads arrive at the leaves of a tree, and a node only propagates an
+/// arrival to its parent when its own surplus goes from zero to non-zero, so
+/// under contention most arrivals and departures never reach the root.
+///
+/// [`Snzi::query`] only reads the root, so it is as cheap as loading a single
+/// counter. All operations are sequentially consistent, so a thread that
+/// arrives and then checks a flag, and a thread that sets the flag and then
+/// queries the indicator, cannot both miss each other.
+pub struct Snzi {
+    root: CachePadded<AtomicUsize>,
+    /// Hierarchical nodes laid out as a binary heap: the parent of node `i` is
+    /// node `(i - 1) / 2`, and the parent of node 0 is the root.
+    nodes: Box<[CachePadded<AtomicU64>]>,
+    num_leaves: usize,
+}
+
+/// A thread's arrival at a [`Snzi`], to be handed back to [`Snzi::depart`].
+#[must_use = "an arrival keeps the indicator non-zero until it departs"]
+#[derive(Debug)]
+pub struct Arrival {
+    leaf: usize,
+}
+
+impl Default for Snzi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Snzi {
+    /// Creates a new [`Snzi`] with a leaf per available core.
+    pub fn new() -> Self {
+        let num_leaves = std::thread::available_parallelism().map_or(8, |n| n.get());
+        Self::with_leaves(num_leaves)
+    }
+
+    /// Creates a new [`Snzi`] with at least `num_leaves` leaves.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_leaves` is zero.
+    pub fn with_leaves(num_leaves: usize) -> Self {
+        assert!(
+            num_leaves > 0,
+            "number of leaves (is {}) should be positive",
+            num_leaves
+        );
+        let num_leaves = num_leaves.next_power_of_two();
+        Self {
+            root: CachePadded::new(AtomicUsize::new(0)),
+            nodes: (0..2 * num_leaves - 1)
+                .map(|_| CachePadded::new(AtomicU64::new(0)))
+                .collect(),
+            num_leaves,
+        }
+    }
+
+    /// Arrives at the indicator, which stays non-zero until the returned
+    /// [`Arrival`] departs.
+    pub fn arrive(&self) -> Arrival {
+        let leaf = self.num_leaves - 1 + super::thread_index() % self.num_leaves;
+        self.arrive_at(leaf);
+        Arrival { leaf }
+    }
+
+    /// Departs from the indicator.
+    ///
+    /// # Panics
+    ///
+    /// May panic if `arrival` was returned by a different [`Snzi`].
+    pub fn depart(&self, arrival: Arrival) {
+        self.depart_at(arrival.leaf);
+    }
+
+    /// Checks whether any thread has arrived and not yet departed.
+    pub fn query(&self) -> bool {
+        self.root.load(Ordering::SeqCst) > 0
+    }
+
+    fn arrive_at(&self, i: usize) {
+        let node = &self.nodes[i];
+        let mut arrived = false;
+        let mut undo = 0;
+        while !arrived {
+            let mut state = node.load(Ordering::SeqCst);
+            if surplus(state) >= ONE && self.cas(i, state, state + ONE) {
+                arrived = true;
+            }
+            if surplus(state) == 0 {
+                let half = pack(HALF, version(state) + 1);
+                if self.cas(i, state, half) {
+                    arrived = true;
+                    state = half;
+                }
+            }
+            if surplus(state) == HALF {
+                // Someone is propagating an arrival to the parent; help, and
+                // take our extra arrival back if they beat us to it.
+                self.arrive_parent(i);
+                if !self.cas(i, state, pack(ONE, version(state))) {
+                    undo += 1;
+                }
+            }
+        }
+        for _ in 0..undo {
+            self.depart_parent(i);
+        }
+    }
+
+    fn depart_at(&self, i: usize) {
+        let node = &self.nodes[i];
+        loop {
+            let state = node.load(Ordering::SeqCst);
+            debug_assert!(surplus(state) >= ONE);
+            if self.cas(i, state, state - ONE) {
+                if surplus(state) == ONE {
+                    self.depart_parent(i);
+                }
+                return;
+            }
+        }
+    }
+
+    fn cas(&self, i: usize, current: u64, new: u64) -> bool {
+        self.nodes[i]
+            .compare_exchange(current, new, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+
+    fn arrive_parent(&self, i: usize) {
+        match i {
+            0 => {
+                self.root.fetch_add(1, Ordering::SeqCst);
+            }
+            _ => self.arrive_at((i - 1) / 2),
+        }
+    }
+
+    fn depart_parent(&self, i: usize) {
+        match i {
+            0 => {
+                self.root.fetch_sub(1, Ordering::SeqCst);
+            }
+            _ => self.depart_at((i - 1) / 2),
+        }
+    }
+}
+
+/// Tracks readers of a shared resource, so that writers can wait until they
+/// have all left.
+///
+/// Readers register by holding a [`ReadGuard`]. Paired with a flag that keeps
+/// new readers out, this gives reader–writer coordination where readers
+/// scale: a writer raises the flag and then calls
+/// [`ReadIndicator::wait_until_empty`], while a reader enters and then backs
+/// off if it sees the flag raised.
+///
+/// ```
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// use rsds::sync::ReadIndicator;
+///
+/// let readers = ReadIndicator::new();
+/// let writing = AtomicBool::new(false);
+///
+/// // reader
+/// let guard = readers.enter();
+/// if !writing.load(Ordering::SeqCst) {
+///     // ... read ...
+/// }
+/// drop(guard);
+///
+/// // writer
+/// writing.store(true, Ordering::SeqCst);
+/// readers.wait_until_empty();
+/// // ... write ...
+/// writing.store(false, Ordering::SeqCst);
+/// ```
+#[derive(Default)]
+pub struct ReadIndicator {
+    snzi: Snzi,
+}
+
+/// A reader registered with a [`ReadIndicator`], which leaves when the guard
+/// is dropped.
+#[must_use = "the reader leaves as soon as the guard is dropped"]
+pub struct ReadGuard<'a> {
+    snzi: &'a Snzi,
+    leaf: usize,
+}
+
+impl Drop for ReadGuard<'_> {
+    fn drop(&mut self) {
+        self.snzi.depart_at(self.leaf);
+    }
+}
+
+impl ReadIndicator {
+    /// Creates a new [`ReadIndicator`] with no readers.
+    pub fn new() -> Self {
+        Self { snzi: Snzi::new() }
+    }
+
+    /// Registers the calling thread as a reader until the returned guard is
+    /// dropped.
+    pub fn enter(&self) -> ReadGuard<'_> {
+        let Arrival { leaf } = self.snzi.arrive();
+        ReadGuard {
+            snzi: &self.snzi,
+            leaf,
+        }
+    }
+
+    /// Checks whether there are no readers.
+    pub fn is_empty(&self) -> bool {
+        !self.snzi.query()
+    }
+
+    /// Spins until there are no readers.
+    pub fn wait_until_empty(&self) {
+        let backoff = Backoff::new();
+        while !self.is_empty() {
+            backoff.snooze();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+
+    use super::*;
+
+    #[test]
+    fn snzi() {
+        let snzi = Snzi::with_leaves(3);
+        assert!(!snzi.query());
+        let a = snzi.arrive();
+        let b = snzi.arrive();
+        assert!(snzi.query());
+        snzi.depart(a);
+        assert!(snzi.query());
+        snzi.depart(b);
+        assert!(!snzi.query());
+    }
+
+    #[test]
+    fn snzi_concurrent() {
+        let snzi = Snzi::with_leaves(4);
+        std::thread::scope(|s| {
+            for _ in 0..8 {
+                s.spawn(|| {
+                    for _ in 0..10_000 {
+                        let arrival = snzi.arrive();
+                        assert!(snzi.query());
+                        snzi.depart(arrival);
+                    }
+                });
+            }
+        });
+        assert!(!snzi.query());
+        assert!(snzi
+            .nodes
+            .iter()
+            .all(|node| surplus(node.load(Ordering::SeqCst)) == 0));
+    }
+
+    #[test]
+    fn read_indicator_excludes_writers() {
+        let readers = ReadIndicator::new();
+        let writing = AtomicBool::new(false);
+        let readers_inside = AtomicUsize::new(0);
+        let writer_inside = AtomicBool::new(false);
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..10_000 {
+                        let _guard = readers.enter();
+                        if writing.load(Ordering::SeqCst) {
+                            continue;
+                        }
+                        readers_inside.fetch_add(1, Ordering::SeqCst);
+                        assert!(!writer_inside.load(Ordering::SeqCst));
+                        readers_inside.fetch_sub(1, Ordering::SeqCst);
+                    }
+                });
+            }
+            s.spawn(|| {
+                for _ in 0..1_000 {
+                    writing.store(true, Ordering::SeqCst);
+                    readers.wait_until_empty();
+                    writer_inside.store(true, Ordering::SeqCst);
+                    assert_eq!(readers_inside.load(Ordering::SeqCst), 0);
+                    writer_inside.store(false, Ordering::SeqCst);
+                    writing.store(false, Ordering::SeqCst);
+                }
+            });
+        });
+        assert!(readers.is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(has_clock)]
+use std::time::{Duration, Instant};
+
+#[cfg(has_clock)]
+use super::RawTimedLock;
+use super::{Backoff, Lock, RawLock, RawTryLock};
+
+/// A test-and-test-and-set spin lock.
+///
+/// Waiting threads spin on a plain load, so they only contend for the lock's
+/// cache line when it looks free, and back off between attempts. Waiters are
+/// not queued, so a waiter may be overtaken any number of times.
+#[derive(Debug, Default)]
+pub struct RawSpinLock {
+    locked: AtomicBool,
+}
+
+/// Data guarded by a [`RawSpinLock`].
+///
+/// ```
+/// use rsds::sync::SpinLock;
+///
+/// let lock = SpinLock::new(Vec::new());
+/// std::thread::scope(|s| {
+///     for i in 0..4 {
+///         let lock = &lock;
+///         s.spawn(move || lock.lock().push(i));
+///     }
+/// });
+/// assert_eq!(lock.into_inner().len(), 4);
+/// ```
+pub type SpinLock<T> = Lock<RawSpinLock, T>;
+
+// SAFETY: the flag is only cleared by the holder, and the acquiring swap and
+// releasing store synchronize.
+unsafe impl RawLock for RawSpinLock {
+    type Token = ();
+
+    fn lock(&self) {
+        let backoff = Backoff::new();
+        while self.locked.swap(true, Ordering::Acquire) {
+            while self.locked.load(Ordering::Relaxed) {
+                backoff.snooze();
+            }
+        }
+    }
+
+    unsafe fn unlock(&self, _token: ()) {
+        self.locked.store(false, Ordering::Release);
+    }
+}
+
+// SAFETY: as above.
+unsafe impl RawTryLock for RawSpinLock {
+    fn try_lock(&self) -> Option<()> {
+        (!self.locked.swap(true, Ordering::Acquire)).then_some(())
+    }
+}
+
+// SAFETY: as above.
+#[cfg(has_clock)]
+unsafe impl RawTimedLock for RawSpinLock {
+    fn try_lock_for(&self, timeout: Duration) -> Option<()> {
+        let deadline = Instant::now() + timeout;
+        let backoff = Backoff::new();
+        loop {
+            if let Some(token) = self.try_lock() {
+                return Some(token);
+            }
+            if Instant::now() >= deadline {
+                return None;
+            }
+            backoff.snooze();
+        }
+    }
+}
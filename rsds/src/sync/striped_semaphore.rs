@@ -0,0 +1,234 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+
+use crossbeam::utils::CachePadded;
+
+use super::{thread_index, Backoff};
+
+/// A counting semaphore whose permits are striped over cache-padded cells.
+///
+/// Each thread takes permits from and returns them to a cell of its own, so
+/// threads acquiring and releasing concurrently rarely touch the same cache
+/// line. When a thread's cell runs short, it gathers permits from the other
+/// cells, and the permits it releases later land in its own cell, so permits
+/// drift towards the threads using them.
+///
+/// Gathering is not atomic across cells: a [`StripedSemaphore::try_acquire`]
+/// may fail if another thread is gathering at the same time, even though
+/// enough permits are available in total. [`StripedSemaphore::acquire`]
+/// retries until it succeeds, so it is unaffected.
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// use rsds::sync::StripedSemaphore;
+///
+/// let semaphore = StripedSemaphore::new(2);
+/// let active = AtomicUsize::new(0);
+/// std::thread::scope(|s| {
+///     for _ in 0..8 {
+///         s.spawn(|| {
+///             semaphore.acquire(1);
+///             let now = active.fetch_add(1, Ordering::SeqCst);
+///             assert!(now < 2);
+///             active.fetch_sub(1, Ordering::SeqCst);
+///             semaphore.release(1);
+///         });
+///     }
+/// });
+/// assert_eq!(semaphore.available_permits(), 2);
+/// ```
+pub struct StripedSemaphore {
+    cells: Box<[CachePadded<AtomicUsize>]>,
+    /// Number of threads blocked in `acquire`, which releases check before
+    /// bothering with the lock.
+    waiters: CachePadded<AtomicUsize>,
+    lock: Mutex<()>,
+    cvar: Condvar,
+}
+
+impl StripedSemaphore {
+    /// Creates a new [`StripedSemaphore`] with `permits` permits, and a cell
+    /// per available core.
+    pub fn new(permits: usize) -> Self {
+        Self::with_cells(
+            permits,
+            std::thread::available_parallelism().map_or(8, |n| n.get()),
+        )
+    }
+
+    /// Creates a new [`StripedSemaphore`] with `permits` permits spread over
+    /// `num_cells` cells.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_cells` is zero.
+    pub fn with_cells(permits: usize, num_cells: usize) -> Self {
+        assert!(
+            num_cells > 0,
+            "number of cells (is {}) should be positive",
+            num_cells
+        );
+        Self {
+            cells: (0..num_cells)
+                .map(|i| {
+                    let share = permits / num_cells + usize::from(i < permits % num_cells);
+                    CachePadded::new(AtomicUsize::new(share))
+                })
+                .collect(),
+            waiters: CachePadded::new(AtomicUsize::new(0)),
+            lock: Mutex::new(()),
+            cvar: Condvar::new(),
+        }
+    }
+
+    fn home(&self) -> usize {
+        thread_index() % self.cells.len()
+    }
+
+    /// Takes up to `wanted` permits from a cell, returning how many it took.
+    fn take(cell: &AtomicUsize, wanted: usize) -> usize {
+        let mut permits = cell.load(Ordering::SeqCst);
+        loop {
+            let taken = permits.min(wanted);
+            if taken == 0 {
+                return 0;
+            }
+            match cell.compare_exchange_weak(
+                permits,
+                permits - taken,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return taken,
+                Err(current) => permits = current,
+            }
+        }
+    }
+
+    /// Tries to acquire `permits` permits without blocking, returning whether
+    /// it succeeded.
+    pub fn try_acquire(&self, permits: usize) -> bool {
+        self.gather(permits, false)
+    }
+
+    /// Gathers `permits` permits from the cells, putting back what it found if
+    /// that is not enough. `locked` says whether the caller holds the lock.
+    fn gather(&self, permits: usize, locked: bool) -> bool {
+        let home = self.home();
+        let mut taken = 0;
+        for i in (home..self.cells.len()).chain(0..home) {
+            taken += Self::take(&self.cells[i], permits - taken);
+            if taken == permits {
+                return true;
+            }
+        }
+        // put back what was gathered, waking anyone who missed it meanwhile
+        if taken > 0 {
+            self.put(taken, locked);
+        }
+        false
+    }
+
+    /// Acquires `permits` permits, blocking until they are available.
+    ///
+    /// Waiters are not queued, so a waiter may be overtaken by threads that
+    /// arrive later, and a waiter for many permits by waiters for fewer.
+    pub fn acquire(&self, permits: usize) {
+        let backoff = Backoff::new();
+        while !backoff.is_completed() {
+            if self.try_acquire(permits) {
+                return;
+            }
+            backoff.snooze();
+        }
+
+        let mut guard = self.lock.lock().unwrap();
+        self.waiters.fetch_add(1, Ordering::SeqCst);
+        // releases add their permits before checking for waiters, so either
+        // this sees the permits or the release sees us and waits for the lock
+        while !self.gather(permits, true) {
+            guard = self.cvar.wait(guard).unwrap();
+        }
+        self.waiters.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    /// Returns `permits` permits to the semaphore.
+    pub fn release(&self, permits: usize) {
+        self.put(permits, false);
+    }
+
+    fn put(&self, permits: usize, locked: bool) {
+        self.cells[self.home()].fetch_add(permits, Ordering::SeqCst);
+        if self.waiters.load(Ordering::SeqCst) > 0 {
+            // taking the lock orders the wakeup after a waiter's last check
+            if !locked {
+                drop(self.lock.lock().unwrap());
+            }
+            self.cvar.notify_all();
+        }
+    }
+
+    /// Returns the number of permits available.
+    ///
+    /// The count is only exact when the semaphore is quiescent.
+    pub fn available_permits(&self) -> usize {
+        self.cells
+            .iter()
+            .map(|cell| cell.load(Ordering::Relaxed))
+            .sum()
+    }
+}
+
+impl fmt::Debug for StripedSemaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StripedSemaphore")
+            .field("available_permits", &self.available_permits())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn striped_semaphore() {
+        let semaphore = StripedSemaphore::with_cells(5, 4);
+        // more than any one cell holds
+        assert!(semaphore.try_acquire(3));
+        assert!(!semaphore.try_acquire(3));
+        assert_eq!(semaphore.available_permits(), 2);
+        assert!(semaphore.try_acquire(2));
+        assert!(!semaphore.try_acquire(1));
+        semaphore.release(5);
+        assert_eq!(semaphore.available_permits(), 5);
+        assert!(semaphore.try_acquire(5));
+    }
+
+    #[test]
+    fn striped_semaphore_concurrent() {
+        let num_thrs = 8;
+        let permits = 3;
+        let semaphore = StripedSemaphore::with_cells(permits, 4);
+        let active = AtomicUsize::new(0);
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let (semaphore, active) = (&semaphore, &active);
+                s.spawn(move || {
+                    for _ in 0..1_000 {
+                        let wanted = t % 2 + 1;
+                        semaphore.acquire(wanted);
+                        let now = active.fetch_add(wanted, Ordering::SeqCst) + wanted;
+                        assert!(now <= permits);
+                        active.fetch_sub(wanted, Ordering::SeqCst);
+                        semaphore.release(wanted);
+                    }
+                });
+            }
+        });
+        assert_eq!(semaphore.available_permits(), permits);
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crossbeam::utils::CachePadded;
+
+use super::{Backoff, Lock, RawLock, RawTryLock};
+
+/// A ticket lock, which grants the lock in the order it was requested.
+///
+/// Each waiter draws a ticket from one counter and waits for a second counter
+/// to reach it, so the lock is fair. Every waiter spins on the same counter,
+/// though, so each release invalidates the cache line of all of them.
+#[derive(Debug, Default)]
+pub struct RawTicketLock {
+    next: CachePadded<AtomicUsize>,
+    serving: CachePadded<AtomicUsize>,
+}
+
+/// Data guarded by a [`RawTicketLock`].
+pub type TicketLock<T> = Lock<RawTicketLock, T>;
+
+// SAFETY: tickets are drawn once each, and only the holder of the ticket
+// being served advances it, with a release store the next holder acquires.
+unsafe impl RawLock for RawTicketLock {
+    type Token = usize;
+
+    fn lock(&self) -> usize {
+        let ticket = self.next.fetch_add(1, Ordering::Relaxed);
+        let backoff = Backoff::new();
+        while self.serving.load(Ordering::Acquire) != ticket {
+            backoff.snooze();
+        }
+        ticket
+    }
+
+    unsafe fn unlock(&self, ticket: usize) {
+        self.serving
+            .store(ticket.wrapping_add(1), Ordering::Release);
+    }
+}
+
+// SAFETY: as above.
+unsafe impl RawTryLock for RawTicketLock {
+    fn try_lock(&self) -> Option<usize> {
+        let ticket = self.serving.load(Ordering::Acquire);
+        // Only draw a ticket if it would be served right away.
+        self.next
+            .compare_exchange(
+                ticket,
+                ticket.wrapping_add(1),
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .ok()
+    }
+}
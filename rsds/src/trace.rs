@@ -0,0 +1,102 @@
+//! Tracing instrumentation for expensive internal operations, such as map
+//! resizes, long bucket scans, retries while a resize is under way, and cache
+//! evictions.
+//!
+//! With the `tracing` feature, the macros here forward to the [`tracing`]
+//! crate's, so that a subscriber can correlate an application's behavior with
+//! the structures'. Without it, they expand to nothing, and their arguments
+//! are not evaluated.
+//!
+//! [`tracing`]: https://docs.rs/tracing
+
+// Without the maps, only the caches' evictions are traced.
+#![cfg_attr(not(feature = "map"), allow(unused_macros, unused_imports))]
+
+/// Emits an event at the given level, such as `debug` or `trace`.
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)+);
+    };
+}
+
+/// Enters a debug-level span until the end of the enclosing block.
+macro_rules! enter_span {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!($($arg)+).entered();
+    };
+}
+
+pub(crate) use {enter_span, event};
+
+#[cfg(all(test, feature = "tracing", feature = "cache", feature = "map"))]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::cache::{Cache, LfuCache};
+    use crate::map::{Map, StripedHashMap};
+
+    /// A subscriber that records the names of the spans created and the
+    /// messages of the events emitted.
+    #[derive(Default)]
+    struct Recorder {
+        next_id: AtomicU64,
+        names: Mutex<Vec<String>>,
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            self.names
+                .lock()
+                .unwrap()
+                .push(span.metadata().name().to_string());
+            Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut message = String::new();
+            event.record(
+                &mut |field: &tracing::field::Field, value: &dyn std::fmt::Debug| {
+                    if field.name() == "message" {
+                        message = format!("{:?}", value);
+                    }
+                },
+            );
+            self.names.lock().unwrap().push(message);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn tracing_events() {
+        let recorder = Arc::new(Recorder::default());
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let map = StripedHashMap::with_capacity(20);
+            for i in 0..100 {
+                map.put(i, i);
+            }
+            let cache = LfuCache::with_shards(1, 1);
+            cache.insert(1, 1);
+            cache.insert(2, 2);
+        });
+        let names = recorder.names.lock().unwrap();
+        assert!(names.iter().any(|name| name == "striped_map_resize"));
+        assert!(names.iter().any(|name| name == "evicted an entry"));
+    }
+}
@@ -0,0 +1,924 @@
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::{fence, AtomicPtr, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+
+use crate::map::Map;
+use crate::reclaim::epoch::{self, Guard};
+use crate::sync::Backoff;
+
+/// Capacities of the node kinds, from smallest to largest.
+const CAPACITIES: [usize; 4] = [4, 16, 48, 256];
+
+// Low bits of a node's version.
+const OBSOLETE: u64 = 0b01;
+const LOCKED: u64 = 0b10;
+
+/// A key-value pair. Leaves are immutable: updating a value swaps in a new
+/// leaf.
+struct Leaf<V> {
+    key: Box<[u8]>,
+    value: V,
+}
+
+/// A child pointer as stored in a node, tagged in its lowest bit if it points
+/// to a leaf. Null means no child.
+type RawChild = *mut u8;
+
+enum Child<V> {
+    Inner(*mut Node<V>),
+    Leaf(*mut Leaf<V>),
+}
+
+impl<V> Child<V> {
+    fn encode(self) -> RawChild {
+        match self {
+            Child::Inner(node) => node.cast(),
+            Child::Leaf(leaf) => leaf.cast::<u8>().wrapping_add(1),
+        }
+    }
+
+    fn decode(raw: RawChild) -> Option<Self> {
+        if raw.is_null() {
+            None
+        } else if raw.addr() & 1 == 1 {
+            Some(Child::Leaf(raw.wrapping_sub(1).cast()))
+        } else {
+            Some(Child::Inner(raw.cast()))
+        }
+    }
+}
+
+fn atomics<T: Default>(len: usize) -> Box<[T]> {
+    (0..len).map(|_| T::default()).collect()
+}
+
+/// The children of a node, laid out according to the node's capacity.
+///
+/// Every field is atomic since optimistic readers race with writers; readers
+/// validate what they read against the node's version before using it.
+enum Body {
+    /// Up to 4 or 16 children, with their key bytes kept sorted.
+    Sorted {
+        keys: Box<[AtomicU8]>,
+        children: Box<[AtomicPtr<u8>]>,
+    },
+    /// Up to 48 children, found through an index from key bytes to slot
+    /// numbers plus one, where zero means no child.
+    Indexed {
+        index: Box<[AtomicU8]>,
+        children: Box<[AtomicPtr<u8>]>,
+    },
+    /// A slot per key byte.
+    Direct { children: Box<[AtomicPtr<u8>]> },
+}
+
+impl Body {
+    fn with_capacity(capacity: usize) -> Self {
+        match capacity {
+            4 | 16 => Body::Sorted {
+                keys: atomics(capacity),
+                children: atomics(capacity),
+            },
+            48 => Body::Indexed {
+                index: atomics(256),
+                children: atomics(48),
+            },
+            _ => Body::Direct {
+                children: atomics(256),
+            },
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        match self {
+            Body::Sorted { children, .. } | Body::Indexed { children, .. } => children.len(),
+            Body::Direct { children } => children.len(),
+        }
+    }
+
+    /// Returns the child under `byte`, or null. `count` may be stale when
+    /// reading optimistically.
+    fn find(&self, count: usize, byte: u8) -> RawChild {
+        match self {
+            Body::Sorted { keys, children } => (0..count.min(keys.len()))
+                .find(|&i| keys[i].load(Ordering::Relaxed) == byte)
+                .map_or(ptr::null_mut(), |i| children[i].load(Ordering::Acquire)),
+            Body::Indexed { index, children } => match index[byte as usize].load(Ordering::Relaxed)
+            {
+                0 => ptr::null_mut(),
+                slot => children[slot as usize - 1].load(Ordering::Acquire),
+            },
+            Body::Direct { children } => children[byte as usize].load(Ordering::Acquire),
+        }
+    }
+
+    /// Returns the children in key order. `count` may be stale when reading
+    /// optimistically.
+    fn entries(&self, count: usize) -> Vec<(u8, RawChild)> {
+        let entries: Vec<_> = match self {
+            Body::Sorted { keys, children } => (0..count.min(keys.len()))
+                .map(|i| {
+                    let byte = keys[i].load(Ordering::Relaxed);
+                    (byte, children[i].load(Ordering::Acquire))
+                })
+                .collect(),
+            Body::Indexed { index, children } => (0..=u8::MAX)
+                .filter_map(|byte| match index[byte as usize].load(Ordering::Relaxed) {
+                    0 => None,
+                    slot => Some((byte, children[slot as usize - 1].load(Ordering::Acquire))),
+                })
+                .collect(),
+            Body::Direct { children } => (0..=u8::MAX)
+                .map(|byte| (byte, children[byte as usize].load(Ordering::Acquire)))
+                .collect(),
+        };
+        entries
+            .into_iter()
+            .filter(|(_, child)| !child.is_null())
+            .collect()
+    }
+
+    /// Adds a child under a byte that has none. Must be called with the lock
+    /// held, on a body with room to spare.
+    fn add(&self, count: usize, byte: u8, child: RawChild) {
+        match self {
+            Body::Sorted { keys, children } => {
+                let pos = (0..count)
+                    .find(|&i| keys[i].load(Ordering::Relaxed) > byte)
+                    .unwrap_or(count);
+                for i in (pos..count).rev() {
+                    keys[i + 1].store(keys[i].load(Ordering::Relaxed), Ordering::Relaxed);
+                    children[i + 1].store(children[i].load(Ordering::Relaxed), Ordering::Release);
+                }
+                keys[pos].store(byte, Ordering::Relaxed);
+                children[pos].store(child, Ordering::Release);
+            }
+            Body::Indexed { index, children } => {
+                let slot = children
+                    .iter()
+                    .position(|child| child.load(Ordering::Relaxed).is_null())
+                    .unwrap();
+                children[slot].store(child, Ordering::Release);
+                index[byte as usize].store(slot as u8 + 1, Ordering::Relaxed);
+            }
+            Body::Direct { children } => children[byte as usize].store(child, Ordering::Release),
+        }
+    }
+
+    /// Replaces the child under `byte`, which must exist. Must be called with
+    /// the lock held.
+    fn replace(&self, count: usize, byte: u8, child: RawChild) {
+        let slot = match self {
+            Body::Sorted { keys, children } => {
+                let pos = (0..count)
+                    .find(|&i| keys[i].load(Ordering::Relaxed) == byte)
+                    .unwrap();
+                &children[pos]
+            }
+            Body::Indexed { index, children } => {
+                &children[index[byte as usize].load(Ordering::Relaxed) as usize - 1]
+            }
+            Body::Direct { children } => &children[byte as usize],
+        };
+        slot.store(child, Ordering::Release);
+    }
+
+    /// Removes the child under `byte`, which must exist. Must be called with
+    /// the lock held.
+    fn remove(&self, count: usize, byte: u8) {
+        match self {
+            Body::Sorted { keys, children } => {
+                let pos = (0..count)
+                    .find(|&i| keys[i].load(Ordering::Relaxed) == byte)
+                    .unwrap();
+                for i in pos + 1..count {
+                    keys[i - 1].store(keys[i].load(Ordering::Relaxed), Ordering::Relaxed);
+                    children[i - 1].store(children[i].load(Ordering::Relaxed), Ordering::Release);
+                }
+                children[count - 1].store(ptr::null_mut(), Ordering::Release);
+            }
+            Body::Indexed { index, children } => {
+                let slot = index[byte as usize].swap(0, Ordering::Relaxed);
+                children[slot as usize - 1].store(ptr::null_mut(), Ordering::Release);
+            }
+            Body::Direct { children } => {
+                children[byte as usize].store(ptr::null_mut(), Ordering::Release)
+            }
+        }
+    }
+}
+
+/// An inner node.
+///
+/// Writers lock the node through its version, and readers read it
+/// optimistically, validating the version afterwards. A node's prefix is
+/// immutable, so changing it replaces the node, as does growing it.
+struct Node<V> {
+    version: AtomicU64,
+    /// Key bytes shared by everything below the node, past its parent's byte.
+    prefix: Box<[u8]>,
+    /// The leaf whose key ends right after the prefix, if any.
+    terminal: AtomicPtr<Leaf<V>>,
+    count: AtomicUsize,
+    body: Body,
+}
+
+impl<V> Node<V> {
+    fn new(prefix: &[u8], capacity: usize) -> Self {
+        Self {
+            version: AtomicU64::new(0),
+            prefix: prefix.into(),
+            terminal: AtomicPtr::new(ptr::null_mut()),
+            count: AtomicUsize::new(0),
+            body: Body::with_capacity(capacity),
+        }
+    }
+
+    fn alloc(self) -> *mut Self {
+        Box::into_raw(Box::new(self))
+    }
+
+    /// Returns a copy of the node with a new prefix and capacity. Must be
+    /// called with the lock held.
+    fn copy(&self, prefix: &[u8], capacity: usize) -> Self {
+        let node = Node::new(prefix, capacity);
+        node.terminal
+            .store(self.terminal.load(Ordering::Relaxed), Ordering::Relaxed);
+        for (byte, child) in self.body.entries(self.count()) {
+            node.body.add(node.count(), byte, child);
+            node.count.fetch_add(1, Ordering::Relaxed);
+        }
+        node
+    }
+
+    fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    fn is_full(&self) -> bool {
+        self.count() == self.body.capacity()
+    }
+
+    fn find(&self, byte: u8) -> Option<Child<V>> {
+        Child::decode(self.body.find(self.count(), byte))
+    }
+
+    fn add(&self, byte: u8, child: Child<V>) {
+        let count = self.count();
+        self.body.add(count, byte, child.encode());
+        self.count.store(count + 1, Ordering::Relaxed);
+    }
+
+    /// Adds `leaf` to a node starting at `depth` of its key.
+    fn add_leaf(&self, depth: usize, leaf: *mut Leaf<V>) {
+        // SAFETY: the leaf is either ours or kept alive by the epoch.
+        let key = unsafe { &(*leaf).key };
+        match key.get(depth) {
+            Some(&byte) => self.add(byte, Child::Leaf(leaf)),
+            None => self.terminal.store(leaf, Ordering::Release),
+        }
+    }
+
+    fn replace(&self, byte: u8, child: Child<V>) {
+        self.body.replace(self.count(), byte, child.encode());
+    }
+
+    fn remove(&self, byte: u8) {
+        let count = self.count();
+        self.body.remove(count, byte);
+        self.count.store(count - 1, Ordering::Relaxed);
+    }
+
+    /// Starts an optimistic read, or fails if the node is locked or obsolete.
+    fn read_lock(&self) -> Option<u64> {
+        let version = self.version.load(Ordering::Acquire);
+        (version & (LOCKED | OBSOLETE) == 0).then_some(version)
+    }
+
+    /// Checks that the node has not changed since `version` was read.
+    fn validate(&self, version: u64) -> Option<()> {
+        fence(Ordering::Acquire);
+        (self.version.load(Ordering::Relaxed) == version).then_some(())
+    }
+
+    /// Locks the node, or fails if it has changed since `version` was read.
+    fn upgrade(&self, version: u64) -> Option<()> {
+        self.version
+            .compare_exchange(
+                version,
+                version + LOCKED,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            )
+            .ok()?;
+        // Pairs with the fence in `validate`: a reader that sees any of our
+        // writes sees the node locked.
+        fence(Ordering::Release);
+        Some(())
+    }
+
+    fn unlock(&self) {
+        self.version.fetch_add(LOCKED, Ordering::Release);
+    }
+
+    /// Unlocks the node and marks it obsolete, so readers restart.
+    fn unlock_obsolete(&self) {
+        self.version.fetch_add(LOCKED | OBSOLETE, Ordering::Release);
+    }
+
+    /// Reads the node's terminal leaf and children consistently, waiting out
+    /// writers. An obsolete node is read as it was when replaced.
+    fn snapshot(&self) -> (*mut Leaf<V>, Vec<(u8, RawChild)>) {
+        let backoff = Backoff::new();
+        loop {
+            let version = self.version.load(Ordering::Acquire);
+            if version & LOCKED == 0 {
+                let terminal = self.terminal.load(Ordering::Acquire);
+                let entries = self.body.entries(self.count());
+                if self.validate(version).is_some() {
+                    return (terminal, entries);
+                }
+            }
+            backoff.snooze();
+        }
+    }
+}
+
+/// Frees everything below `node`.
+///
+/// # Safety
+///
+/// The caller must have exclusive access to `node` and everything below it.
+unsafe fn free_children<V>(node: &Node<V>) {
+    let terminal = node.terminal.load(Ordering::Relaxed);
+    if !terminal.is_null() {
+        drop(Box::from_raw(terminal));
+    }
+    for (_, child) in node.body.entries(node.count()) {
+        match Child::<V>::decode(child) {
+            Some(Child::Leaf(leaf)) => drop(Box::from_raw(leaf)),
+            Some(Child::Inner(inner)) => {
+                free_children(&*inner);
+                drop(Box::from_raw(inner));
+            }
+            None => {}
+        }
+    }
+}
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// A concurrent adaptive radix tree (ART), mapping byte strings to values in
+/// lexicographic order.
+///
+/// Inner nodes come in four sizes, holding up to 4, 16, 48 or 256 children,
+/// and grow as children are added, so sparse and dense key spaces alike are
+/// stored compactly. Paths without branches are collapsed into node prefixes.
+///
+/// Synchronization uses optimistic lock coupling: lookups take no locks and
+/// write nothing to shared memory, validating each node's version instead,
+/// and updates only lock the one or two nodes they modify. Replaced nodes and
+/// leaves are freed through epoch-based reclamation. Nodes are not shrunk or
+/// merged when entries are removed.
+///
+/// ```
+/// use rsds::tree::ArtMap;
+///
+/// let map = ArtMap::new();
+/// map.insert(b"apple", 1);
+/// map.insert(b"apricot", 2);
+/// map.insert(b"banana", 3);
+///
+/// let mut keys = Vec::new();
+/// map.for_each_prefix(b"ap", |key, _| keys.push(key.to_vec()));
+/// assert_eq!(keys, [b"apple".to_vec(), b"apricot".to_vec()]);
+/// ```
+pub struct ArtMap<V> {
+    root: Box<Node<V>>,
+    len: AtomicUsize,
+    _marker: PhantomData<V>,
+}
+
+/// A reference to a value in an [`ArtMap`].
+///
+/// The value stays alive for as long as the reference does, even if the entry
+/// is replaced or removed in the meantime. Holding on to it holds back
+/// reclamation for every epoch-based structure, so it should be short-lived.
+pub struct ArtRef<'a, V> {
+    value: &'a V,
+    _epoch: Guard,
+}
+
+impl<'a, V> Deref for ArtRef<'a, V> {
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+// SAFETY: values are shared between threads through `get`, and moved between
+// threads through `insert` and reclamation.
+unsafe impl<V: Send> Send for ArtMap<V> {}
+unsafe impl<V: Send + Sync> Sync for ArtMap<V> {}
+
+impl<V> Default for ArtMap<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> ArtMap<V> {
+    /// Creates a new, empty [`ArtMap`].
+    pub fn new() -> Self {
+        Self {
+            // The root is never full and has no prefix, so it is never
+            // replaced.
+            root: Box::new(Node::new(&[], 256)),
+            len: AtomicUsize::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the value mapped to `key`, if any.
+    pub fn get(&self, key: &[u8]) -> Option<ArtRef<'_, V>> {
+        let guard = epoch::pin();
+        let backoff = Backoff::new();
+        let leaf = loop {
+            match self.try_get(key) {
+                Some(leaf) => break leaf?,
+                None => backoff.spin(),
+            }
+        };
+        Some(ArtRef {
+            // SAFETY: the leaf was reachable while we were pinned, so it
+            // outlives the guard.
+            value: unsafe { &(*leaf).value },
+            _epoch: guard,
+        })
+    }
+
+    /// Checks whether the map contains `key`.
+    pub fn contains_key(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Calls `f` on every entry whose key starts with `prefix`, in key order.
+    ///
+    /// The traversal is weakly consistent: it sees every entry present for
+    /// the whole call, and may or may not see entries inserted or removed
+    /// concurrently.
+    pub fn for_each_prefix<F>(&self, prefix: &[u8], mut f: F)
+    where
+        F: FnMut(&[u8], &V),
+    {
+        let _guard = epoch::pin();
+        let mut node: &Node<V> = &self.root;
+        let mut depth = 0;
+        // Find the node below which every key starts with the prefix.
+        loop {
+            let rest = &prefix[depth..];
+            if node.prefix.starts_with(rest) {
+                break;
+            }
+            if !rest.starts_with(&node.prefix) {
+                return;
+            }
+            depth += node.prefix.len();
+            let byte = prefix[depth];
+            let (_, entries) = node.snapshot();
+            let child = entries.into_iter().find(|&(b, _)| b == byte);
+            // SAFETY: children read from a snapshot were reachable while we
+            // were pinned.
+            match child.and_then(|(_, child)| Child::decode(child)) {
+                None => return,
+                Some(Child::Leaf(leaf)) => {
+                    let leaf = unsafe { &*leaf };
+                    if leaf.key.starts_with(prefix) {
+                        f(&leaf.key, &leaf.value);
+                    }
+                    return;
+                }
+                Some(Child::Inner(child)) => {
+                    node = unsafe { &*child };
+                    depth += 1;
+                }
+            }
+        }
+        Self::visit(node, &mut f);
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Checks whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn try_get(&self, key: &[u8]) -> Option<Option<*mut Leaf<V>>> {
+        let mut node: &Node<V> = &self.root;
+        let mut version = node.read_lock()?;
+        let mut depth = 0;
+        loop {
+            if !key[depth..].starts_with(&node.prefix) {
+                node.validate(version)?;
+                return Some(None);
+            }
+            depth += node.prefix.len();
+            let Some(&byte) = key.get(depth) else {
+                let leaf = node.terminal.load(Ordering::Acquire);
+                node.validate(version)?;
+                return Some((!leaf.is_null()).then_some(leaf));
+            };
+
+            let child = node.find(byte);
+            node.validate(version)?;
+            // SAFETY: children of a validated node are kept alive by the
+            // epoch.
+            match child {
+                None => return Some(None),
+                Some(Child::Leaf(leaf)) => {
+                    return Some((unsafe { &*(*leaf).key } == key).then_some(leaf));
+                }
+                Some(Child::Inner(child)) => {
+                    let child = unsafe { &*child };
+                    let child_version = child.read_lock()?;
+                    node.validate(version)?;
+                    node = child;
+                    version = child_version;
+                    depth += 1;
+                }
+            }
+        }
+    }
+
+    fn visit<F>(node: &Node<V>, f: &mut F)
+    where
+        F: FnMut(&[u8], &V),
+    {
+        let (terminal, entries) = node.snapshot();
+        // SAFETY: the caller is pinned, and everything in the snapshot was
+        // reachable while it was.
+        if let Some(leaf) = unsafe { terminal.as_ref() } {
+            f(&leaf.key, &leaf.value);
+        }
+        for (_, child) in entries {
+            match Child::decode(child) {
+                Some(Child::Leaf(leaf)) => {
+                    let leaf = unsafe { &*leaf };
+                    f(&leaf.key, &leaf.value);
+                }
+                Some(Child::Inner(child)) => Self::visit(unsafe { &*child }, f),
+                None => {}
+            }
+        }
+    }
+}
+
+impl<V> ArtMap<V>
+where
+    V: Send + 'static,
+{
+    /// Maps `key` to `value`, returning whether the key was not in the map
+    /// before.
+    pub fn insert(&self, key: &[u8], value: V) -> bool {
+        let guard = epoch::pin();
+        let leaf = Box::into_raw(Box::new(Leaf {
+            key: key.into(),
+            value,
+        }));
+        let backoff = Backoff::new();
+        loop {
+            match self.try_insert(leaf, &guard) {
+                Some(inserted) => {
+                    if inserted {
+                        self.len.fetch_add(1, Ordering::Relaxed);
+                    }
+                    return inserted;
+                }
+                None => backoff.spin(),
+            }
+        }
+    }
+
+    /// Removes `key` from the map, returning whether it was in the map.
+    pub fn remove(&self, key: &[u8]) -> bool {
+        let guard = epoch::pin();
+        let backoff = Backoff::new();
+        loop {
+            match self.try_remove(key, &guard) {
+                Some(removed) => {
+                    if removed {
+                        self.len.fetch_sub(1, Ordering::Relaxed);
+                    }
+                    return removed;
+                }
+                None => backoff.spin(),
+            }
+        }
+    }
+
+    fn try_insert(&self, leaf: *mut Leaf<V>, guard: &Guard) -> Option<bool> {
+        // SAFETY: the leaf is ours until it is linked.
+        let key = unsafe { &(*leaf).key };
+        let mut parent: Option<(&Node<V>, u64, u8)> = None;
+        let mut node: &Node<V> = &self.root;
+        let mut version = node.read_lock()?;
+        let mut depth = 0;
+        loop {
+            let matched = common_prefix_len(&node.prefix, &key[depth..]);
+            if matched < node.prefix.len() {
+                // The key leaves the node's prefix: put a new node holding the
+                // common part above both the node and the leaf. The root has
+                // no prefix, so the node has a parent.
+                let (parent, parent_version, parent_byte) = parent.unwrap();
+                parent.upgrade(parent_version)?;
+                if node.upgrade(version).is_none() {
+                    parent.unlock();
+                    return None;
+                }
+                let split = Node::new(&node.prefix[..matched], CAPACITIES[0]);
+                let shortened = node.copy(&node.prefix[matched + 1..], node.body.capacity());
+                split.add(node.prefix[matched], Child::Inner(shortened.alloc()));
+                split.add_leaf(depth + matched, leaf);
+                parent.replace(parent_byte, Child::Inner(split.alloc()));
+                parent.unlock();
+                self.retire_node(node, guard);
+                return Some(true);
+            }
+            depth += node.prefix.len();
+            let Some(&byte) = key.get(depth) else {
+                node.upgrade(version)?;
+                let old = node.terminal.swap(leaf, Ordering::AcqRel);
+                node.unlock();
+                if old.is_null() {
+                    return Some(true);
+                }
+                // SAFETY: the old leaf is unlinked, and `V: Send + 'static`.
+                unsafe { epoch::retire(guard, old) };
+                return Some(false);
+            };
+
+            let child = node.find(byte);
+            node.validate(version)?;
+            match child {
+                None if !node.is_full() => {
+                    node.upgrade(version)?;
+                    node.add(byte, Child::Leaf(leaf));
+                    node.unlock();
+                    return Some(true);
+                }
+                None => {
+                    // The root never fills up, so the node has a parent.
+                    let (parent, parent_version, parent_byte) = parent.unwrap();
+                    parent.upgrade(parent_version)?;
+                    if node.upgrade(version).is_none() {
+                        parent.unlock();
+                        return None;
+                    }
+                    let capacity = node.body.capacity();
+                    let next = CAPACITIES.iter().find(|&&c| c > capacity).unwrap();
+                    let grown = node.copy(&node.prefix, *next);
+                    grown.add(byte, Child::Leaf(leaf));
+                    parent.replace(parent_byte, Child::Inner(grown.alloc()));
+                    parent.unlock();
+                    self.retire_node(node, guard);
+                    return Some(true);
+                }
+                Some(Child::Leaf(existing)) => {
+                    // SAFETY: children of a validated node are kept alive by
+                    // the epoch.
+                    let existing_key = unsafe { &(*existing).key };
+                    node.upgrade(version)?;
+                    if existing_key == key {
+                        node.replace(byte, Child::Leaf(leaf));
+                        node.unlock();
+                        // SAFETY: the old leaf is unlinked, and
+                        // `V: Send + 'static`.
+                        unsafe { epoch::retire(guard, existing) };
+                        return Some(false);
+                    }
+                    // Two keys share the byte: push both leaves down into a
+                    // new node holding the rest of their common prefix.
+                    let depth = depth + 1;
+                    let common = common_prefix_len(&existing_key[depth..], &key[depth..]);
+                    let inner = Node::new(&key[depth..depth + common], CAPACITIES[0]);
+                    inner.add_leaf(depth + common, existing);
+                    inner.add_leaf(depth + common, leaf);
+                    node.replace(byte, Child::Inner(inner.alloc()));
+                    node.unlock();
+                    return Some(true);
+                }
+                Some(Child::Inner(child)) => {
+                    // SAFETY: as above.
+                    let child = unsafe { &*child };
+                    let child_version = child.read_lock()?;
+                    node.validate(version)?;
+                    parent = Some((node, version, byte));
+                    node = child;
+                    version = child_version;
+                    depth += 1;
+                }
+            }
+        }
+    }
+
+    fn try_remove(&self, key: &[u8], guard: &Guard) -> Option<bool> {
+        let mut node: &Node<V> = &self.root;
+        let mut version = node.read_lock()?;
+        let mut depth = 0;
+        loop {
+            if !key[depth..].starts_with(&node.prefix) {
+                node.validate(version)?;
+                return Some(false);
+            }
+            depth += node.prefix.len();
+            let Some(&byte) = key.get(depth) else {
+                let leaf = node.terminal.load(Ordering::Acquire);
+                node.validate(version)?;
+                if leaf.is_null() {
+                    return Some(false);
+                }
+                node.upgrade(version)?;
+                node.terminal.store(ptr::null_mut(), Ordering::Release);
+                node.unlock();
+                // SAFETY: the leaf is unlinked, and `V: Send + 'static`.
+                unsafe { epoch::retire(guard, leaf) };
+                return Some(true);
+            };
+
+            let child = node.find(byte);
+            node.validate(version)?;
+            // SAFETY: children of a validated node are kept alive by the
+            // epoch.
+            match child {
+                None => return Some(false),
+                Some(Child::Leaf(leaf)) => {
+                    if unsafe { &*(*leaf).key } != key {
+                        return Some(false);
+                    }
+                    node.upgrade(version)?;
+                    node.remove(byte);
+                    node.unlock();
+                    // SAFETY: the leaf is unlinked, and `V: Send + 'static`.
+                    unsafe { epoch::retire(guard, leaf) };
+                    return Some(true);
+                }
+                Some(Child::Inner(child)) => {
+                    let child = unsafe { &*child };
+                    let child_version = child.read_lock()?;
+                    node.validate(version)?;
+                    node = child;
+                    version = child_version;
+                    depth += 1;
+                }
+            }
+        }
+    }
+
+    /// Marks a locked node that has been replaced obsolete, and retires it.
+    fn retire_node(&self, node: &Node<V>, guard: &Guard) {
+        node.unlock_obsolete();
+        // SAFETY: the node is unlinked, and its children now belong to its
+        // replacement, so dropping it only frees the node itself.
+        unsafe { epoch::retire(guard, node as *const Node<V> as *mut Node<V>) };
+    }
+}
+
+impl<V> Drop for ArtMap<V> {
+    fn drop(&mut self) {
+        // SAFETY: we have exclusive access. Replaced nodes and leaves are no
+        // longer reachable, and are freed by the epoch collector.
+        unsafe { free_children(&self.root) };
+    }
+}
+
+impl<V> Map for ArtMap<V>
+where
+    V: Send + 'static,
+{
+    type Key = Vec<u8>;
+    type Val = V;
+    type ValueRef<'a> = ArtRef<'a, V>;
+
+    fn get(&self, key: &Vec<u8>) -> Option<ArtRef<'_, V>> {
+        ArtMap::get(self, key)
+    }
+
+    fn contains(&self, key: &Vec<u8>) -> bool {
+        self.contains_key(key)
+    }
+
+    fn put(&self, key: Vec<u8>, value: V) {
+        self.insert(&key, value);
+    }
+
+    fn remove(&self, key: &Vec<u8>) -> bool {
+        ArtMap::remove(self, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn art_map() {
+        let map = ArtMap::new();
+        let keys: [&[u8]; 7] = [b"", b"a", b"ab", b"abc", b"abd", b"b", b"abcdefgh"];
+        for (i, key) in keys.iter().enumerate() {
+            assert!(map.insert(key, i));
+        }
+        assert_eq!(map.len(), keys.len());
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(*map.get(key).unwrap(), i);
+        }
+        assert!(map.get(b"abcd").is_none());
+        assert!(map.get(b"c").is_none());
+
+        assert!(!map.insert(b"ab", 10));
+        assert_eq!(*map.get(b"ab").unwrap(), 10);
+
+        let mut entries = Vec::new();
+        map.for_each_prefix(b"ab", |key, &value| entries.push((key.to_vec(), value)));
+        assert_eq!(
+            entries,
+            [
+                (b"ab".to_vec(), 10),
+                (b"abc".to_vec(), 3),
+                (b"abcdefgh".to_vec(), 6),
+                (b"abd".to_vec(), 4)
+            ]
+        );
+
+        assert!(map.remove(b"abc"));
+        assert!(!map.remove(b"abc"));
+        assert!(map.remove(b""));
+        assert!(map.get(b"abc").is_none());
+        assert_eq!(*map.get(b"abcdefgh").unwrap(), 6);
+        assert_eq!(map.len(), keys.len() - 2);
+    }
+
+    #[test]
+    fn art_map_grows_and_splits() {
+        let map = ArtMap::new();
+        // every byte under a shared prefix grows a node through every size
+        for byte in 0..=u8::MAX {
+            assert!(map.insert(&[7, 7, 7, byte], byte));
+        }
+        // keys diverging inside the prefix split it
+        assert!(map.insert(&[7, 8], 0));
+        assert!(map.insert(&[7, 7], 0));
+        for byte in 0..=u8::MAX {
+            assert_eq!(*map.get(&[7, 7, 7, byte]).unwrap(), byte);
+        }
+
+        let mut count = 0;
+        let mut last = Vec::new();
+        map.for_each_prefix(&[7], |key, _| {
+            assert!(key > &last[..]);
+            last = key.to_vec();
+            count += 1;
+        });
+        assert_eq!(count, 258);
+    }
+
+    #[test]
+    fn art_map_concurrent() {
+        let num_thrs = 8;
+        let num_keys = 2_000u32;
+        let map = ArtMap::new();
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let map = &map;
+                s.spawn(move || {
+                    for i in 0..num_keys {
+                        let key = (i * num_thrs + t).to_be_bytes();
+                        assert!(map.insert(&key, i));
+                        assert_eq!(*map.get(&key).unwrap(), i);
+                        if i % 2 == 0 {
+                            assert!(map.remove(&key));
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(map.len(), (num_thrs * num_keys / 2) as usize);
+        let mut count = 0;
+        map.for_each_prefix(&[], |key, &value| {
+            let key = u32::from_be_bytes(key.try_into().unwrap());
+            assert_eq!(value, key / num_thrs);
+            assert_eq!(value % 2, 1);
+            count += 1;
+        });
+        assert_eq!(count, map.len());
+    }
+}
@@ -0,0 +1,361 @@
+use std::fmt;
+use std::ops::Range;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::RwLock;
+
+struct Node<K, V> {
+    /// Every range stored at this node contains the center.
+    center: K,
+    /// The node's ranges, sorted by start and then end.
+    entries: RwLock<Vec<(Range<K>, V)>>,
+    /// Subtree of ranges ending at or before the center.
+    left: AtomicPtr<Node<K, V>>,
+    /// Subtree of ranges starting after the center.
+    right: AtomicPtr<Node<K, V>>,
+}
+
+impl<K, V> Node<K, V> {
+    fn new(center: K) -> *mut Self {
+        Box::into_raw(Box::new(Node {
+            center,
+            entries: RwLock::new(Vec::new()),
+            left: AtomicPtr::new(ptr::null_mut()),
+            right: AtomicPtr::new(ptr::null_mut()),
+        }))
+    }
+}
+
+/// Where a range lies relative to a node's center.
+enum Side {
+    Here,
+    Left,
+    Right,
+}
+
+fn side<K: Ord>(range: &Range<K>, center: &K) -> Side {
+    if range.end <= *center {
+        Side::Left
+    } else if range.start > *center {
+        Side::Right
+    } else {
+        Side::Here
+    }
+}
+
+fn compare<K: Ord>(a: &Range<K>, b: &Range<K>) -> std::cmp::Ordering {
+    a.start.cmp(&b.start).then_with(|| a.end.cmp(&b.end))
+}
+
+/// A concurrent map from half-open ranges to values, answering which ranges
+/// contain a point or overlap a range.
+///
+/// The map is a centered interval tree. Each node has a center point, and
+/// holds the ranges containing it under a lock of its own; ranges wholly
+/// before or after the center go to the node's left or right subtree. A
+/// node's center is the start of the first range that reaches it, and nodes
+/// are linked in with a compare-and-swap and never unlinked while the map is
+/// alive. Walking the tree therefore takes no locks, and operations only
+/// contend when they touch ranges stored at the same node.
+///
+/// The tree is not rebalanced, so its depth depends on the order ranges are
+/// inserted in, as with an unbalanced binary search tree: ranges inserted in
+/// random order keep it shallow, while disjoint ranges inserted in sorted
+/// order degrade it to a list.
+///
+/// ```
+/// use rsds::tree::IntervalTree;
+///
+/// let reservations = IntervalTree::new();
+/// std::thread::scope(|s| {
+///     s.spawn(|| reservations.insert(9..12, "standup"));
+///     s.spawn(|| reservations.insert(11..13, "lunch"));
+///     s.spawn(|| reservations.insert(14..16, "review"));
+/// });
+///
+/// let mut clashes = Vec::new();
+/// reservations.for_each_overlapping(&(11..15), |_, name| clashes.push(*name));
+/// clashes.sort_unstable();
+/// assert_eq!(clashes, ["lunch", "review", "standup"]);
+/// ```
+pub struct IntervalTree<K, V> {
+    root: AtomicPtr<Node<K, V>>,
+    len: AtomicUsize,
+}
+
+// SAFETY: ranges and values are shared between threads through queries, and
+// moved in from other threads through insertion.
+unsafe impl<K: Send, V: Send> Send for IntervalTree<K, V> {}
+unsafe impl<K: Send + Sync, V: Send + Sync> Sync for IntervalTree<K, V> {}
+
+impl<K, V> Default for IntervalTree<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> IntervalTree<K, V> {
+    /// Creates a new, empty [`IntervalTree`].
+    pub fn new() -> Self {
+        Self {
+            root: AtomicPtr::new(ptr::null_mut()),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of ranges in the map.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Checks whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<K, V> IntervalTree<K, V>
+where
+    K: Ord + Clone,
+{
+    /// Maps `range` to `value`, returning the value it was mapped to before,
+    /// if any.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is empty.
+    pub fn insert(&self, range: Range<K>, value: V) -> Option<V> {
+        assert!(range.start < range.end, "range should not be empty");
+        let mut link = &self.root;
+        loop {
+            let mut node = link.load(Ordering::Acquire);
+            if node.is_null() {
+                let new = Node::new(range.start.clone());
+                match link.compare_exchange(node, new, Ordering::AcqRel, Ordering::Acquire) {
+                    Ok(_) => node = new,
+                    Err(winner) => {
+                        // SAFETY: our node was never published.
+                        drop(unsafe { Box::from_raw(new) });
+                        node = winner;
+                    }
+                }
+            }
+            // SAFETY: nodes are only freed when the tree is dropped.
+            let node = unsafe { &*node };
+            link = match side(&range, &node.center) {
+                Side::Left => &node.left,
+                Side::Right => &node.right,
+                Side::Here => {
+                    let mut entries = node.entries.write().unwrap();
+                    return match entries.binary_search_by(|(r, _)| compare(r, &range)) {
+                        Ok(i) => Some(std::mem::replace(&mut entries[i].1, value)),
+                        Err(i) => {
+                            entries.insert(i, (range, value));
+                            self.len.fetch_add(1, Ordering::Relaxed);
+                            None
+                        }
+                    };
+                }
+            };
+        }
+    }
+
+    /// Removes `range` from the map, returning the value it was mapped to, if
+    /// any.
+    pub fn remove(&self, range: &Range<K>) -> Option<V> {
+        let node = self.find(range)?;
+        let mut entries = node.entries.write().unwrap();
+        let i = entries.binary_search_by(|(r, _)| compare(r, range)).ok()?;
+        self.len.fetch_sub(1, Ordering::Relaxed);
+        Some(entries.remove(i).1)
+    }
+
+    /// Checks whether the map contains `range` itself.
+    pub fn contains_range(&self, range: &Range<K>) -> bool {
+        self.find(range).is_some_and(|node| {
+            let entries = node.entries.read().unwrap();
+            entries.binary_search_by(|(r, _)| compare(r, range)).is_ok()
+        })
+    }
+
+    /// Calls `f` on every range containing `point`, and its value.
+    ///
+    /// Each node's ranges are visited under its read lock, so `f` must not
+    /// modify the map. The traversal is weakly consistent: it sees every
+    /// range present for the whole call, and may or may not see ranges
+    /// inserted or removed concurrently.
+    pub fn for_each_stabbing<F>(&self, point: &K, mut f: F)
+    where
+        F: FnMut(&Range<K>, &V),
+    {
+        let mut node = self.root.load(Ordering::Acquire);
+        while !node.is_null() {
+            // SAFETY: nodes are only freed when the tree is dropped.
+            let n = unsafe { &*node };
+            let entries = n.entries.read().unwrap();
+            for (range, value) in entries.iter().take_while(|(r, _)| r.start <= *point) {
+                if *point < range.end {
+                    f(range, value);
+                }
+            }
+            node = match point.cmp(&n.center) {
+                std::cmp::Ordering::Less => n.left.load(Ordering::Acquire),
+                std::cmp::Ordering::Greater => n.right.load(Ordering::Acquire),
+                // Ranges in either subtree end at or before the center, or
+                // start after it.
+                std::cmp::Ordering::Equal => break,
+            };
+        }
+    }
+
+    /// Calls `f` on every range overlapping `range`, and its value.
+    ///
+    /// The same caveats as for [`for_each_stabbing`] apply.
+    ///
+    /// [`for_each_stabbing`]: IntervalTree::for_each_stabbing
+    pub fn for_each_overlapping<F>(&self, range: &Range<K>, mut f: F)
+    where
+        F: FnMut(&Range<K>, &V),
+    {
+        let mut stack = vec![self.root.load(Ordering::Acquire)];
+        while let Some(node) = stack.pop() {
+            if node.is_null() {
+                continue;
+            }
+            // SAFETY: nodes are only freed when the tree is dropped.
+            let n = unsafe { &*node };
+            let entries = n.entries.read().unwrap();
+            for (r, value) in entries.iter().take_while(|(r, _)| r.start < range.end) {
+                if range.start < r.end {
+                    f(r, value);
+                }
+            }
+            if range.start < n.center {
+                stack.push(n.left.load(Ordering::Acquire));
+            }
+            if range.end > n.center {
+                stack.push(n.right.load(Ordering::Acquire));
+            }
+        }
+    }
+
+    /// Checks whether any range in the map overlaps `range`.
+    pub fn overlaps(&self, range: &Range<K>) -> bool {
+        let mut found = false;
+        self.for_each_overlapping(range, |_, _| found = true);
+        found
+    }
+
+    /// Returns the node that holds `range`, if it exists.
+    fn find(&self, range: &Range<K>) -> Option<&Node<K, V>> {
+        let mut node = self.root.load(Ordering::Acquire);
+        loop {
+            if node.is_null() {
+                return None;
+            }
+            // SAFETY: nodes are only freed when the tree is dropped.
+            let n = unsafe { &*node };
+            node = match side(range, &n.center) {
+                Side::Left => n.left.load(Ordering::Acquire),
+                Side::Right => n.right.load(Ordering::Acquire),
+                Side::Here => return Some(n),
+            };
+        }
+    }
+}
+
+impl<K, V> Drop for IntervalTree<K, V> {
+    fn drop(&mut self) {
+        let mut stack = vec![*self.root.get_mut()];
+        while let Some(node) = stack.pop() {
+            if node.is_null() {
+                continue;
+            }
+            // SAFETY: we have exclusive access, and each node is linked once.
+            let mut node = unsafe { Box::from_raw(node) };
+            stack.push(*node.left.get_mut());
+            stack.push(*node.right.get_mut());
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for IntervalTree<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IntervalTree")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stabbing(tree: &IntervalTree<u32, u32>, point: u32) -> Vec<u32> {
+        let mut values = Vec::new();
+        tree.for_each_stabbing(&point, |_, &v| values.push(v));
+        values.sort_unstable();
+        values
+    }
+
+    #[test]
+    fn interval_tree() {
+        let tree = IntervalTree::new();
+        assert_eq!(tree.insert(10..20, 0), None);
+        assert_eq!(tree.insert(0..5, 1), None);
+        assert_eq!(tree.insert(15..30, 2), None);
+        assert_eq!(tree.insert(12..14, 3), None);
+        assert_eq!(tree.insert(25..26, 4), None);
+        assert_eq!(tree.insert(10..20, 5), Some(0));
+        assert_eq!(tree.len(), 5);
+
+        assert_eq!(stabbing(&tree, 10), [5]);
+        assert_eq!(stabbing(&tree, 13), [3, 5]);
+        assert_eq!(stabbing(&tree, 20), [2]);
+        assert_eq!(stabbing(&tree, 25), [2, 4]);
+        assert!(stabbing(&tree, 5).is_empty());
+        assert!(!tree.overlaps(&(5..10)));
+        assert!(tree.overlaps(&(5..11)));
+
+        assert_eq!(tree.remove(&(15..30)), Some(2));
+        assert_eq!(tree.remove(&(15..30)), None);
+        assert!(!tree.contains_range(&(15..30)));
+        assert!(tree.contains_range(&(25..26)));
+        assert_eq!(stabbing(&tree, 25), [4]);
+        assert_eq!(tree.len(), 4);
+    }
+
+    #[test]
+    fn interval_tree_concurrent() {
+        let num_thrs = 8;
+        let num_elems = 1_000;
+        let tree = IntervalTree::new();
+
+        // thread `t` reserves every slot `i..i + 1` with `i % num_thrs == t`,
+        // along with a wider range that it removes again
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let tree = &tree;
+                s.spawn(move || {
+                    for i in (t..num_elems).step_by(num_thrs) {
+                        let i = i as u32;
+                        tree.insert(i..i + 1, i);
+                        tree.insert(i..i + 3, i);
+                        assert!(tree.remove(&(i..i + 3)).is_some());
+                    }
+                });
+            }
+        });
+
+        assert_eq!(tree.len(), num_elems);
+        for point in 0..num_elems as u32 {
+            assert_eq!(stabbing(&tree, point), [point]);
+        }
+        let mut count = 0;
+        tree.for_each_overlapping(&(100..200), |range, &v| {
+            assert_eq!(range.start, v);
+            count += 1;
+        });
+        assert_eq!(count, 100);
+    }
+}
@@ -0,0 +1,12 @@
+//! This module contains concurrent trees, mapping keys or ranges of keys to
+//! values in order.
+
+mod art;
+mod interval_tree;
+mod radix_tree;
+mod relaxed_tree;
+
+pub use art::{ArtMap, ArtRef};
+pub use interval_tree::IntervalTree;
+pub use radix_tree::{RadixRef, RadixTree};
+pub use relaxed_tree::{RelaxedTreeMap, RelaxedTreeRef};
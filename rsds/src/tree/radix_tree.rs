@@ -0,0 +1,425 @@
+use std::marker::PhantomData;
+use std::ops::{Bound, Deref, RangeBounds};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+use crate::map::Map;
+use crate::reclaim::epoch::{self, Guard};
+
+/// Number of key bits each level consumes.
+const BITS: u32 = 6;
+/// Number of slots in a node.
+const FANOUT: usize = 1 << BITS;
+/// Height of a root covering every key.
+const MAX_HEIGHT: u32 = (u64::BITS - 1) / BITS;
+
+/// A node of the tree. Slots of nodes at height zero point to values, and
+/// slots of higher nodes point to nodes one level down.
+struct Node {
+    height: u32,
+    slots: [AtomicPtr<()>; FANOUT],
+}
+
+impl Node {
+    fn alloc(height: u32) -> *mut Node {
+        Box::into_raw(Box::new(Node {
+            height,
+            slots: [(); FANOUT].map(|_| AtomicPtr::new(ptr::null_mut())),
+        }))
+    }
+
+    /// Checks whether the node, as the root, covers `key`.
+    fn covers(&self, key: u64) -> bool {
+        self.height == MAX_HEIGHT || key >> (BITS * (self.height + 1)) == 0
+    }
+
+    fn slot(&self, key: u64) -> &AtomicPtr<()> {
+        &self.slots[(key >> (BITS * self.height)) as usize % FANOUT]
+    }
+}
+
+/// Frees `node` and everything below it.
+///
+/// # Safety
+///
+/// The caller must have exclusive access to `node` and everything below it,
+/// and values must be of type `V`.
+unsafe fn free<V>(node: *mut Node) {
+    let node = Box::from_raw(node);
+    for slot in node.slots.iter() {
+        let ptr = slot.load(Ordering::Relaxed);
+        if ptr.is_null() {
+            continue;
+        }
+        match node.height {
+            0 => drop(Box::from_raw(ptr.cast::<V>())),
+            _ => free::<V>(ptr.cast()),
+        }
+    }
+}
+
+/// A concurrent radix tree mapping `u64` keys to values, in key order.
+///
+/// Like the Linux kernel's radix tree, each level of the tree consumes six
+/// bits of the key, so a node has 64 slots and the tree grows in height only
+/// as far as the largest key needs. Dense or clustered integer IDs thus share
+/// nodes, which makes the tree much more compact and cache-friendly than a
+/// hash map for them.
+///
+/// Lookups take no locks. Inserts install missing nodes with a single
+/// compare-and-swap, and values are swapped in and out of their slots
+/// atomically, so no operation ever blocks another. Replaced and removed
+/// values are freed through epoch-based reclamation. Nodes are only freed
+/// when the tree is dropped, even if they become empty.
+///
+/// ```
+/// use rsds::tree::RadixTree;
+///
+/// let tree = RadixTree::new();
+/// tree.insert(1_000_000, "b");
+/// tree.insert(7, "a");
+///
+/// let mut entries = Vec::new();
+/// tree.for_each_range(.., |key, &value| entries.push((key, value)));
+/// assert_eq!(entries, [(7, "a"), (1_000_000, "b")]);
+/// ```
+pub struct RadixTree<V> {
+    root: AtomicPtr<Node>,
+    len: AtomicUsize,
+    _marker: PhantomData<V>,
+}
+
+/// A reference to a value in a [`RadixTree`].
+///
+/// The value stays alive for as long as the reference does, even if the entry
+/// is replaced or removed in the meantime. Holding on to it holds back
+/// reclamation for every epoch-based structure, so it should be short-lived.
+pub struct RadixRef<'a, V> {
+    value: &'a V,
+    _epoch: Guard,
+}
+
+impl<'a, V> Deref for RadixRef<'a, V> {
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+// SAFETY: values are shared between threads through `get`, and moved between
+// threads through `insert` and reclamation.
+unsafe impl<V: Send> Send for RadixTree<V> {}
+unsafe impl<V: Send + Sync> Sync for RadixTree<V> {}
+
+impl<V> Default for RadixTree<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> RadixTree<V> {
+    /// Creates a new, empty [`RadixTree`].
+    pub fn new() -> Self {
+        Self {
+            root: AtomicPtr::new(Node::alloc(0)),
+            len: AtomicUsize::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the value mapped to `key`, if any.
+    pub fn get(&self, key: u64) -> Option<RadixRef<'_, V>> {
+        let guard = epoch::pin();
+        // SAFETY: nodes are only freed when the tree is dropped.
+        let mut node = unsafe { &*self.root.load(Ordering::Acquire) };
+        if !node.covers(key) {
+            return None;
+        }
+        loop {
+            let ptr = node.slot(key).load(Ordering::Acquire);
+            if ptr.is_null() {
+                return None;
+            }
+            if node.height == 0 {
+                return Some(RadixRef {
+                    // SAFETY: the value was reachable while we were pinned,
+                    // so it outlives the guard.
+                    value: unsafe { &*ptr.cast::<V>() },
+                    _epoch: guard,
+                });
+            }
+            // SAFETY: as above.
+            node = unsafe { &*ptr.cast::<Node>() };
+        }
+    }
+
+    /// Checks whether the tree contains `key`.
+    pub fn contains_key(&self, key: u64) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Calls `f` on every entry whose key lies in `range`, in key order.
+    ///
+    /// The traversal is weakly consistent: it sees every entry present for
+    /// the whole call, and may or may not see entries inserted or removed
+    /// concurrently.
+    pub fn for_each_range<R, F>(&self, range: R, mut f: F)
+    where
+        R: RangeBounds<u64>,
+        F: FnMut(u64, &V),
+    {
+        let lo = match range.start_bound() {
+            Bound::Included(&lo) => lo as u128,
+            Bound::Excluded(&lo) => lo as u128 + 1,
+            Bound::Unbounded => 0,
+        };
+        let hi = match range.end_bound() {
+            Bound::Included(&hi) => hi as u128 + 1,
+            Bound::Excluded(&hi) => hi as u128,
+            Bound::Unbounded => u64::MAX as u128 + 1,
+        };
+        let _guard = epoch::pin();
+        // SAFETY: nodes are only freed when the tree is dropped.
+        let root = unsafe { &*self.root.load(Ordering::Acquire) };
+        Self::visit(root, 0, lo..hi, &mut f);
+    }
+
+    /// Returns the number of entries in the tree.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Checks whether the tree is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Visits the entries below `node`, whose keys start at `base`, that lie
+    /// in `range`. Must be called while pinned.
+    fn visit<F>(node: &Node, base: u128, range: std::ops::Range<u128>, f: &mut F)
+    where
+        F: FnMut(u64, &V),
+    {
+        let span = 1u128 << (BITS * node.height);
+        for (i, slot) in node.slots.iter().enumerate() {
+            let start = base + i as u128 * span;
+            if start >= range.end {
+                break;
+            }
+            if start + span <= range.start {
+                continue;
+            }
+            let ptr = slot.load(Ordering::Acquire);
+            if ptr.is_null() {
+                continue;
+            }
+            // SAFETY: nodes are only freed when the tree is dropped, and the
+            // caller is pinned, so values stay alive.
+            match node.height {
+                0 => f(start as u64, unsafe { &*ptr.cast::<V>() }),
+                _ => Self::visit(unsafe { &*ptr.cast::<Node>() }, start, range.clone(), f),
+            }
+        }
+    }
+}
+
+impl<V> RadixTree<V>
+where
+    V: Send + 'static,
+{
+    /// Maps `key` to `value`, returning whether the key was not in the tree
+    /// before.
+    pub fn insert(&self, key: u64, value: V) -> bool {
+        let guard = epoch::pin();
+        let value = Box::into_raw(Box::new(value));
+        let mut node = self.grow_to_cover(key);
+        while node.height > 0 {
+            let slot = node.slot(key);
+            let mut child = slot.load(Ordering::Acquire);
+            if child.is_null() {
+                let new = Node::alloc(node.height - 1);
+                child = match slot.compare_exchange(
+                    ptr::null_mut(),
+                    new.cast(),
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => new.cast(),
+                    Err(winner) => {
+                        // SAFETY: our node was never published.
+                        drop(unsafe { Box::from_raw(new) });
+                        winner
+                    }
+                };
+            }
+            // SAFETY: nodes are only freed when the tree is dropped.
+            node = unsafe { &*child.cast::<Node>() };
+        }
+
+        let old = node.slot(key).swap(value.cast(), Ordering::AcqRel);
+        if old.is_null() {
+            self.len.fetch_add(1, Ordering::Relaxed);
+            return true;
+        }
+        // SAFETY: the old value is unlinked, and `V: Send + 'static`.
+        unsafe { epoch::retire(&guard, old.cast::<V>()) };
+        false
+    }
+
+    /// Removes `key` from the tree, returning whether it was in the tree.
+    pub fn remove(&self, key: u64) -> bool {
+        let guard = epoch::pin();
+        // SAFETY: nodes are only freed when the tree is dropped.
+        let mut node = unsafe { &*self.root.load(Ordering::Acquire) };
+        if !node.covers(key) {
+            return false;
+        }
+        while node.height > 0 {
+            let child = node.slot(key).load(Ordering::Acquire);
+            if child.is_null() {
+                return false;
+            }
+            // SAFETY: as above.
+            node = unsafe { &*child.cast::<Node>() };
+        }
+
+        let old = node.slot(key).swap(ptr::null_mut(), Ordering::AcqRel);
+        if old.is_null() {
+            return false;
+        }
+        self.len.fetch_sub(1, Ordering::Relaxed);
+        // SAFETY: the value is unlinked, and `V: Send + 'static`.
+        unsafe { epoch::retire(&guard, old.cast::<V>()) };
+        true
+    }
+
+    /// Adds levels on top of the root until it covers `key`, returning the
+    /// root.
+    fn grow_to_cover(&self, key: u64) -> &Node {
+        loop {
+            let root = self.root.load(Ordering::Acquire);
+            // SAFETY: nodes are only freed when the tree is dropped.
+            let node = unsafe { &*root };
+            if node.covers(key) {
+                return node;
+            }
+            // Every key the old root covers stays in the same place under
+            // the first slot of the new one.
+            let new = Node::alloc(node.height + 1);
+            // SAFETY: the new node is ours until it is published.
+            unsafe { (*new).slots[0].store(root.cast(), Ordering::Relaxed) };
+            if self
+                .root
+                .compare_exchange(root, new, Ordering::AcqRel, Ordering::Acquire)
+                .is_err()
+            {
+                // SAFETY: our node was never published, and dropping it does
+                // not touch the old root.
+                drop(unsafe { Box::from_raw(new) });
+            }
+        }
+    }
+}
+
+impl<V> Drop for RadixTree<V> {
+    fn drop(&mut self) {
+        // SAFETY: we have exclusive access. Replaced values are no longer
+        // reachable, and are freed by the epoch collector.
+        unsafe { free::<V>(self.root.load(Ordering::Relaxed)) };
+    }
+}
+
+impl<V> Map for RadixTree<V>
+where
+    V: Send + 'static,
+{
+    type Key = u64;
+    type Val = V;
+    type ValueRef<'a> = RadixRef<'a, V>;
+
+    fn get(&self, key: &u64) -> Option<RadixRef<'_, V>> {
+        RadixTree::get(self, *key)
+    }
+
+    fn contains(&self, key: &u64) -> bool {
+        self.contains_key(*key)
+    }
+
+    fn put(&self, key: u64, value: V) {
+        self.insert(key, value);
+    }
+
+    fn remove(&self, key: &u64) -> bool {
+        RadixTree::remove(self, *key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn radix_tree() {
+        let tree = RadixTree::new();
+        let keys = [0, 1, 63, 64, 4095, 4096, 1 << 40, u64::MAX - 1, u64::MAX];
+        for (i, &key) in keys.iter().enumerate() {
+            assert!(tree.insert(key, i));
+        }
+        assert_eq!(tree.len(), keys.len());
+        for (i, &key) in keys.iter().enumerate() {
+            assert_eq!(*tree.get(key).unwrap(), i);
+        }
+        assert!(tree.get(2).is_none());
+        assert!(tree.get(1 << 41).is_none());
+
+        assert!(!tree.insert(64, 10));
+        assert_eq!(*tree.get(64).unwrap(), 10);
+        assert!(tree.remove(4095));
+        assert!(!tree.remove(4095));
+        assert!(!tree.remove(5));
+
+        let mut entries = Vec::new();
+        tree.for_each_range(63..=4096, |key, &value| entries.push((key, value)));
+        assert_eq!(entries, [(63, 2), (64, 10), (4096, 5)]);
+        entries.clear();
+        tree.for_each_range(1 << 40.., |key, &value| entries.push((key, value)));
+        assert_eq!(entries, [(1 << 40, 6), (u64::MAX - 1, 7), (u64::MAX, 8)]);
+    }
+
+    #[test]
+    fn radix_tree_concurrent() {
+        let num_thrs = 8;
+        let num_keys = 10_000;
+        let tree = RadixTree::new();
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let tree = &tree;
+                s.spawn(move || {
+                    // interleaved keys, and the tree grows while threads race
+                    for i in 0..num_keys {
+                        let key = (i * num_thrs + t) << 20;
+                        assert!(tree.insert(key, i));
+                        assert_eq!(*tree.get(key).unwrap(), i);
+                        if i % 2 == 0 {
+                            assert!(tree.remove(key));
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(tree.len(), (num_thrs * num_keys / 2) as usize);
+        let mut last = None;
+        let mut count = 0;
+        tree.for_each_range(.., |key, &value| {
+            assert!(last < Some(key));
+            assert_eq!(value, (key >> 20) / num_thrs);
+            assert_eq!(value % 2, 1);
+            last = Some(key);
+            count += 1;
+        });
+        assert_eq!(count, tree.len());
+    }
+}
@@ -0,0 +1,612 @@
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::ops::{Bound, Deref, RangeBounds};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+use crate::map::Map;
+use crate::reclaim::epoch::{self, Guard};
+use crate::sync::Backoff;
+
+const LEFT: usize = 0;
+const RIGHT: usize = 1;
+
+/// A node of the tree.
+///
+/// A node's value is null when it is logically deleted. A node that has been
+/// physically removed, either unlinked or replaced by a copy during a
+/// rotation, is marked so and never modified again.
+struct Node<K, V> {
+    /// The key, or `None` for the root sentinel, which is greater than every
+    /// key.
+    key: Option<K>,
+    value: AtomicPtr<V>,
+    children: [AtomicPtr<Node<K, V>>; 2],
+    /// Estimated height of the node's subtree, maintained lazily.
+    height: AtomicUsize,
+    removed: AtomicBool,
+    lock: Mutex<()>,
+}
+
+impl<K, V> Node<K, V>
+where
+    K: Ord,
+{
+    fn alloc(key: Option<K>, value: *mut V, children: [*mut Self; 2]) -> *mut Self {
+        let height = 1 + height(children[LEFT]).max(height(children[RIGHT]));
+        Box::into_raw(Box::new(Self {
+            key,
+            value: AtomicPtr::new(value),
+            children: children.map(AtomicPtr::new),
+            height: AtomicUsize::new(height),
+            removed: AtomicBool::new(false),
+            lock: Mutex::new(()),
+        }))
+    }
+
+    /// Returns the direction to take from this node to find `key`.
+    fn dir(&self, key: &K) -> usize {
+        match &self.key {
+            Some(k) if key > k => RIGHT,
+            _ => LEFT,
+        }
+    }
+
+    fn child(&self, dir: usize) -> *mut Self {
+        self.children[dir].load(Ordering::Acquire)
+    }
+
+    /// Returns the direction of `child` below this node, if it is a child.
+    fn dir_of(&self, child: &Self) -> Option<usize> {
+        (0..2).find(|&dir| ptr::eq(self.child(dir), child))
+    }
+
+    fn is_removed(&self) -> bool {
+        self.removed.load(Ordering::SeqCst)
+    }
+
+    /// Locks the node if it is free and not removed.
+    fn try_lock(&self) -> Option<MutexGuard<'_, ()>> {
+        let guard = match self.lock.try_lock() {
+            Ok(guard) => guard,
+            Err(TryLockError::WouldBlock) => return None,
+            Err(TryLockError::Poisoned(err)) => panic!("{}", err),
+        };
+        (!self.is_removed()).then_some(guard)
+    }
+
+    fn update_height(&self) {
+        let height = 1 + height(self.child(LEFT)).max(height(self.child(RIGHT)));
+        self.height.store(height, Ordering::Relaxed);
+    }
+}
+
+/// Returns the estimated height of the subtree rooted at `node`. The node
+/// must be null or kept alive by the epoch.
+fn height<K, V>(node: *mut Node<K, V>) -> usize {
+    // SAFETY: guaranteed by the caller.
+    unsafe { node.as_ref() }.map_or(0, |node| node.height.load(Ordering::Relaxed))
+}
+
+/// A concurrent binary search tree map with relaxed balancing, after Crain,
+/// Gramoli and Raynal's contention-friendly tree.
+///
+/// Updates are kept as short as possible: inserting adds a leaf or revives a
+/// logically deleted node, and removing only marks a node deleted, each
+/// under the lock of a single node. Restructuring is decoupled from the
+/// update itself: after updating, a thread walks back up its path, unlinking
+/// deleted nodes with at most one child and rotating nodes whose subtrees'
+/// heights differ by more than one, AVL-style. Restructuring only uses
+/// `try_lock`, and simply skips a step if a node is busy, so under contention
+/// the tree is allowed to drift out of balance rather than have threads wait
+/// on each other; later updates along the same path fix it up.
+///
+/// Lookups and scans take no locks. A rotated node is replaced by a copy
+/// rather than modified, and an unlinked node points back to its parent, so
+/// concurrent traversals never lose their way. Removed nodes and replaced
+/// values are freed through epoch-based reclamation.
+///
+/// ```
+/// use rsds::tree::RelaxedTreeMap;
+///
+/// let map = RelaxedTreeMap::new();
+/// for key in [5, 1, 4, 2, 3] {
+///     map.insert(key, key * 10);
+/// }
+/// map.remove(&4);
+///
+/// let mut entries = Vec::new();
+/// map.for_each_range(2.., |&key, &value| entries.push((key, value)));
+/// assert_eq!(entries, [(2, 20), (3, 30), (5, 50)]);
+/// ```
+pub struct RelaxedTreeMap<K, V> {
+    root: Box<Node<K, V>>,
+    len: AtomicUsize,
+    _marker: PhantomData<(K, V)>,
+}
+
+/// A reference to a value in a [`RelaxedTreeMap`].
+///
+/// The value stays alive for as long as the reference does, even if the entry
+/// is replaced or removed in the meantime. Holding on to it holds back
+/// reclamation for every epoch-based structure, so it should be short-lived.
+pub struct RelaxedTreeRef<'a, V> {
+    value: &'a V,
+    _epoch: Guard,
+}
+
+impl<'a, V> Deref for RelaxedTreeRef<'a, V> {
+    type Target = V;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+// SAFETY: keys and values are shared between threads through lookups, and
+// moved between threads through updates and reclamation.
+unsafe impl<K: Send, V: Send> Send for RelaxedTreeMap<K, V> {}
+unsafe impl<K: Send + Sync, V: Send + Sync> Sync for RelaxedTreeMap<K, V> {}
+
+impl<K, V> Default for RelaxedTreeMap<K, V>
+where
+    K: Ord,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V> RelaxedTreeMap<K, V>
+where
+    K: Ord,
+{
+    /// Creates a new, empty [`RelaxedTreeMap`].
+    pub fn new() -> Self {
+        // SAFETY: the sentinel was just allocated with `Box`.
+        let root = unsafe {
+            Box::from_raw(Node::alloc(
+                None,
+                ptr::null_mut(),
+                [ptr::null_mut(), ptr::null_mut()],
+            ))
+        };
+        Self {
+            root,
+            len: AtomicUsize::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns a reference to the value mapped to `key`, if any.
+    pub fn get(&self, key: &K) -> Option<RelaxedTreeRef<'_, V>> {
+        let guard = epoch::pin();
+        let backoff = Backoff::new();
+        let value = loop {
+            let (path, found) = self.search(key, &guard);
+            if !found {
+                return None;
+            }
+            let node = path.last().unwrap();
+            let value = node.value.load(Ordering::SeqCst);
+            // The value is current as long as the node was not removed
+            // before we read it.
+            if !node.is_removed() {
+                break value;
+            }
+            backoff.spin();
+        };
+        Some(RelaxedTreeRef {
+            // SAFETY: the value was reachable while we were pinned, so it
+            // outlives the guard.
+            value: unsafe { value.as_ref() }?,
+            _epoch: guard,
+        })
+    }
+
+    /// Checks whether the map contains `key`.
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Calls `f` on every entry whose key lies in `range`, in key order.
+    ///
+    /// The scan is weakly consistent: it sees every entry present for the
+    /// whole call, and may or may not see entries inserted or removed
+    /// concurrently. Each step is a search from the root, so a scan over `n`
+    /// entries takes `O(n log n)` time.
+    pub fn for_each_range<R, F>(&self, range: R, mut f: F)
+    where
+        R: RangeBounds<K>,
+        F: FnMut(&K, &V),
+    {
+        let guard = epoch::pin();
+        let mut lower = range.start_bound();
+        while let Some((key, value)) = self.ceiling(lower, &guard) {
+            let in_range = match range.end_bound() {
+                Bound::Included(end) => key <= end,
+                Bound::Excluded(end) => key < end,
+                Bound::Unbounded => true,
+            };
+            if !in_range {
+                return;
+            }
+            f(key, value);
+            lower = Bound::Excluded(key);
+        }
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Checks whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the path from the root to the node holding `key`, or else to
+    /// the node below which it belongs, and whether the key was found.
+    fn search<'g>(&'g self, key: &K, _guard: &'g Guard) -> (Vec<&'g Node<K, V>>, bool) {
+        let mut path: Vec<&Node<K, V>> = vec![&self.root];
+        loop {
+            let node = *path.last().unwrap();
+            if node.key.as_ref() == Some(key) {
+                return (path, true);
+            }
+            // SAFETY: nodes reachable while we are pinned stay alive.
+            match unsafe { node.child(node.dir(key)).as_ref() } {
+                Some(child) => path.push(child),
+                None => return (path, false),
+            }
+        }
+    }
+
+    /// Returns the entry with the smallest key above `lower`, if any.
+    fn ceiling<'g>(&'g self, mut lower: Bound<&'g K>, _guard: &'g Guard) -> Option<(&'g K, &'g V)> {
+        let backoff = Backoff::new();
+        loop {
+            let mut best: Option<&Node<K, V>> = None;
+            let mut curr = self.root.child(LEFT);
+            // SAFETY: nodes and values reachable while we are pinned stay
+            // alive.
+            while let Some(node) = unsafe { curr.as_ref() } {
+                let above = match (&node.key, lower) {
+                    (None, _) => true,
+                    (Some(_), Bound::Unbounded) => true,
+                    (Some(key), Bound::Included(lower)) => key >= lower,
+                    (Some(key), Bound::Excluded(lower)) => key > lower,
+                };
+                if above {
+                    if node.key.is_some() {
+                        best = Some(node);
+                    }
+                    curr = node.child(LEFT);
+                } else {
+                    curr = node.child(RIGHT);
+                }
+            }
+
+            let node = best?;
+            let value = node.value.load(Ordering::SeqCst);
+            if node.is_removed() {
+                backoff.spin();
+                continue;
+            }
+            let key = node.key.as_ref().unwrap();
+            match unsafe { value.as_ref() } {
+                Some(value) => return Some((key, value)),
+                // Logically deleted; look further.
+                None => lower = Bound::Excluded(key),
+            }
+        }
+    }
+}
+
+impl<K, V> RelaxedTreeMap<K, V>
+where
+    K: Ord + Clone + Send + 'static,
+    V: Send + 'static,
+{
+    /// Maps `key` to `value`, returning whether the key was not in the map
+    /// before.
+    pub fn insert(&self, key: K, value: V) -> bool {
+        let guard = epoch::pin();
+        let value = Box::into_raw(Box::new(value));
+        let backoff = Backoff::new();
+        loop {
+            let (mut path, found) = self.search(&key, &guard);
+            let node = *path.last().unwrap();
+            let lock = node.lock.lock().unwrap();
+            if node.is_removed() {
+                drop(lock);
+                backoff.spin();
+                continue;
+            }
+
+            if found {
+                let old = node.value.swap(value, Ordering::SeqCst);
+                drop(lock);
+                if !old.is_null() {
+                    // SAFETY: the old value is unlinked, and
+                    // `V: Send + 'static`.
+                    unsafe { epoch::retire(&guard, old) };
+                    return false;
+                }
+            } else {
+                let dir = node.dir(&key);
+                if !node.child(dir).is_null() {
+                    // Someone got there first.
+                    continue;
+                }
+                let new = Node::alloc(Some(key), value, [ptr::null_mut(), ptr::null_mut()]);
+                node.children[dir].store(new, Ordering::Release);
+                drop(lock);
+                // SAFETY: the node is linked, and we are pinned.
+                path.push(unsafe { &*new });
+            }
+            self.len.fetch_add(1, Ordering::Relaxed);
+            self.restructure(&path, &guard);
+            return true;
+        }
+    }
+
+    /// Removes `key` from the map, returning whether it was in the map.
+    pub fn remove(&self, key: &K) -> bool {
+        let guard = epoch::pin();
+        let backoff = Backoff::new();
+        loop {
+            let (path, found) = self.search(key, &guard);
+            if !found {
+                return false;
+            }
+            let node = *path.last().unwrap();
+            let lock = node.lock.lock().unwrap();
+            if node.is_removed() {
+                drop(lock);
+                backoff.spin();
+                continue;
+            }
+            let old = node.value.swap(ptr::null_mut(), Ordering::SeqCst);
+            drop(lock);
+            if old.is_null() {
+                return false;
+            }
+            // SAFETY: the value is unlinked, and `V: Send + 'static`.
+            unsafe { epoch::retire(&guard, old) };
+            self.len.fetch_sub(1, Ordering::Relaxed);
+            self.restructure(&path, &guard);
+            return true;
+        }
+    }
+
+    /// Walks `path` bottom-up, unlinking deleted nodes, updating heights and
+    /// rotating where the tree is out of balance.
+    fn restructure(&self, path: &[&Node<K, V>], guard: &Guard) {
+        for pair in path.windows(2).rev() {
+            let (parent, node) = (pair[0], pair[1]);
+            if node.is_removed() {
+                continue;
+            }
+            let (left, right) = (node.child(LEFT), node.child(RIGHT));
+            if node.value.load(Ordering::SeqCst).is_null() && (left.is_null() || right.is_null()) {
+                self.try_unlink(parent, node, guard);
+                continue;
+            }
+
+            node.update_height();
+            let (left_height, right_height) = (height(left), height(right));
+            for (rising, rising_height, other_height) in [
+                (LEFT, left_height, right_height),
+                (RIGHT, right_height, left_height),
+            ] {
+                if rising_height <= other_height + 1 {
+                    continue;
+                }
+                // SAFETY: the child is taller than the other one, so it is not
+                // null, and we are pinned.
+                let child = unsafe { &*node.child(rising) };
+                // A child leaning the other way needs a double rotation.
+                if height(child.child(1 - rising)) > height(child.child(rising)) {
+                    self.try_rotate(node, child, 1 - rising, guard);
+                }
+                self.try_rotate(parent, node, rising, guard);
+            }
+        }
+    }
+
+    /// Unlinks `node`, which must be deleted and have at most one child, from
+    /// below `parent`. Gives up if either is busy or has changed.
+    fn try_unlink(&self, parent: &Node<K, V>, node: &Node<K, V>, guard: &Guard) {
+        let Some(_parent_lock) = parent.try_lock() else {
+            return;
+        };
+        let Some(dir) = parent.dir_of(node) else {
+            return;
+        };
+        let Some(_node_lock) = node.try_lock() else {
+            return;
+        };
+        let (left, right) = (node.child(LEFT), node.child(RIGHT));
+        if !node.value.load(Ordering::SeqCst).is_null() || (!left.is_null() && !right.is_null()) {
+            return;
+        }
+
+        parent.children[dir].store(if left.is_null() { right } else { left }, Ordering::Release);
+        node.removed.store(true, Ordering::SeqCst);
+        // Traversals still at the node go back up and take the new path.
+        let parent_ptr = parent as *const Node<K, V> as *mut Node<K, V>;
+        node.children[LEFT].store(parent_ptr, Ordering::Release);
+        node.children[RIGHT].store(parent_ptr, Ordering::Release);
+        parent.update_height();
+        // SAFETY: the node is unlinked, holds no value, and
+        // `K: Send + 'static`.
+        unsafe { epoch::retire(guard, node as *const Node<K, V> as *mut Node<K, V>) };
+    }
+
+    /// Rotates the child of `node` in direction `rising` up into the place of
+    /// `node` below `parent`. Gives up if any of them is busy or has changed.
+    fn try_rotate(&self, parent: &Node<K, V>, node: &Node<K, V>, rising: usize, guard: &Guard) {
+        let other = 1 - rising;
+        let Some(_parent_lock) = parent.try_lock() else {
+            return;
+        };
+        let Some(dir) = parent.dir_of(node) else {
+            return;
+        };
+        let Some(_node_lock) = node.try_lock() else {
+            return;
+        };
+        let child_ptr = node.child(rising);
+        // SAFETY: children of a locked node stay alive.
+        let Some(child) = (unsafe { child_ptr.as_ref() }) else {
+            return;
+        };
+        let Some(_child_lock) = child.try_lock() else {
+            return;
+        };
+
+        // The node moves down as a copy, so traversals at the node still
+        // find their way.
+        let mut children = [ptr::null_mut(); 2];
+        children[rising] = child.child(other);
+        children[other] = node.child(other);
+        let copy = Node::alloc(
+            node.key.clone(),
+            node.value.load(Ordering::SeqCst),
+            children,
+        );
+        node.removed.store(true, Ordering::SeqCst);
+        child.children[other].store(copy, Ordering::Release);
+        child.update_height();
+        parent.children[dir].store(child_ptr, Ordering::Release);
+        parent.update_height();
+        // SAFETY: the node is unlinked, its value now belongs to the copy, and
+        // `K: Send + 'static`.
+        unsafe { epoch::retire(guard, node as *const Node<K, V> as *mut Node<K, V>) };
+    }
+}
+
+impl<K, V> Drop for RelaxedTreeMap<K, V> {
+    fn drop(&mut self) {
+        // SAFETY: we have exclusive access, so every reachable node is live.
+        // Removed nodes and replaced values are freed by the epoch collector.
+        let mut stack = vec![self.root.children[LEFT].load(Ordering::Relaxed)];
+        while let Some(node) = stack.pop() {
+            if node.is_null() {
+                continue;
+            }
+            let node = unsafe { Box::from_raw(node) };
+            stack.extend(
+                node.children
+                    .iter()
+                    .map(|child| child.load(Ordering::Relaxed)),
+            );
+            let value = node.value.load(Ordering::Relaxed);
+            if !value.is_null() {
+                drop(unsafe { Box::from_raw(value) });
+            }
+        }
+    }
+}
+
+impl<K, V> Map for RelaxedTreeMap<K, V>
+where
+    K: Hash + Ord + Clone + Send + 'static,
+    V: Send + 'static,
+{
+    type Key = K;
+    type Val = V;
+    type ValueRef<'a> = RelaxedTreeRef<'a, V>;
+
+    fn get(&self, key: &K) -> Option<RelaxedTreeRef<'_, V>> {
+        RelaxedTreeMap::get(self, key)
+    }
+
+    fn contains(&self, key: &K) -> bool {
+        self.contains_key(key)
+    }
+
+    fn put(&self, key: K, value: V) {
+        self.insert(key, value);
+    }
+
+    fn remove(&self, key: &K) -> bool {
+        RelaxedTreeMap::remove(self, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn depth<K: Ord, V>(node: *mut Node<K, V>) -> usize {
+        match unsafe { node.as_ref() } {
+            None => 0,
+            Some(node) => 1 + depth(node.child(LEFT)).max(depth(node.child(RIGHT))),
+        }
+    }
+
+    #[test]
+    fn relaxed_tree_map() {
+        let map = RelaxedTreeMap::new();
+        let num_keys = 10_000;
+        // sorted inserts are the worst case for an unbalanced tree
+        for key in 0..num_keys {
+            assert!(map.insert(key, key));
+        }
+        assert!(!map.insert(7, 70));
+        assert_eq!(*map.get(&7).unwrap(), 70);
+        assert_eq!(map.len(), num_keys);
+        assert!(depth(map.root.child(LEFT)) <= 20);
+
+        for key in (0..num_keys).filter(|key| key % 3 != 0) {
+            assert!(map.remove(&key));
+        }
+        assert!(!map.remove(&1));
+        assert!(map.get(&1).is_none());
+        assert_eq!(map.len(), num_keys.div_ceil(3));
+
+        let mut keys = Vec::new();
+        map.for_each_range(10..=21, |&key, _| keys.push(key));
+        assert_eq!(keys, [12, 15, 18, 21]);
+    }
+
+    #[test]
+    fn relaxed_tree_map_concurrent() {
+        let num_thrs = 8;
+        let num_keys = 5_000;
+        let map = RelaxedTreeMap::new();
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let map = &map;
+                s.spawn(move || {
+                    for i in 0..num_keys {
+                        let key = i * num_thrs + t;
+                        assert!(map.insert(key, i));
+                        assert_eq!(*map.get(&key).unwrap(), i);
+                        if i % 2 == 0 {
+                            assert!(map.remove(&key));
+                            assert!(map.get(&key).is_none());
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_eq!(map.len(), num_thrs * num_keys / 2);
+        let mut last = None;
+        let mut count = 0;
+        map.for_each_range(.., |&key, &value| {
+            assert!(last < Some(key));
+            assert_eq!(value, key / num_thrs);
+            assert_eq!(value % 2, 1);
+            last = Some(key);
+            count += 1;
+        });
+        assert_eq!(count, map.len());
+    }
+}
@@ -0,0 +1,207 @@
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::buckets::Buckets;
+
+struct Slot<T> {
+    value: UnsafeCell<MaybeUninit<T>>,
+    ready: AtomicBool,
+}
+
+impl<T> Slot<T> {
+    fn new(_index: usize) -> Self {
+        Self {
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            ready: AtomicBool::new(false),
+        }
+    }
+}
+
+/// An append-only vector that many threads can push to and read from at once,
+/// in the style of Intel TBB's `concurrent_vector`.
+///
+/// Elements live in buckets of doubling sizes that are never moved or freed
+/// while the vector is alive, so a pushed element keeps its index and its
+/// address for good. Pushing claims an index with a single atomic increment,
+/// allocates the index's bucket if no one has yet, and commits the element;
+/// it is wait-free, as installing a bucket takes a single compare-and-swap
+/// whichever thread wins it. Reads are lock-free and see an element once its
+/// push has committed it.
+///
+/// ```
+/// use rsds::vec::ConcurrentVec;
+///
+/// let vec = ConcurrentVec::new();
+/// std::thread::scope(|s| {
+///     for i in 0..4 {
+///         let vec = &vec;
+///         s.spawn(move || {
+///             let index = vec.push(i);
+///             assert_eq!(vec.get(index), Some(&i));
+///         });
+///     }
+/// });
+/// assert_eq!(vec.iter().sum::<i32>(), 6);
+/// ```
+pub struct ConcurrentVec<T> {
+    slots: Buckets<Slot<T>>,
+    len: AtomicUsize,
+}
+
+// SAFETY: elements are shared between threads through `get`, and moved in
+// from other threads through `push`.
+unsafe impl<T: Send> Send for ConcurrentVec<T> {}
+unsafe impl<T: Send + Sync> Sync for ConcurrentVec<T> {}
+
+impl<T> Default for ConcurrentVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> ConcurrentVec<T> {
+    /// Creates a new, empty [`ConcurrentVec`].
+    pub fn new() -> Self {
+        Self {
+            slots: Buckets::new(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Appends `value` to the vector, returning its index.
+    pub fn push(&self, value: T) -> usize {
+        let index = self.len.fetch_add(1, Ordering::Relaxed);
+        let slot = self.slots.get_or_alloc(index, Slot::new);
+        // SAFETY: the index is ours alone.
+        unsafe { (*slot.value.get()).write(value) };
+        slot.ready.store(true, Ordering::Release);
+        index
+    }
+
+    /// Returns the element at `index`, or `None` if it is out of bounds or
+    /// its push has not committed it yet.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len() {
+            return None;
+        }
+        let slot = self.slots.get(index)?;
+        if !slot.ready.load(Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: a ready slot is never written again.
+        Some(unsafe { (*slot.value.get()).assume_init_ref() })
+    }
+
+    /// Returns the number of indices handed out so far, including those of
+    /// pushes that have not committed their element yet.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /// Checks whether the vector is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterates over the committed elements in index order, skipping those
+    /// still being pushed.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        (0..self.len()).filter_map(move |index| self.get(index))
+    }
+}
+
+impl<T> Drop for ConcurrentVec<T> {
+    fn drop(&mut self) {
+        for (_, slot) in self.slots.iter_mut() {
+            if *slot.ready.get_mut() {
+                // SAFETY: ready slots hold an initialized value.
+                unsafe { slot.value.get_mut().assume_init_drop() };
+            }
+        }
+    }
+}
+
+impl<T> FromIterator<T> for ConcurrentVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let vec = Self::new();
+        for value in iter {
+            vec.push(value);
+        }
+        vec
+    }
+}
+
+impl<T> fmt::Debug for ConcurrentVec<T>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[test]
+    fn concurrent_vec() {
+        let vec: ConcurrentVec<_> = (0..100).map(|i| i.to_string()).collect();
+        assert_eq!(vec.len(), 100);
+        assert_eq!(vec.get(0).unwrap(), "0");
+        assert_eq!(vec.get(63).unwrap(), "63");
+        assert!(vec.get(100).is_none());
+        assert_eq!(format!("{:?}", ConcurrentVec::from_iter([1, 2])), "[1, 2]");
+    }
+
+    #[test]
+    fn concurrent_vec_concurrent() {
+        let num_thrs = 8;
+        let num_elems = 10_000;
+        let vec = ConcurrentVec::new();
+        let counted = Arc::new(());
+
+        std::thread::scope(|s| {
+            for t in 0..num_thrs {
+                let (vec, counted) = (&vec, &counted);
+                s.spawn(move || {
+                    for i in 0..num_elems {
+                        let value = (t * num_elems + i, counted.clone());
+                        let index = vec.push(value);
+                        // addresses are stable while others keep pushing
+                        let elem = vec.get(index).unwrap();
+                        assert_eq!(elem.0, t * num_elems + i);
+                    }
+                });
+            }
+        });
+
+        let mut values: Vec<_> = vec.iter().map(|(value, _)| *value).collect();
+        values.sort_unstable();
+        assert!(values.into_iter().eq(0..num_thrs * num_elems));
+        drop(vec);
+        assert_eq!(Arc::strong_count(&counted), 1);
+    }
+
+    #[test]
+    fn miri_concurrent_vec() {
+        // few enough operations to run under Miri, over several buckets
+        let vec = ConcurrentVec::new();
+        std::thread::scope(|s| {
+            for t in 0..2 {
+                let vec = &vec;
+                s.spawn(move || {
+                    for i in 0..8 {
+                        let index = vec.push((t, i.to_string()));
+                        assert_eq!(vec.get(index).unwrap().1, i.to_string());
+                    }
+                });
+            }
+        });
+        assert_eq!(vec.iter().count(), 16);
+    }
+}
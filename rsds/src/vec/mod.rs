@@ -0,0 +1,5 @@
+//! This module contains concurrent vectors.
+
+mod concurrent_vec;
+
+pub use concurrent_vec::ConcurrentVec;
@@ -0,0 +1,241 @@
+//! A harness for driving scripted operations against any structure with
+//! map-like operations, as the `bench` crate does.
+//!
+//! Every [`Map`] and [`Set`] of `u64`s implements [`MapLike`], so it can be
+//! driven with no glue: a set ignores the values of insertions. Other types,
+//! such as other crates' maps, implement [`MapLike`] with a marker type of
+//! their own as its parameter, which keeps their impls apart from the blanket
+//! ones.
+//!
+//! The harness is behind the `workload` feature.
+//!
+//! ```
+//! use rsds::list_set::FineGrainedSet;
+//! use rsds::map::StripedHashMap;
+//! use rsds::workload::{self, Op};
+//!
+//! let scripts = vec![vec![Op::Insert(1, 10), Op::Get(1)], vec![Op::Remove(2)]];
+//! workload::run(&StripedHashMap::<u64, u64>::new(), &scripts, 100);
+//! workload::run(&FineGrainedSet::<u64>::default(), &scripts, 100);
+//! ```
+//!
+//! [`Map`]: crate::map::Map
+//! [`Set`]: crate::list_set::Set
+
+#[cfg(has_clock)]
+use std::hint::black_box;
+#[cfg(has_clock)]
+use std::sync::Barrier;
+#[cfg(has_clock)]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "list-set")]
+use crate::list_set::Set;
+#[cfg(feature = "map")]
+use crate::map::Map;
+
+/// An operation in a workload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    /// Looks up a key.
+    Get(u64),
+    /// Maps a key to a value.
+    Insert(u64, u64),
+    /// Removes a key.
+    Remove(u64),
+}
+
+impl Op {
+    /// Runs the operation against `target`, returning whether it found its
+    /// key. Insertions always return `true`.
+    pub fn apply<T, Via>(self, target: &T) -> bool
+    where
+        T: MapLike<Via> + ?Sized,
+    {
+        match self {
+            Op::Get(key) => target.get(key),
+            Op::Insert(key, value) => {
+                target.insert(key, value);
+                true
+            }
+            Op::Remove(key) => target.remove(key),
+        }
+    }
+}
+
+/// The operations of a workload, over the structures it runs against.
+///
+/// `Via` tells apart impls that would otherwise overlap: it is [`ViaMap`]
+/// for [`Map`](crate::map::Map)s, [`ViaSet`] for
+/// [`Set`](crate::list_set::Set)s, and a type of the implementor's own for
+/// anything else.
+pub trait MapLike<Via>: Sync {
+    /// Looks up `key`, returning whether it was found.
+    fn get(&self, key: u64) -> bool;
+
+    /// Maps `key` to `value`.
+    fn insert(&self, key: u64, value: u64);
+
+    /// Removes `key`, returning whether it was found.
+    fn remove(&self, key: u64) -> bool;
+}
+
+/// Marks the [`MapLike`] impl of every [`Map`](crate::map::Map).
+#[derive(Debug)]
+pub enum ViaMap {}
+
+/// Marks the [`MapLike`] impl of every [`Set`](crate::list_set::Set).
+#[derive(Debug)]
+pub enum ViaSet {}
+
+#[cfg(feature = "map")]
+impl<M> MapLike<ViaMap> for M
+where
+    M: Map<Key = u64, Val = u64> + Sync,
+{
+    fn get(&self, key: u64) -> bool {
+        Map::get(self, &key).is_some()
+    }
+
+    fn insert(&self, key: u64, value: u64) {
+        self.put(key, value);
+    }
+
+    fn remove(&self, key: u64) -> bool {
+        Map::remove(self, &key)
+    }
+}
+
+#[cfg(feature = "list-set")]
+impl<S> MapLike<ViaSet> for S
+where
+    S: Set<Elem = u64> + Sync,
+{
+    fn get(&self, key: u64) -> bool {
+        self.contains(&key)
+    }
+
+    fn insert(&self, key: u64, _: u64) {
+        self.add(key);
+    }
+
+    fn remove(&self, key: u64) -> bool {
+        Set::remove(self, &key)
+    }
+}
+
+/// Inserts every other key below `keys`, so that lookups of keys drawn from
+/// `0..keys` start out hitting about half the time.
+pub fn prefill<T, Via>(target: &T, keys: u64)
+where
+    T: MapLike<Via> + ?Sized,
+{
+    for key in (0..keys).step_by(2) {
+        target.insert(key, key);
+    }
+}
+
+/// Runs `total` operations against `target`, split evenly between a thread
+/// per script, each cycling through its script. Returns the wall-clock time
+/// from when all threads start until the last one finishes.
+///
+/// # Panics
+///
+/// Panics if `scripts` is empty, or if a script is empty while the thread
+/// running it has operations to run.
+#[cfg(has_clock)]
+pub fn run<T, Via>(target: &T, scripts: &[Vec<Op>], total: u64) -> Duration
+where
+    T: MapLike<Via> + ?Sized,
+{
+    assert!(!scripts.is_empty(), "scripts should not be empty");
+    let per_thread = total.div_ceil(scripts.len() as u64) as usize;
+    assert!(
+        per_thread == 0 || scripts.iter().all(|script| !script.is_empty()),
+        "every script should have operations"
+    );
+    let barrier = Barrier::new(scripts.len() + 1);
+    let mut start = None;
+    std::thread::scope(|s| {
+        for script in scripts {
+            let barrier = &barrier;
+            s.spawn(move || {
+                barrier.wait();
+                for &op in script.iter().cycle().take(per_thread) {
+                    black_box(op.apply(target));
+                }
+            });
+        }
+        barrier.wait();
+        start = Some(Instant::now());
+    });
+    start.unwrap().elapsed()
+}
+
+#[cfg(all(test, feature = "list-set", feature = "map"))]
+mod tests {
+    use super::*;
+    use crate::list_set::CoarseSet;
+    use crate::map::StripedHashMap;
+
+    #[test]
+    fn workload() {
+        let map = StripedHashMap::<u64, u64>::new();
+        prefill(&map, 10);
+        assert!(Op::Get(4).apply(&map) && !Op::Get(5).apply(&map));
+        assert!(Op::Insert(5, 50).apply(&map));
+        assert_eq!(*Map::get(&map, &5).unwrap(), 50);
+        assert!(Op::Remove(5).apply(&map) && !Op::Remove(5).apply(&map));
+
+        // sets drop the values
+        let set = CoarseSet::<u64>::default();
+        assert!(Op::Insert(1, 10).apply(&set));
+        assert!(Op::Get(1).apply(&set) && !Op::Get(10).apply(&set));
+        assert!(Op::Remove(1).apply(&set) && !Op::Get(1).apply(&set));
+
+        // other types pick a marker of their own
+        struct Keys(std::sync::Mutex<Vec<u64>>);
+        enum ViaKeys {}
+        impl MapLike<ViaKeys> for Keys {
+            fn get(&self, key: u64) -> bool {
+                self.0.lock().unwrap().contains(&key)
+            }
+
+            fn insert(&self, key: u64, _: u64) {
+                self.0.lock().unwrap().push(key);
+            }
+
+            fn remove(&self, key: u64) -> bool {
+                let mut keys = self.0.lock().unwrap();
+                let len = keys.len();
+                keys.retain(|&k| k != key);
+                keys.len() < len
+            }
+        }
+        let keys = Keys(Default::default());
+        prefill(&keys, 6);
+        assert_eq!(*keys.0.lock().unwrap(), [0, 2, 4]);
+    }
+
+    #[test]
+    fn workload_concurrent() {
+        let map = StripedHashMap::<u64, u64>::new();
+        // each thread inserts then removes its own keys, so that the map
+        // ends up empty whatever the interleaving
+        let scripts: Vec<Vec<_>> = (0..4)
+            .map(|t| {
+                let keys = (0..100).map(|i| i * 4 + t);
+                keys.clone()
+                    .map(|key| Op::Insert(key, key))
+                    .chain(keys.map(Op::Remove))
+                    .collect()
+            })
+            .collect();
+        run(&map, &scripts, 4 * 200);
+        assert!((0..400).all(|key| !Op::Get(key).apply(&map)));
+
+        let set = CoarseSet::<u64>::default();
+        run(&set, &scripts, 4 * 100);
+        assert!((0..400).all(|key| Op::Get(key).apply(&set)));
+    }
+}